@@ -9,30 +9,248 @@ CREATE TABLE IF NOT EXISTS users (
     nickname VARCHAR(100) NOT NULL,
     password_hash VARCHAR(255) NOT NULL,
     email VARCHAR(100),
+    role VARCHAR(20) NOT NULL DEFAULT 'user', -- user, admin
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_email (email) -- 用于找回密码，每个邮箱只能绑定一个账号
+) ENGINE=InnoDB;
+
+-- 创建登录会话表，访问令牌中携带会话ID，会话被吊销后令牌立即失效
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    user_agent VARCHAR(255) NULL,
+    ip_address VARCHAR(45) NULL,
+    expires_at TIMESTAMP NOT NULL, -- 最新刷新令牌的过期时间
+    revoked_at TIMESTAMP NULL DEFAULT NULL,
+    last_used_at TIMESTAMP NULL DEFAULT NULL, -- 最近一次刷新的时间
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    KEY idx_user (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建刷新令牌表，只保存令牌的SHA-256摘要，每次刷新后旧令牌作废
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    session_id INT NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL DEFAULT NULL, -- 已用于刷新，再次使用视为令牌被盗用
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_token_hash (token_hash),
+    FOREIGN KEY (session_id) REFERENCES user_sessions(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建两步验证表，enabled_at为空表示已生成密钥但尚未验证第一个验证码
+CREATE TABLE IF NOT EXISTS user_totp (
+    user_id INT PRIMARY KEY,
+    secret VARCHAR(64) NOT NULL, -- Base32编码的密钥
+    last_used_step BIGINT NULL, -- 最近一次使用的时间片，同一验证码不能重复使用
+    enabled_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建两步验证恢复码表，只保存摘要，每个恢复码只能使用一次
+CREATE TABLE IF NOT EXISTS totp_recovery_codes (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    KEY idx_user (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建登录挑战表，密码验证通过但需要两步验证时发放，只保存令牌的摘要
+CREATE TABLE IF NOT EXISTS login_challenges (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    user_agent VARCHAR(255) NULL,
+    ip_address VARCHAR(45) NULL,
+    failed_attempts INT NOT NULL DEFAULT 0, -- 验证码错误次数，达到上限后作废
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_token_hash (token_hash),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建登录失败计数表，按账号和IP分别累计，用于限制暴力破解
+CREATE TABLE IF NOT EXISTS login_failures (
+    scope VARCHAR(10) NOT NULL, -- account或ip
+    subject VARCHAR(255) NOT NULL, -- 小写的用户名或IP地址
+    failure_count INT NOT NULL DEFAULT 0,
+    first_failed_at TIMESTAMP NOT NULL, -- 当前统计窗口内的首次失败时间
+    last_failed_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NULL DEFAULT NULL,
+    PRIMARY KEY (scope, subject)
+) ENGINE=InnoDB;
+
+-- 创建登录记录表，记录每次登录尝试，供用户查看最近的登录
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NULL, -- 用户名不存在时为空
+    username VARCHAR(50) NOT NULL,
+    success BOOLEAN NOT NULL,
+    failure_reason VARCHAR(30) NULL, -- invalid_credentials, invalid_two_factor, locked, throttled；单点登录成功时为空
+    user_agent VARCHAR(255) NULL,
+    ip_address VARCHAR(45) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    KEY idx_user_created (user_id, created_at),
+    KEY idx_ip_created (ip_address, created_at),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建外部身份表，记录通过单点登录关联到账号的身份提供方用户
+CREATE TABLE IF NOT EXISTS user_identities (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    issuer VARCHAR(255) NOT NULL,
+    subject VARCHAR(255) NOT NULL, -- ID令牌中的sub
+    email VARCHAR(100) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    last_login_at TIMESTAMP NULL DEFAULT NULL,
+    UNIQUE KEY uk_issuer_subject (issuer, subject),
+    KEY idx_user (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建单点登录状态表，保存发起登录时生成的PKCE校验码和nonce，回调时使用一次后删除
+CREATE TABLE IF NOT EXISTS oidc_login_states (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    state_hash CHAR(64) NOT NULL,
+    code_verifier VARCHAR(128) NOT NULL,
+    nonce VARCHAR(64) NOT NULL,
+    link_user_id INT NULL, -- 已登录用户关联外部身份时发起，为空表示登录
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_state_hash (state_hash),
+    FOREIGN KEY (link_user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建个人访问令牌表，只保存令牌的SHA-256摘要，供脚本按权限范围调用接口
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    name VARCHAR(50) NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    token_prefix VARCHAR(20) NOT NULL,
+    scopes VARCHAR(255) NOT NULL, -- 逗号分隔，如questionnaires:read,responses:read
+    expires_at TIMESTAMP NULL DEFAULT NULL, -- 为空表示永不过期
+    last_used_at TIMESTAMP NULL DEFAULT NULL,
+    revoked_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_token_hash (token_hash),
+    KEY idx_user (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建重置密码令牌表，只保存令牌的SHA-256摘要，使用一次后作废
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_token_hash (token_hash),
+    KEY idx_user (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建组织表，组织的设置作用于其名下的所有问卷
+CREATE TABLE IF NOT EXISTS organizations (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    anonymous_responses BOOLEAN NOT NULL DEFAULT FALSE, -- 新建问卷默认匿名收集回答
+    response_retention_days INT NULL, -- 回答的保留天数，超过后自动删除，为空表示永久保留
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
 ) ENGINE=InnoDB;
 
+-- 创建组织成员表
+CREATE TABLE IF NOT EXISTS organization_members (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    organization_id INT NOT NULL,
+    user_id INT NOT NULL,
+    role VARCHAR(20) NOT NULL DEFAULT 'member', -- owner, admin, member
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_organization_user (organization_id, user_id),
+    KEY idx_user (user_id),
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
 -- 创建问卷表
 CREATE TABLE IF NOT EXISTS questionnaires (
     id INT AUTO_INCREMENT PRIMARY KEY,
     title VARCHAR(255) NOT NULL,
     description TEXT,
     is_public BOOLEAN DEFAULT FALSE,
-    creator_id INT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'draft', -- draft, published, paused, closed, archived
+    opens_at TIMESTAMP NULL DEFAULT NULL, -- 开始接受回答的时间，为空表示不限制
+    closes_at TIMESTAMP NULL DEFAULT NULL, -- 截止时间，到期后自动关闭
+    max_responses INT NULL, -- 回答总数上限，达到后自动关闭
+    one_per_account BOOLEAN NOT NULL DEFAULT TRUE, -- 每个账号只能提交一次
+    one_per_device BOOLEAN NOT NULL DEFAULT FALSE, -- 每个设备（Cookie）只能提交一次
+    one_per_ip BOOLEAN NOT NULL DEFAULT FALSE, -- 每个IP只能提交一次
+    invite_only BOOLEAN NOT NULL DEFAULT FALSE, -- 只接受通过邀请链接提交的回答
+    anonymous BOOLEAN NOT NULL DEFAULT FALSE, -- 匿名收集，查看和导出回答时不显示提交人
+    creator_id INT NULL, -- 创建人，个人问卷的所有者
+    organization_id INT NULL, -- 所属组织，为空表示个人问卷
+    current_version_id INT NULL, -- 当前发布的版本
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    KEY idx_organization (organization_id),
+    -- 注销账号时个人问卷需先删除或转交，组织的问卷保留在组织中
+    FOREIGN KEY (creator_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE RESTRICT
+) ENGINE=InnoDB;
+
+-- 创建问卷协作者表，邀请被接受后协作者按角色获得问卷的权限
+CREATE TABLE IF NOT EXISTS questionnaire_collaborators (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    questionnaire_id INT NOT NULL,
+    user_id INT NOT NULL,
+    role VARCHAR(20) NOT NULL, -- editor, viewer
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- pending, accepted
+    invited_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    accepted_at TIMESTAMP NULL DEFAULT NULL,
+    UNIQUE KEY uk_questionnaire_user (questionnaire_id, user_id),
+    KEY idx_user (user_id),
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (invited_by) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB;
+
+-- 创建问卷分页表
+CREATE TABLE IF NOT EXISTS question_sections (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    questionnaire_id INT NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    display_order INT NOT NULL,
+    retired_at TIMESTAMP NULL DEFAULT NULL, -- 编辑时被移除的分页
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
-    FOREIGN KEY (creator_id) REFERENCES users(id) ON DELETE CASCADE
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE
 ) ENGINE=InnoDB;
 
 -- 创建问题表
 CREATE TABLE IF NOT EXISTS questions (
     id INT AUTO_INCREMENT PRIMARY KEY,
     questionnaire_id INT NOT NULL,
+    section_id INT NULL, -- 所属分页，问卷未分页时为空
     title VARCHAR(255) NOT NULL,
-    question_type VARCHAR(50) NOT NULL, -- text, radio, checkbox
+    question_type VARCHAR(50) NOT NULL, -- text, radio, checkbox, rating, nps, number, date, email, phone, matrix_radio, matrix_checkbox, ranking
     required BOOLEAN DEFAULT TRUE,
     display_order INT NOT NULL,
+    config TEXT, -- 题型相关配置的JSON，如评分范围、数字上下限、日期类型
+    logic TEXT, -- 显示条件和跳转规则的JSON
+    retired_at TIMESTAMP NULL DEFAULT NULL, -- 编辑时被移除的问题，保留以维持历史回答
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
     FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE
@@ -43,19 +261,50 @@ CREATE TABLE IF NOT EXISTS question_options (
     id INT AUTO_INCREMENT PRIMARY KEY,
     question_id INT NOT NULL,
     option_text VARCHAR(255) NOT NULL,
+    role VARCHAR(20) NOT NULL DEFAULT 'option', -- option为选项（矩阵题的列），row为矩阵题的行
     display_order INT NOT NULL,
+    retired_at TIMESTAMP NULL DEFAULT NULL,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
     FOREIGN KEY (question_id) REFERENCES questions(id) ON DELETE CASCADE
 ) ENGINE=InnoDB;
 
+-- 创建问卷版本表，发布时保存问题和选项的快照
+CREATE TABLE IF NOT EXISTS questionnaire_versions (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    questionnaire_id INT NOT NULL,
+    version_number INT NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    snapshot MEDIUMTEXT NOT NULL, -- 问题和选项的JSON快照
+    sections TEXT, -- 分页的JSON快照，问卷未分页时为空
+    created_by INT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_questionnaire_version (questionnaire_id, version_number),
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB;
+
 -- 创建问卷回答表
 CREATE TABLE IF NOT EXISTS questionnaire_responses (
     id INT AUTO_INCREMENT PRIMARY KEY,
     questionnaire_id INT NOT NULL,
+    version_id INT NULL, -- 回答时问卷所处的版本
     respondent_id INT,
+    status VARCHAR(20) NOT NULL DEFAULT 'completed', -- in_progress: 草稿, completed: 已提交
+    resume_token VARCHAR(64) NULL, -- 草稿的续答凭证，提交后清空
+    draft_answers MEDIUMTEXT, -- 草稿中已保存的回答JSON
+    current_section_id INT NULL, -- 草稿当前所在的分页
+    device_id VARCHAR(64) NULL, -- 提交设备的Cookie标识
+    ip_address VARCHAR(45) NULL,
+    started_at TIMESTAMP NULL DEFAULT NULL, -- 开始作答的时间，用于计算完成用时
+    submitted_at TIMESTAMP NULL DEFAULT NULL,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_resume_token (resume_token),
+    KEY idx_questionnaire_created (questionnaire_id, created_at),
     FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE,
+    FOREIGN KEY (version_id) REFERENCES questionnaire_versions(id) ON DELETE SET NULL,
     FOREIGN KEY (respondent_id) REFERENCES users(id) ON DELETE SET NULL
 ) ENGINE=InnoDB;
 
@@ -74,14 +323,235 @@ CREATE TABLE IF NOT EXISTS text_responses (
     id INT AUTO_INCREMENT PRIMARY KEY,
     question_response_id INT NOT NULL,
     text_value TEXT NOT NULL,
+    FULLTEXT KEY ft_text_value (text_value) WITH PARSER ngram, -- ngram分词支持中文全文搜索
     FOREIGN KEY (question_response_id) REFERENCES question_responses(id) ON DELETE CASCADE
 ) ENGINE=InnoDB;
 
+-- 创建文本回答标签表，用于对开放题的回答进行人工编码
+CREATE TABLE IF NOT EXISTS text_answer_tags (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    text_response_id INT NOT NULL,
+    questionnaire_id INT NOT NULL,
+    question_id INT NOT NULL,
+    tag VARCHAR(50) NOT NULL,
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_response_tag (text_response_id, tag),
+    KEY idx_questionnaire_tag (questionnaire_id, question_id, tag),
+    FOREIGN KEY (text_response_id) REFERENCES text_responses(id) ON DELETE CASCADE,
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE,
+    FOREIGN KEY (question_id) REFERENCES questions(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB;
+
 -- 创建选项回答表
 CREATE TABLE IF NOT EXISTS option_responses (
     id INT AUTO_INCREMENT PRIMARY KEY,
     question_response_id INT NOT NULL,
     option_id INT NOT NULL,
+    row_option_id INT NULL, -- 矩阵题所在的行
+    rank_position INT NULL, -- 排序题中的名次，从1开始
     FOREIGN KEY (question_response_id) REFERENCES question_responses(id) ON DELETE CASCADE,
+    FOREIGN KEY (option_id) REFERENCES question_options(id) ON DELETE CASCADE,
+    FOREIGN KEY (row_option_id) REFERENCES question_options(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建数值回答表（评分题、NPS题、数字题）
+CREATE TABLE IF NOT EXISTS numeric_responses (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    question_response_id INT NOT NULL,
+    numeric_value DOUBLE NOT NULL,
+    FOREIGN KEY (question_response_id) REFERENCES question_responses(id) ON DELETE CASCADE
+) ENGINE=InnoDB; 
+
+-- 创建统计汇总表，首次查看统计时由已有回答生成，此后在提交回答时增量更新
+CREATE TABLE IF NOT EXISTS questionnaire_statistics (
+    questionnaire_id INT PRIMARY KEY,
+    response_count INT NOT NULL DEFAULT 0, -- 已提交的回答数
+    rebuilt_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 各问题的作答人数，矩阵题另按行记录
+CREATE TABLE IF NOT EXISTS question_statistics (
+    question_id INT NOT NULL,
+    row_option_id INT NOT NULL DEFAULT 0, -- 矩阵题所在的行，其他为0
+    questionnaire_id INT NOT NULL,
+    answer_count INT NOT NULL DEFAULT 0,
+    earliest_text VARCHAR(64) NULL, -- 日期题的最早日期
+    latest_text VARCHAR(64) NULL, -- 日期题的最晚日期
+    PRIMARY KEY (question_id, row_option_id),
+    KEY idx_questionnaire (questionnaire_id),
+    FOREIGN KEY (question_id) REFERENCES questions(id) ON DELETE CASCADE,
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 各选项的选择人数，排序题同时记录名次之和
+CREATE TABLE IF NOT EXISTS option_statistics (
+    option_id INT NOT NULL,
+    row_option_id INT NOT NULL DEFAULT 0, -- 矩阵题所在的行，其他为0
+    question_id INT NOT NULL,
+    questionnaire_id INT NOT NULL,
+    selection_count INT NOT NULL DEFAULT 0,
+    rank_sum BIGINT NOT NULL DEFAULT 0,
+    first_place_count INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (option_id, row_option_id),
+    KEY idx_questionnaire (questionnaire_id),
+    FOREIGN KEY (option_id) REFERENCES question_options(id) ON DELETE CASCADE,
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 数值题各分值的回答人数，用于计算均值、中位数和分布
+CREATE TABLE IF NOT EXISTS value_statistics (
+    question_id INT NOT NULL,
+    numeric_value DOUBLE NOT NULL,
+    questionnaire_id INT NOT NULL,
+    value_count INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (question_id, numeric_value),
+    KEY idx_questionnaire (questionnaire_id),
+    FOREIGN KEY (question_id) REFERENCES questions(id) ON DELETE CASCADE,
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建选项配额表
+CREATE TABLE IF NOT EXISTS response_quotas (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    questionnaire_id INT NOT NULL,
+    question_id INT NOT NULL,
+    option_id INT NOT NULL,
+    quota_limit INT NOT NULL, -- 选择该选项的回答数上限
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_quota_option (option_id),
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE,
+    FOREIGN KEY (question_id) REFERENCES questions(id) ON DELETE CASCADE,
     FOREIGN KEY (option_id) REFERENCES question_options(id) ON DELETE CASCADE
-) ENGINE=InnoDB; 
\ No newline at end of file
+) ENGINE=InnoDB;
+
+-- 创建Webhook订阅表，问卷发生订阅的事件时向url推送带签名的通知
+CREATE TABLE IF NOT EXISTS webhooks (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    questionnaire_id INT NOT NULL,
+    url VARCHAR(500) NOT NULL,
+    secret VARCHAR(100) NOT NULL, -- 用于计算签名，需要原文
+    events VARCHAR(255) NOT NULL, -- 逗号分隔，如response.submitted,questionnaire.closed
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    KEY idx_questionnaire (questionnaire_id),
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB;
+
+-- 创建Webhook推送表，与触发事件的操作在同一事务中写入，由后台任务投递
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    webhook_id INT NOT NULL,
+    delivery_uuid CHAR(36) NOT NULL, -- 通过X-Webhook-Delivery请求头发送，接收方可据此去重
+    event VARCHAR(50) NOT NULL,
+    payload MEDIUMTEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- pending, delivered, failed
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NULL DEFAULT NULL, -- 投递中的推送会被顺延，避免多个实例重复投递
+    last_status_code INT NULL,
+    last_error VARCHAR(500) NULL,
+    delivered_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_delivery_uuid (delivery_uuid),
+    KEY idx_webhook (webhook_id, id),
+    KEY idx_due (status, next_attempt_at),
+    FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建Webhook投递记录表，记录每次投递的结果
+CREATE TABLE IF NOT EXISTS webhook_delivery_attempts (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    delivery_id INT NOT NULL,
+    status_code INT NULL, -- 请求失败（如连接超时）时为空
+    error VARCHAR(500) NULL,
+    response_body VARCHAR(1000) NULL, -- 响应内容的开头部分
+    duration_ms INT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    KEY idx_delivery (delivery_id),
+    FOREIGN KEY (delivery_id) REFERENCES webhook_deliveries(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建通知设置表，没有记录的用户使用默认设置
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id INT PRIMARY KEY,
+    response_mode VARCHAR(20) NOT NULL DEFAULT 'off', -- 自己创建的问卷收到回答时的通知方式：off, instant, daily
+    receipt BOOLEAN NOT NULL DEFAULT TRUE, -- 提交回答后是否给自己发送回答副本
+    digest_hour TINYINT NOT NULL DEFAULT 8, -- 每日摘要的发送时间（当地时间的小时）
+    timezone VARCHAR(50) NOT NULL DEFAULT 'UTC', -- 每日摘要和邮件中时间使用的时区
+    last_digest_at TIMESTAMP NULL DEFAULT NULL, -- 上次生成每日摘要的时间，下次摘要从这里开始统计
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建问卷通知设置表，覆盖用户对单个问卷的通知方式，协作者和组织成员也可以订阅
+CREATE TABLE IF NOT EXISTS questionnaire_notification_settings (
+    user_id INT NOT NULL,
+    questionnaire_id INT NOT NULL,
+    response_mode VARCHAR(20) NOT NULL, -- off, instant, daily
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, questionnaire_id),
+    KEY idx_questionnaire (questionnaire_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建邮件发件箱，通知邮件与触发的操作在同一事务中写入，由后台任务发送，发送失败不影响操作本身
+CREATE TABLE IF NOT EXISTS notification_outbox (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NULL, -- 收件用户，发给受邀人的邮件为空
+    kind VARCHAR(20) NOT NULL, -- response, receipt, digest, invitation, reminder
+    recipient VARCHAR(100) NOT NULL, -- 写入时用户绑定的邮箱
+    subject VARCHAR(255) NOT NULL,
+    body MEDIUMTEXT NOT NULL, -- 发送成功或失败后清空，不保留邀请链接和回答内容
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- pending, sent, failed
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NULL DEFAULT CURRENT_TIMESTAMP, -- 发送中的邮件会被顺延，避免多个实例重复发送
+    last_error VARCHAR(500) NULL,
+    sent_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    KEY idx_due (status, next_attempt_at),
+    KEY idx_user (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
+-- 创建邀请活动表，向名单中的受邀人发送专属的问卷链接
+CREATE TABLE IF NOT EXISTS campaigns (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    questionnaire_id INT NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    message TEXT NULL, -- 附加在邀请和提醒邮件中的说明
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    KEY idx_questionnaire (questionnaire_id),
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB;
+
+-- 创建受邀人表，每个受邀人有一个只能提交一次的专属链接
+CREATE TABLE IF NOT EXISTS campaign_recipients (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    campaign_id INT NOT NULL,
+    email VARCHAR(100) NOT NULL,
+    name VARCHAR(100) NULL,
+    token_hash CHAR(64) NOT NULL, -- 链接中令牌的哈希，令牌原文只保留在发件箱中尚未发送的邮件里
+    sent_at TIMESTAMP NULL DEFAULT NULL, -- 邀请邮件加入发送队列的时间
+    opened_at TIMESTAMP NULL DEFAULT NULL, -- 第一次打开链接的时间
+    started_at TIMESTAMP NULL DEFAULT NULL, -- 开始作答的时间
+    completed_at TIMESTAMP NULL DEFAULT NULL, -- 提交回答的时间，匿名问卷中各阶段的时间只保留到日期
+    response_id INT NULL, -- 提交的回答，匿名问卷不保存
+    reminder_count INT NOT NULL DEFAULT 0,
+    last_reminded_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_token_hash (token_hash),
+    UNIQUE KEY uk_campaign_email (campaign_id, email),
+    KEY idx_response (response_id),
+    FOREIGN KEY (campaign_id) REFERENCES campaigns(id) ON DELETE CASCADE,
+    FOREIGN KEY (response_id) REFERENCES questionnaire_responses(id) ON DELETE SET NULL
+) ENGINE=InnoDB;
@@ -1,18 +1,28 @@
 use serde::Deserialize;
 use std::env;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub jwt: JwtConfig,
+    pub lifecycle: LifecycleConfig,
+    pub mail: MailConfig,
+    pub account: AccountConfig,
+    pub login: LoginConfig,
+    pub oidc: Option<OidcConfig>, // 未配置OIDC_ISSUER时不启用单点登录
+    pub webhook: WebhookConfig,
+    pub notification: NotificationConfig,
+    pub campaign: CampaignConfig,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    pub trust_proxy: bool, // 是否信任反向代理提供的X-Forwarded-For
+    pub trusted_proxy_hops: usize, // 客户端与服务之间的反向代理层数
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -24,7 +34,115 @@ pub struct DatabaseConfig {
 #[derive(Clone, Debug, Deserialize)]
 pub struct JwtConfig {
     pub secret: String,
-    pub expiration: String,
+    pub expiration: u64,         // 访问令牌有效期（秒）
+    pub refresh_expiration: u64, // 刷新令牌有效期（秒）
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LifecycleConfig {
+    pub close_check_interval: u64, // 检查到期问卷的间隔（秒）
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MailConfig {
+    pub backend: MailBackend,
+    pub from: String,
+    pub dir: String,               // file方式下邮件的保存目录
+    pub smtp: Option<SmtpConfig>, // smtp方式下的服务器配置
+}
+
+// 邮件发送方式：smtp通过邮件服务器发送，log只写入日志，file将每封邮件保存为文件，供本地调试和测试
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    Smtp,
+    Log,
+    File,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>, // 不设置时不进行认证
+    pub password: Option<String>,
+    pub tls: SmtpTls,
+}
+
+// 与邮件服务器之间的加密方式：starttls在明文连接上升级，tls直接建立加密连接（通常为465端口）
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    Starttls,
+    Tls,
+    None,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccountConfig {
+    pub password_reset_url: String,      // 重置密码页面地址，邮件中的链接会附加token参数
+    pub password_reset_expiration: u64, // 重置密码链接有效期（秒）
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LoginConfig {
+    pub store: LockoutStoreKind,
+    pub max_failures: u32,     // 同一账号连续失败多少次后锁定
+    pub ip_max_failures: u32,  // 同一IP连续失败多少次后锁定
+    pub failure_window: u64,   // 失败次数的统计窗口（秒），超过后重新计数
+    pub lockout_duration: u64, // 锁定时长（秒）
+    pub max_delay: u64,        // 两次失败之间的最长等待时间（秒）
+    pub challenge_expiration: u64, // 两步验证登录挑战的有效期（秒）
+    pub totp_issuer: String,       // 验证器应用中显示的服务名称
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OidcConfig {
+    pub issuer: String,                // 身份提供方地址，通过{issuer}/.well-known/openid-configuration获取端点
+    pub client_id: String,
+    pub client_secret: Option<String>, // 公开客户端可以不设置，只依靠PKCE
+    pub redirect_uri: String,          // 身份提供方登录后跳转的前端页面，需与身份提供方中登记的一致
+    pub scopes: String,
+    pub username_claim: String,        // 自动创建账号时作为用户名的声明
+    pub nickname_claim: String,
+    pub email_claim: String,
+    pub auto_provision: bool,          // 首次登录时是否自动创建账号
+    pub link_by_email: bool,           // 是否按已验证的邮箱自动关联已有账号
+    pub state_expiration: u64,         // 发起登录到回调之间允许的最长时间（秒）
+    pub skip_two_factor: bool,         // 是否信任身份提供方的认证，不再要求本地的两步验证
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub worker_interval: u64,        // 检查待投递推送的间隔（秒）
+    pub batch_size: i64,             // 每次最多投递的推送数
+    pub max_attempts: i32,           // 最多投递次数，用完后标记为失败
+    pub retry_base_delay: u64,       // 第一次重试前的等待时间（秒），之后每次翻倍
+    pub max_retry_delay: u64,        // 两次重试之间的最长等待时间（秒）
+    pub timeout: u64,                // 单次请求的超时时间（秒）
+    pub allow_private_targets: bool, // 是否允许推送到内网、回环等非公网地址，仅用于内网部署和本地调试
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NotificationConfig {
+    pub worker_interval: u64,  // 发送邮件和生成每日摘要的间隔（秒）
+    pub batch_size: i64,       // 每次最多发送的邮件数
+    pub max_attempts: i32,     // 最多发送次数，用完后标记为失败
+    pub retry_base_delay: u64, // 第一次重试前的等待时间（秒），之后每次翻倍
+    pub max_retry_delay: u64,  // 两次重试之间的最长等待时间（秒）
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CampaignConfig {
+    pub link_url: String, // 受邀人作答页面地址，邀请邮件中的链接会附加token参数
+}
+
+// 登录失败计数的存储位置：memory只保存在当前进程中，适用于测试和单实例部署
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LockoutStoreKind {
+    Memory,
+    Mysql,
 }
 
 impl Config {
@@ -38,6 +156,14 @@ impl Config {
                 .unwrap_or_else(|_| "3000".to_string())
                 .parse()
                 .unwrap_or(3000),
+            trust_proxy: env::var("TRUST_PROXY")
+                .map(|value| value == "true" || value == "1")
+                .unwrap_or(false),
+            trusted_proxy_hops: env::var("TRUSTED_PROXY_HOPS")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse::<usize>()
+                .unwrap_or(1)
+                .max(1),
         };
 
         let database = DatabaseConfig {
@@ -50,13 +176,182 @@ impl Config {
 
         let jwt = JwtConfig {
             secret: env::var("JWT_SECRET").expect("JWT_SECRET must be set"),
-            expiration: env::var("JWT_EXPIRATION").unwrap_or_else(|_| "24h".to_string()),
+            expiration: duration_var("JWT_EXPIRATION", "15m")?,
+            refresh_expiration: duration_var("JWT_REFRESH_EXPIRATION", "30d")?,
+        };
+
+        let lifecycle = LifecycleConfig {
+            close_check_interval: env::var("CLOSE_CHECK_INTERVAL")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+        };
+
+        let backend = match env::var("MAIL_BACKEND").as_deref().unwrap_or("log") {
+            "smtp" => MailBackend::Smtp,
+            "log" => MailBackend::Log,
+            "file" => MailBackend::File,
+            other => return Err(anyhow!("MAIL_BACKEND 的取值不正确: {}", other)),
+        };
+        let smtp = match backend {
+            MailBackend::Smtp => Some(SmtpConfig {
+                host: env::var("SMTP_HOST")
+                    .map_err(|_| anyhow!("MAIL_BACKEND为smtp时必须设置SMTP_HOST"))?,
+                port: env::var("SMTP_PORT")
+                    .unwrap_or_else(|_| "587".to_string())
+                    .parse()
+                    .unwrap_or(587),
+                username: env::var("SMTP_USERNAME").ok().filter(|value| !value.is_empty()),
+                password: env::var("SMTP_PASSWORD").ok().filter(|value| !value.is_empty()),
+                tls: match env::var("SMTP_TLS").as_deref().unwrap_or("starttls") {
+                    "starttls" => SmtpTls::Starttls,
+                    "tls" => SmtpTls::Tls,
+                    "none" => SmtpTls::None,
+                    other => return Err(anyhow!("SMTP_TLS 的取值不正确: {}", other)),
+                },
+            }),
+            _ => None,
+        };
+
+        let mail = MailConfig {
+            backend,
+            from: env::var("MAIL_FROM").unwrap_or_else(|_| "noreply@localhost".to_string()),
+            dir: env::var("MAIL_DIR").unwrap_or_else(|_| "mail".to_string()),
+            smtp,
+        };
+
+        let account = AccountConfig {
+            password_reset_url: env::var("PASSWORD_RESET_URL")
+                .unwrap_or_else(|_| "http://localhost:5173/reset-password".to_string()),
+            password_reset_expiration: duration_var("PASSWORD_RESET_EXPIRATION", "1h")?,
+        };
+
+        let login = LoginConfig {
+            store: match env::var("LOGIN_LOCKOUT_STORE").as_deref().unwrap_or("mysql") {
+                "memory" => LockoutStoreKind::Memory,
+                "mysql" => LockoutStoreKind::Mysql,
+                other => return Err(anyhow!("LOGIN_LOCKOUT_STORE 的取值不正确: {}", other)),
+            },
+            max_failures: env::var("LOGIN_MAX_FAILURES")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            ip_max_failures: env::var("LOGIN_IP_MAX_FAILURES")
+                .unwrap_or_else(|_| "50".to_string())
+                .parse()
+                .unwrap_or(50),
+            failure_window: duration_var("LOGIN_FAILURE_WINDOW", "15m")?,
+            lockout_duration: duration_var("LOGIN_LOCKOUT_DURATION", "15m")?,
+            max_delay: duration_var("LOGIN_MAX_DELAY", "30s")?,
+            challenge_expiration: duration_var("LOGIN_CHALLENGE_EXPIRATION", "5m")?,
+            totp_issuer: env::var("TOTP_ISSUER").unwrap_or_else(|_| "问卷星".to_string()),
+        };
+
+        let oidc = match env::var("OIDC_ISSUER") {
+            Ok(issuer) if !issuer.trim().is_empty() => Some(OidcConfig {
+                issuer: issuer.trim().trim_end_matches('/').to_string(),
+                client_id: env::var("OIDC_CLIENT_ID")
+                    .map_err(|_| anyhow!("配置了OIDC_ISSUER时必须设置OIDC_CLIENT_ID"))?,
+                client_secret: env::var("OIDC_CLIENT_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty()),
+                redirect_uri: env::var("OIDC_REDIRECT_URI")
+                    .unwrap_or_else(|_| "http://localhost:5173/sso/callback".to_string()),
+                scopes: env::var("OIDC_SCOPES")
+                    .unwrap_or_else(|_| "openid profile email".to_string()),
+                username_claim: env::var("OIDC_USERNAME_CLAIM")
+                    .unwrap_or_else(|_| "preferred_username".to_string()),
+                nickname_claim: env::var("OIDC_NICKNAME_CLAIM")
+                    .unwrap_or_else(|_| "name".to_string()),
+                email_claim: env::var("OIDC_EMAIL_CLAIM").unwrap_or_else(|_| "email".to_string()),
+                auto_provision: env::var("OIDC_AUTO_PROVISION")
+                    .map(|value| value == "true" || value == "1")
+                    .unwrap_or(true),
+                link_by_email: env::var("OIDC_LINK_BY_EMAIL")
+                    .map(|value| value == "true" || value == "1")
+                    .unwrap_or(false),
+                state_expiration: duration_var("OIDC_STATE_EXPIRATION", "10m")?,
+                skip_two_factor: env::var("OIDC_SKIP_TWO_FACTOR")
+                    .map(|value| value == "true" || value == "1")
+                    .unwrap_or(false),
+            }),
+            _ => None,
+        };
+
+        let webhook = WebhookConfig {
+            worker_interval: duration_var("WEBHOOK_WORKER_INTERVAL", "10s")?,
+            batch_size: env::var("WEBHOOK_BATCH_SIZE")
+                .unwrap_or_else(|_| "50".to_string())
+                .parse()
+                .unwrap_or(50),
+            max_attempts: env::var("WEBHOOK_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()
+                .unwrap_or(8),
+            retry_base_delay: duration_var("WEBHOOK_RETRY_BASE_DELAY", "30s")?,
+            max_retry_delay: duration_var("WEBHOOK_MAX_RETRY_DELAY", "6h")?,
+            timeout: duration_var("WEBHOOK_TIMEOUT", "10s")?,
+            allow_private_targets: env::var("WEBHOOK_ALLOW_PRIVATE_TARGETS")
+                .map(|value| value == "true" || value == "1")
+                .unwrap_or(false),
+        };
+
+        let notification = NotificationConfig {
+            worker_interval: duration_var("NOTIFICATION_WORKER_INTERVAL", "30s")?,
+            batch_size: env::var("NOTIFICATION_BATCH_SIZE")
+                .unwrap_or_else(|_| "50".to_string())
+                .parse()
+                .unwrap_or(50),
+            max_attempts: env::var("NOTIFICATION_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            retry_base_delay: duration_var("NOTIFICATION_RETRY_BASE_DELAY", "1m")?,
+            max_retry_delay: duration_var("NOTIFICATION_MAX_RETRY_DELAY", "1h")?,
+        };
+
+        let campaign = CampaignConfig {
+            link_url: env::var("CAMPAIGN_LINK_URL")
+                .unwrap_or_else(|_| "http://localhost:5173/invite".to_string()),
         };
 
         Ok(Config {
             server,
             database,
             jwt,
+            lifecycle,
+            mail,
+            account,
+            login,
+            oidc,
+            webhook,
+            notification,
+            campaign,
         })
     }
-} 
\ No newline at end of file
+}
+
+// 读取时长配置，格式为数字加单位s、m、h、d，不带单位时为秒
+fn duration_var(name: &str, default: &str) -> Result<u64> {
+    let value = env::var(name).unwrap_or_else(|_| default.to_string());
+    parse_duration(&value)
+        .filter(|seconds| *seconds > 0)
+        .ok_or_else(|| anyhow!("{} 的格式不正确: {}", name, value))
+}
+
+fn parse_duration(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit) = match value.char_indices().last()? {
+        (index, unit) if unit.is_ascii_alphabetic() => (&value[..index], unit),
+        _ => (value, 's'),
+    };
+    let seconds = match unit.to_ascii_lowercase() {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+
+    number.trim().parse::<u64>().ok()?.checked_mul(seconds)
+}
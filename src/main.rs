@@ -4,17 +4,20 @@ mod routes;
 mod services;
 mod utils;
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use axum::Server;
-use sqlx::mysql::MySqlPoolOptions;
+use sqlx::{mysql::MySqlPoolOptions, MySqlPool};
 use tokio::signal;
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{error, info};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::config::Config;
+use crate::config::{Config, WebhookConfig};
 use crate::routes::create_router;
+use crate::services::lifecycle_service::LifecycleService;
+use crate::services::notification_service::NotificationService;
+use crate::services::webhook_service::WebhookService;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -45,6 +48,18 @@ async fn main() -> anyhow::Result<()> {
     let db_pool = Arc::new(db_pool);
     let config = Arc::new(config);
 
+    // 启动后台任务，定时关闭已到截止时间的问卷并清理过期回答
+    tokio::spawn(close_expired_questionnaires(
+        db_pool.clone(),
+        config.lifecycle.close_check_interval,
+    ));
+
+    // 启动后台任务，投递Webhook推送并按退避策略重试失败的推送
+    tokio::spawn(deliver_webhooks(db_pool.clone(), config.webhook.clone()));
+
+    // 启动后台任务，生成每日摘要并发送发件箱中的通知邮件
+    tokio::spawn(send_notifications(db_pool.clone(), config.clone()));
+
     // 创建路由
     let app = create_router(config, db_pool)
         .layer(TraceLayer::new_for_http());
@@ -54,13 +69,71 @@ async fn main() -> anyhow::Result<()> {
     
     // 针对axum 0.6.x的使用方法
     Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await?;
 
     Ok(())
 }
 
+// 定时关闭已到截止时间的问卷，并清理超过保留期限的回答
+async fn close_expired_questionnaires(db: Arc<MySqlPool>, interval_secs: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+
+    loop {
+        interval.tick().await;
+
+        match LifecycleService::close_expired(&db).await {
+            Ok(0) => {}
+            Ok(count) => info!("Closed {} expired questionnaires", count),
+            Err(e) => error!("Failed to close expired questionnaires: {}", e),
+        }
+
+        match LifecycleService::purge_expired_responses(&db).await {
+            Ok(0) => {}
+            Ok(count) => info!("Purged {} responses past their retention period", count),
+            Err(e) => error!("Failed to purge expired responses: {}", e),
+        }
+    }
+}
+
+// 定时投递到期的Webhook推送
+async fn deliver_webhooks(db: Arc<MySqlPool>, config: WebhookConfig) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.worker_interval));
+
+    loop {
+        interval.tick().await;
+
+        match WebhookService::deliver_due(&db, &config).await {
+            Ok(0) => {}
+            Ok(count) => info!("Delivered {} webhook notifications", count),
+            Err(e) => error!("Failed to deliver webhook notifications: {}", e),
+        }
+    }
+}
+
+// 定时生成每日摘要，并发送发件箱中到期的邮件
+async fn send_notifications(db: Arc<MySqlPool>, config: Arc<Config>) {
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(config.notification.worker_interval));
+
+    loop {
+        interval.tick().await;
+
+        match NotificationService::enqueue_digests(&db).await {
+            Ok(0) => {}
+            Ok(count) => info!("Generated {} daily digests", count),
+            Err(e) => error!("Failed to generate daily digests: {}", e),
+        }
+
+        match NotificationService::send_due(&db, &config.mail, &config.notification).await {
+            Ok(0) => {}
+            Ok(count) => info!("Sent {} notification mails", count),
+            Err(e) => error!("Failed to send notification mails: {}", e),
+        }
+    }
+}
+
 // 处理优雅关闭信号
 async fn shutdown_signal() {
     let ctrl_c = async {
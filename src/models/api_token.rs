@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+// 个人访问令牌的权限范围，每个接口最多要求其中一种
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ApiScope {
+    #[serde(rename = "questionnaires:read")]
+    QuestionnairesRead,
+    #[serde(rename = "responses:read")]
+    ResponsesRead,
+    #[serde(rename = "responses:export")]
+    ResponsesExport,
+}
+
+impl ApiScope {
+    pub const ALL: [Self; 3] = [
+        Self::QuestionnairesRead,
+        Self::ResponsesRead,
+        Self::ResponsesExport,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::QuestionnairesRead => "questionnaires:read",
+            Self::ResponsesRead => "responses:read",
+            Self::ResponsesExport => "responses:export",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+
+    fn bit(&self) -> u8 {
+        1 << (*self as u8)
+    }
+}
+
+// 令牌拥有的权限范围，数据库中保存为逗号分隔的字符串
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ApiScopes(u8);
+
+impl ApiScopes {
+    pub fn contains(&self, scope: ApiScope) -> bool {
+        self.0 & scope.bit() != 0
+    }
+
+    // 数据库中未知的权限范围忽略
+    pub fn parse(value: &str) -> Self {
+        value
+            .split(',')
+            .filter_map(|scope| ApiScope::parse(scope.trim()))
+            .collect()
+    }
+
+    pub fn to_vec(&self) -> Vec<ApiScope> {
+        ApiScope::ALL
+            .into_iter()
+            .filter(|scope| self.contains(*scope))
+            .collect()
+    }
+
+    pub fn to_db(&self) -> String {
+        self.to_vec()
+            .iter()
+            .map(ApiScope::as_str)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl FromIterator<ApiScope> for ApiScopes {
+    fn from_iter<I: IntoIterator<Item = ApiScope>>(iter: I) -> Self {
+        Self(iter.into_iter().fold(0, |bits, scope| bits | scope.bit()))
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiTokenRequest {
+    #[validate(length(min = 1, max = 50, message = "令牌名称不能为空且长度不能超过50"))]
+    pub name: String,
+
+    #[validate(length(min = 1, message = "请至少选择一个权限范围"))]
+    pub scopes: Vec<ApiScope>,
+
+    // 有效天数，为空表示永不过期
+    #[validate(range(min = 1, max = 3650, message = "有效天数必须在1-3650之间"))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub token_prefix: String, // 令牌的前几位，便于用户辨认
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 创建令牌的结果，完整的令牌只在创建时返回一次
+#[derive(Debug, Serialize)]
+pub struct CreatedApiToken {
+    pub token: String,
+    #[serde(flatten)]
+    pub info: ApiToken,
+}
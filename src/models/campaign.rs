@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::questionnaire::QuestionnaireResponse;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCampaignRequest {
+    #[validate(length(min = 1, max = 100, message = "活动名称不能为空且长度不能超过100"))]
+    pub name: String,
+
+    // 附加在邀请和提醒邮件中的说明
+    #[validate(length(max = 2000, message = "说明长度不能超过2000"))]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Campaign {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub name: String,
+    pub message: Option<String>,
+    pub stats: CampaignStats,
+    pub created_at: DateTime<Utc>,
+}
+
+// 各阶段的受邀人数，已完成的受邀人也计入之前的阶段
+#[derive(Debug, Serialize)]
+pub struct CampaignStats {
+    pub recipients: i64,
+    pub sent: i64,
+    pub opened: i64,
+    pub started: i64,
+    pub completed: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddRecipientsRequest {
+    #[validate(length(min = 1, max = 1000, message = "每次可以添加1-1000个受邀人"), nested)]
+    pub recipients: Vec<RecipientInput>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct RecipientInput {
+    #[validate(
+        email(message = "邮箱格式不正确"),
+        length(max = 100, message = "邮箱长度不能超过100")
+    )]
+    pub email: String,
+
+    #[validate(length(max = 100, message = "姓名长度不能超过100"))]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddRecipientsResponse {
+    pub added: u64,
+    pub skipped: u64, // 已在名单中的邮箱
+}
+
+// 受邀人当前所处的阶段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecipientStatus {
+    Pending, // 尚未发送邀请
+    Sent,
+    Opened,
+    Started,
+    Completed,
+}
+
+impl RecipientStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Opened => "opened",
+            Self::Started => "started",
+            Self::Completed => "completed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(Self::Pending),
+            "sent" => Some(Self::Sent),
+            "opened" => Some(Self::Opened),
+            "started" => Some(Self::Started),
+            "completed" => Some(Self::Completed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecipientQuery {
+    pub status: Option<RecipientStatus>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CampaignRecipient {
+    pub id: i32,
+    pub email: String,
+    pub name: Option<String>,
+    pub status: RecipientStatus,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub opened_at: Option<DateTime<Utc>>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub response_id: Option<i32>, // 匿名问卷为空
+    pub reminder_count: i32,
+    pub last_reminded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecipientPage {
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub recipients: Vec<CampaignRecipient>,
+}
+
+// 发送邀请或提醒的结果
+#[derive(Debug, Serialize)]
+pub struct DispatchResponse {
+    pub queued: usize, // 加入发送队列的邮件数
+}
+
+// 受邀人打开链接时返回的内容
+#[derive(Debug, Serialize)]
+pub struct InvitationView {
+    pub name: Option<String>,
+    pub completed: bool, // 已经通过该链接提交过回答
+    pub questionnaire: QuestionnaireResponse,
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// 协作者角色：编辑者可以修改问卷，查看者只能查看问卷和回答结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CollaboratorRole {
+    Editor,
+    Viewer,
+}
+
+impl CollaboratorRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Editor => "editor",
+            Self::Viewer => "viewer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "editor" => Some(Self::Editor),
+            "viewer" => Some(Self::Viewer),
+            _ => None,
+        }
+    }
+}
+
+// 按用户名或邮箱邀请协作者，两者提供其一
+#[derive(Debug, Deserialize)]
+pub struct InviteCollaboratorRequest {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub role: CollaboratorRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCollaboratorRequest {
+    pub role: CollaboratorRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Collaborator {
+    pub user_id: i32,
+    pub username: String,
+    pub nickname: String,
+    pub role: CollaboratorRole,
+    pub status: String, // pending: 等待对方接受, accepted: 已加入
+    pub invited_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+}
+
+// 收到的协作邀请
+#[derive(Debug, Serialize)]
+pub struct Invitation {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub questionnaire_title: String,
+    pub role: CollaboratorRole,
+    pub invited_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 与我共享的问卷
+#[derive(Debug, Serialize)]
+pub struct SharedQuestionnaire {
+    pub id: i32,
+    pub title: String,
+    pub status: String,
+    pub owner: String, // 所属组织或所有者的用户名
+    pub role: CollaboratorRole,
+    pub accepted_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::questionnaire::{CreateQuestionnaireRequest, QuestionnaireResponse};
+
+// 问卷定义的文档格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefinitionFormat {
+    Json,
+    Yaml,
+    // 类似Markdown的文本格式：一行一个问题，选项以列表项表示
+    Text,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportRequest {
+    pub format: DefinitionFormat,
+    pub content: String,
+    // 只解析和验证，不创建问卷
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    pub dry_run: bool,
+    // 解析后的问卷定义，与创建问卷接口的请求格式相同
+    pub definition: CreateQuestionnaireRequest,
+    pub section_count: usize,
+    pub question_count: usize,
+    // 实际导入时为创建的问卷，预览时为空
+    pub questionnaire: Option<QuestionnaireResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DefinitionQuery {
+    pub format: DefinitionFormat,
+}
@@ -1,11 +1,15 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
+use std::collections::BTreeMap;
 use thiserror::Error;
 
+// 按字段（或问题）分组的验证错误信息
+pub type FieldErrors = BTreeMap<String, Vec<String>>;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("认证错误: {0}")]
@@ -14,8 +18,11 @@ pub enum AppError {
     #[error("数据库错误: {0}")]
     DatabaseError(#[from] sqlx::Error),
 
-    #[error("验证错误: {0}")]
-    ValidationError(String),
+    #[error("验证错误: {message}")]
+    ValidationError {
+        message: String,
+        fields: FieldErrors,
+    },
 
     #[error("找不到资源: {0}")]
     NotFoundError(String),
@@ -28,13 +35,66 @@ pub enum AppError {
 
     #[error("无效的请求: {0}")]
     BadRequestError(String),
+
+    #[error("问卷未开放: {0}")]
+    QuestionnaireNotOpen(String),
+
+    // retry_after为可以重试前需要等待的秒数，通过Retry-After响应头返回
+    #[error("请求过于频繁: {message}")]
+    TooManyRequests { message: String, retry_after: u64 },
+}
+
+impl AppError {
+    // 不针对具体字段的验证错误
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::ValidationError {
+            message: message.into(),
+            fields: FieldErrors::new(),
+        }
+    }
+
+    // 带有字段错误明细的验证错误
+    pub fn validation_fields(message: impl Into<String>, fields: FieldErrors) -> Self {
+        Self::ValidationError {
+            message: message.into(),
+            fields,
+        }
+    }
+}
+
+impl From<validator::ValidationErrors> for AppError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        let fields = errors
+            .field_errors()
+            .into_iter()
+            .map(|(field, errors)| {
+                let messages = errors
+                    .iter()
+                    .map(|e| {
+                        e.message
+                            .as_ref()
+                            .map(|m| m.to_string())
+                            .unwrap_or_else(|| e.code.to_string())
+                    })
+                    .collect();
+                (field.to_string(), messages)
+            })
+            .collect();
+
+        Self::validation_fields(errors.to_string(), fields)
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let mut fields = FieldErrors::new();
+        let mut retry_after = None;
         let (status, error_message) = match self {
             Self::AuthError(message) => (StatusCode::UNAUTHORIZED, message),
-            Self::ValidationError(message) => (StatusCode::BAD_REQUEST, message),
+            Self::ValidationError { message, fields: field_errors } => {
+                fields = field_errors;
+                (StatusCode::BAD_REQUEST, message)
+            }
             Self::NotFoundError(message) => (StatusCode::NOT_FOUND, message),
             Self::PermissionError(message) => (StatusCode::FORBIDDEN, message),
             Self::DatabaseError(e) => (
@@ -43,15 +103,30 @@ impl IntoResponse for AppError {
             ),
             Self::InternalServerError(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
             Self::BadRequestError(message) => (StatusCode::BAD_REQUEST, message),
+            Self::QuestionnaireNotOpen(message) => (StatusCode::CONFLICT, message),
+            Self::TooManyRequests { message, retry_after: seconds } => {
+                retry_after = Some(seconds);
+                (StatusCode::TOO_MANY_REQUESTS, message)
+            }
         };
 
-        let body = Json(json!({
+        let mut body = json!({
             "code": status.as_u16(),
             "success": false,
             "message": error_message,
-        }));
+        });
+
+        if !fields.is_empty() {
+            body["errors"] = json!(fields);
+        }
 
-        (status, body).into_response()
+        let mut response = (status, Json(body)).into_response();
+        if let Some(seconds) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, seconds.into());
+        }
+        response
     }
 }
 
@@ -0,0 +1,29 @@
+use serde::Deserialize;
+
+// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    // 数据文件使用数字编码，附带变量说明和SPSS导入语法，打包为zip
+    Spss,
+}
+
+// 多选题（多选题、矩阵多选题）的展开方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MultiSelectMode {
+    // 所有选中的选项合并在一列中，以分号分隔
+    #[default]
+    Joined,
+    // 每个选项一列，选中为1，未选为0
+    OneHot,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub multi_select: MultiSelectMode,
+}
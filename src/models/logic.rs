@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+// 问题的条件逻辑，以JSON形式保存在questions.logic中
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuestionLogic {
+    // 显示条件，不满足时该问题被隐藏，只能引用排在前面的问题
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_if: Option<Condition>,
+    // 跳转规则，回答本题后按顺序检查，命中第一条后跳过中间的问题
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub jumps: Vec<JumpRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JumpRule {
+    pub when: Condition,
+    pub to: JumpTarget,
+}
+
+// 跳转目标：排在后面的某个问题、某个分页的第一个问题，或直接结束问卷
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JumpTarget {
+    Question(QuestionRef),
+    Section(SectionRef),
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    All { conditions: Vec<Condition> },
+    Any { conditions: Vec<Condition> },
+    Not { condition: Box<Condition> },
+    // 问题已作答
+    Answered { question: QuestionRef },
+    // 选择了某个选项（矩阵题任意一行选择了该列也算）
+    Selected { question: QuestionRef, option: OptionRef },
+    // 数值回答与给定值比较
+    Compare {
+        question: QuestionRef,
+        operator: CompareOperator,
+        value: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareOperator {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl CompareOperator {
+    pub fn apply(self, left: f64, right: f64) -> bool {
+        match self {
+            Self::Eq => (left - right).abs() < 1e-9,
+            Self::Ne => (left - right).abs() >= 1e-9,
+            Self::Lt => left < right,
+            Self::Lte => left <= right,
+            Self::Gt => left > right,
+            Self::Gte => left >= right,
+        }
+    }
+}
+
+// 引用问题：已有问题可使用question_id，新建的问题使用其在请求中的位置（从0开始）
+// 保存后统一解析为question_id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuestionRef {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub question_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub question_index: Option<usize>,
+}
+
+// 引用分页：已有分页可使用section_id，新建的分页使用其在请求中的位置（从0开始）
+// 保存后统一解析为section_id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SectionRef {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section_index: Option<usize>,
+}
+
+// 引用选项：使用option_id或选项文本，保存后统一解析为option_id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OptionRef {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub option_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub option_text: Option<String>,
+}
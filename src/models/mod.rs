@@ -1,4 +1,17 @@
 pub mod user;
 pub mod questionnaire;
+pub mod logic;
 pub mod response;
+pub mod version;
+pub mod quota;
+pub mod export;
+pub mod definition;
+pub mod statistics;
+pub mod text_analysis;
+pub mod collaborator;
+pub mod organization;
+pub mod api_token;
+pub mod webhook;
+pub mod notification;
+pub mod campaign;
 pub mod error; 
\ No newline at end of file
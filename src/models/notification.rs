@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+// 问卷收到回答时的通知方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationMode {
+    Off,
+    Instant, // 每份回答发送一封邮件
+    Daily,   // 每天汇总发送一封摘要
+}
+
+impl NotificationMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Instant => "instant",
+            Self::Daily => "daily",
+        }
+    }
+
+    // 数据库中未知的取值视为关闭
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "instant" => Self::Instant,
+            "daily" => Self::Daily,
+            _ => Self::Off,
+        }
+    }
+}
+
+// 发件箱中邮件的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    Response,   // 问卷收到新回答
+    Receipt,    // 发给提交人的回答副本
+    Digest,     // 每日摘要
+    Invitation, // 发给受邀人的问卷邀请
+    Reminder,   // 提醒尚未回答的受邀人
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Response => "response",
+            Self::Receipt => "receipt",
+            Self::Digest => "digest",
+            Self::Invitation => "invitation",
+            Self::Reminder => "reminder",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationPreferences {
+    pub response_mode: NotificationMode, // 自己创建的问卷的默认通知方式
+    pub receipt: bool,
+    pub digest_hour: u8,
+    pub timezone: String,
+    pub questionnaires: Vec<QuestionnaireNotificationSetting>, // 单独设置过通知方式的问卷
+}
+
+// 未提供的字段保持不变
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateNotificationPreferencesRequest {
+    pub response_mode: Option<NotificationMode>,
+
+    pub receipt: Option<bool>,
+
+    #[validate(range(max = 23, message = "摘要发送时间必须在0-23之间"))]
+    pub digest_hour: Option<u8>,
+
+    #[validate(length(min = 1, max = 50, message = "时区不能为空且长度不能超过50"))]
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuestionnaireNotificationSetting {
+    pub questionnaire_id: i32,
+    pub title: String,
+    pub response_mode: NotificationMode,
+    pub overridden: bool, // 是否单独设置，否则沿用默认的通知方式
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateQuestionnaireNotificationRequest {
+    pub response_mode: NotificationMode,
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+// 组织成员角色，按权限从低到高排列
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum OrganizationRole {
+    Member, // 可以创建和编辑组织的问卷
+    Admin,  // 还可以删除问卷、管理成员和组织设置
+    Owner,  // 还可以删除组织、任命其他所有者
+}
+
+impl OrganizationRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "member" => Some(Self::Member),
+            "admin" => Some(Self::Admin),
+            "owner" => Some(Self::Owner),
+            _ => None,
+        }
+    }
+}
+
+// 创建或修改组织，设置作用于组织名下的所有问卷
+#[derive(Debug, Deserialize, Validate)]
+pub struct OrganizationRequest {
+    #[validate(length(min = 1, max = 100, message = "组织名称不能为空且长度不能超过100"))]
+    pub name: String,
+
+    // 组织中新建的问卷默认匿名收集回答
+    #[serde(default)]
+    pub anonymous_responses: bool,
+
+    // 回答的保留天数，超过后自动删除，为空表示永久保留
+    #[validate(range(min = 1, max = 36500, message = "保留天数必须在1-36500之间"))]
+    pub response_retention_days: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Organization {
+    pub id: i32,
+    pub name: String,
+    pub anonymous_responses: bool,
+    pub response_retention_days: Option<i32>,
+    pub role: OrganizationRole, // 当前用户在组织中的角色
+    pub member_count: i64,
+    pub questionnaire_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+// 按用户名或邮箱添加成员，两者提供其一
+#[derive(Debug, Deserialize)]
+pub struct AddMemberRequest {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    #[serde(default = "default_member_role")]
+    pub role: OrganizationRole,
+}
+
+fn default_member_role() -> OrganizationRole {
+    OrganizationRole::Member
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: OrganizationRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrganizationMember {
+    pub user_id: i32,
+    pub username: String,
+    pub nickname: String,
+    pub role: OrganizationRole,
+    pub joined_at: DateTime<Utc>,
+}
@@ -3,13 +3,86 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use validator::Validate;
 
+use crate::models::logic::QuestionLogic;
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Questionnaire {
     pub id: i32,
     pub title: String,
     pub description: String,
     pub is_public: bool,
-    pub creator_id: i32,
+    pub status: String, // 取值见QuestionnaireStatus
+    pub opens_at: Option<DateTime<Utc>>,
+    pub closes_at: Option<DateTime<Utc>>,
+    pub max_responses: Option<i32>,
+    pub one_per_account: bool,
+    pub one_per_device: bool,
+    pub one_per_ip: bool,
+    pub anonymous: bool,
+    pub creator_id: Option<i32>,      // 创建人注销后为空
+    pub organization_id: Option<i32>, // 所属组织，为空表示个人问卷
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// 问卷的生命周期状态，只有已发布的问卷接受回答
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionnaireStatus {
+    Draft,     // 草稿，编辑中
+    Published, // 已发布，接受回答
+    Paused,    // 暂停收集
+    Closed,    // 已关闭，不再接受回答
+    Archived,  // 已归档，不能再修改
+}
+
+impl QuestionnaireStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Published => "published",
+            Self::Paused => "paused",
+            Self::Closed => "closed",
+            Self::Archived => "archived",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "draft" => Some(Self::Draft),
+            "published" => Some(Self::Published),
+            "paused" => Some(Self::Paused),
+            "closed" => Some(Self::Closed),
+            "archived" => Some(Self::Archived),
+            _ => None,
+        }
+    }
+
+    // 允许的状态转换
+    pub fn can_transition_to(self, target: Self) -> bool {
+        matches!(
+            (self, target),
+            (Self::Draft, Self::Published)
+                | (Self::Draft, Self::Archived)
+                | (Self::Published, Self::Paused)
+                | (Self::Published, Self::Closed)
+                | (Self::Paused, Self::Published)
+                | (Self::Paused, Self::Closed)
+                | (Self::Closed, Self::Published)
+                | (Self::Closed, Self::Archived)
+                | (Self::Archived, Self::Closed)
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct QuestionSection {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub display_order: i32,
+    pub retired_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -18,10 +91,14 @@ pub struct Questionnaire {
 pub struct Question {
     pub id: i32,
     pub questionnaire_id: i32,
+    pub section_id: Option<i32>, // 所属分页
     pub title: String,
     pub question_type: String,
     pub required: bool,
     pub display_order: i32,
+    pub config: Option<String>, // 题型相关配置的JSON
+    pub logic: Option<String>,  // 显示条件和跳转规则的JSON
+    pub retired_at: Option<DateTime<Utc>>, // 非空表示该问题已在编辑中被移除，仅保留历史回答
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -31,7 +108,9 @@ pub struct QuestionOption {
     pub id: i32,
     pub question_id: i32,
     pub option_text: String,
+    pub role: String, // "option"为选项（矩阵题的列），"row"为矩阵题的行
     pub display_order: i32,
+    pub retired_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -46,18 +125,195 @@ pub struct CreateQuestionnaireRequest {
     pub description: String,
     
     pub is_public: bool,
-    
+
+    // 分页，为空时所有问题显示在同一页
+    #[serde(default)]
+    pub sections: Vec<SectionRequest>,
+
     pub questions: Vec<QuestionRequest>,
 }
 
+// 问卷所在的工作区，为空表示个人工作区
+#[derive(Debug, Default, Deserialize)]
+pub struct WorkspaceQuery {
+    pub organization_id: Option<i32>,
+}
+
+// 转移问卷的所有权，organization_id和username提供其一
+#[derive(Debug, Deserialize)]
+pub struct TransferQuestionnaireRequest {
+    pub organization_id: Option<i32>,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnonymityRequest {
+    pub anonymous: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangeStatusRequest {
+    pub status: QuestionnaireStatus,
+}
+
+// 设置接受回答的时间范围，字段为空表示不限制
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScheduleRequest {
+    #[serde(default)]
+    pub opens_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub closes_at: Option<DateTime<Utc>>,
+}
+
+// 提交限制，在提交回答的事务中检查
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct SubmissionLimits {
+    #[validate(range(min = 1, message = "回答总数上限必须大于0"))]
+    pub max_responses: Option<i32>, // 达到上限后问卷自动关闭，为空表示不限制
+    pub one_per_account: bool,
+    pub one_per_device: bool,
+    pub one_per_ip: bool,
+    #[serde(default)]
+    pub invite_only: bool, // 只接受通过邀请链接提交的回答
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SectionRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QuestionRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub id: Option<i32>,
     pub title: String,
     #[serde(rename = "type")]
-    pub question_type: String, // "text", "radio", "checkbox"
+    pub question_type: String, // 取值见QUESTION_TYPES
     pub required: bool,
-    pub options: Vec<String>,
+    #[serde(default)]
+    pub options: Vec<OptionRequest>, // 单选、多选、排序题的选项，矩阵题的列
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<OptionRequest>, // 矩阵题的行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<QuestionConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logic: Option<QuestionLogic>,
+    // 所属分页在sections中的位置（从0开始），问卷分页时必填
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<usize>,
+}
+
+// 评分题最多的档数（不含最小值），统计时需要列出每一档
+pub const MAX_RATING_STEPS: i32 = 100;
+
+// 支持的题型
+pub const QUESTION_TYPES: &[&str] = &[
+    "text",            // 文本
+    "radio",           // 单选
+    "checkbox",        // 多选
+    "rating",          // 评分（线性量表或星级）
+    "nps",             // 净推荐值，0-10分
+    "number",          // 数字
+    "date",            // 日期/时间
+    "email",           // 邮箱
+    "phone",           // 电话
+    "matrix_radio",    // 矩阵单选，每行选一列
+    "matrix_checkbox", // 矩阵多选，每行可选多列
+    "ranking",         // 排序
+];
+
+// 使用选项表保存选项的题型（矩阵题的选项为列）
+pub fn uses_options(question_type: &str) -> bool {
+    matches!(
+        question_type,
+        "radio" | "checkbox" | "ranking" | "matrix_radio" | "matrix_checkbox"
+    )
+}
+
+// 矩阵题，行同样保存在选项表中
+pub fn is_matrix(question_type: &str) -> bool {
+    matches!(question_type, "matrix_radio" | "matrix_checkbox")
+}
+
+// 以数值保存回答的题型
+pub fn is_numeric(question_type: &str) -> bool {
+    matches!(question_type, "rating" | "nps" | "number")
+}
+
+// 题型相关的配置，以JSON形式保存在questions.config中，各题型只使用其中的部分字段
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuestionConfig {
+    // 评分题、数字题的取值范围，评分题默认为1-5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    // 评分题的步长，默认为1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<f64>,
+    // 数字题是否只允许整数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integer_only: Option<bool>,
+    // 评分题样式: "scale"为线性量表, "star"为星级
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    // 量表两端的说明文字
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_label: Option<String>,
+    // 日期题的取值类型: "date", "time", "datetime"，默认为"date"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_mode: Option<String>,
+}
+
+impl QuestionConfig {
+    // 评分题的取值范围和步长
+    pub fn rating_scale(&self) -> (f64, f64, f64) {
+        (
+            self.min.unwrap_or(1.0),
+            self.max.unwrap_or(5.0),
+            self.step.unwrap_or(1.0),
+        )
+    }
+
+    // 评分题从最小值到最大值的档数，不超过MAX_RATING_STEPS
+    pub fn rating_steps(&self) -> i32 {
+        let (min, max, step) = self.rating_scale();
+        ((max - min) / step).round().clamp(0.0, MAX_RATING_STEPS as f64) as i32
+    }
+
+    pub fn date_mode(&self) -> &str {
+        self.date_mode.as_deref().unwrap_or("date")
+    }
+}
+
+// 选项既可以是纯文本（新建），也可以携带已有选项的ID（编辑时原地更新）
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OptionRequest {
+    Text(String),
+    Detailed { id: Option<i32>, text: String },
+}
+
+impl OptionRequest {
+    pub fn id(&self) -> Option<i32> {
+        match self {
+            Self::Text(_) => None,
+            Self::Detailed { id, .. } => *id,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Text(text) => text,
+            Self::Detailed { text, .. } => text,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -66,13 +322,28 @@ pub struct QuestionnaireResponse {
     pub title: String,
     pub description: String,
     pub is_public: bool,
-    pub creator_id: i32,
+    pub status: QuestionnaireStatus,
+    pub opens_at: Option<DateTime<Utc>>,
+    pub closes_at: Option<DateTime<Utc>>,
+    pub limits: SubmissionLimits,
+    pub anonymous: bool,
+    pub creator_id: Option<i32>,
+    pub organization_id: Option<i32>,
+    pub current_version_id: Option<i32>, // 当前发布的版本，未发布时为空
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub sections: Vec<SectionResponse>,
     pub questions: Vec<QuestionResponse>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionResponse {
+    pub id: i32,
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuestionResponse {
     pub id: i32,
     pub title: String,
@@ -80,6 +351,22 @@ pub struct QuestionResponse {
     pub question_type: String,
     pub required: bool,
     pub options: Vec<String>,
+    #[serde(default)]
+    pub option_items: Vec<OptionItem>,
+    #[serde(default)]
+    pub rows: Vec<OptionItem>,
+    #[serde(default)]
+    pub config: Option<QuestionConfig>,
+    #[serde(default)]
+    pub logic: Option<QuestionLogic>,
+    #[serde(default)]
+    pub section_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionItem {
+    pub id: i32,
+    pub text: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,7 +374,9 @@ pub struct QuestionnaireListItem {
     pub id: i32,
     pub title: String,
     pub description: String,
+    pub status: QuestionnaireStatus,
     pub creator: String,
+    pub organization: Option<String>, // 所属组织的名称
     pub created_at: DateTime<Utc>,
     pub response_count: i32,
 }
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// 数据库模型
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ResponseQuota {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub question_id: i32,
+    pub option_id: i32,
+    pub quota_limit: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+// API请求和响应模型
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuotaRequest {
+    pub question_id: i32,
+    pub option_id: i32,
+    pub limit: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateQuotasRequest {
+    pub quotas: Vec<QuotaRequest>, // 替换问卷现有的全部配额
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuotaStatus {
+    pub id: i32,
+    pub question_id: i32,
+    pub question_title: String,
+    pub option_id: i32,
+    pub option_text: String,
+    pub limit: i32,
+    pub filled: i32, // 已提交且选择了该选项的回答数
+    pub remaining: i32,
+}
@@ -3,13 +3,25 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use validator::Validate;
 
+use crate::models::text_analysis::TagCount;
+
 // 数据库模型
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct QuestionnaireResponse {
     pub id: i32,
     pub questionnaire_id: i32,
+    pub version_id: Option<i32>,
     pub respondent_id: Option<i32>,
+    pub status: String, // "in_progress"为草稿，"completed"为已提交
+    pub resume_token: Option<String>,
+    pub draft_answers: Option<String>, // 草稿中已保存的回答JSON
+    pub current_section_id: Option<i32>,
+    pub device_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub started_at: Option<DateTime<Utc>>, // 开始作答的时间
+    pub submitted_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -32,22 +44,81 @@ pub struct OptionResponse {
     pub id: i32,
     pub question_response_id: i32,
     pub option_id: i32,
+    pub row_option_id: Option<i32>, // 矩阵题所在的行
+    pub rank_position: Option<i32>, // 排序题中的名次
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct NumericResponse {
+    pub id: i32,
+    pub question_response_id: i32,
+    pub numeric_value: f64,
+}
+
+// 提交回答的人：登录用户、设备Cookie和IP，用于检查提交限制
+#[derive(Debug, Clone, Default)]
+pub struct Respondent {
+    pub user_id: Option<i32>,
+    pub device_id: Option<String>,
+    pub ip_address: Option<String>,
 }
 
 // API请求和响应模型
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct SubmitResponseRequest {
     pub questionnaire_id: i32,
+    #[serde(default)]
     pub answers: Vec<QuestionAnswer>,
+    // 提交草稿时携带，请求中的回答会覆盖草稿中同一问题的回答
+    #[serde(default)]
+    pub resume_token: Option<String>,
+    // 打开问卷的时间，由前端记录，用于统计完成用时
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+// 保存草稿，每次只需提交当前分页的回答，会与已保存的回答合并
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct SaveDraftRequest {
+    pub questionnaire_id: i32,
+    #[serde(default)]
+    pub answers: Vec<QuestionAnswer>,
+    #[serde(default)]
+    pub resume_token: Option<String>, // 为空时创建新草稿
+    #[serde(default)]
+    pub current_section_id: Option<i32>,
+    // 打开问卷的时间，为空时以首次保存草稿的时间为准
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DraftResponse {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub resume_token: String,
+    pub current_section_id: Option<i32>,
+    pub answers: Vec<QuestionAnswer>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QuestionAnswer {
     pub question_id: i32,
-    pub answer_type: String, // "text", "option", "options"
+    // "text"对应文本、日期、邮箱、电话题, "option"对应单选题, "options"对应多选题,
+    // "number"对应评分、NPS、数字题, "matrix"对应矩阵题, "ranking"对应排序题
+    pub answer_type: String,
     pub text_value: Option<String>,
-    pub option_ids: Option<Vec<i32>>,
+    pub option_ids: Option<Vec<i32>>, // 排序题按名次从高到低排列
     pub option_values: Option<Vec<String>>, // 用于前端提交选项文本而非ID
+    pub numeric_value: Option<f64>,
+    pub matrix_values: Option<Vec<MatrixAnswer>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatrixAnswer {
+    pub row_id: i32,
+    pub option_ids: Vec<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -63,7 +134,8 @@ pub struct SubmitResponseResponse {
 pub struct QuestionnaireStatistics {
     pub questionnaire_id: i32,
     pub title: String,
-    pub response_count: i32,
+    pub response_count: i32, // 已提交的回答数
+    pub draft_count: i32,    // 尚未提交的草稿数，不计入各题统计
     pub questions: Vec<QuestionStatistics>,
 }
 
@@ -72,8 +144,65 @@ pub struct QuestionStatistics {
     pub question_id: i32,
     pub title: String,
     pub question_type: String,
-    pub text_responses: Option<Vec<String>>,
-    pub option_counts: Option<Vec<OptionCount>>,
+    pub retired: bool, // 问题已在编辑中被移除，仅展示历史回答
+    pub answer_count: i32, // 作答人数，选项的百分比以此为基数
+    pub option_counts: Option<Vec<OptionCount>>, // 单选、多选题
+    pub numeric_summary: Option<NumericSummary>, // 评分、NPS、数字题
+    pub nps: Option<NpsSummary>,
+    pub date_summary: Option<DateSummary>,
+    pub matrix_counts: Option<Vec<MatrixRowStatistics>>,
+    pub ranking: Option<Vec<RankingStatistics>>,
+    pub tag_counts: Option<Vec<TagCount>>, // 文本题回答的人工标签
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NumericSummary {
+    pub count: i32,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+    pub distribution: Vec<ValueCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValueCount {
+    pub value: f64,
+    pub count: i32,
+    pub percentage: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NpsSummary {
+    pub promoters: i32,  // 9-10分
+    pub passives: i32,   // 7-8分
+    pub detractors: i32, // 0-6分
+    pub score: f64,      // 推荐者比例减去贬损者比例，范围-100到100
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DateSummary {
+    pub count: i32,
+    pub earliest: Option<String>,
+    pub latest: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatrixRowStatistics {
+    pub row_id: i32,
+    pub row_text: String,
+    pub answer_count: i32, // 回答了该行的人数
+    pub option_counts: Vec<OptionCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RankingStatistics {
+    pub option_id: i32,
+    pub option_text: String,
+    pub count: i32,
+    pub average_rank: f64,
+    pub first_place_count: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -81,7 +210,26 @@ pub struct OptionCount {
     pub option_id: i32,
     pub option_text: String,
     pub count: i32,
-    pub percentage: f64,
+    pub percentage: f64, // 选择人数占作答人数的比例，多选题各选项之和可超过100
+}
+
+// 文本、日期、邮箱、电话题的回答，分页获取
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TextAnswerPage {
+    pub question_id: i32,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub answers: Vec<TextAnswer>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TextAnswer {
+    pub text_response_id: i32,
+    pub response_id: i32,
+    pub text: String,
+    pub tags: Vec<String>,
+    pub submitted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -90,6 +238,7 @@ pub struct ResponseListItem {
     pub questionnaire_id: i32,
     pub respondent: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub submitted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -97,8 +246,11 @@ pub struct ResponseDetails {
     pub id: i32,
     pub questionnaire_id: i32,
     pub questionnaire_title: String,
+    pub version_id: Option<i32>, // 回答时问卷所处的版本，发布前的回答为空
+    pub version_number: Option<i32>,
     pub respondent: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub submitted_at: Option<DateTime<Utc>>,
     pub answers: Vec<AnswerDetail>,
 }
 
@@ -108,5 +260,13 @@ pub struct AnswerDetail {
     pub question_title: String,
     pub question_type: String,
     pub text_value: Option<String>,
-    pub selected_options: Option<Vec<String>>,
+    pub numeric_value: Option<f64>,
+    pub selected_options: Option<Vec<String>>, // 排序题按名次排列
+    pub matrix_answers: Option<Vec<MatrixAnswerDetail>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatrixAnswerDetail {
+    pub row: String,
+    pub selected_options: Vec<String>,
 } 
\ No newline at end of file
@@ -0,0 +1,155 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+
+// 按回答筛选：选择了option_ids中的任一选项，且数值回答在min和max之间（均包含）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnswerFilter {
+    pub question_id: i32,
+    #[serde(default)]
+    pub option_ids: Vec<i32>,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+// 统计查询：筛选条件之间为“且”的关系
+#[derive(Debug, Deserialize)]
+pub struct StatisticsQuery {
+    #[serde(default)]
+    pub filters: Vec<AnswerFilter>,
+    // 提交时间范围，包含开始时间，不包含结束时间
+    #[serde(default)]
+    pub submitted_from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub submitted_to: Option<DateTime<Utc>>,
+    // 交叉分析的维度，如“部门”，为空时只统计筛选后的结果
+    #[serde(default)]
+    pub dimension_question_id: Option<i32>,
+    // 需要统计的问题，为空时统计除维度外的所有可分类问题
+    #[serde(default)]
+    pub question_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatisticsQueryResult {
+    pub questionnaire_id: i32,
+    pub response_count: i32, // 满足筛选条件的回答数
+    pub dimension: Option<CrossTabAxis>,
+    pub tables: Vec<ContingencyTable>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrossTabAxis {
+    pub question_id: i32,
+    pub title: String,
+    pub question_type: String,
+}
+
+// 分类：单选、多选题的选项，或评分、NPS题的分值
+#[derive(Debug, Clone, Serialize)]
+pub struct Category {
+    pub option_id: Option<i32>,
+    pub value: Option<f64>,
+    pub label: String,
+}
+
+// 列联表，行为维度的分类，列为被统计问题的分类；没有维度时只有一行
+#[derive(Debug, Serialize)]
+pub struct ContingencyTable {
+    pub question_id: i32,
+    pub title: String,
+    pub question_type: String,
+    pub rows: Vec<Category>,
+    pub columns: Vec<Category>,
+    pub cells: Vec<Vec<CrossTabCell>>,
+    // 各行、各列的回答人数，多选题中一人可计入多列，因此不一定等于单元格之和
+    pub row_totals: Vec<i32>,
+    pub column_totals: Vec<i32>,
+    pub total: i32, // 两个问题都作答的回答数
+    // 两个问题都是单选类题型时才进行卡方检验
+    pub chi_square: Option<ChiSquareTest>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrossTabCell {
+    pub count: i32,
+    pub row_percentage: f64,
+    pub column_percentage: f64,
+    pub total_percentage: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChiSquareTest {
+    pub statistic: f64,
+    pub degrees_of_freedom: i32,
+    pub p_value: f64,
+    pub cramers_v: f64,
+    // 超过20%的单元格期望频数小于5时，检验结果可能不可靠
+    pub low_expected_frequency: bool,
+}
+
+// 时间序列的统计粒度，周以周一为起始
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeInterval {
+    Hour,
+    Day,
+    Week,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimelineQuery {
+    pub interval: TimeInterval,
+    // IANA时区名称，如Asia/Shanghai，决定每天、每周的起止时间
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    // 统计范围，包含开始时间，不包含结束时间；为空时取第一份和最后一份回答
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    // 需要跟踪选择比例的选项
+    pub option_id: Option<i32>,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseTimeline {
+    pub questionnaire_id: i32,
+    pub interval: TimeInterval,
+    pub timezone: String,
+    pub total: i32,
+    // 只统计记录了开始时间的回答
+    pub timed_count: i32,
+    pub median_completion_seconds: Option<f64>,
+    pub option: Option<TimelineOption>,
+    pub buckets: Vec<TimelineBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineOption {
+    pub question_id: i32,
+    pub option_id: i32,
+    pub option_text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineBucket {
+    pub start: DateTime<FixedOffset>, // 所选时区的本地时间
+    pub count: i32,
+    pub cumulative_count: i32,
+    pub median_completion_seconds: Option<f64>,
+    pub option: Option<OptionTrend>,
+}
+
+// 截至该时间段的累计选择比例，以回答了该问题的人数为基数
+#[derive(Debug, Serialize)]
+pub struct OptionTrend {
+    pub selected: i32,
+    pub answered: i32,
+    pub cumulative_selected: i32,
+    pub cumulative_answered: i32,
+    pub cumulative_percentage: f64,
+}
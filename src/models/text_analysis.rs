@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct TextAnalysisQuery {
+    // 返回出现次数最多的前top个词语和短语
+    #[serde(default = "default_top")]
+    pub top: usize,
+    // 词语的最少字数，默认为2以忽略单字
+    #[serde(default = "default_min_length")]
+    pub min_length: usize,
+}
+
+fn default_top() -> usize {
+    50
+}
+
+fn default_min_length() -> usize {
+    2
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextAnalysis {
+    pub question_id: i32,
+    pub answer_count: i32,
+    pub words: Vec<TermFrequency>,
+    // 相邻两个词语组成的短语，不跨越标点和停用词
+    pub phrases: Vec<TermFrequency>,
+    pub tags: Vec<TagCount>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TermFrequency {
+    pub term: String,
+    pub count: i32,        // 出现次数
+    pub answer_count: i32, // 包含该词语的回答数
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i32,
+    pub percentage: f64, // 占该问题作答人数的比例
+}
+
+// 在问卷的文本回答中搜索，q和tag至少需要一个
+#[derive(Debug, Deserialize)]
+pub struct TextSearchQuery {
+    // 以空格分隔的搜索词，需全部出现
+    pub q: Option<String>,
+    pub tag: Option<String>,
+    pub question_id: Option<i32>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextSearchResult {
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub matches: Vec<TextSearchMatch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextSearchMatch {
+    pub text_response_id: i32,
+    pub response_id: i32,
+    pub question_id: i32,
+    pub question_title: String,
+    pub text: String,
+    pub tags: Vec<String>,
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+// 替换文本回答的全部标签
+#[derive(Debug, Deserialize)]
+pub struct UpdateTagsRequest {
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextAnswerTags {
+    pub text_response_id: i32,
+    pub tags: Vec<String>,
+}
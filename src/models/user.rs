@@ -10,10 +10,36 @@ pub struct User {
     pub nickname: String,
     pub password_hash: String,
     pub email: Option<String>,
+    pub role: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+// 站点角色，管理员可以管理用户和所有问卷
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    User,
+    Admin,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Admin => "admin",
+        }
+    }
+
+    // 数据库中未知的取值按普通用户处理
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "admin" => Self::Admin,
+            _ => Self::User,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateUserRequest {
     #[validate(length(min = 3, max = 20, message = "用户名长度必须在3-20之间"))]
@@ -44,17 +70,226 @@ pub struct UserResponse {
     pub username: String,
     pub nickname: String,
     pub email: Option<String>,
+    pub role: UserRole,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthResponse {
     pub token: String,
+    pub refresh_token: String,
+    pub expires_in: u64, // 访问令牌的有效期（秒）
     pub user: UserResponse,
 }
 
+// 登录结果：未开启两步验证时直接返回令牌，否则返回登录挑战，
+// 客户端需使用挑战令牌和验证码调用两步验证接口完成登录
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    TwoFactorRequired {
+        challenge_token: String,
+        expires_in: u64, // 挑战令牌的有效期（秒）
+    },
+}
+
+// code为验证器应用中的6位验证码或一次性恢复码
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorSetupRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    pub code: String,
+}
+
+// 恢复码只在生成时返回一次
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub recovery_codes_remaining: i64,
+}
+
+// 单点登录的跳转地址，state在expires_in秒内有效
+#[derive(Debug, Serialize)]
+pub struct SsoAuthorizeResponse {
+    pub authorization_url: String,
+    pub expires_in: u64,
+}
+
+// 身份提供方跳转回前端时携带的参数
+#[derive(Debug, Deserialize)]
+pub struct SsoCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
+
+// 单点登录回调的结果：发起登录时返回令牌，账号开启了两步验证时与密码登录一样返回登录挑战，
+// 已登录用户发起关联时返回关联的身份
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SsoCallbackResponse {
+    Authenticated(AuthResponse),
+    TwoFactorRequired {
+        challenge_token: String,
+        expires_in: u64, // 挑战令牌的有效期（秒）
+    },
+    Linked(UserIdentity),
+}
+
+// 关联到账号的外部身份
+#[derive(Debug, Serialize)]
+pub struct UserIdentity {
+    pub id: i32,
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+// 刷新后的令牌，旧的刷新令牌随即作废
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogoutAllResponse {
+    pub revoked_sessions: u64,
+}
+
+// 修改个人资料，未提供的字段保持不变
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(length(min = 1, max = 50, message = "昵称不能为空且长度不能超过50"))]
+    pub nickname: Option<String>,
+
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+
+    #[validate(length(min = 6, message = "密码长度不能少于6个字符"))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+
+    #[validate(length(min = 6, message = "密码长度不能少于6个字符"))]
+    pub new_password: String,
+}
+
+// 注销账号时对名下问卷的处理方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OwnedQuestionnaireAction {
+    Delete,   // 删除问卷及其全部回答
+    Transfer, // 转交给transfer_to指定的用户
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+    // 名下有问卷时必须指定
+    pub questionnaires: Option<OwnedQuestionnaireAction>,
+    // 接收问卷的用户名
+    pub transfer_to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteAccountResponse {
+    pub deleted_questionnaires: u64,
+    pub transferred_questionnaires: u64,
+}
+
+// 管理员查看的用户列表
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminUserItem {
+    pub id: i32,
+    pub username: String,
+    pub nickname: String,
+    pub email: Option<String>,
+    pub role: UserRole,
+    pub questionnaire_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminUserListResponse {
+    pub items: Vec<AdminUserItem>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub role: UserRole,
+}
+
+// 一次登录尝试，failure_reason为invalid_credentials、invalid_two_factor、locked或throttled
+#[derive(Debug, Serialize)]
+pub struct LoginRecord {
+    pub success: bool,
+    pub failure_reason: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginHistoryQuery {
+    pub limit: Option<i64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // 用户ID
+    pub sid: i32,    // 登录会话ID
     pub exp: usize,  // 过期时间
     pub iat: usize,  // 颁发时间
 }
@@ -66,6 +301,7 @@ impl From<User> for UserResponse {
             username: user.username,
             nickname: user.nickname,
             email: user.email,
+            role: UserRole::parse(&user.role),
         }
     }
 } 
\ No newline at end of file
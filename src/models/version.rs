@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::models::questionnaire::{OptionItem, QuestionResponse, SectionResponse};
+
+// 数据库模型
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct QuestionnaireVersion {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub version_number: i32,
+    pub title: String,
+    pub description: String,
+    pub snapshot: String, // 问题和选项的JSON快照
+    pub sections: Option<String>, // 分页的JSON快照
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+// API请求和响应模型
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionListItem {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub version_number: i32,
+    pub title: String,
+    pub is_current: bool,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub response_count: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionDetail {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub version_number: i32,
+    pub title: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    pub sections: Vec<SectionResponse>,
+    pub questions: Vec<QuestionResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VersionDiffQuery {
+    pub from: i32, // 版本号
+    pub to: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionDiff {
+    pub questionnaire_id: i32,
+    pub from_version: i32,
+    pub to_version: i32,
+    pub changes: Vec<FieldChange>, // 问卷标题、描述的变化
+    pub added_questions: Vec<QuestionResponse>,
+    pub removed_questions: Vec<QuestionResponse>,
+    pub modified_questions: Vec<QuestionDiff>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuestionDiff {
+    pub question_id: i32,
+    pub title: String,
+    pub changes: Vec<FieldChange>,
+    pub added_options: Vec<OptionItem>,
+    pub removed_options: Vec<OptionItem>,
+    pub renamed_options: Vec<OptionRename>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OptionRename {
+    pub option_id: i32,
+    pub from: String,
+    pub to: String,
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::questionnaire::QuestionnaireStatus;
+use crate::models::response::{ResponseDetails, SubmitResponseResponse};
+
+// 可以订阅的事件
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WebhookEvent {
+    #[serde(rename = "response.submitted")]
+    ResponseSubmitted,
+    #[serde(rename = "questionnaire.published")]
+    QuestionnairePublished,
+    #[serde(rename = "questionnaire.closed")]
+    QuestionnaireClosed,
+}
+
+impl WebhookEvent {
+    pub const ALL: [Self; 3] = [
+        Self::ResponseSubmitted,
+        Self::QuestionnairePublished,
+        Self::QuestionnaireClosed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ResponseSubmitted => "response.submitted",
+            Self::QuestionnairePublished => "questionnaire.published",
+            Self::QuestionnaireClosed => "questionnaire.closed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.as_str() == value)
+    }
+
+    // 数据库中保存为逗号分隔的字符串，未知的事件忽略
+    pub fn parse_list(value: &str) -> Vec<Self> {
+        value
+            .split(',')
+            .filter_map(|event| Self::parse(event.trim()))
+            .collect()
+    }
+
+    pub fn join(events: &[Self]) -> String {
+        Self::ALL
+            .into_iter()
+            .filter(|event| events.contains(event))
+            .map(|event| event.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWebhookRequest {
+    #[validate(length(min = 1, max = 500, message = "地址不能为空且长度不能超过500"))]
+    pub url: String,
+
+    #[validate(length(min = 1, message = "请至少订阅一个事件"))]
+    pub events: Vec<WebhookEvent>,
+
+    // 签名密钥，为空时自动生成
+    #[validate(length(min = 16, max = 100, message = "签名密钥长度必须在16-100之间"))]
+    pub secret: Option<String>,
+}
+
+// 未提供的字段保持不变
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateWebhookRequest {
+    #[validate(length(min = 1, max = 500, message = "地址不能为空且长度不能超过500"))]
+    pub url: Option<String>,
+
+    #[validate(length(min = 1, message = "请至少订阅一个事件"))]
+    pub events: Option<Vec<WebhookEvent>>,
+
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Webhook {
+    pub id: i32,
+    pub questionnaire_id: i32,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// 创建订阅的结果，签名密钥只在创建时返回一次
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    pub secret: String,
+    #[serde(flatten)]
+    pub webhook: Webhook,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending, // 等待投递或重试
+    Delivered,
+    Failed, // 重试次数用完仍未成功，可以手动重新投递
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(Self::Pending),
+            "delivered" => Some(Self::Delivered),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub status: Option<DeliveryStatus>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub delivery_uuid: String,
+    pub event: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>, // 仅pending状态有意义
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryPage {
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub deliveries: Vec<WebhookDelivery>,
+}
+
+// 推送详情，包括推送内容和每次投递的结果
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveryDetail {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub payload: serde_json::Value,
+    pub attempt_log: Vec<DeliveryAttempt>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryAttempt {
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub response_body: Option<String>,
+    pub duration_ms: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+// 推送的请求体，id与X-Webhook-Delivery请求头相同
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a, T: Serialize> {
+    pub id: &'a str,
+    pub event: WebhookEvent,
+    pub questionnaire_id: i32,
+    pub created_at: DateTime<Utc>,
+    pub data: &'a T,
+}
+
+// response.submitted事件的数据
+#[derive(Debug, Serialize)]
+pub struct ResponseSubmittedData<'a> {
+    pub submission: &'a SubmitResponseResponse,
+    pub response: &'a ResponseDetails,
+}
+
+// questionnaire.published和questionnaire.closed事件的数据
+#[derive(Debug, Serialize)]
+pub struct QuestionnaireEventData {
+    pub id: i32,
+    pub title: String,
+    pub status: QuestionnaireStatus,
+    // 触发状态变化的原因：manual为手动变更，schedule为到达截止时间，response_cap为回答数达到上限
+    pub trigger: &'static str,
+}
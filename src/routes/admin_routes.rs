@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    middleware,
+    routing::{get, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+
+use crate::config::Config;
+use crate::models::error::AppResult;
+use crate::models::user::UpdateRoleRequest;
+use crate::services::admin_service::AdminService;
+use crate::utils::auth::{auth_middleware, AuthState, CurrentUser};
+use crate::utils::response::ApiResponse;
+
+// 定义应用程序状态
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub db: Arc<MySqlPool>,
+}
+
+// 为AppState实现FromRef，使CurrentUser可以从中提取Config和数据库连接池
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<MySqlPool> {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PaginationQuery {
+    page: Option<i64>,
+    page_size: Option<i64>,
+    search: Option<String>,
+}
+
+// 获取用户列表
+async fn list_users(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Query(query): Query<PaginationQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let service = AdminService::new(state.db, state.config);
+    let users = service
+        .list_users(current_user.0, query.search, page, page_size)
+        .await?;
+
+    Ok(ApiResponse::success(users, "获取用户列表成功"))
+}
+
+// 设置用户角色
+async fn update_user_role(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(user_id): Path<i32>,
+    Json(req): Json<UpdateRoleRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let role = req.role;
+
+    let service = AdminService::new(state.db, state.config);
+    service.update_role(current_user.0, user_id, req).await?;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({"id": user_id, "role": role}),
+        "用户角色修改成功",
+    ))
+}
+
+// 创建管理路由，所有接口都需要管理员权限
+pub fn routes(config: Arc<Config>, db: Arc<MySqlPool>) -> Router {
+    let state = AppState { config: config.clone(), db: db.clone() };
+
+    Router::new()
+        .route("/users", get(list_users))
+        .route("/users/:id/role", put(update_user_role))
+        .route_layer(middleware::from_fn_with_state(
+            AuthState { config, db },
+            auth_middleware,
+        ))
+        .with_state(state)
+}
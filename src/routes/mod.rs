@@ -1,6 +1,8 @@
 mod user_routes;
 mod questionnaire_routes;
 mod response_routes;
+mod admin_routes;
+mod org_routes;
 
 use axum::Router;
 use axum::http::{Method, HeaderName, HeaderValue};
@@ -42,5 +44,7 @@ pub fn create_router(config: Arc<Config>, db_pool: Arc<MySqlPool>) -> Router {
             questionnaire_routes::routes(config.clone(), db_pool.clone()),
         )
         .nest("/responses", response_routes::routes(config.clone(), db_pool.clone()))
+        .nest("/admin", admin_routes::routes(config.clone(), db_pool.clone()))
+        .nest("/organizations", org_routes::routes(config.clone(), db_pool.clone()))
         .layer(cors)
 } 
\ No newline at end of file
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRef, Path, State},
+    middleware,
+    routing::{get, put},
+    Json, Router,
+};
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::config::Config;
+use crate::models::error::AppResult;
+use crate::models::organization::{AddMemberRequest, OrganizationRequest, UpdateMemberRequest};
+use crate::services::organization_service::OrganizationService;
+use crate::utils::auth::{auth_middleware, AuthState, CurrentUser};
+use crate::utils::response::ApiResponse;
+
+// 定义应用程序状态
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub db: Arc<MySqlPool>,
+}
+
+// 为AppState实现FromRef，使CurrentUser可以从中提取Config和数据库连接池
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<MySqlPool> {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+// 创建组织
+async fn create_organization(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<OrganizationRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = OrganizationService::new(state.db, state.config);
+    let organization = service.create(current_user.0, req).await?;
+
+    Ok(ApiResponse::success(organization, "组织创建成功"))
+}
+
+// 获取我加入的组织
+async fn list_organizations(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = OrganizationService::new(state.db, state.config);
+    let organizations = service.list(current_user.0).await?;
+
+    Ok(ApiResponse::success(organizations, "获取组织列表成功"))
+}
+
+// 获取组织详情
+async fn get_organization(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = OrganizationService::new(state.db, state.config);
+    let organization = service.get(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(organization, "获取组织成功"))
+}
+
+// 修改组织名称和设置
+async fn update_organization(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<OrganizationRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = OrganizationService::new(state.db, state.config);
+    let organization = service.update(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(organization, "组织更新成功"))
+}
+
+// 删除组织
+async fn delete_organization(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = OrganizationService::new(state.db, state.config);
+    service.delete(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(serde_json::json!({"id": id}), "组织删除成功"))
+}
+
+// 获取组织成员
+async fn list_members(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = OrganizationService::new(state.db, state.config);
+    let members = service.members(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(members, "获取成员列表成功"))
+}
+
+// 添加组织成员
+async fn add_member(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<AddMemberRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = OrganizationService::new(state.db, state.config);
+    let member = service.add_member(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(member, "成员添加成功"))
+}
+
+// 修改成员角色
+async fn update_member(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, user_id)): Path<(i32, i32)>,
+    Json(req): Json<UpdateMemberRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = OrganizationService::new(state.db, state.config);
+    let member = service.update_member(current_user.0, id, user_id, req).await?;
+
+    Ok(ApiResponse::success(member, "成员角色修改成功"))
+}
+
+// 移除成员或退出组织
+async fn remove_member(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = OrganizationService::new(state.db, state.config);
+    service.remove_member(current_user.0, id, user_id).await?;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({"organization_id": id, "user_id": user_id}),
+        "成员移除成功",
+    ))
+}
+
+// 创建组织路由，所有接口都需要登录
+pub fn routes(config: Arc<Config>, db: Arc<MySqlPool>) -> Router {
+    let state = AppState { config: config.clone(), db: db.clone() };
+
+    Router::new()
+        .route("/", get(list_organizations).post(create_organization))
+        .route(
+            "/:id",
+            get(get_organization)
+                .put(update_organization)
+                .delete(delete_organization),
+        )
+        .route("/:id/members", get(list_members).post(add_member))
+        .route(
+            "/:id/members/:user_id",
+            put(update_member).delete(remove_member),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            AuthState { config, db },
+            auth_middleware,
+        ))
+        .with_state(state)
+}
@@ -2,19 +2,39 @@ use std::sync::Arc;
 
 use axum::{
     extract::{FromRef, Path, Query, State},
+    http::header,
     middleware,
     routing::{delete, get, post, put},
-    Json, Router,
+    Extension, Json, Router,
 };
 use serde::Deserialize;
 use sqlx::MySqlPool;
 use validator::Validate;
 
 use crate::config::Config;
-use crate::models::error::{AppError, AppResult};
-use crate::models::questionnaire::CreateQuestionnaireRequest;
+use crate::models::api_token::ApiScope;
+use crate::models::campaign::{AddRecipientsRequest, CreateCampaignRequest, RecipientQuery};
+use crate::models::collaborator::{InviteCollaboratorRequest, UpdateCollaboratorRequest};
+use crate::models::definition::{DefinitionFormat, DefinitionQuery, ImportRequest};
+use crate::models::error::AppResult;
+use crate::models::notification::UpdateQuestionnaireNotificationRequest;
+use crate::models::questionnaire::{
+    AnonymityRequest, ChangeStatusRequest, CreateQuestionnaireRequest, QuestionnaireStatus,
+    ScheduleRequest, SubmissionLimits, TransferQuestionnaireRequest, WorkspaceQuery,
+};
+use crate::models::quota::UpdateQuotasRequest;
+use crate::models::version::VersionDiffQuery;
+use crate::models::webhook::{CreateWebhookRequest, DeliveryQuery, UpdateWebhookRequest};
+use crate::services::campaign_service::CampaignService;
+use crate::services::collaborator_service::CollaboratorService;
+use crate::services::definition_service::DefinitionService;
+use crate::services::lifecycle_service::LifecycleService;
+use crate::services::notification_service::NotificationService;
 use crate::services::questionnaire_service::QuestionnaireService;
-use crate::utils::auth::{auth_middleware, CurrentUser};
+use crate::services::quota_service::QuotaService;
+use crate::services::version_service::VersionService;
+use crate::services::webhook_service::WebhookService;
+use crate::utils::auth::{auth_middleware, AuthState, CurrentUser};
 use crate::utils::response::ApiResponse;
 
 // 定义应用程序状态
@@ -24,13 +44,19 @@ pub struct AppState {
     pub db: Arc<MySqlPool>,
 }
 
-// 为AppState实现FromRef，使CurrentUser可以从中提取Config
+// 为AppState实现FromRef，使CurrentUser可以从中提取Config和数据库连接池
 impl FromRef<AppState> for Arc<Config> {
     fn from_ref(state: &AppState) -> Self {
         state.config.clone()
     }
 }
 
+impl FromRef<AppState> for Arc<MySqlPool> {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct PaginationQuery {
     page: Option<i64>,
@@ -38,19 +64,21 @@ struct PaginationQuery {
     search: Option<String>,
 }
 
-// 创建问卷
+// 创建问卷，指定organization_id时创建在组织中
 async fn create_questionnaire(
     State(state): State<AppState>,
     current_user: CurrentUser,
+    Query(workspace): Query<WorkspaceQuery>,
     Json(req): Json<CreateQuestionnaireRequest>,
 ) -> AppResult<impl axum::response::IntoResponse> {
     // 验证请求
-    req.validate()
-        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    req.validate()?;
 
     // 创建问卷
     let service = QuestionnaireService::new(state.db, state.config);
-    let questionnaire = service.create_questionnaire(current_user.0, req).await?;
+    let questionnaire = service
+        .create_questionnaire(current_user.0, workspace.organization_id, req)
+        .await?;
 
     Ok(ApiResponse::success(questionnaire, "问卷创建成功"))
 }
@@ -63,8 +91,7 @@ async fn update_questionnaire(
     Json(req): Json<CreateQuestionnaireRequest>,
 ) -> AppResult<impl axum::response::IntoResponse> {
     // 验证请求
-    req.validate()
-        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    req.validate()?;
 
     // 更新问卷
     let service = QuestionnaireService::new(state.db, state.config);
@@ -73,6 +100,50 @@ async fn update_questionnaire(
     Ok(ApiResponse::success(questionnaire, "问卷更新成功"))
 }
 
+// 从JSON、YAML或文本格式导入问卷，dry_run为true时只预览不创建
+async fn import_questionnaire(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Query(workspace): Query<WorkspaceQuery>,
+    Json(req): Json<ImportRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let dry_run = req.dry_run;
+
+    let service = DefinitionService::new(state.db, state.config);
+    let result = service
+        .import(current_user.0, workspace.organization_id, req)
+        .await?;
+
+    let message = if dry_run { "问卷定义验证通过" } else { "问卷导入成功" };
+    Ok(ApiResponse::success(result, message))
+}
+
+// 导出问卷定义
+async fn export_definition(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Query(query): Query<DefinitionQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = DefinitionService::new(state.db, state.config);
+    let content = service.export(current_user.0, id, query.format).await?;
+
+    let (content_type, extension) = match query.format {
+        DefinitionFormat::Json => ("application/json; charset=utf-8", "json"),
+        DefinitionFormat::Yaml => ("application/yaml; charset=utf-8", "yaml"),
+        DefinitionFormat::Text => ("text/plain; charset=utf-8", "txt"),
+    };
+    let headers = [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"questionnaire_{}.{}\"", id, extension),
+        ),
+    ];
+
+    Ok((headers, content))
+}
+
 // 获取问卷详情
 async fn get_questionnaire(
     State(state): State<AppState>,
@@ -84,18 +155,19 @@ async fn get_questionnaire(
     Ok(ApiResponse::success(questionnaire, "获取问卷成功"))
 }
 
-// 获取我的问卷列表
+// 获取我的问卷列表，指定organization_id时返回组织中的问卷
 async fn get_my_questionnaires(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Query(query): Query<PaginationQuery>,
+    Query(workspace): Query<WorkspaceQuery>,
 ) -> AppResult<impl axum::response::IntoResponse> {
     let page = query.page.unwrap_or(1);
     let page_size = query.page_size.unwrap_or(10);
 
     let service = QuestionnaireService::new(state.db, state.config);
     let questionnaires = service
-        .get_user_questionnaires(current_user.0, page, page_size)
+        .get_user_questionnaires(current_user.0, workspace.organization_id, page, page_size)
         .await?;
 
     Ok(ApiResponse::success(questionnaires, "获取我的问卷列表成功"))
@@ -132,18 +204,630 @@ async fn delete_questionnaire(
     ))
 }
 
+// 将问卷转移到组织或其他用户
+async fn transfer_questionnaire(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<TransferQuestionnaireRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = QuestionnaireService::new(state.db, state.config);
+    let questionnaire = service.transfer(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(questionnaire, "问卷转移成功"))
+}
+
+// 设置问卷是否匿名收集回答
+async fn update_anonymity(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<AnonymityRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = QuestionnaireService::new(state.db, state.config);
+    let questionnaire = service.update_anonymity(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(questionnaire, "匿名设置成功"))
+}
+
+// 发布问卷，首次发布时生成版本
+async fn publish_questionnaire(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = LifecycleService::new(state.db, state.config);
+    let questionnaire = service
+        .change_status(current_user.0, id, QuestionnaireStatus::Published)
+        .await?;
+
+    Ok(ApiResponse::success(questionnaire, "问卷发布成功"))
+}
+
+// 变更问卷状态
+async fn change_status(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<ChangeStatusRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = LifecycleService::new(state.db, state.config);
+    let questionnaire = service.change_status(current_user.0, id, req.status).await?;
+
+    Ok(ApiResponse::success(questionnaire, "问卷状态更新成功"))
+}
+
+// 设置问卷接受回答的时间范围
+async fn update_schedule(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<ScheduleRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = LifecycleService::new(state.db, state.config);
+    let questionnaire = service.update_schedule(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(questionnaire, "问卷时间设置成功"))
+}
+
+// 设置问卷的提交限制
+async fn update_limits(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<SubmissionLimits>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = QuotaService::new(state.db, state.config);
+    let questionnaire = service.update_limits(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(questionnaire, "提交限制设置成功"))
+}
+
+// 获取问卷的选项配额
+async fn get_quotas(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = QuotaService::new(state.db, state.config);
+    let quotas = service.get_quotas(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(quotas, "获取配额成功"))
+}
+
+// 设置问卷的选项配额
+async fn update_quotas(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateQuotasRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = QuotaService::new(state.db, state.config);
+    let quotas = service.update_quotas(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(quotas, "配额设置成功"))
+}
+
+// 获取问卷的版本列表
+async fn list_versions(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = VersionService::new(state.db, state.config);
+    let versions = service.list_versions(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(versions, "获取问卷版本列表成功"))
+}
+
+// 获取指定版本的问卷结构
+async fn get_version(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, version_number)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = VersionService::new(state.db, state.config);
+    let version = service.get_version(current_user.0, id, version_number).await?;
+
+    Ok(ApiResponse::success(version, "获取问卷版本成功"))
+}
+
+// 比较两个版本
+async fn diff_versions(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Query(query): Query<VersionDiffQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = VersionService::new(state.db, state.config);
+    let diff = service
+        .diff_versions(current_user.0, id, query.from, query.to)
+        .await?;
+
+    Ok(ApiResponse::success(diff, "比较问卷版本成功"))
+}
+
+// 恢复到指定版本
+async fn restore_version(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, version_number)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = VersionService::new(state.db, state.config);
+    let questionnaire = service
+        .restore_version(current_user.0, id, version_number)
+        .await?;
+
+    Ok(ApiResponse::success(questionnaire, "问卷版本恢复成功"))
+}
+
+// 获取问卷的协作者
+async fn list_collaborators(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CollaboratorService::new(state.db, state.config);
+    let collaborators = service.list(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(collaborators, "获取协作者列表成功"))
+}
+
+// 邀请协作者
+async fn invite_collaborator(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<InviteCollaboratorRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CollaboratorService::new(state.db, state.config);
+    let collaborator = service.invite(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(collaborator, "邀请已发送"))
+}
+
+// 修改协作者的角色
+async fn update_collaborator(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, user_id)): Path<(i32, i32)>,
+    Json(req): Json<UpdateCollaboratorRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CollaboratorService::new(state.db, state.config);
+    let collaborator = service
+        .update_role(current_user.0, id, user_id, req)
+        .await?;
+
+    Ok(ApiResponse::success(collaborator, "协作者角色修改成功"))
+}
+
+// 移除协作者，协作者移除自己即退出协作
+async fn remove_collaborator(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CollaboratorService::new(state.db, state.config);
+    service.remove(current_user.0, id, user_id).await?;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({"questionnaire_id": id, "user_id": user_id}),
+        "协作者已移除",
+    ))
+}
+
+// 获取与我共享的问卷
+async fn get_shared_questionnaires(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CollaboratorService::new(state.db, state.config);
+    let questionnaires = service.shared_questionnaires(current_user.0).await?;
+
+    Ok(ApiResponse::success(questionnaires, "获取共享问卷列表成功"))
+}
+
+// 获取我收到的协作邀请
+async fn get_invitations(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CollaboratorService::new(state.db, state.config);
+    let invitations = service.invitations(current_user.0).await?;
+
+    Ok(ApiResponse::success(invitations, "获取协作邀请成功"))
+}
+
+// 接受协作邀请
+async fn accept_invitation(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CollaboratorService::new(state.db, state.config);
+    service.accept(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(serde_json::json!({"id": id}), "已接受邀请"))
+}
+
+// 拒绝协作邀请
+async fn decline_invitation(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CollaboratorService::new(state.db, state.config);
+    service.decline(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(serde_json::json!({"id": id}), "已拒绝邀请"))
+}
+
+// 获取我对问卷的通知设置
+async fn get_notification_setting(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = NotificationService::new(state.db);
+    let setting = service.get_questionnaire_setting(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(setting, "获取通知设置成功"))
+}
+
+// 单独设置问卷的通知方式
+async fn update_notification_setting(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateQuestionnaireNotificationRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = NotificationService::new(state.db);
+    let setting = service
+        .update_questionnaire_setting(current_user.0, id, req)
+        .await?;
+
+    Ok(ApiResponse::success(setting, "通知设置已保存"))
+}
+
+// 恢复使用默认的通知方式
+async fn reset_notification_setting(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = NotificationService::new(state.db);
+    let setting = service.reset_questionnaire_setting(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(setting, "已恢复默认的通知设置"))
+}
+
+// 获取问卷的Webhook订阅
+async fn list_webhooks(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = WebhookService::new(state.db, state.config);
+    let webhooks = service.list(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(webhooks, "获取Webhook列表成功"))
+}
+
+// 创建Webhook订阅
+async fn create_webhook(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<CreateWebhookRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = WebhookService::new(state.db, state.config);
+    let webhook = service.create(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(webhook, "Webhook创建成功，请妥善保存签名密钥"))
+}
+
+// 修改Webhook订阅
+async fn update_webhook(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, webhook_id)): Path<(i32, i32)>,
+    Json(req): Json<UpdateWebhookRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = WebhookService::new(state.db, state.config);
+    let webhook = service.update(current_user.0, id, webhook_id, req).await?;
+
+    Ok(ApiResponse::success(webhook, "Webhook修改成功"))
+}
+
+// 删除Webhook订阅
+async fn delete_webhook(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, webhook_id)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = WebhookService::new(state.db, state.config);
+    service.delete(current_user.0, id, webhook_id).await?;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({"id": webhook_id}),
+        "Webhook删除成功",
+    ))
+}
+
+// 获取Webhook的推送记录
+async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, webhook_id)): Path<(i32, i32)>,
+    Query(query): Query<DeliveryQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = WebhookService::new(state.db, state.config);
+    let deliveries = service
+        .list_deliveries(current_user.0, id, webhook_id, query)
+        .await?;
+
+    Ok(ApiResponse::success(deliveries, "获取推送记录成功"))
+}
+
+// 获取推送详情
+async fn get_webhook_delivery(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, webhook_id, delivery_id)): Path<(i32, i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = WebhookService::new(state.db, state.config);
+    let delivery = service
+        .get_delivery(current_user.0, id, webhook_id, delivery_id)
+        .await?;
+
+    Ok(ApiResponse::success(delivery, "获取推送详情成功"))
+}
+
+// 重新投递推送
+async fn redeliver_webhook_delivery(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, webhook_id, delivery_id)): Path<(i32, i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = WebhookService::new(state.db, state.config);
+    let delivery = service
+        .redeliver(current_user.0, id, webhook_id, delivery_id)
+        .await?;
+
+    Ok(ApiResponse::success(delivery, "推送已重新加入投递队列"))
+}
+
+// 获取问卷的邀请活动
+async fn list_campaigns(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    let campaigns = service.list(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(campaigns, "获取邀请活动列表成功"))
+}
+
+// 创建邀请活动
+async fn create_campaign(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(req): Json<CreateCampaignRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = CampaignService::new(state.db, state.config);
+    let campaign = service.create(current_user.0, id, req).await?;
+
+    Ok(ApiResponse::success(campaign, "邀请活动创建成功"))
+}
+
+// 获取邀请活动详情
+async fn get_campaign(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, campaign_id)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    let campaign = service.get(current_user.0, id, campaign_id).await?;
+
+    Ok(ApiResponse::success(campaign, "获取邀请活动成功"))
+}
+
+// 删除邀请活动
+async fn delete_campaign(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, campaign_id)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    service.delete(current_user.0, id, campaign_id).await?;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({"id": campaign_id}),
+        "邀请活动删除成功",
+    ))
+}
+
+// 获取受邀人名单
+async fn list_campaign_recipients(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, campaign_id)): Path<(i32, i32)>,
+    Query(query): Query<RecipientQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    let recipients = service
+        .list_recipients(current_user.0, id, campaign_id, query)
+        .await?;
+
+    Ok(ApiResponse::success(recipients, "获取受邀人名单成功"))
+}
+
+// 添加受邀人
+async fn add_campaign_recipients(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, campaign_id)): Path<(i32, i32)>,
+    Json(req): Json<AddRecipientsRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = CampaignService::new(state.db, state.config);
+    let result = service
+        .add_recipients(current_user.0, id, campaign_id, req)
+        .await?;
+
+    Ok(ApiResponse::success(result, "受邀人添加成功"))
+}
+
+// 移除受邀人
+async fn remove_campaign_recipient(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, campaign_id, recipient_id)): Path<(i32, i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    service
+        .remove_recipient(current_user.0, id, campaign_id, recipient_id)
+        .await?;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({"id": recipient_id}),
+        "受邀人移除成功",
+    ))
+}
+
+// 发送邀请邮件
+async fn send_campaign(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, campaign_id)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    let result = service.send(current_user.0, id, campaign_id).await?;
+
+    Ok(ApiResponse::success(result, "邀请邮件已加入发送队列"))
+}
+
+// 提醒尚未回答的受邀人
+async fn remind_campaign(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((id, campaign_id)): Path<(i32, i32)>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    let result = service.remind(current_user.0, id, campaign_id).await?;
+
+    Ok(ApiResponse::success(result, "提醒邮件已加入发送队列"))
+}
+
 // 创建问卷路由
 pub fn routes(config: Arc<Config>, db: Arc<MySqlPool>) -> Router {
     let state = AppState { config: config.clone(), db: db.clone() };
     
-    // 需要认证的路由
+    // 需要认证的路由，带有Extension(ApiScope)的路由也接受具有该权限的个人访问令牌
     let authenticated_routes = Router::new()
         .route("/", post(create_questionnaire))
         .route("/:id", put(update_questionnaire))
-        .route("/my", get(get_my_questionnaires))
+        .route(
+            "/my",
+            get(get_my_questionnaires).route_layer(Extension(ApiScope::QuestionnairesRead)),
+        )
+        .route("/import", post(import_questionnaire))
+        .route(
+            "/:id/definition",
+            get(export_definition).route_layer(Extension(ApiScope::QuestionnairesRead)),
+        )
         .route("/:id", delete(delete_questionnaire))
+        .route("/:id/transfer", post(transfer_questionnaire))
+        .route("/:id/anonymity", put(update_anonymity))
+        .route("/:id/publish", post(publish_questionnaire))
+        .route("/:id/status", post(change_status))
+        .route("/:id/schedule", put(update_schedule))
+        .route("/:id/limits", put(update_limits))
+        .route(
+            "/:id/quotas",
+            get(get_quotas)
+                .route_layer(Extension(ApiScope::QuestionnairesRead))
+                .put(update_quotas),
+        )
+        .route(
+            "/:id/versions",
+            get(list_versions).route_layer(Extension(ApiScope::QuestionnairesRead)),
+        )
+        .route(
+            "/:id/versions/diff",
+            get(diff_versions).route_layer(Extension(ApiScope::QuestionnairesRead)),
+        )
+        .route(
+            "/:id/versions/:version",
+            get(get_version).route_layer(Extension(ApiScope::QuestionnairesRead)),
+        )
+        .route("/:id/versions/:version/restore", post(restore_version))
+        .route(
+            "/:id/collaborators",
+            get(list_collaborators).post(invite_collaborator),
+        )
+        .route(
+            "/:id/collaborators/:user_id",
+            put(update_collaborator).delete(remove_collaborator),
+        )
+        .route(
+            "/:id/notifications",
+            get(get_notification_setting)
+                .put(update_notification_setting)
+                .delete(reset_notification_setting),
+        )
+        .route("/:id/webhooks", get(list_webhooks).post(create_webhook))
+        .route(
+            "/:id/webhooks/:webhook_id",
+            put(update_webhook).delete(delete_webhook),
+        )
+        .route(
+            "/:id/webhooks/:webhook_id/deliveries",
+            get(list_webhook_deliveries),
+        )
+        .route(
+            "/:id/webhooks/:webhook_id/deliveries/:delivery_id",
+            get(get_webhook_delivery),
+        )
+        .route(
+            "/:id/webhooks/:webhook_id/deliveries/:delivery_id/redeliver",
+            post(redeliver_webhook_delivery),
+        )
+        .route("/:id/campaigns", get(list_campaigns).post(create_campaign))
+        .route(
+            "/:id/campaigns/:campaign_id",
+            get(get_campaign).delete(delete_campaign),
+        )
+        .route(
+            "/:id/campaigns/:campaign_id/recipients",
+            get(list_campaign_recipients).post(add_campaign_recipients),
+        )
+        .route(
+            "/:id/campaigns/:campaign_id/recipients/:recipient_id",
+            delete(remove_campaign_recipient),
+        )
+        .route("/:id/campaigns/:campaign_id/send", post(send_campaign))
+        .route("/:id/campaigns/:campaign_id/remind", post(remind_campaign))
+        .route(
+            "/shared",
+            get(get_shared_questionnaires).route_layer(Extension(ApiScope::QuestionnairesRead)),
+        )
+        .route("/invitations", get(get_invitations))
+        .route("/invitations/:id/accept", post(accept_invitation))
+        .route("/invitations/:id/decline", post(decline_invitation))
         .route_layer(middleware::from_fn_with_state(
-            config.clone(),
+            AuthState { config: config.clone(), db: db.clone() },
             auth_middleware,
         ))
         .with_state(state.clone());
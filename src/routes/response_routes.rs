@@ -1,20 +1,31 @@
 use std::sync::Arc;
 
 use axum::{
+    body::StreamBody,
     extract::{FromRef, Path, Query, State},
+    http::header,
     middleware,
-    routing::{get, post},
-    Json, Router,
+    routing::{get, post, put},
+    Extension, Json, Router,
 };
 use serde::Deserialize;
 use sqlx::MySqlPool;
 use validator::Validate;
 
 use crate::config::Config;
-use crate::models::error::{AppError, AppResult};
-use crate::models::response::SubmitResponseRequest;
+use crate::models::api_token::ApiScope;
+use crate::models::error::AppResult;
+use crate::models::export::ExportQuery;
+use crate::models::response::{SaveDraftRequest, SubmitResponseRequest};
+use crate::models::statistics::{StatisticsQuery, TimelineQuery};
+use crate::models::text_analysis::{TextAnalysisQuery, TextSearchQuery, UpdateTagsRequest};
+use crate::services::campaign_service::CampaignService;
+use crate::services::export_service::ExportService;
 use crate::services::response_service::ResponseService;
-use crate::utils::auth::{auth_middleware, CurrentUser};
+use crate::services::statistics_service::StatisticsService;
+use crate::services::text_analysis_service::TextAnalysisService;
+use crate::utils::auth::{auth_middleware, AuthState, CurrentUser};
+use crate::utils::client::ClientInfo;
 use crate::utils::response::ApiResponse;
 
 // 定义应用程序状态
@@ -24,48 +35,142 @@ pub struct AppState {
     pub db: Arc<MySqlPool>,
 }
 
-// 为AppState实现FromRef，使CurrentUser可以从中提取Config
+// 为AppState实现FromRef，使CurrentUser可以从中提取Config和数据库连接池
 impl FromRef<AppState> for Arc<Config> {
     fn from_ref(state: &AppState) -> Self {
         state.config.clone()
     }
 }
 
+impl FromRef<AppState> for Arc<MySqlPool> {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct PaginationQuery {
     page: Option<i64>,
     page_size: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+struct StatisticsRefreshQuery {
+    // 为true时由全部回答重新生成统计汇总
+    #[serde(default)]
+    refresh: bool,
+}
+
 // 提交问卷回答
 async fn submit_response(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(req): Json<SubmitResponseRequest>,
 ) -> AppResult<impl axum::response::IntoResponse> {
     // 验证请求
-    req.validate()
-        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    req.validate()?;
 
-    // 提交回答 - 匿名回答无需用户ID
+    // 提交回答 - 匿名回答按设备和IP识别提交人
     let service = ResponseService::new(state.db, state.config);
-    let response = service.submit_response(None, req).await?;
+    let response = service.submit_response(client.respondent(None), req).await?;
 
-    Ok(ApiResponse::success(response, "问卷提交成功"))
+    Ok((client.cookie_header(), ApiResponse::success(response, "问卷提交成功")))
 }
 
 // 提交问卷回答 - 已认证用户
 async fn submit_response_auth(
     State(state): State<AppState>,
     current_user: CurrentUser,
+    client: ClientInfo,
     Json(req): Json<SubmitResponseRequest>,
 ) -> AppResult<impl axum::response::IntoResponse> {
     // 验证请求
-    req.validate()
-        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    req.validate()?;
 
     // 提交回答 - 使用认证用户ID
     let service = ResponseService::new(state.db, state.config);
-    let response = service.submit_response(Some(current_user.0), req).await?;
+    let response = service
+        .submit_response(client.respondent(Some(current_user.0)), req)
+        .await?;
+
+    Ok((client.cookie_header(), ApiResponse::success(response, "问卷提交成功")))
+}
+
+// 保存草稿
+async fn save_draft(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(req): Json<SaveDraftRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = ResponseService::new(state.db, state.config);
+    let draft = service.save_draft(client.respondent(None), req).await?;
+
+    Ok((client.cookie_header(), ApiResponse::success(draft, "草稿保存成功")))
+}
+
+// 保存草稿 - 已认证用户
+async fn save_draft_auth(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    client: ClientInfo,
+    Json(req): Json<SaveDraftRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = ResponseService::new(state.db, state.config);
+    let draft = service
+        .save_draft(client.respondent(Some(current_user.0)), req)
+        .await?;
+
+    Ok((client.cookie_header(), ApiResponse::success(draft, "草稿保存成功")))
+}
+
+// 通过续答凭证获取草稿
+async fn get_draft(
+    State(state): State<AppState>,
+    Path(resume_token): Path<String>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = ResponseService::new(state.db, state.config);
+    let draft = service.get_draft(&resume_token).await?;
+
+    Ok(ApiResponse::success(draft, "获取草稿成功"))
+}
+
+// 打开邀请链接，返回受邀人对应的问卷
+async fn open_invitation(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    let invitation = service.open(&token).await?;
+
+    Ok(ApiResponse::success(invitation, "获取邀请成功"))
+}
+
+// 受邀人开始作答
+async fn start_invitation(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = CampaignService::new(state.db, state.config);
+    service.start(&token).await?;
+
+    Ok(ApiResponse::success((), "已开始作答"))
+}
+
+// 通过邀请链接提交问卷回答
+async fn submit_invited_response(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(req): Json<SubmitResponseRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    // 提交回答 - 由邀请链接识别受邀人
+    let service = ResponseService::new(state.db, state.config);
+    let response = service.submit_invited_response(&token, req).await?;
 
     Ok(ApiResponse::success(response, "问卷提交成功"))
 }
@@ -75,15 +180,109 @@ async fn get_questionnaire_statistics(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Path(questionnaire_id): Path<i32>,
+    Query(query): Query<StatisticsRefreshQuery>,
 ) -> AppResult<impl axum::response::IntoResponse> {
-    let service = ResponseService::new(state.db, state.config);
+    let service = StatisticsService::new(state.db, state.config);
     let stats = service
-        .get_questionnaire_statistics(current_user.0, questionnaire_id)
+        .get_questionnaire_statistics(current_user.0, questionnaire_id, query.refresh)
         .await?;
 
     Ok(ApiResponse::success(stats, "获取问卷统计信息成功"))
 }
 
+// 分页获取文本类问题的回答
+async fn get_text_answers(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((questionnaire_id, question_id)): Path<(i32, i32)>,
+    Query(query): Query<PaginationQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(20);
+
+    let service = StatisticsService::new(state.db, state.config);
+    let answers = service
+        .get_text_answers(current_user.0, questionnaire_id, question_id, page, page_size)
+        .await?;
+
+    Ok(ApiResponse::success(answers, "获取文本回答成功"))
+}
+
+// 分析文本题回答的词频
+async fn analyze_text_answers(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path((questionnaire_id, question_id)): Path<(i32, i32)>,
+    Query(query): Query<TextAnalysisQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = TextAnalysisService::new(state.db, state.config);
+    let analysis = service
+        .analyze(current_user.0, questionnaire_id, question_id, query)
+        .await?;
+
+    Ok(ApiResponse::success(analysis, "获取文本分析结果成功"))
+}
+
+// 搜索问卷的文本回答
+async fn search_text_answers(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(questionnaire_id): Path<i32>,
+    Query(query): Query<TextSearchQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = TextAnalysisService::new(state.db, state.config);
+    let result = service
+        .search(current_user.0, questionnaire_id, query)
+        .await?;
+
+    Ok(ApiResponse::success(result, "搜索文本回答成功"))
+}
+
+// 设置文本回答的标签
+async fn update_text_answer_tags(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(text_response_id): Path<i32>,
+    Json(req): Json<UpdateTagsRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = TextAnalysisService::new(state.db, state.config);
+    let tags = service
+        .update_tags(current_user.0, text_response_id, req)
+        .await?;
+
+    Ok(ApiResponse::success(tags, "更新标签成功"))
+}
+
+// 按条件筛选回答并进行交叉分析
+async fn query_statistics(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(questionnaire_id): Path<i32>,
+    Json(query): Json<StatisticsQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = StatisticsService::new(state.db, state.config);
+    let result = service
+        .query_statistics(current_user.0, questionnaire_id, query)
+        .await?;
+
+    Ok(ApiResponse::success(result, "获取统计结果成功"))
+}
+
+// 按时间段统计回答数量
+async fn get_response_timeline(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(questionnaire_id): Path<i32>,
+    Query(query): Query<TimelineQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = StatisticsService::new(state.db, state.config);
+    let timeline = service
+        .response_timeline(current_user.0, questionnaire_id, query)
+        .await?;
+
+    Ok(ApiResponse::success(timeline, "获取回答趋势成功"))
+}
+
 // 获取问卷的回答列表
 async fn get_questionnaire_responses(
     State(state): State<AppState>,
@@ -102,6 +301,29 @@ async fn get_questionnaire_responses(
     Ok(ApiResponse::success(responses, "获取问卷回答列表成功"))
 }
 
+// 导出问卷的全部回答，文件内容以流的形式返回
+async fn export_responses(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(questionnaire_id): Path<i32>,
+    Query(query): Query<ExportQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = ExportService::new(state.db, state.config);
+    let file = service
+        .export_responses(current_user.0, questionnaire_id, query)
+        .await?;
+
+    let headers = [
+        (header::CONTENT_TYPE, file.content_type.to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file.filename),
+        ),
+    ];
+
+    Ok((headers, StreamBody::new(file.body)))
+}
+
 // 获取回答详情
 async fn get_response_detail(
     State(state): State<AppState>,
@@ -121,16 +343,56 @@ pub fn routes(config: Arc<Config>, db: Arc<MySqlPool>) -> Router {
     // 不需要认证的路由
     let public_routes = Router::new()
         .route("/submit", post(submit_response))
+        .route("/drafts", post(save_draft))
+        .route("/drafts/:token", get(get_draft))
+        .route("/invite/:token", get(open_invitation))
+        .route("/invite/:token/start", post(start_invitation))
+        .route("/invite/:token/submit", post(submit_invited_response))
         .with_state(state.clone());
 
-    // 需要认证的路由
+    // 需要认证的路由，带有Extension(ApiScope)的路由也接受具有该权限的个人访问令牌
     let authenticated_routes = Router::new()
         .route("/submit/auth", post(submit_response_auth))
-        .route("/questionnaires/:id/statistics", get(get_questionnaire_statistics))
-        .route("/questionnaires/:id/responses", get(get_questionnaire_responses))
-        .route("/:id", get(get_response_detail))
+        .route("/drafts/auth", post(save_draft_auth))
+        .route(
+            "/questionnaires/:id/statistics",
+            get(get_questionnaire_statistics).route_layer(Extension(ApiScope::ResponsesRead)),
+        )
+        .route(
+            "/questionnaires/:id/statistics/query",
+            post(query_statistics).route_layer(Extension(ApiScope::ResponsesRead)),
+        )
+        .route(
+            "/questionnaires/:id/statistics/timeline",
+            get(get_response_timeline).route_layer(Extension(ApiScope::ResponsesRead)),
+        )
+        .route(
+            "/questionnaires/:id/questions/:question_id/answers",
+            get(get_text_answers).route_layer(Extension(ApiScope::ResponsesRead)),
+        )
+        .route(
+            "/questionnaires/:id/questions/:question_id/analysis",
+            get(analyze_text_answers).route_layer(Extension(ApiScope::ResponsesRead)),
+        )
+        .route(
+            "/questionnaires/:id/text-search",
+            get(search_text_answers).route_layer(Extension(ApiScope::ResponsesRead)),
+        )
+        .route("/text-answers/:id/tags", put(update_text_answer_tags))
+        .route(
+            "/questionnaires/:id/responses",
+            get(get_questionnaire_responses).route_layer(Extension(ApiScope::ResponsesRead)),
+        )
+        .route(
+            "/questionnaires/:id/export",
+            get(export_responses).route_layer(Extension(ApiScope::ResponsesExport)),
+        )
+        .route(
+            "/:id",
+            get(get_response_detail).route_layer(Extension(ApiScope::ResponsesRead)),
+        )
         .route_layer(middleware::from_fn_with_state(
-            config.clone(),
+            AuthState { config: config.clone(), db: db.clone() },
             auth_middleware,
         ))
         .with_state(state);
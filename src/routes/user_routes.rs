@@ -1,18 +1,32 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{FromRef, State},
-    routing::{get, post},
+    extract::{FromRef, Path, Query, State},
+    http::{header, HeaderMap},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use sqlx::MySqlPool;
 use validator::Validate;
 
 use crate::config::Config;
-use crate::models::error::{AppError, AppResult};
-use crate::models::user::{CreateUserRequest, LoginRequest};
+use crate::models::api_token::CreateApiTokenRequest;
+use crate::models::error::AppResult;
+use crate::models::notification::UpdateNotificationPreferencesRequest;
+use crate::models::user::{
+    ChangePasswordRequest, CreateUserRequest, DeleteAccountRequest, DisableTwoFactorRequest,
+    ForgotPasswordRequest, LoginHistoryQuery, LoginRequest, LoginResponse, LogoutAllResponse,
+    RefreshTokenRequest, ResetPasswordRequest, SsoCallbackRequest, SsoCallbackResponse,
+    TwoFactorCodeRequest, TwoFactorLoginRequest, TwoFactorSetupRequest, UpdateProfileRequest,
+};
+use crate::services::api_token_service::ApiTokenService;
+use crate::services::notification_service::NotificationService;
+use crate::services::session_service::SessionService;
+use crate::services::sso_service::SsoService;
+use crate::services::two_factor_service::TwoFactorService;
 use crate::services::user_service::UserService;
-use crate::utils::auth::CurrentUser;
+use crate::utils::auth::{AuthSession, CurrentUser};
+use crate::utils::client::ClientInfo;
 use crate::utils::response::ApiResponse;
 
 // 定义应用程序状态
@@ -22,21 +36,26 @@ pub struct AppState {
     pub db: Arc<MySqlPool>,
 }
 
-// 为AppState实现FromRef，使CurrentUser可以从中提取Config
+// 为AppState实现FromRef，使CurrentUser可以从中提取Config和数据库连接池
 impl FromRef<AppState> for Arc<Config> {
     fn from_ref(state: &AppState) -> Self {
         state.config.clone()
     }
 }
 
+impl FromRef<AppState> for Arc<MySqlPool> {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
 // 用户注册
 async fn register(
     State(state): State<AppState>,
     Json(req): Json<CreateUserRequest>,
 ) -> AppResult<impl axum::response::IntoResponse> {
     // 验证请求
-    req.validate()
-        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    req.validate()?;
 
     // 创建用户
     let user_service = UserService::new(state.db, state.config);
@@ -48,19 +67,116 @@ async fn register(
 // 用户登录
 async fn login(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    client: ClientInfo,
     Json(req): Json<LoginRequest>,
 ) -> AppResult<impl axum::response::IntoResponse> {
     // 验证请求
-    req.validate()
-        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    req.validate()?;
 
-    // 登录
+    // 登录 - 记录设备信息以便区分各个登录会话
+    let user_service = UserService::new(state.db, state.config);
+    let result = user_service
+        .login(req, user_agent(&headers), client.ip_address)
+        .await?;
+
+    let message = match result {
+        LoginResponse::Authenticated(_) => "登录成功",
+        LoginResponse::TwoFactorRequired { .. } => "请输入两步验证码",
+    };
+    Ok(ApiResponse::success(result, message))
+}
+
+// 使用登录挑战和验证码完成两步验证登录
+async fn login_two_factor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: ClientInfo,
+    Json(req): Json<TwoFactorLoginRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
     let user_service = UserService::new(state.db, state.config);
-    let auth = user_service.login(req).await?;
+    let auth = user_service
+        .complete_two_factor(req, user_agent(&headers), client.ip_address)
+        .await?;
 
     Ok(ApiResponse::success(auth, "登录成功"))
 }
 
+// 获取跳转到身份提供方的单点登录地址
+async fn sso_authorize(
+    State(state): State<AppState>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = SsoService::new(state.db, state.config);
+    let result = service.authorize(None).await?;
+
+    Ok(ApiResponse::success(result, "请跳转到身份提供方登录"))
+}
+
+// 身份提供方跳转回前端后，提交授权码完成单点登录或关联外部身份
+async fn sso_callback(
+    State(state): State<AppState>,
+    current_user: Option<CurrentUser>,
+    headers: HeaderMap,
+    client: ClientInfo,
+    Json(req): Json<SsoCallbackRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = SsoService::new(state.db, state.config);
+    let result = service
+        .callback(
+            req,
+            current_user.map(|user| user.0),
+            user_agent(&headers),
+            client.ip_address,
+        )
+        .await?;
+
+    let message = match result {
+        SsoCallbackResponse::Authenticated(_) => "登录成功",
+        SsoCallbackResponse::TwoFactorRequired { .. } => "请输入两步验证码",
+        SsoCallbackResponse::Linked(_) => "外部身份关联成功",
+    };
+    Ok(ApiResponse::success(result, message))
+}
+
+// 刷新访问令牌
+async fn refresh_token(
+    State(state): State<AppState>,
+    Json(req): Json<RefreshTokenRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let session_service = SessionService::new(state.db, state.config);
+    let tokens = session_service.refresh(&req.refresh_token).await?;
+
+    Ok(ApiResponse::success(tokens, "刷新令牌成功"))
+}
+
+// 退出当前登录
+async fn logout(
+    State(state): State<AppState>,
+    session: AuthSession,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let session_service = SessionService::new(state.db, state.config);
+    session_service.revoke(session.user_id, session.session_id).await?;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({"session_id": session.session_id}),
+        "退出登录成功",
+    ))
+}
+
+// 退出所有设备上的登录
+async fn logout_all(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let session_service = SessionService::new(state.db, state.config);
+    let revoked_sessions = session_service.revoke_all(current_user.0).await?;
+
+    Ok(ApiResponse::success(
+        LogoutAllResponse { revoked_sessions },
+        "已退出所有登录",
+    ))
+}
+
 // 获取当前用户信息
 async fn get_current_user(
     State(state): State<AppState>,
@@ -72,6 +188,257 @@ async fn get_current_user(
     Ok(ApiResponse::success(user, "获取用户信息成功"))
 }
 
+// 获取最近的登录记录
+async fn get_login_history(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Query(query): Query<LoginHistoryQuery>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    let user_service = UserService::new(state.db, state.config);
+    let records = user_service.login_history(current_user.0, limit).await?;
+
+    Ok(ApiResponse::success(records, "获取登录记录成功"))
+}
+
+// 获取两步验证状态
+async fn get_two_factor_status(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = TwoFactorService::new(state.db, state.config);
+    let status = service.status(current_user.0).await?;
+
+    Ok(ApiResponse::success(status, "获取两步验证状态成功"))
+}
+
+// 生成两步验证密钥
+async fn setup_two_factor(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<TwoFactorSetupRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = TwoFactorService::new(state.db, state.config);
+    let setup = service.setup(current_user.0, req).await?;
+
+    Ok(ApiResponse::success(setup, "请使用验证器应用扫描后输入验证码"))
+}
+
+// 验证第一个验证码，开启两步验证
+async fn enable_two_factor(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = TwoFactorService::new(state.db, state.config);
+    let codes = service.enable(current_user.0, &req.code).await?;
+
+    Ok(ApiResponse::success(codes, "两步验证已开启，请妥善保存恢复码"))
+}
+
+// 关闭两步验证
+async fn disable_two_factor(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<DisableTwoFactorRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = TwoFactorService::new(state.db, state.config);
+    service.disable(current_user.0, req).await?;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({"enabled": false}),
+        "两步验证已关闭",
+    ))
+}
+
+// 重新生成恢复码
+async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = TwoFactorService::new(state.db, state.config);
+    let codes = service
+        .regenerate_recovery_codes(current_user.0, &req.code)
+        .await?;
+
+    Ok(ApiResponse::success(codes, "恢复码已重新生成，原有的恢复码已作废"))
+}
+
+// 获取关联到账号的外部身份
+async fn get_identities(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = SsoService::new(state.db, state.config);
+    let identities = service.list_identities(current_user.0).await?;
+
+    Ok(ApiResponse::success(identities, "获取外部身份成功"))
+}
+
+// 获取关联外部身份的单点登录地址
+async fn link_identity(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = SsoService::new(state.db, state.config);
+    let result = service.authorize(Some(current_user.0)).await?;
+
+    Ok(ApiResponse::success(result, "请跳转到身份提供方完成关联"))
+}
+
+// 取消关联外部身份
+async fn unlink_identity(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = SsoService::new(state.db, state.config);
+    service.unlink(current_user.0, id).await?;
+
+    Ok(ApiResponse::success((), "已取消关联外部身份"))
+}
+
+// 获取个人访问令牌列表
+async fn get_api_tokens(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = ApiTokenService::new(state.db);
+    let tokens = service.list(current_user.0).await?;
+
+    Ok(ApiResponse::success(tokens, "获取个人访问令牌成功"))
+}
+
+// 创建个人访问令牌
+async fn create_api_token(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<CreateApiTokenRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = ApiTokenService::new(state.db);
+    let token = service.create(current_user.0, req).await?;
+
+    Ok(ApiResponse::success(token, "个人访问令牌已创建，请立即复制保存，之后将无法再次查看"))
+}
+
+// 撤销个人访问令牌
+async fn revoke_api_token(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = ApiTokenService::new(state.db);
+    service.revoke(current_user.0, id).await?;
+
+    Ok(ApiResponse::success(serde_json::json!({"id": id}), "个人访问令牌已撤销"))
+}
+
+// 获取通知设置
+async fn get_notification_preferences(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let service = NotificationService::new(state.db);
+    let preferences = service.get_preferences(current_user.0).await?;
+
+    Ok(ApiResponse::success(preferences, "获取通知设置成功"))
+}
+
+// 修改通知设置
+async fn update_notification_preferences(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<UpdateNotificationPreferencesRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let service = NotificationService::new(state.db);
+    let preferences = service.update_preferences(current_user.0, req).await?;
+
+    Ok(ApiResponse::success(preferences, "通知设置已保存"))
+}
+
+// 修改个人资料
+async fn update_profile(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<UpdateProfileRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let user_service = UserService::new(state.db, state.config);
+    let user = user_service.update_profile(current_user.0, req).await?;
+
+    Ok(ApiResponse::success(user, "个人资料修改成功"))
+}
+
+// 修改密码，返回当前设备的新令牌
+async fn change_password(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    headers: HeaderMap,
+    client: ClientInfo,
+    Json(req): Json<ChangePasswordRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let user_service = UserService::new(state.db, state.config);
+    let tokens = user_service
+        .change_password(current_user.0, req, user_agent(&headers), client.ip_address)
+        .await?;
+
+    Ok(ApiResponse::success(tokens, "密码修改成功，其他设备需重新登录"))
+}
+
+// 申请重置密码，向邮箱发送重置链接
+async fn forgot_password(
+    State(state): State<AppState>,
+    Json(req): Json<ForgotPasswordRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let user_service = UserService::new(state.db, state.config);
+    user_service.request_password_reset(req).await?;
+
+    Ok(ApiResponse::success((), "如果该邮箱已绑定账号，重置密码的邮件已发送"))
+}
+
+// 通过邮件中的令牌重置密码
+async fn reset_password(
+    State(state): State<AppState>,
+    Json(req): Json<ResetPasswordRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    req.validate()?;
+
+    let user_service = UserService::new(state.db, state.config);
+    user_service.reset_password(req).await?;
+
+    Ok(ApiResponse::success((), "密码重置成功，请重新登录"))
+}
+
+// 注销账号
+async fn delete_account(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<DeleteAccountRequest>,
+) -> AppResult<impl axum::response::IntoResponse> {
+    let user_service = UserService::new(state.db, state.config);
+    let result = user_service.delete_account(current_user.0, req).await?;
+
+    Ok(ApiResponse::success(result, "账号已注销"))
+}
+
+fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
 // 创建用户路由
 pub fn routes(config: Arc<Config>, db: Arc<MySqlPool>) -> Router {
     let state = AppState { config, db };
@@ -79,6 +446,33 @@ pub fn routes(config: Arc<Config>, db: Arc<MySqlPool>) -> Router {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
-        .route("/me", get(get_current_user))
+        .route("/login/2fa", post(login_two_factor))
+        .route("/sso/authorize", get(sso_authorize))
+        .route("/sso/callback", post(sso_callback))
+        .route("/refresh", post(refresh_token))
+        .route("/logout", post(logout))
+        .route("/logout-all", post(logout_all))
+        .route(
+            "/me",
+            get(get_current_user).put(update_profile).delete(delete_account),
+        )
+        .route("/me/password", put(change_password))
+        .route("/me/logins", get(get_login_history))
+        .route("/me/2fa", get(get_two_factor_status))
+        .route("/me/2fa/setup", post(setup_two_factor))
+        .route("/me/2fa/enable", post(enable_two_factor))
+        .route("/me/2fa/disable", post(disable_two_factor))
+        .route("/me/2fa/recovery-codes", post(regenerate_recovery_codes))
+        .route("/me/identities", get(get_identities))
+        .route("/me/identities/link", post(link_identity))
+        .route("/me/identities/:id", delete(unlink_identity))
+        .route("/me/tokens", get(get_api_tokens).post(create_api_token))
+        .route("/me/tokens/:id", delete(revoke_api_token))
+        .route(
+            "/me/notifications",
+            get(get_notification_preferences).put(update_notification_preferences),
+        )
+        .route("/password-reset", post(forgot_password))
+        .route("/password-reset/confirm", post(reset_password))
         .with_state(state)
 } 
\ No newline at end of file
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use sqlx::{MySql, Pool};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult};
+use crate::models::user::{AdminUserItem, AdminUserListResponse, UpdateRoleRequest, UserRole};
+use crate::services::permission_service::PermissionService;
+
+pub struct AdminService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl AdminService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 分页获取用户列表，可按用户名、昵称或邮箱搜索
+    pub async fn list_users(
+        &self,
+        admin_id: i32,
+        search: Option<String>,
+        page: i64,
+        page_size: i64,
+    ) -> AppResult<AdminUserListResponse> {
+        self.require_admin(admin_id).await?;
+
+        let pattern = search
+            .as_deref()
+            .map(str::trim)
+            .filter(|search| !search.is_empty())
+            .map(|search| format!("%{}%", search));
+
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM users
+            WHERE ? IS NULL OR username LIKE ? OR nickname LIKE ? OR email LIKE ?
+            "#,
+            pattern,
+            pattern,
+            pattern,
+            pattern
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        let items = sqlx::query!(
+            r#"
+            SELECT u.id, u.username, u.nickname, u.email, u.role,
+                   (SELECT COUNT(*) FROM questionnaires q WHERE q.creator_id = u.id)
+                       as "questionnaire_count!: i64",
+                   u.created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM users u
+            WHERE ? IS NULL OR u.username LIKE ? OR u.nickname LIKE ? OR u.email LIKE ?
+            ORDER BY u.id
+            LIMIT ? OFFSET ?
+            "#,
+            pattern,
+            pattern,
+            pattern,
+            pattern,
+            page_size,
+            (page - 1) * page_size
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| AdminUserItem {
+            id: row.id,
+            username: row.username,
+            nickname: row.nickname,
+            email: row.email,
+            role: UserRole::parse(&row.role),
+            questionnaire_count: row.questionnaire_count,
+            created_at: row.created_at.expect("创建时间不应为空"),
+        })
+        .collect();
+
+        Ok(AdminUserListResponse {
+            items,
+            total,
+            page,
+            page_size,
+        })
+    }
+
+    // 设置用户的站点角色
+    pub async fn update_role(
+        &self,
+        admin_id: i32,
+        user_id: i32,
+        req: UpdateRoleRequest,
+    ) -> AppResult<()> {
+        self.require_admin(admin_id).await?;
+
+        // 避免管理员误操作后站点没有管理员
+        if admin_id == user_id {
+            return Err(AppError::validation("不能修改自己的角色"));
+        }
+
+        let result = sqlx::query!(
+            "UPDATE users SET role = ? WHERE id = ?",
+            req.role.as_str(),
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            let exists = sqlx::query!("SELECT id FROM users WHERE id = ?", user_id)
+                .fetch_optional(&*self.db)
+                .await?;
+            if exists.is_none() {
+                return Err(AppError::NotFoundError(format!("未找到ID为{}的用户", user_id)));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn require_admin(&self, user_id: i32) -> AppResult<()> {
+        PermissionService::new(self.db.clone())
+            .require_admin(user_id)
+            .await
+    }
+}
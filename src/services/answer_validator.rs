@@ -0,0 +1,499 @@
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use sqlx::{MySql, Transaction};
+use validator::ValidateEmail;
+
+use crate::models::error::{AppError, AppResult, FieldErrors};
+use crate::models::logic::QuestionLogic;
+use crate::models::questionnaire::QuestionConfig;
+use crate::models::response::QuestionAnswer;
+use crate::services::question_logic;
+
+// 提交回答时使用的问卷定义（仅包含当前启用的问题和选项）
+pub struct QuestionnaireDefinition {
+    pub questionnaire_id: i32,
+    pub questions: Vec<QuestionDefinition>,
+}
+
+pub struct QuestionDefinition {
+    pub id: i32,
+    pub section_id: Option<i32>,
+    pub question_type: String,
+    pub required: bool,
+    pub config: QuestionConfig,
+    pub logic: Option<QuestionLogic>,
+    pub options: Vec<(i32, String)>, // 矩阵题为列
+    pub rows: Vec<(i32, String)>,    // 矩阵题的行
+}
+
+// 通过验证并规范化后的回答，选项文本已解析为选项ID
+pub struct ValidatedAnswer {
+    pub question_id: i32,
+    pub text_value: Option<String>,
+    pub numeric_value: Option<f64>,
+    pub selections: Vec<Selection>,
+}
+
+// 一条选项回答记录
+pub struct Selection {
+    pub option_id: i32,
+    pub row_option_id: Option<i32>,
+    pub rank_position: Option<i32>,
+}
+
+impl Selection {
+    fn option(option_id: i32) -> Self {
+        Self {
+            option_id,
+            row_option_id: None,
+            rank_position: None,
+        }
+    }
+}
+
+impl QuestionnaireDefinition {
+    // 在事务中加载问卷的问题和选项
+    pub async fn load(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+    ) -> AppResult<Self> {
+        let questions = sqlx::query!(
+            r#"
+            SELECT id, section_id, question_type, required, config, logic
+            FROM questions
+            WHERE questionnaire_id = ? AND retired_at IS NULL
+            ORDER BY display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let options = sqlx::query!(
+            r#"
+            SELECT qo.id, qo.question_id, qo.option_text, qo.role
+            FROM question_options qo
+            JOIN questions q ON qo.question_id = q.id
+            WHERE q.questionnaire_id = ? AND q.retired_at IS NULL AND qo.retired_at IS NULL
+            ORDER BY qo.display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let mut definitions = Vec::new();
+        for question in questions {
+            let config = question
+                .config
+                .as_deref()
+                .map(serde_json::from_str::<QuestionConfig>)
+                .transpose()
+                .map_err(|e| AppError::InternalServerError(format!("解析题型配置失败: {}", e)))?
+                .unwrap_or_default();
+            let logic = question
+                .logic
+                .as_deref()
+                .map(serde_json::from_str::<QuestionLogic>)
+                .transpose()
+                .map_err(|e| AppError::InternalServerError(format!("解析条件逻辑失败: {}", e)))?;
+
+            let items = |role: &str| {
+                options
+                    .iter()
+                    .filter(|option| option.question_id == question.id && option.role == role)
+                    .map(|option| (option.id, option.option_text.clone()))
+                    .collect::<Vec<_>>()
+            };
+
+            definitions.push(QuestionDefinition {
+                id: question.id,
+                section_id: question.section_id,
+                required: question.required.map(|v| v != 0).unwrap_or(true),
+                config,
+                logic,
+                options: items("option"),
+                rows: items("row"),
+                question_type: question.question_type,
+            });
+        }
+
+        Ok(Self {
+            questionnaire_id,
+            questions: definitions,
+        })
+    }
+
+    // 验证提交的回答，所有问题的错误会一并返回
+    // 被条件逻辑隐藏的问题不要求作答，提交的回答也会被丢弃
+    pub fn validate(&self, answers: &[QuestionAnswer]) -> AppResult<Vec<ValidatedAnswer>> {
+        let mut errors = FieldErrors::new();
+        let (mut validated, mut question_errors) = self.validate_each(answers, &mut errors);
+
+        // 只根据通过验证的回答计算可见问题
+        let visible = question_logic::visible_questions(
+            &self
+                .questions
+                .iter()
+                .map(|question| (question.id, question.section_id, question.logic.as_ref()))
+                .collect::<Vec<_>>(),
+            &validated.iter().map(|(id, answer)| (*id, answer)).collect(),
+        );
+
+        for question in &self.questions {
+            if !visible.contains(&question.id) {
+                continue;
+            }
+
+            let key = field_key(question.id);
+            if let Some(messages) = question_errors.remove(&question.id) {
+                errors.entry(key).or_default().extend(messages);
+            } else if question.required && !validated.contains_key(&question.id) {
+                push_error(&mut errors, &key, "此题为必答题");
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(AppError::validation_fields("提交的回答未通过验证", errors));
+        }
+
+        // 按问题顺序返回可见问题的回答
+        Ok(self
+            .questions
+            .iter()
+            .filter(|question| visible.contains(&question.id))
+            .filter_map(|question| validated.remove(&question.id))
+            .collect())
+    }
+
+    // 验证草稿中的回答：只检查已作答问题的格式，不检查必答题和条件逻辑
+    pub fn validate_partial(&self, answers: &[QuestionAnswer]) -> AppResult<()> {
+        let mut errors = FieldErrors::new();
+        let (_, question_errors) = self.validate_each(answers, &mut errors);
+
+        for (question_id, messages) in question_errors {
+            errors.entry(field_key(question_id)).or_default().extend(messages);
+        }
+
+        if !errors.is_empty() {
+            return Err(AppError::validation_fields("保存的回答未通过验证", errors));
+        }
+
+        Ok(())
+    }
+
+    // 问题是否属于当前的问卷定义
+    pub fn contains(&self, question_id: i32) -> bool {
+        self.questions.iter().any(|question| question.id == question_id)
+    }
+
+    // 逐题验证回答，不属于问卷或重复的回答直接记入errors，各问题自身的错误单独返回
+    fn validate_each(
+        &self,
+        answers: &[QuestionAnswer],
+        errors: &mut FieldErrors,
+    ) -> (HashMap<i32, ValidatedAnswer>, HashMap<i32, Vec<String>>) {
+        let mut answered = HashSet::new();
+        let mut question_errors: HashMap<i32, Vec<String>> = HashMap::new();
+        let mut validated: HashMap<i32, ValidatedAnswer> = HashMap::new();
+
+        for answer in answers {
+            let key = field_key(answer.question_id);
+
+            let Some(question) = self.questions.iter().find(|q| q.id == answer.question_id) else {
+                push_error(errors, &key, format!("问题不属于问卷 {}", self.questionnaire_id));
+                continue;
+            };
+
+            if !answered.insert(question.id) {
+                push_error(errors, &key, "同一问题只能回答一次");
+                continue;
+            }
+
+            match question.validate(answer) {
+                Ok(Some(answer)) => {
+                    validated.insert(question.id, answer);
+                }
+                Ok(None) => {}
+                Err(messages) => {
+                    question_errors.insert(question.id, messages);
+                }
+            }
+        }
+
+        (validated, question_errors)
+    }
+}
+
+impl QuestionDefinition {
+    // 题型对应的回答类型
+    fn expected_answer_type(&self) -> &'static str {
+        match self.question_type.as_str() {
+            "radio" => "option",
+            "checkbox" => "options",
+            "rating" | "nps" | "number" => "number",
+            "matrix_radio" | "matrix_checkbox" => "matrix",
+            "ranking" => "ranking",
+            _ => "text",
+        }
+    }
+
+    // 验证单个问题的回答，空回答返回None
+    fn validate(&self, answer: &QuestionAnswer) -> Result<Option<ValidatedAnswer>, Vec<String>> {
+        let expected = self.expected_answer_type();
+        if answer.answer_type != expected {
+            return Err(vec![format!(
+                "回答类型 {} 与题型 {} 不匹配，应为 {}",
+                answer.answer_type, self.question_type, expected
+            )]);
+        }
+
+        let validated = |text_value, numeric_value, selections| ValidatedAnswer {
+            question_id: self.id,
+            text_value,
+            numeric_value,
+            selections,
+        };
+
+        match expected {
+            "number" => {
+                let Some(value) = answer.numeric_value else {
+                    return Ok(None);
+                };
+                self.validate_number(value)?;
+                Ok(Some(validated(None, Some(value), Vec::new())))
+            }
+            "matrix" => {
+                let selections = self.validate_matrix(answer)?;
+                Ok((!selections.is_empty()).then(|| validated(None, None, selections)))
+            }
+            "ranking" => {
+                let option_ids = self.resolve_options(answer)?;
+                if option_ids.is_empty() {
+                    return Ok(None);
+                }
+                if option_ids.len() != self.options.len() {
+                    return Err(vec!["排序题需要对所有选项进行排序".to_string()]);
+                }
+                let selections = option_ids
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, option_id)| Selection {
+                        option_id,
+                        row_option_id: None,
+                        rank_position: Some(index as i32 + 1),
+                    })
+                    .collect();
+                Ok(Some(validated(None, None, selections)))
+            }
+            "option" | "options" => {
+                let option_ids = self.resolve_options(answer)?;
+                if option_ids.is_empty() {
+                    return Ok(None);
+                }
+                if expected == "option" && option_ids.len() > 1 {
+                    return Err(vec!["单选题只能选择一个选项".to_string()]);
+                }
+                let selections = option_ids.into_iter().map(Selection::option).collect();
+                Ok(Some(validated(None, None, selections)))
+            }
+            _ => {
+                let Some(text) = answer
+                    .text_value
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|text| !text.is_empty())
+                else {
+                    return Ok(None);
+                };
+                let text = self.validate_text(text)?;
+                Ok(Some(validated(Some(text), None, Vec::new())))
+            }
+        }
+    }
+
+    // 验证数值回答的取值范围
+    fn validate_number(&self, value: f64) -> Result<(), Vec<String>> {
+        if !value.is_finite() {
+            return Err(vec!["请输入有效的数字".to_string()]);
+        }
+
+        match self.question_type.as_str() {
+            "rating" => {
+                let (min, max, step) = self.config.rating_scale();
+                let steps = (value - min) / step;
+                if value < min || value > max || (steps - steps.round()).abs() > 1e-9 {
+                    return Err(vec![format!("评分必须在 {} 到 {} 之间，步长为 {}", min, max, step)]);
+                }
+            }
+            "nps" => {
+                if !(0.0..=10.0).contains(&value) || value.fract() != 0.0 {
+                    return Err(vec!["NPS评分必须是0到10之间的整数".to_string()]);
+                }
+            }
+            _ => {
+                let mut messages = Vec::new();
+                if self.config.integer_only.unwrap_or(false) && value.fract() != 0.0 {
+                    messages.push("请输入整数".to_string());
+                }
+                if let Some(min) = self.config.min.filter(|min| value < *min) {
+                    messages.push(format!("不能小于 {}", min));
+                }
+                if let Some(max) = self.config.max.filter(|max| value > *max) {
+                    messages.push(format!("不能大于 {}", max));
+                }
+                if !messages.is_empty() {
+                    return Err(messages);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // 验证文本类回答，日期题会被规范化为统一格式
+    fn validate_text(&self, text: &str) -> Result<String, Vec<String>> {
+        match self.question_type.as_str() {
+            "email" => {
+                if !text.validate_email() {
+                    return Err(vec!["邮箱格式不正确".to_string()]);
+                }
+            }
+            "phone" => {
+                let digits = text.chars().filter(|c| c.is_ascii_digit()).count();
+                let allowed = text
+                    .chars()
+                    .enumerate()
+                    .all(|(i, c)| c.is_ascii_digit() || " -()".contains(c) || (c == '+' && i == 0));
+                if !allowed || !(5..=20).contains(&digits) {
+                    return Err(vec!["电话号码格式不正确".to_string()]);
+                }
+            }
+            "date" => {
+                return normalize_date(self.config.date_mode(), text).ok_or_else(|| {
+                    vec![format!("日期格式不正确，类型为 {}", self.config.date_mode())]
+                });
+            }
+            _ => {}
+        }
+
+        Ok(text.to_string())
+    }
+
+    // 验证矩阵题：行和列都必须属于本题，矩阵单选每行只能选一列，必答时每行都要作答
+    fn validate_matrix(&self, answer: &QuestionAnswer) -> Result<Vec<Selection>, Vec<String>> {
+        let mut messages = Vec::new();
+        let mut selections = Vec::new();
+        let mut answered_rows = HashSet::new();
+
+        for row in answer.matrix_values.iter().flatten() {
+            if !self.rows.iter().any(|(id, _)| *id == row.row_id) {
+                messages.push(format!("行ID {} 不属于该问题", row.row_id));
+                continue;
+            }
+            if !answered_rows.insert(row.row_id) {
+                messages.push(format!("行ID {} 重复作答", row.row_id));
+                continue;
+            }
+            if self.question_type == "matrix_radio" && row.option_ids.len() > 1 {
+                messages.push(format!("行ID {} 只能选择一列", row.row_id));
+            }
+
+            let unique: HashSet<_> = row.option_ids.iter().collect();
+            if unique.len() != row.option_ids.len() {
+                messages.push(format!("行ID {} 不能重复选择同一列", row.row_id));
+            }
+
+            for option_id in &row.option_ids {
+                if self.options.iter().any(|(id, _)| id == option_id) {
+                    selections.push(Selection {
+                        option_id: *option_id,
+                        row_option_id: Some(row.row_id),
+                        rank_position: None,
+                    });
+                } else {
+                    messages.push(format!("列ID {} 不属于该问题", option_id));
+                }
+            }
+        }
+
+        if self.required && !selections.is_empty() {
+            let answered: HashSet<_> = selections.iter().filter_map(|s| s.row_option_id).collect();
+            for (row_id, text) in &self.rows {
+                if !answered.contains(row_id) {
+                    messages.push(format!("第\"{}\"行为必答", text));
+                }
+            }
+        }
+
+        if messages.is_empty() {
+            Ok(selections)
+        } else {
+            Err(messages)
+        }
+    }
+
+    // 将提交的选项ID或选项文本解析为本题的选项ID
+    fn resolve_options(&self, answer: &QuestionAnswer) -> Result<Vec<i32>, Vec<String>> {
+        let mut messages = Vec::new();
+        let mut option_ids = Vec::new();
+
+        if let Some(ids) = &answer.option_ids {
+            for option_id in ids {
+                if self.options.iter().any(|(id, _)| id == option_id) {
+                    option_ids.push(*option_id);
+                } else {
+                    messages.push(format!("选项ID {} 不属于该问题", option_id));
+                }
+            }
+        } else if let Some(values) = &answer.option_values {
+            for value in values {
+                match self.options.iter().find(|(_, text)| text == value) {
+                    Some((id, _)) => option_ids.push(*id),
+                    None => messages.push(format!("选项 {} 不存在", value)),
+                }
+            }
+        }
+
+        let unique: HashSet<_> = option_ids.iter().collect();
+        if unique.len() != option_ids.len() {
+            messages.push("不能重复选择同一选项".to_string());
+        }
+
+        if messages.is_empty() {
+            Ok(option_ids)
+        } else {
+            Err(messages)
+        }
+    }
+}
+
+// 将日期、时间或日期时间规范化为统一格式，无法解析时返回None
+fn normalize_date(mode: &str, text: &str) -> Option<String> {
+    match mode {
+        "time" => ["%H:%M:%S", "%H:%M"]
+            .iter()
+            .find_map(|format| NaiveTime::parse_from_str(text, format).ok())
+            .map(|time| time.format("%H:%M:%S").to_string()),
+        "datetime" => DateTime::parse_from_rfc3339(text)
+            .map(|dt| dt.with_timezone(&Utc).naive_utc())
+            .ok()
+            .or_else(|| {
+                ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+                    .iter()
+                    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+            })
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string()),
+        _ => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.format("%Y-%m-%d").to_string()),
+    }
+}
+
+fn field_key(question_id: i32) -> String {
+    format!("questions.{}", question_id)
+}
+
+fn push_error(errors: &mut FieldErrors, key: &str, message: impl Into<String>) {
+    errors.entry(key.to_string()).or_default().push(message.into());
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::models::api_token::{ApiScopes, ApiToken, CreateApiTokenRequest, CreatedApiToken};
+use crate::models::error::{AppError, AppResult};
+use crate::utils::auth::{generate_secret_token, hash_token, API_TOKEN_PREFIX};
+
+// 每个用户最多保留的有效令牌数量
+const MAX_TOKENS_PER_USER: i64 = 20;
+// 列表中显示的令牌前缀长度（含qpat_）
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+
+pub struct ApiTokenService {
+    db: Arc<Pool<MySql>>,
+}
+
+impl ApiTokenService {
+    pub fn new(db: Arc<Pool<MySql>>) -> Self {
+        Self { db }
+    }
+
+    // 创建个人访问令牌，完整的令牌只返回这一次
+    pub async fn create(
+        &self,
+        user_id: i32,
+        req: CreateApiTokenRequest,
+    ) -> AppResult<CreatedApiToken> {
+        let active = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM api_tokens
+            WHERE user_id = ? AND revoked_at IS NULL
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            "#,
+            user_id
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        if active >= MAX_TOKENS_PER_USER {
+            return Err(AppError::validation(format!(
+                "最多只能创建{}个有效的个人访问令牌，请先撤销不再使用的令牌",
+                MAX_TOKENS_PER_USER
+            )));
+        }
+
+        let token = format!("{}{}", API_TOKEN_PREFIX, generate_secret_token());
+        let token_prefix: String = token.chars().take(DISPLAY_PREFIX_LENGTH).collect();
+        let scopes: ApiScopes = req.scopes.into_iter().collect();
+        let expires_at = req
+            .expires_in_days
+            .map(|days| Utc::now() + Duration::days(days));
+
+        let id = sqlx::query!(
+            r#"
+            INSERT INTO api_tokens (user_id, name, token_hash, token_prefix, scopes, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            user_id,
+            req.name.trim(),
+            hash_token(&token),
+            token_prefix,
+            scopes.to_db(),
+            expires_at
+        )
+        .execute(&*self.db)
+        .await?
+        .last_insert_id() as i32;
+
+        let info = self
+            .list(user_id)
+            .await?
+            .into_iter()
+            .find(|api_token| api_token.id == id)
+            .ok_or_else(|| AppError::InternalServerError("创建个人访问令牌失败".to_string()))?;
+
+        Ok(CreatedApiToken { token, info })
+    }
+
+    // 获取尚未撤销的令牌，包括已过期的
+    pub async fn list(&self, user_id: i32) -> AppResult<Vec<ApiToken>> {
+        let tokens = sqlx::query!(
+            r#"
+            SELECT id, name, token_prefix, scopes,
+                   expires_at as "expires_at: chrono::DateTime<chrono::Utc>",
+                   last_used_at as "last_used_at: chrono::DateTime<chrono::Utc>",
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM api_tokens
+            WHERE user_id = ? AND revoked_at IS NULL
+            ORDER BY id DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| ApiToken {
+            id: row.id,
+            name: row.name,
+            token_prefix: row.token_prefix,
+            scopes: ApiScopes::parse(&row.scopes).to_vec(),
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+            created_at: row.created_at.expect("创建时间不应为空"),
+        })
+        .collect();
+
+        Ok(tokens)
+    }
+
+    // 撤销令牌，之后使用该令牌的请求立即失败
+    pub async fn revoke(&self, user_id: i32, token_id: i32) -> AppResult<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND user_id = ? AND revoked_at IS NULL
+            "#,
+            token_id,
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFoundError(format!(
+                "个人访问令牌ID {} 不存在",
+                token_id
+            )));
+        }
+
+        Ok(())
+    }
+}
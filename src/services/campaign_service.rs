@@ -0,0 +1,724 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool, Transaction};
+
+use crate::config::Config;
+use crate::models::campaign::{
+    AddRecipientsRequest, AddRecipientsResponse, Campaign, CampaignRecipient, CampaignStats,
+    CreateCampaignRequest, DispatchResponse, InvitationView, RecipientPage, RecipientQuery,
+    RecipientStatus,
+};
+use crate::models::error::{AppError, AppResult};
+use crate::models::notification::NotificationKind;
+use crate::models::questionnaire::QuestionnaireStatus;
+use crate::services::notification_service::NotificationService;
+use crate::services::permission_service::{Access, PermissionService};
+use crate::services::questionnaire_service::QuestionnaireService;
+use crate::utils::auth::{generate_secret_token, hash_token};
+use crate::utils::mail::{MailMessage, MailTemplate};
+
+// 每个活动最多的受邀人数量
+const MAX_RECIPIENTS_PER_CAMPAIGN: i64 = 5000;
+// 同一受邀人两次提醒之间的最短间隔（小时）
+const MIN_REMINDER_INTERVAL: i64 = 24;
+
+const INVITATION_MAIL: MailTemplate = MailTemplate {
+    subject: "问卷邀请：{title}",
+    body: "{greeting}\n\n诚邀你填写问卷「{title}」。{message}\n\n请打开以下专属链接作答，链接只能提交一次，请勿转发：\n{link}{deadline}",
+};
+
+const REMINDER_MAIL: MailTemplate = MailTemplate {
+    subject: "提醒：请填写问卷「{title}」",
+    body: "{greeting}\n\n你还没有填写问卷「{title}」。{message}\n\n请打开以下专属链接作答：\n{link}{deadline}",
+};
+
+pub struct CampaignService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl CampaignService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 获取问卷的邀请活动
+    pub async fn list(&self, user_id: i32, questionnaire_id: i32) -> AppResult<Vec<Campaign>> {
+        self.authorize(user_id, questionnaire_id).await?;
+
+        let campaigns = sqlx::query!(
+            r#"
+            SELECT c.id, c.questionnaire_id, c.name, c.message,
+                   c.created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                   COUNT(r.id) as recipients, COUNT(r.sent_at) as sent,
+                   COUNT(r.opened_at) as opened, COUNT(r.started_at) as started,
+                   COUNT(r.completed_at) as completed
+            FROM campaigns c
+            LEFT JOIN campaign_recipients r ON r.campaign_id = c.id
+            WHERE c.questionnaire_id = ?
+            GROUP BY c.id, c.questionnaire_id, c.name, c.message, c.created_at
+            ORDER BY c.id DESC
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| Campaign {
+            id: row.id,
+            questionnaire_id: row.questionnaire_id,
+            name: row.name,
+            message: row.message,
+            stats: CampaignStats {
+                recipients: row.recipients,
+                sent: row.sent,
+                opened: row.opened,
+                started: row.started,
+                completed: row.completed,
+            },
+            created_at: row.created_at.expect("创建时间不应为空"),
+        })
+        .collect();
+
+        Ok(campaigns)
+    }
+
+    // 创建邀请活动
+    pub async fn create(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        req: CreateCampaignRequest,
+    ) -> AppResult<Campaign> {
+        self.authorize(user_id, questionnaire_id).await?;
+
+        let message = req
+            .message
+            .as_deref()
+            .map(str::trim)
+            .filter(|message| !message.is_empty());
+
+        let id = sqlx::query!(
+            r#"
+            INSERT INTO campaigns (questionnaire_id, name, message, created_by)
+            VALUES (?, ?, ?, ?)
+            "#,
+            questionnaire_id,
+            req.name.trim(),
+            message,
+            user_id
+        )
+        .execute(&*self.db)
+        .await?
+        .last_insert_id() as i32;
+
+        self.find(questionnaire_id, id).await
+    }
+
+    // 获取邀请活动及各阶段的人数
+    pub async fn get(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        campaign_id: i32,
+    ) -> AppResult<Campaign> {
+        self.authorize(user_id, questionnaire_id).await?;
+        self.find(questionnaire_id, campaign_id).await
+    }
+
+    // 删除邀请活动，受邀人的链接随之失效，已提交的回答保留
+    pub async fn delete(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        campaign_id: i32,
+    ) -> AppResult<()> {
+        self.authorize(user_id, questionnaire_id).await?;
+        self.find(questionnaire_id, campaign_id).await?;
+
+        sqlx::query!("DELETE FROM campaigns WHERE id = ?", campaign_id)
+            .execute(&*self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    // 添加受邀人，已在名单中的邮箱跳过
+    pub async fn add_recipients(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        campaign_id: i32,
+        req: AddRecipientsRequest,
+    ) -> AppResult<AddRecipientsResponse> {
+        self.authorize(user_id, questionnaire_id).await?;
+        self.find(questionnaire_id, campaign_id).await?;
+
+        // 邮箱不区分大小写，同一请求中重复的邮箱只保留第一个
+        let mut seen = HashSet::new();
+        let recipients: Vec<_> = req
+            .recipients
+            .into_iter()
+            .map(|recipient| {
+                let name = recipient
+                    .name
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty());
+                (recipient.email.trim().to_lowercase(), name)
+            })
+            .filter(|(email, _)| seen.insert(email.clone()))
+            .collect();
+
+        let mut tx = self.db.begin().await?;
+
+        // 锁定活动，避免并发添加时超过人数上限
+        sqlx::query!("SELECT id FROM campaigns WHERE id = ? FOR UPDATE", campaign_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let existing = sqlx::query!(
+            "SELECT COUNT(*) as count FROM campaign_recipients WHERE campaign_id = ?",
+            campaign_id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .count;
+
+        if existing + recipients.len() as i64 > MAX_RECIPIENTS_PER_CAMPAIGN {
+            return Err(AppError::validation(format!(
+                "每个活动最多{}个受邀人，当前已有{}个",
+                MAX_RECIPIENTS_PER_CAMPAIGN, existing
+            )));
+        }
+
+        // 链接令牌在发送邀请时生成，添加时先写入一个不对应任何链接的随机值
+        let mut added = 0;
+        for (email, name) in &recipients {
+            added += sqlx::query!(
+                r#"
+                INSERT IGNORE INTO campaign_recipients (campaign_id, email, name, token_hash)
+                VALUES (?, ?, ?, ?)
+                "#,
+                campaign_id,
+                email,
+                name,
+                hash_token(&generate_secret_token())
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        tx.commit().await?;
+
+        Ok(AddRecipientsResponse {
+            added,
+            skipped: recipients.len() as u64 - added,
+        })
+    }
+
+    // 分页获取受邀人及其所处的阶段
+    pub async fn list_recipients(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        campaign_id: i32,
+        query: RecipientQuery,
+    ) -> AppResult<RecipientPage> {
+        self.authorize(user_id, questionnaire_id).await?;
+        self.find(questionnaire_id, campaign_id).await?;
+
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+        let status = query.status.map(|status| status.as_str());
+
+        // 受邀人的阶段取已到达的最后一个阶段
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM campaign_recipients
+            WHERE campaign_id = ?
+              AND (? IS NULL OR CASE
+                    WHEN completed_at IS NOT NULL THEN 'completed'
+                    WHEN started_at IS NOT NULL THEN 'started'
+                    WHEN opened_at IS NOT NULL THEN 'opened'
+                    WHEN sent_at IS NOT NULL THEN 'sent'
+                    ELSE 'pending' END = ?)
+            "#,
+            campaign_id,
+            status,
+            status
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        // 匿名问卷不显示受邀人对应的回答
+        let recipients = sqlx::query!(
+            r#"
+            SELECT r.id, r.email, r.name, r.reminder_count,
+                   CASE
+                    WHEN r.completed_at IS NOT NULL THEN 'completed'
+                    WHEN r.started_at IS NOT NULL THEN 'started'
+                    WHEN r.opened_at IS NOT NULL THEN 'opened'
+                    WHEN r.sent_at IS NOT NULL THEN 'sent'
+                    ELSE 'pending' END as "status!: String",
+                   r.sent_at as "sent_at: chrono::DateTime<chrono::Utc>",
+                   r.opened_at as "opened_at: chrono::DateTime<chrono::Utc>",
+                   r.started_at as "started_at: chrono::DateTime<chrono::Utc>",
+                   r.completed_at as "completed_at: chrono::DateTime<chrono::Utc>",
+                   r.last_reminded_at as "last_reminded_at: chrono::DateTime<chrono::Utc>",
+                   IF(q.anonymous, NULL, r.response_id) as "response_id?: i32"
+            FROM campaign_recipients r
+            JOIN campaigns c ON r.campaign_id = c.id
+            JOIN questionnaires q ON c.questionnaire_id = q.id
+            WHERE r.campaign_id = ?
+              AND (? IS NULL OR CASE
+                    WHEN r.completed_at IS NOT NULL THEN 'completed'
+                    WHEN r.started_at IS NOT NULL THEN 'started'
+                    WHEN r.opened_at IS NOT NULL THEN 'opened'
+                    WHEN r.sent_at IS NOT NULL THEN 'sent'
+                    ELSE 'pending' END = ?)
+            ORDER BY r.id
+            LIMIT ? OFFSET ?
+            "#,
+            campaign_id,
+            status,
+            status,
+            page_size,
+            (page - 1) * page_size
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| CampaignRecipient {
+            id: row.id,
+            email: row.email,
+            name: row.name,
+            status: RecipientStatus::parse(&row.status).unwrap_or(RecipientStatus::Pending),
+            sent_at: row.sent_at,
+            opened_at: row.opened_at,
+            started_at: row.started_at,
+            completed_at: row.completed_at,
+            response_id: row.response_id,
+            reminder_count: row.reminder_count,
+            last_reminded_at: row.last_reminded_at,
+        })
+        .collect();
+
+        Ok(RecipientPage {
+            total,
+            page,
+            page_size,
+            recipients,
+        })
+    }
+
+    // 移除受邀人，其链接立即失效
+    pub async fn remove_recipient(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        campaign_id: i32,
+        recipient_id: i32,
+    ) -> AppResult<()> {
+        self.authorize(user_id, questionnaire_id).await?;
+        self.find(questionnaire_id, campaign_id).await?;
+
+        let result = sqlx::query!(
+            "DELETE FROM campaign_recipients WHERE id = ? AND campaign_id = ?",
+            recipient_id,
+            campaign_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFoundError(format!(
+                "受邀人ID {} 不存在",
+                recipient_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    // 向尚未发送过邀请的受邀人发送邀请邮件，链接令牌只出现在邮件中，数据库只保存哈希
+    pub async fn send(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        campaign_id: i32,
+    ) -> AppResult<DispatchResponse> {
+        self.authorize(user_id, questionnaire_id).await?;
+        let campaign = self.find(questionnaire_id, campaign_id).await?;
+
+        let mut tx = self.db.begin().await?;
+        let context =
+            Self::mail_context(&mut tx, questionnaire_id, campaign.message.as_deref()).await?;
+
+        let recipients = sqlx::query!(
+            r#"
+            SELECT id, email, name FROM campaign_recipients
+            WHERE campaign_id = ? AND sent_at IS NULL AND completed_at IS NULL
+            FOR UPDATE
+            "#,
+            campaign_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for recipient in &recipients {
+            let token = generate_secret_token();
+            let message = self.render(
+                &INVITATION_MAIL,
+                &context,
+                recipient.email.clone(),
+                recipient.name.as_deref(),
+                &token,
+            );
+            NotificationService::enqueue(&mut tx, None, NotificationKind::Invitation, message)
+                .await?;
+
+            sqlx::query!(
+                r#"
+                UPDATE campaign_recipients SET token_hash = ?, sent_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+                "#,
+                hash_token(&token),
+                recipient.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(DispatchResponse {
+            queued: recipients.len(),
+        })
+    }
+
+    // 只提醒已发送过邀请但尚未提交的受邀人，同一受邀人每24小时最多提醒一次；
+    // 提醒邮件中是新生成的链接，之前的链接随之失效
+    pub async fn remind(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        campaign_id: i32,
+    ) -> AppResult<DispatchResponse> {
+        self.authorize(user_id, questionnaire_id).await?;
+        let campaign = self.find(questionnaire_id, campaign_id).await?;
+
+        let mut tx = self.db.begin().await?;
+        let context =
+            Self::mail_context(&mut tx, questionnaire_id, campaign.message.as_deref()).await?;
+
+        let recipients = sqlx::query!(
+            r#"
+            SELECT id, email, name FROM campaign_recipients
+            WHERE campaign_id = ? AND sent_at IS NOT NULL AND completed_at IS NULL
+              AND (last_reminded_at IS NULL OR last_reminded_at < ?)
+            FOR UPDATE
+            "#,
+            campaign_id,
+            Utc::now() - Duration::hours(MIN_REMINDER_INTERVAL)
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for recipient in &recipients {
+            let token = generate_secret_token();
+            let message = self.render(
+                &REMINDER_MAIL,
+                &context,
+                recipient.email.clone(),
+                recipient.name.as_deref(),
+                &token,
+            );
+            NotificationService::enqueue(&mut tx, None, NotificationKind::Reminder, message)
+                .await?;
+
+            sqlx::query!(
+                r#"
+                UPDATE campaign_recipients
+                SET token_hash = ?, reminder_count = reminder_count + 1,
+                    last_reminded_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+                "#,
+                hash_token(&token),
+                recipient.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(DispatchResponse {
+            queued: recipients.len(),
+        })
+    }
+
+    // 受邀人打开专属链接，返回问卷内容并记录打开时间
+    pub async fn open(&self, token: &str) -> AppResult<InvitationView> {
+        let recipient = sqlx::query!(
+            r#"
+            SELECT r.id, r.name, r.completed_at as "completed_at: chrono::DateTime<chrono::Utc>",
+                   c.questionnaire_id
+            FROM campaign_recipients r
+            JOIN campaigns c ON r.campaign_id = c.id
+            WHERE r.token_hash = ?
+            "#,
+            hash_token(token)
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(invalid_link)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE campaign_recipients SET opened_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND opened_at IS NULL
+            "#,
+            recipient.id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        let questionnaire = QuestionnaireService::new(self.db.clone(), self.config.clone())
+            .get_questionnaire(recipient.questionnaire_id)
+            .await?;
+
+        Ok(InvitationView {
+            name: recipient.name,
+            completed: recipient.completed_at.is_some(),
+            questionnaire,
+        })
+    }
+
+    // 受邀人开始作答，由前端在第一次填写时调用
+    pub async fn start(&self, token: &str) -> AppResult<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE campaign_recipients
+            SET opened_at = COALESCE(opened_at, CURRENT_TIMESTAMP),
+                started_at = COALESCE(started_at, CURRENT_TIMESTAMP)
+            WHERE token_hash = ?
+            "#,
+            hash_token(token)
+        )
+        .execute(&*self.db)
+        .await?;
+
+        // 已记录过开始时间时影响行数同样为1，为0说明链接不存在
+        if result.rows_affected() == 0 {
+            return Err(invalid_link());
+        }
+
+        Ok(())
+    }
+
+    // 通过专属链接提交时锁定受邀人，检查链接属于该问卷且尚未使用，返回受邀人ID
+    pub(crate) async fn lock_recipient(
+        tx: &mut Transaction<'_, MySql>,
+        token: &str,
+        questionnaire_id: i32,
+    ) -> AppResult<i32> {
+        let recipient = sqlx::query!(
+            r#"
+            SELECT r.id, r.completed_at as "completed_at: chrono::DateTime<chrono::Utc>",
+                   c.questionnaire_id
+            FROM campaign_recipients r
+            JOIN campaigns c ON r.campaign_id = c.id
+            WHERE r.token_hash = ?
+            FOR UPDATE
+            "#,
+            hash_token(token)
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .filter(|recipient| recipient.questionnaire_id == questionnaire_id)
+        .ok_or_else(invalid_link)?;
+
+        if recipient.completed_at.is_some() {
+            return Err(AppError::validation("该邀请链接已经提交过回答，不能重复提交"));
+        }
+
+        Ok(recipient.id)
+    }
+
+    // 记录受邀人提交的回答，链接随之失效；匿名问卷不保存对应的回答，
+    // 各阶段的时间只保留到日期，避免按提交时间把受邀人和回答对应起来
+    pub(crate) async fn complete_recipient(
+        tx: &mut Transaction<'_, MySql>,
+        recipient_id: i32,
+        response_id: i32,
+    ) -> AppResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE campaign_recipients r
+            JOIN campaigns c ON r.campaign_id = c.id
+            JOIN questionnaires q ON c.questionnaire_id = q.id
+            SET r.opened_at = IF(q.anonymous, DATE(COALESCE(r.opened_at, CURRENT_TIMESTAMP)),
+                                 COALESCE(r.opened_at, CURRENT_TIMESTAMP)),
+                r.started_at = IF(q.anonymous, DATE(COALESCE(r.started_at, CURRENT_TIMESTAMP)),
+                                  COALESCE(r.started_at, CURRENT_TIMESTAMP)),
+                r.completed_at = IF(q.anonymous, CURRENT_DATE, CURRENT_TIMESTAMP),
+                r.response_id = IF(q.anonymous, NULL, ?)
+            WHERE r.id = ?
+            "#,
+            response_id,
+            recipient_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // 问卷改为匿名时解除已完成的受邀人与回答的对应关系，各阶段的时间只保留到日期，
+    // 之后再取消匿名也不会显示这些回答的受邀人
+    pub(crate) async fn detach_responses(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+    ) -> AppResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE campaign_recipients r
+            JOIN campaigns c ON r.campaign_id = c.id
+            SET r.response_id = NULL,
+                r.opened_at = DATE(r.opened_at),
+                r.started_at = DATE(r.started_at),
+                r.completed_at = DATE(r.completed_at)
+            WHERE c.questionnaire_id = ? AND r.completed_at IS NOT NULL
+            "#,
+            questionnaire_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // 只有已发布的问卷可以发送邀请，返回邮件中所有受邀人相同的部分
+    async fn mail_context(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        message: Option<&str>,
+    ) -> AppResult<MailContext> {
+        let questionnaire = sqlx::query!(
+            r#"
+            SELECT title, status, closes_at as "closes_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaires WHERE id = ?
+            "#,
+            questionnaire_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        let status = QuestionnaireStatus::parse(&questionnaire.status);
+        if status != Some(QuestionnaireStatus::Published) {
+            return Err(AppError::validation("问卷发布后才能发送邀请和提醒"));
+        }
+
+        Ok(MailContext {
+            title: questionnaire.title,
+            message: message
+                .map(|message| format!("\n\n{}", message))
+                .unwrap_or_default(),
+            deadline: questionnaire
+                .closes_at
+                .map(|closes_at| {
+                    format!("\n\n问卷将于{}截止。", closes_at.format("%Y-%m-%d %H:%M UTC"))
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    fn render(
+        &self,
+        template: &MailTemplate,
+        context: &MailContext,
+        to: String,
+        name: Option<&str>,
+        token: &str,
+    ) -> MailMessage {
+        let greeting = match name {
+            Some(name) => format!("{}，你好：", name),
+            None => "你好：".to_string(),
+        };
+
+        template.render(
+            to,
+            &[
+                ("greeting", &greeting),
+                ("title", &context.title),
+                ("message", &context.message),
+                ("link", &self.link(token)),
+                ("deadline", &context.deadline),
+            ],
+        )
+    }
+
+    fn link(&self, token: &str) -> String {
+        let url = &self.config.campaign.link_url;
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{}{}token={}", url, separator, token)
+    }
+
+    async fn find(&self, questionnaire_id: i32, campaign_id: i32) -> AppResult<Campaign> {
+        let row = sqlx::query!(
+            r#"
+            SELECT c.id, c.questionnaire_id, c.name, c.message,
+                   c.created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                   COUNT(r.id) as recipients, COUNT(r.sent_at) as sent,
+                   COUNT(r.opened_at) as opened, COUNT(r.started_at) as started,
+                   COUNT(r.completed_at) as completed
+            FROM campaigns c
+            LEFT JOIN campaign_recipients r ON r.campaign_id = c.id
+            WHERE c.id = ? AND c.questionnaire_id = ?
+            GROUP BY c.id, c.questionnaire_id, c.name, c.message, c.created_at
+            "#,
+            campaign_id,
+            questionnaire_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("邀请活动ID {} 不存在", campaign_id)))?;
+
+        Ok(Campaign {
+            id: row.id,
+            questionnaire_id: row.questionnaire_id,
+            name: row.name,
+            message: row.message,
+            stats: CampaignStats {
+                recipients: row.recipients,
+                sent: row.sent,
+                opened: row.opened,
+                started: row.started,
+                completed: row.completed,
+            },
+            created_at: row.created_at.expect("创建时间不应为空"),
+        })
+    }
+
+    // 受邀人名单包含邮箱，需要修改问卷的权限
+    async fn authorize(&self, user_id: i32, questionnaire_id: i32) -> AppResult<()> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::Edit)
+            .await?;
+        Ok(())
+    }
+}
+
+// 邀请和提醒邮件中所有受邀人相同的部分
+struct MailContext {
+    title: String,
+    message: String,  // 活动的说明，为空时不显示
+    deadline: String, // 问卷的截止时间，未设置时不显示
+}
+
+fn invalid_link() -> AppError {
+    AppError::NotFoundError("邀请链接无效或已失效".to_string())
+}
@@ -0,0 +1,375 @@
+use std::sync::Arc;
+use sqlx::{MySql, Pool};
+use tracing::error;
+
+use crate::config::Config;
+use crate::models::collaborator::{
+    Collaborator, CollaboratorRole, Invitation, InviteCollaboratorRequest, SharedQuestionnaire,
+    UpdateCollaboratorRequest,
+};
+use crate::models::error::{AppError, AppResult};
+use crate::services::permission_service::{Access, PermissionService};
+use crate::utils::mail::{mail_sender, MailMessage};
+
+pub struct CollaboratorService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl CollaboratorService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 获取问卷的协作者，包括尚未接受邀请的
+    pub async fn list(&self, user_id: i32, questionnaire_id: i32) -> AppResult<Vec<Collaborator>> {
+        self.permissions()
+            .authorize(user_id, questionnaire_id, Access::View)
+            .await?;
+
+        let collaborators = sqlx::query!(
+            r#"
+            SELECT c.user_id, u.username, u.nickname, c.role, c.status,
+                   inviter.username as "invited_by?",
+                   c.created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                   c.accepted_at as "accepted_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaire_collaborators c
+            JOIN users u ON c.user_id = u.id
+            LEFT JOIN users inviter ON c.invited_by = inviter.id
+            WHERE c.questionnaire_id = ?
+            ORDER BY c.created_at
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(Collaborator {
+                user_id: row.user_id,
+                username: row.username,
+                nickname: row.nickname,
+                role: parse_role(&row.role)?,
+                status: row.status,
+                invited_by: row.invited_by,
+                created_at: row.created_at.expect("创建时间不应为空"),
+                accepted_at: row.accepted_at,
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(collaborators)
+    }
+
+    // 按用户名或邮箱邀请协作者，对方接受后生效
+    pub async fn invite(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        req: InviteCollaboratorRequest,
+    ) -> AppResult<Collaborator> {
+        self.permissions()
+            .authorize(user_id, questionnaire_id, Access::Manage)
+            .await?;
+
+        let username = req.username.as_deref().map(str::trim).filter(|v| !v.is_empty());
+        let email = req.email.as_deref().map(str::trim).filter(|v| !v.is_empty());
+
+        let invitee = match (username, email) {
+            (Some(username), None) => {
+                sqlx::query!(
+                    "SELECT id, nickname, email FROM users WHERE username = ?",
+                    username
+                )
+                .fetch_optional(&*self.db)
+                .await?
+                .map(|user| (user.id, user.nickname, user.email))
+            }
+            (None, Some(email)) => {
+                sqlx::query!(
+                    "SELECT id, nickname, email FROM users WHERE email = ?",
+                    email
+                )
+                .fetch_optional(&*self.db)
+                .await?
+                .map(|user| (user.id, user.nickname, user.email))
+            }
+            _ => return Err(AppError::validation("请提供被邀请用户的用户名或邮箱之一")),
+        };
+        let (invitee_id, invitee_nickname, invitee_email) =
+            invitee.ok_or_else(|| AppError::NotFoundError("被邀请的用户不存在".to_string()))?;
+
+        let questionnaire = sqlx::query!(
+            "SELECT title, creator_id FROM questionnaires WHERE id = ?",
+            questionnaire_id
+        )
+        .fetch_one(&*self.db)
+        .await?;
+
+        if questionnaire.creator_id == Some(invitee_id) {
+            return Err(AppError::validation("不能邀请问卷的所有者"));
+        }
+
+        let existing = sqlx::query!(
+            r#"
+            SELECT status FROM questionnaire_collaborators
+            WHERE questionnaire_id = ? AND user_id = ?
+            "#,
+            questionnaire_id,
+            invitee_id
+        )
+        .fetch_optional(&*self.db)
+        .await?;
+
+        if let Some(existing) = existing {
+            return Err(AppError::validation(if existing.status == "accepted" {
+                "该用户已是问卷的协作者"
+            } else {
+                "已邀请该用户，正在等待对方接受"
+            }));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO questionnaire_collaborators (questionnaire_id, user_id, role, invited_by)
+            VALUES (?, ?, ?, ?)
+            "#,
+            questionnaire_id,
+            invitee_id,
+            req.role.as_str(),
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        // 通知被邀请人，发送失败不影响邀请本身
+        if let Some(to) = invitee_email {
+            let message = MailMessage {
+                to,
+                subject: format!("协作邀请：{}", questionnaire.title),
+                body: format!(
+                    "{}，你好：\n\n你被邀请以{}身份参与问卷「{}」，登录后可在协作邀请中接受或拒绝。",
+                    invitee_nickname,
+                    role_label(req.role),
+                    questionnaire.title
+                ),
+            };
+            if let Err(e) = mail_sender(&self.config.mail).send(message).await {
+                error!("Failed to send invitation mail to user {}: {}", invitee_id, e);
+            }
+        }
+
+        self.find(questionnaire_id, invitee_id).await
+    }
+
+    // 修改协作者的角色
+    pub async fn update_role(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        collaborator_id: i32,
+        req: UpdateCollaboratorRequest,
+    ) -> AppResult<Collaborator> {
+        self.permissions()
+            .authorize(user_id, questionnaire_id, Access::Manage)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE questionnaire_collaborators SET role = ?
+            WHERE questionnaire_id = ? AND user_id = ?
+            "#,
+            req.role.as_str(),
+            questionnaire_id,
+            collaborator_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        // 协作者不存在时返回404
+        self.find(questionnaire_id, collaborator_id).await
+    }
+
+    // 移除协作者或撤回邀请；协作者也可以自行退出
+    pub async fn remove(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        collaborator_id: i32,
+    ) -> AppResult<()> {
+        if user_id != collaborator_id {
+            self.permissions()
+                .authorize(user_id, questionnaire_id, Access::Manage)
+                .await?;
+        }
+
+        let result = sqlx::query!(
+            "DELETE FROM questionnaire_collaborators WHERE questionnaire_id = ? AND user_id = ?",
+            questionnaire_id,
+            collaborator_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFoundError("该用户不是问卷的协作者".to_string()));
+        }
+
+        Ok(())
+    }
+
+    // 获取我收到的尚未处理的邀请
+    pub async fn invitations(&self, user_id: i32) -> AppResult<Vec<Invitation>> {
+        let invitations = sqlx::query!(
+            r#"
+            SELECT c.id, c.questionnaire_id, q.title, c.role,
+                   inviter.username as "invited_by?",
+                   c.created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaire_collaborators c
+            JOIN questionnaires q ON c.questionnaire_id = q.id
+            LEFT JOIN users inviter ON c.invited_by = inviter.id
+            WHERE c.user_id = ? AND c.status = 'pending'
+            ORDER BY c.created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(Invitation {
+                id: row.id,
+                questionnaire_id: row.questionnaire_id,
+                questionnaire_title: row.title,
+                role: parse_role(&row.role)?,
+                invited_by: row.invited_by,
+                created_at: row.created_at.expect("创建时间不应为空"),
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(invitations)
+    }
+
+    // 接受邀请
+    pub async fn accept(&self, user_id: i32, invitation_id: i32) -> AppResult<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE questionnaire_collaborators
+            SET status = 'accepted', accepted_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND user_id = ? AND status = 'pending'
+            "#,
+            invitation_id,
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFoundError(format!("邀请ID {} 不存在", invitation_id)));
+        }
+
+        Ok(())
+    }
+
+    // 拒绝邀请
+    pub async fn decline(&self, user_id: i32, invitation_id: i32) -> AppResult<()> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM questionnaire_collaborators
+            WHERE id = ? AND user_id = ? AND status = 'pending'
+            "#,
+            invitation_id,
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFoundError(format!("邀请ID {} 不存在", invitation_id)));
+        }
+
+        Ok(())
+    }
+
+    // 获取与我共享的问卷
+    pub async fn shared_questionnaires(&self, user_id: i32) -> AppResult<Vec<SharedQuestionnaire>> {
+        let questionnaires = sqlx::query!(
+            r#"
+            SELECT q.id, q.title, q.status,
+                   COALESCE(o.name, u.username, '') as "owner!: String", c.role,
+                   c.accepted_at as "accepted_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaire_collaborators c
+            JOIN questionnaires q ON c.questionnaire_id = q.id
+            LEFT JOIN users u ON q.creator_id = u.id
+            LEFT JOIN organizations o ON q.organization_id = o.id
+            WHERE c.user_id = ? AND c.status = 'accepted'
+            ORDER BY c.accepted_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(SharedQuestionnaire {
+                id: row.id,
+                title: row.title,
+                status: row.status,
+                owner: row.owner,
+                role: parse_role(&row.role)?,
+                accepted_at: row.accepted_at,
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(questionnaires)
+    }
+
+    async fn find(&self, questionnaire_id: i32, collaborator_id: i32) -> AppResult<Collaborator> {
+        let row = sqlx::query!(
+            r#"
+            SELECT c.user_id, u.username, u.nickname, c.role, c.status,
+                   inviter.username as "invited_by?",
+                   c.created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                   c.accepted_at as "accepted_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaire_collaborators c
+            JOIN users u ON c.user_id = u.id
+            LEFT JOIN users inviter ON c.invited_by = inviter.id
+            WHERE c.questionnaire_id = ? AND c.user_id = ?
+            "#,
+            questionnaire_id,
+            collaborator_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("该用户不是问卷的协作者".to_string()))?;
+
+        Ok(Collaborator {
+            user_id: row.user_id,
+            username: row.username,
+            nickname: row.nickname,
+            role: parse_role(&row.role)?,
+            status: row.status,
+            invited_by: row.invited_by,
+            created_at: row.created_at.expect("创建时间不应为空"),
+            accepted_at: row.accepted_at,
+        })
+    }
+
+    fn permissions(&self) -> PermissionService {
+        PermissionService::new(self.db.clone())
+    }
+}
+
+fn parse_role(role: &str) -> AppResult<CollaboratorRole> {
+    CollaboratorRole::parse(role)
+        .ok_or_else(|| AppError::InternalServerError(format!("未知的协作者角色: {}", role)))
+}
+
+fn role_label(role: CollaboratorRole) -> &'static str {
+    match role {
+        CollaboratorRole::Editor => "编辑者",
+        CollaboratorRole::Viewer => "查看者",
+    }
+}
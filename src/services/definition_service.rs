@@ -0,0 +1,267 @@
+use std::sync::Arc;
+use sqlx::{MySql, Pool};
+use validator::Validate;
+
+use crate::config::Config;
+use crate::models::definition::{DefinitionFormat, ImportRequest, ImportResult};
+use crate::models::error::{AppError, AppResult};
+use crate::models::organization::OrganizationRole;
+use crate::models::logic::{
+    Condition, JumpRule, JumpTarget, OptionRef, QuestionLogic, QuestionRef, SectionRef,
+};
+use crate::models::questionnaire::{
+    CreateQuestionnaireRequest, OptionRequest, QuestionRequest, QuestionResponse,
+    QuestionnaireResponse, SectionRequest,
+};
+use crate::services::questionnaire_service::QuestionnaireService;
+use crate::services::permission_service::{Access, PermissionService};
+use crate::utils::definition::{self, ParsedDefinition};
+
+pub struct DefinitionService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl DefinitionService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 从JSON、YAML或文本格式导入问卷，dry_run时只返回解析结果
+    pub async fn import(
+        &self,
+        user_id: i32,
+        organization_id: Option<i32>,
+        req: ImportRequest,
+    ) -> AppResult<ImportResult> {
+        if let Some(organization_id) = organization_id {
+            PermissionService::new(self.db.clone())
+                .require_member(user_id, organization_id, OrganizationRole::Member)
+                .await?;
+        }
+
+        let parsed = match req.format {
+            DefinitionFormat::Json => definition::parse_json(&req.content),
+            DefinitionFormat::Yaml => definition::parse_yaml(&req.content),
+            DefinitionFormat::Text => definition::parse_text(&req.content),
+        }
+        .map_err(|errors| AppError::validation_fields("问卷定义格式不正确", errors))?;
+
+        parsed
+            .definition
+            .validate()
+            .map_err(|e| locate_error(&parsed, e.into()))?;
+
+        // 预览时同样在事务中创建问卷，以完成包括条件逻辑在内的全部验证，随后回滚
+        let mut tx = self.db.begin().await?;
+        let questionnaire_id =
+            QuestionnaireService::insert_definition(&mut tx, user_id, organization_id, &parsed.definition)
+                .await
+                .map_err(|e| locate_error(&parsed, e))?;
+
+        let questionnaire = if req.dry_run {
+            tx.rollback().await?;
+            None
+        } else {
+            tx.commit().await?;
+            Some(
+                QuestionnaireService::new(self.db.clone(), self.config.clone())
+                    .get_questionnaire(questionnaire_id)
+                    .await?,
+            )
+        };
+
+        Ok(ImportResult {
+            dry_run: req.dry_run,
+            section_count: parsed.definition.sections.len(),
+            question_count: parsed.definition.questions.len(),
+            definition: parsed.definition,
+            questionnaire,
+        })
+    }
+
+    // 导出问卷定义，结果可以直接用于导入
+    pub async fn export(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        format: DefinitionFormat,
+    ) -> AppResult<String> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::View)
+            .await?;
+
+        let questionnaire = QuestionnaireService::new(self.db.clone(), self.config.clone())
+            .get_questionnaire(questionnaire_id)
+            .await?;
+
+        let document = to_definition(questionnaire);
+
+        match format {
+            DefinitionFormat::Json => serde_json::to_string_pretty(&document)
+                .map_err(|e| e.to_string()),
+            DefinitionFormat::Yaml => definition::to_yaml(&document),
+            DefinitionFormat::Text => definition::to_text(&document),
+        }
+        .map_err(|e| AppError::InternalServerError(format!("导出问卷定义失败: {}", e)))
+    }
+}
+
+// 验证错误的字段加上所在行
+fn locate_error(parsed: &ParsedDefinition, error: AppError) -> AppError {
+    match error {
+        AppError::ValidationError { message, fields } => AppError::ValidationError {
+            message,
+            fields: parsed.locate(fields),
+        },
+        error => error,
+    }
+}
+
+// 转换为不含ID的问卷定义，条件逻辑中的引用改为问题位置和选项文本
+fn to_definition(questionnaire: QuestionnaireResponse) -> CreateQuestionnaireRequest {
+    let section_ids: Vec<i32> = questionnaire.sections.iter().map(|section| section.id).collect();
+    let questions = &questionnaire.questions;
+
+    let converted = questions
+        .iter()
+        .map(|question| QuestionRequest {
+            id: None,
+            title: question.title.clone(),
+            question_type: question.question_type.clone(),
+            required: question.required,
+            options: question
+                .options
+                .iter()
+                .map(|option| OptionRequest::Text(option.clone()))
+                .collect(),
+            rows: question
+                .rows
+                .iter()
+                .map(|row| OptionRequest::Text(row.text.clone()))
+                .collect(),
+            config: question.config.clone(),
+            logic: question
+                .logic
+                .as_ref()
+                .map(|logic| portable_logic(logic, questions, &section_ids)),
+            section: question
+                .section_id
+                .and_then(|id| section_ids.iter().position(|section| *section == id)),
+        })
+        .collect();
+
+    CreateQuestionnaireRequest {
+        title: questionnaire.title,
+        description: questionnaire.description,
+        is_public: questionnaire.is_public,
+        sections: questionnaire
+            .sections
+            .into_iter()
+            .map(|section| SectionRequest {
+                id: None,
+                title: section.title,
+                description: section.description,
+            })
+            .collect(),
+        questions: converted,
+    }
+}
+
+fn portable_logic(
+    logic: &QuestionLogic,
+    questions: &[QuestionResponse],
+    section_ids: &[i32],
+) -> QuestionLogic {
+    QuestionLogic {
+        show_if: logic
+            .show_if
+            .as_ref()
+            .map(|condition| portable_condition(condition, questions)),
+        jumps: logic
+            .jumps
+            .iter()
+            .map(|jump| JumpRule {
+                when: portable_condition(&jump.when, questions),
+                to: match &jump.to {
+                    JumpTarget::Question(question) => {
+                        JumpTarget::Question(portable_question(question, questions))
+                    }
+                    JumpTarget::Section(section) => {
+                        JumpTarget::Section(portable_section(section, section_ids))
+                    }
+                    JumpTarget::End => JumpTarget::End,
+                },
+            })
+            .collect(),
+    }
+}
+
+fn portable_condition(condition: &Condition, questions: &[QuestionResponse]) -> Condition {
+    match condition {
+        Condition::All { conditions } => Condition::All {
+            conditions: conditions
+                .iter()
+                .map(|c| portable_condition(c, questions))
+                .collect(),
+        },
+        Condition::Any { conditions } => Condition::Any {
+            conditions: conditions
+                .iter()
+                .map(|c| portable_condition(c, questions))
+                .collect(),
+        },
+        Condition::Not { condition } => Condition::Not {
+            condition: Box::new(portable_condition(condition, questions)),
+        },
+        Condition::Answered { question } => Condition::Answered {
+            question: portable_question(question, questions),
+        },
+        Condition::Selected { question, option } => {
+            let text = questions
+                .iter()
+                .find(|q| Some(q.id) == question.question_id)
+                .and_then(|q| q.option_items.iter().find(|o| Some(o.id) == option.option_id))
+                .map(|o| o.text.clone());
+            Condition::Selected {
+                question: portable_question(question, questions),
+                option: match text {
+                    Some(text) => OptionRef {
+                        option_id: None,
+                        option_text: Some(text),
+                    },
+                    None => option.clone(),
+                },
+            }
+        }
+        Condition::Compare {
+            question,
+            operator,
+            value,
+        } => Condition::Compare {
+            question: portable_question(question, questions),
+            operator: *operator,
+            value: *value,
+        },
+    }
+}
+
+fn portable_section(section: &SectionRef, section_ids: &[i32]) -> SectionRef {
+    match section_ids.iter().position(|id| Some(*id) == section.section_id) {
+        Some(index) => SectionRef {
+            section_id: None,
+            section_index: Some(index),
+        },
+        None => section.clone(),
+    }
+}
+
+fn portable_question(question: &QuestionRef, questions: &[QuestionResponse]) -> QuestionRef {
+    match questions.iter().position(|q| Some(q.id) == question.question_id) {
+        Some(index) => QuestionRef {
+            question_id: None,
+            question_index: Some(index),
+        },
+        None => question.clone(),
+    }
+}
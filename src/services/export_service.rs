@@ -0,0 +1,523 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use sqlx::{MySql, Pool};
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::config::Config;
+use crate::models::error::AppResult;
+use crate::models::export::{ExportFormat, ExportQuery, MultiSelectMode};
+use crate::services::permission_service::{Access, PermissionService};
+use crate::utils::export::{Cell, Column, ColumnKind, CsvWriter, ExportWriter, SpssWriter, XlsxWriter};
+
+// 缓冲区超过该大小时发送给客户端
+const FLUSH_SIZE: usize = 64 * 1024;
+
+pub type ExportChunk = Result<Vec<u8>, io::Error>;
+
+// 导出的文件，内容在后台任务中从数据库逐行读取并生成
+pub struct ExportFile {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub body: BoxStream<'static, ExportChunk>,
+}
+
+pub struct ExportService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl ExportService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 导出问卷的全部已提交回答
+    pub async fn export_responses(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        query: ExportQuery,
+    ) -> AppResult<ExportFile> {
+        // 检查问卷是否存在且用户是否有权限查看
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::View)
+            .await?;
+
+        // SPSS格式的选项一律使用数字编码，多选题按选项展开
+        let (layout, writer, filename, content_type): (_, Box<dyn ExportWriter>, _, _) =
+            match query.format {
+                ExportFormat::Csv => (
+                    self.load_layout(questionnaire_id, query.multi_select, false).await?,
+                    Box::new(CsvWriter),
+                    format!("questionnaire_{}_responses.csv", questionnaire_id),
+                    "text/csv; charset=utf-8",
+                ),
+                ExportFormat::Xlsx => (
+                    self.load_layout(questionnaire_id, query.multi_select, false).await?,
+                    Box::new(XlsxWriter::new()),
+                    format!("questionnaire_{}_responses.xlsx", questionnaire_id),
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                ),
+                ExportFormat::Spss => (
+                    self.load_layout(questionnaire_id, MultiSelectMode::OneHot, true).await?,
+                    Box::new(SpssWriter::new()),
+                    format!("questionnaire_{}_spss.zip", questionnaire_id),
+                    "application/zip",
+                ),
+            };
+
+        let (sender, receiver) = mpsc::channel::<ExportChunk>(4);
+        let db = self.db.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = write_rows(db, questionnaire_id, layout, writer, &sender).await {
+                error!("导出问卷 {} 的回答失败: {}", questionnaire_id, err);
+                // 发送错误使响应中断，避免客户端收到不完整却看似正常的文件
+                let _ = sender.send(Err(io::Error::other(err.to_string()))).await;
+            }
+        });
+
+        let body = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        })
+        .boxed();
+
+        Ok(ExportFile { filename, content_type, body })
+    }
+
+    // 读取问题和选项，确定导出的列
+    async fn load_layout(
+        &self,
+        questionnaire_id: i32,
+        multi_select: MultiSelectMode,
+        coded: bool,
+    ) -> AppResult<Layout> {
+        // 已停用但仍有历史回答的问题也一并导出
+        let questions = sqlx::query!(
+            r#"
+            SELECT q.id, q.title, q.question_type
+            FROM questions q
+            WHERE q.questionnaire_id = ?
+            AND (
+                q.retired_at IS NULL
+                OR EXISTS (SELECT 1 FROM question_responses qr WHERE qr.question_id = q.id)
+            )
+            ORDER BY q.retired_at IS NOT NULL, q.display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        let options = sqlx::query!(
+            r#"
+            SELECT qo.id, qo.question_id, qo.option_text, qo.role
+            FROM question_options qo
+            JOIN questions q ON qo.question_id = q.id
+            WHERE q.questionnaire_id = ?
+            AND (
+                qo.retired_at IS NULL
+                OR EXISTS (
+                    SELECT 1 FROM option_responses o
+                    WHERE o.option_id = qo.id OR o.row_option_id = qo.id
+                )
+            )
+            ORDER BY qo.retired_at IS NOT NULL, qo.display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        let questions = questions
+            .into_iter()
+            .map(|question| {
+                let mut layout = QuestionLayout {
+                    id: question.id,
+                    title: question.title,
+                    question_type: question.question_type,
+                    options: Vec::new(),
+                    rows: Vec::new(),
+                };
+                for option in options.iter().filter(|option| option.question_id == question.id) {
+                    let entry = (option.id, option.option_text.clone());
+                    if option.role == "row" {
+                        layout.rows.push(entry);
+                    } else {
+                        layout.options.push(entry);
+                    }
+                }
+                layout
+            })
+            .collect();
+
+        Ok(Layout::new(questions, multi_select, coded))
+    }
+}
+
+// 从数据库逐行读取回答并写出，同一份回答的所有行是连续的
+async fn write_rows(
+    db: Arc<Pool<MySql>>,
+    questionnaire_id: i32,
+    layout: Layout,
+    mut writer: Box<dyn ExportWriter>,
+    sender: &mpsc::Sender<ExportChunk>,
+) -> AppResult<()> {
+    let mut buffer = Vec::new();
+    writer.begin(&layout.columns, &mut buffer);
+
+    let mut rows = sqlx::query!(
+        r#"
+        SELECT
+            r.id as response_id,
+            r.submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
+            COALESCE(u.username, cr.email) as "respondent?",
+            qr.question_id as "question_id?",
+            tr.text_value as "text_value?",
+            nr.numeric_value as "numeric_value?",
+            o.option_id as "option_id?",
+            o.row_option_id as "row_option_id?",
+            o.rank_position as "rank_position?"
+        FROM questionnaire_responses r
+        JOIN questionnaires q ON r.questionnaire_id = q.id
+        LEFT JOIN users u ON r.respondent_id = u.id AND q.anonymous = FALSE
+        LEFT JOIN campaign_recipients cr ON cr.response_id = r.id AND q.anonymous = FALSE
+        LEFT JOIN question_responses qr ON qr.questionnaire_response_id = r.id
+        LEFT JOIN text_responses tr ON tr.question_response_id = qr.id
+        LEFT JOIN numeric_responses nr ON nr.question_response_id = qr.id
+        LEFT JOIN option_responses o ON o.question_response_id = qr.id
+        WHERE r.questionnaire_id = ? AND r.status = 'completed'
+        ORDER BY r.id, qr.id, o.id
+        "#,
+        questionnaire_id
+    )
+    .fetch(&*db);
+
+    let mut current: Option<ResponseRow> = None;
+
+    while let Some(row) = rows.try_next().await? {
+        if current.as_ref().map(|response| response.id) != Some(row.response_id) {
+            if let Some(response) = current.take() {
+                writer.row(&layout.cells(&response), &mut buffer);
+            }
+            current = Some(ResponseRow {
+                id: row.response_id,
+                submitted_at: row.submitted_at.map(|time| time.to_rfc3339()),
+                respondent: row.respondent,
+                answers: HashMap::new(),
+            });
+        }
+
+        let (Some(response), Some(question_id)) = (current.as_mut(), row.question_id) else {
+            continue;
+        };
+        let answer = response.answers.entry(question_id).or_default();
+        if row.text_value.is_some() {
+            answer.text = row.text_value;
+        }
+        if row.numeric_value.is_some() {
+            answer.number = row.numeric_value;
+        }
+        if let Some(option_id) = row.option_id {
+            answer.selections.push(Selection {
+                option_id,
+                row_option_id: row.row_option_id,
+                rank_position: row.rank_position,
+            });
+        }
+
+        if buffer.len() >= FLUSH_SIZE && !send(sender, &mut buffer).await {
+            return Ok(());
+        }
+    }
+
+    if let Some(response) = current.take() {
+        writer.row(&layout.cells(&response), &mut buffer);
+    }
+    writer.finish(&mut buffer);
+    send(sender, &mut buffer).await;
+
+    Ok(())
+}
+
+// 发送缓冲区中的内容，客户端已断开时返回false
+async fn send(sender: &mpsc::Sender<ExportChunk>, buffer: &mut Vec<u8>) -> bool {
+    sender.send(Ok(std::mem::take(buffer))).await.is_ok()
+}
+
+struct ResponseRow {
+    id: i32,
+    submitted_at: Option<String>,
+    respondent: Option<String>,
+    answers: HashMap<i32, Answer>,
+}
+
+#[derive(Default)]
+struct Answer {
+    text: Option<String>,
+    number: Option<f64>,
+    selections: Vec<Selection>,
+}
+
+struct Selection {
+    option_id: i32,
+    row_option_id: Option<i32>,
+    rank_position: Option<i32>,
+}
+
+struct QuestionLayout {
+    id: i32,
+    title: String,
+    question_type: String,
+    options: Vec<(i32, String)>, // 选项（矩阵题的列）
+    rows: Vec<(i32, String)>,    // 矩阵题的行
+}
+
+// 导出的列及每个问题的展开方式
+struct Layout {
+    questions: Vec<QuestionLayout>,
+    multi_select: MultiSelectMode,
+    coded: bool, // 单选使用选项序号而不是选项文本
+    columns: Vec<Column>,
+}
+
+impl Layout {
+    fn new(questions: Vec<QuestionLayout>, multi_select: MultiSelectMode, coded: bool) -> Self {
+        let mut layout = Self {
+            questions,
+            multi_select,
+            coded,
+            columns: Vec::new(),
+        };
+
+        let mut columns = vec![
+            column("response_id", "回答ID", ColumnKind::Number),
+            column("submitted_at", "提交时间", ColumnKind::Text),
+            column("respondent", "回答者", ColumnKind::Text),
+        ];
+        for (index, question) in layout.questions.iter().enumerate() {
+            layout.question_columns(&format!("Q{}", index + 1), question, &mut columns);
+        }
+        layout.columns = columns;
+
+        layout
+    }
+
+    fn question_columns(&self, name: &str, question: &QuestionLayout, columns: &mut Vec<Column>) {
+        let one_hot = self.multi_select == MultiSelectMode::OneHot;
+
+        match question.question_type.as_str() {
+            "rating" | "nps" | "number" => {
+                columns.push(column(name, &question.title, ColumnKind::Number));
+            }
+            "radio" => {
+                columns.push(column(name, &question.title, self.choice_kind(&question.options)));
+            }
+            "checkbox" if one_hot => {
+                for (index, (_, text)) in question.options.iter().enumerate() {
+                    columns.push(column(
+                        &format!("{}_{}", name, index + 1),
+                        &format!("{} - {}", question.title, text),
+                        self.flag_kind(),
+                    ));
+                }
+            }
+            "matrix_radio" => {
+                for (index, (_, row)) in question.rows.iter().enumerate() {
+                    columns.push(column(
+                        &format!("{}_{}", name, index + 1),
+                        &format!("{} - {}", question.title, row),
+                        self.choice_kind(&question.options),
+                    ));
+                }
+            }
+            "matrix_checkbox" => {
+                for (row_index, (_, row)) in question.rows.iter().enumerate() {
+                    if !one_hot {
+                        columns.push(column(
+                            &format!("{}_{}", name, row_index + 1),
+                            &format!("{} - {}", question.title, row),
+                            ColumnKind::Text,
+                        ));
+                        continue;
+                    }
+                    for (index, (_, text)) in question.options.iter().enumerate() {
+                        columns.push(column(
+                            &format!("{}_{}_{}", name, row_index + 1, index + 1),
+                            &format!("{} - {} - {}", question.title, row, text),
+                            self.flag_kind(),
+                        ));
+                    }
+                }
+            }
+            "ranking" => {
+                for (index, (_, text)) in question.options.iter().enumerate() {
+                    columns.push(column(
+                        &format!("{}_{}", name, index + 1),
+                        &format!("{} - {}", question.title, text),
+                        ColumnKind::Number,
+                    ));
+                }
+            }
+            // 文本类题型和合并展示的多选题
+            _ => {
+                columns.push(column(name, &question.title, ColumnKind::Text));
+            }
+        }
+    }
+
+    fn choice_kind(&self, options: &[(i32, String)]) -> ColumnKind {
+        if self.coded {
+            ColumnKind::Coded(
+                options
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (_, text))| (index as i32 + 1, text.clone()))
+                    .collect(),
+            )
+        } else {
+            ColumnKind::Text
+        }
+    }
+
+    fn flag_kind(&self) -> ColumnKind {
+        if self.coded {
+            ColumnKind::Coded(vec![(0, "未选".to_string()), (1, "已选".to_string())])
+        } else {
+            ColumnKind::Number
+        }
+    }
+
+    // 生成一份回答的所有单元格，顺序与columns一致
+    fn cells(&self, response: &ResponseRow) -> Vec<Cell> {
+        let mut cells = vec![
+            Cell::Number(response.id as f64),
+            text_cell(response.submitted_at.clone()),
+            text_cell(response.respondent.clone()),
+        ];
+
+        for question in &self.questions {
+            self.question_cells(question, response.answers.get(&question.id), &mut cells);
+        }
+
+        cells
+    }
+
+    fn question_cells(&self, question: &QuestionLayout, answer: Option<&Answer>, cells: &mut Vec<Cell>) {
+        let one_hot = self.multi_select == MultiSelectMode::OneHot;
+        let selected = |row_id: Option<i32>| -> Vec<&Selection> {
+            answer
+                .map(|answer| {
+                    answer
+                        .selections
+                        .iter()
+                        .filter(|selection| selection.row_option_id == row_id)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        match question.question_type.as_str() {
+            "rating" | "nps" | "number" => {
+                cells.push(answer.and_then(|answer| answer.number).map_or(Cell::Empty, Cell::Number));
+            }
+            "radio" => {
+                cells.push(self.choice_cell(&question.options, selected(None).first().copied()));
+            }
+            "checkbox" if one_hot => {
+                let chosen = selected(None);
+                for (option_id, _) in &question.options {
+                    cells.push(flag_cell(answer.is_some(), &chosen, *option_id));
+                }
+            }
+            "checkbox" => {
+                cells.push(joined_cell(&question.options, &selected(None), answer.is_some()));
+            }
+            "matrix_radio" => {
+                for (row_id, _) in &question.rows {
+                    let chosen = selected(Some(*row_id));
+                    cells.push(self.choice_cell(&question.options, chosen.first().copied()));
+                }
+            }
+            "matrix_checkbox" => {
+                for (row_id, _) in &question.rows {
+                    let chosen = selected(Some(*row_id));
+                    if one_hot {
+                        for (option_id, _) in &question.options {
+                            cells.push(flag_cell(answer.is_some(), &chosen, *option_id));
+                        }
+                    } else {
+                        cells.push(joined_cell(&question.options, &chosen, answer.is_some()));
+                    }
+                }
+            }
+            "ranking" => {
+                let chosen = selected(None);
+                for (option_id, _) in &question.options {
+                    cells.push(
+                        chosen
+                            .iter()
+                            .find(|selection| selection.option_id == *option_id)
+                            .and_then(|selection| selection.rank_position)
+                            .map_or(Cell::Empty, |rank| Cell::Number(rank as f64)),
+                    );
+                }
+            }
+            _ => {
+                cells.push(text_cell(answer.and_then(|answer| answer.text.clone())));
+            }
+        }
+    }
+
+    fn choice_cell(&self, options: &[(i32, String)], selection: Option<&Selection>) -> Cell {
+        let Some(selection) = selection else {
+            return Cell::Empty;
+        };
+        let Some(index) = options.iter().position(|(id, _)| *id == selection.option_id) else {
+            return Cell::Empty;
+        };
+
+        if self.coded {
+            Cell::Number(index as f64 + 1.0)
+        } else {
+            Cell::Text(options[index].1.clone())
+        }
+    }
+}
+
+fn column(name: &str, label: &str, kind: ColumnKind) -> Column {
+    Column {
+        name: name.to_string(),
+        label: label.to_string(),
+        kind,
+    }
+}
+
+fn text_cell(value: Option<String>) -> Cell {
+    value.map_or(Cell::Empty, Cell::Text)
+}
+
+// 未回答的问题留空，已回答的问题中未选的选项为0
+fn flag_cell(answered: bool, chosen: &[&Selection], option_id: i32) -> Cell {
+    if !answered {
+        return Cell::Empty;
+    }
+    let selected = chosen.iter().any(|selection| selection.option_id == option_id);
+    Cell::Number(if selected { 1.0 } else { 0.0 })
+}
+
+// 按选项顺序合并选中的选项文本
+fn joined_cell(options: &[(i32, String)], chosen: &[&Selection], answered: bool) -> Cell {
+    if !answered || chosen.is_empty() {
+        return Cell::Empty;
+    }
+    let texts: Vec<&str> = options
+        .iter()
+        .filter(|(id, _)| chosen.iter().any(|selection| selection.option_id == *id))
+        .map(|(_, text)| text.as_str())
+        .collect();
+    Cell::Text(texts.join("; "))
+}
@@ -0,0 +1,278 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult};
+use crate::models::questionnaire::{QuestionnaireResponse, QuestionnaireStatus, ScheduleRequest};
+use crate::models::webhook::{QuestionnaireEventData, WebhookEvent};
+use crate::services::questionnaire_service::QuestionnaireService;
+use crate::services::permission_service::{Access, PermissionService};
+use crate::services::version_service::VersionService;
+use crate::services::webhook_service::WebhookService;
+
+pub struct LifecycleService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl LifecycleService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 变更问卷状态，首次发布时生成版本
+    pub async fn change_status(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        target: QuestionnaireStatus,
+    ) -> AppResult<QuestionnaireResponse> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::Edit)
+            .await?;
+
+        let questionnaire = sqlx::query!(
+            r#"
+            SELECT title, status, current_version_id,
+                   closes_at as "closes_at: DateTime<Utc>"
+            FROM questionnaires
+            WHERE id = ?
+            "#,
+            questionnaire_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("问卷ID {} 不存在", questionnaire_id)))?;
+
+        let current = parse_status(&questionnaire.status)?;
+        if current == target {
+            return Err(AppError::validation(format!(
+                "问卷已处于{}状态",
+                target.as_str()
+            )));
+        }
+        if !current.can_transition_to(target) {
+            return Err(AppError::validation(format!(
+                "问卷不能从{}状态变为{}状态",
+                current.as_str(),
+                target.as_str()
+            )));
+        }
+
+        if target == QuestionnaireStatus::Published {
+            let question_count = sqlx::query!(
+                r#"
+                SELECT COUNT(*) as count FROM questions
+                WHERE questionnaire_id = ? AND retired_at IS NULL
+                "#,
+                questionnaire_id
+            )
+            .fetch_one(&*self.db)
+            .await?
+            .count;
+
+            if question_count == 0 {
+                return Err(AppError::validation("问卷至少需要一个问题才能发布"));
+            }
+
+            if questionnaire.closes_at.is_some_and(|closes_at| closes_at <= Utc::now()) {
+                return Err(AppError::validation("问卷的截止时间已过，请先修改截止时间"));
+            }
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            "UPDATE questionnaires SET status = ? WHERE id = ?",
+            target.as_str(),
+            questionnaire_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let event = match target {
+            QuestionnaireStatus::Published => Some(WebhookEvent::QuestionnairePublished),
+            QuestionnaireStatus::Closed => Some(WebhookEvent::QuestionnaireClosed),
+            _ => None,
+        };
+        if let Some(event) = event {
+            WebhookService::notify(
+                &mut tx,
+                questionnaire_id,
+                event,
+                &QuestionnaireEventData {
+                    id: questionnaire_id,
+                    title: questionnaire.title,
+                    status: target,
+                    trigger: "manual",
+                },
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        // 首次发布时保存问卷结构的快照，之后的修改会自动生成新版本
+        if target == QuestionnaireStatus::Published && questionnaire.current_version_id.is_none() {
+            VersionService::new(self.db.clone(), self.config.clone())
+                .create_version(user_id, questionnaire_id)
+                .await?;
+        }
+
+        QuestionnaireService::new(self.db.clone(), self.config.clone())
+            .get_questionnaire(questionnaire_id)
+            .await
+    }
+
+    // 设置问卷接受回答的时间范围
+    pub async fn update_schedule(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        req: ScheduleRequest,
+    ) -> AppResult<QuestionnaireResponse> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::Edit)
+            .await?;
+
+        if let (Some(opens_at), Some(closes_at)) = (req.opens_at, req.closes_at) {
+            if opens_at >= closes_at {
+                return Err(AppError::validation("开始时间必须早于截止时间"));
+            }
+        }
+
+        sqlx::query!(
+            "UPDATE questionnaires SET opens_at = ?, closes_at = ? WHERE id = ?",
+            req.opens_at,
+            req.closes_at,
+            questionnaire_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        QuestionnaireService::new(self.db.clone(), self.config.clone())
+            .get_questionnaire(questionnaire_id)
+            .await
+    }
+
+    // 关闭已到截止时间的问卷，返回关闭的数量，由后台任务定时调用
+    pub async fn close_expired(db: &Pool<MySql>) -> AppResult<u64> {
+        let mut tx = db.begin().await?;
+
+        let expired = sqlx::query!(
+            r#"
+            SELECT id, title FROM questionnaires
+            WHERE status IN ('published', 'paused')
+            AND closes_at IS NOT NULL AND closes_at <= CURRENT_TIMESTAMP
+            FOR UPDATE
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for questionnaire in &expired {
+            sqlx::query!(
+                "UPDATE questionnaires SET status = 'closed' WHERE id = ?",
+                questionnaire.id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            WebhookService::notify(
+                &mut tx,
+                questionnaire.id,
+                WebhookEvent::QuestionnaireClosed,
+                &QuestionnaireEventData {
+                    id: questionnaire.id,
+                    title: questionnaire.title.clone(),
+                    status: QuestionnaireStatus::Closed,
+                    trigger: "schedule",
+                },
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(expired.len() as u64)
+    }
+
+    // 按组织设置的保留天数删除过期的回答，返回删除的数量，由后台任务定时调用
+    pub async fn purge_expired_responses(db: &Pool<MySql>) -> AppResult<u64> {
+        let mut tx = db.begin().await?;
+
+        // 删除受影响问卷的统计汇总，下次查看统计时由剩余的回答重新生成
+        sqlx::query!(
+            r#"
+            DELETE s FROM questionnaire_statistics s
+            JOIN questionnaires q ON s.questionnaire_id = q.id
+            JOIN organizations o ON q.organization_id = o.id
+            WHERE o.response_retention_days IS NOT NULL
+            AND EXISTS (
+                SELECT 1 FROM questionnaire_responses r
+                WHERE r.questionnaire_id = q.id
+                AND COALESCE(r.submitted_at, r.created_at)
+                    < CURRENT_TIMESTAMP - INTERVAL o.response_retention_days DAY
+            )
+            "#
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // 问题回答、选项、文本和标签随回答级联删除
+        let result = sqlx::query!(
+            r#"
+            DELETE r FROM questionnaire_responses r
+            JOIN questionnaires q ON r.questionnaire_id = q.id
+            JOIN organizations o ON q.organization_id = o.id
+            WHERE o.response_retention_days IS NOT NULL
+            AND COALESCE(r.submitted_at, r.created_at)
+                < CURRENT_TIMESTAMP - INTERVAL o.response_retention_days DAY
+            "#
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// 检查问卷当前是否接受回答
+pub fn ensure_accepting(
+    status: &str,
+    opens_at: Option<DateTime<Utc>>,
+    closes_at: Option<DateTime<Utc>>,
+) -> AppResult<()> {
+    let now = Utc::now();
+
+    match parse_status(status)? {
+        QuestionnaireStatus::Published => {}
+        QuestionnaireStatus::Draft => {
+            return Err(AppError::QuestionnaireNotOpen("问卷尚未发布".to_string()))
+        }
+        QuestionnaireStatus::Paused => {
+            return Err(AppError::QuestionnaireNotOpen("问卷已暂停收集".to_string()))
+        }
+        QuestionnaireStatus::Closed | QuestionnaireStatus::Archived => {
+            return Err(AppError::QuestionnaireNotOpen("问卷已关闭".to_string()))
+        }
+    }
+
+    if opens_at.is_some_and(|opens_at| now < opens_at) {
+        return Err(AppError::QuestionnaireNotOpen("问卷尚未开始收集".to_string()));
+    }
+
+    if closes_at.is_some_and(|closes_at| now >= closes_at) {
+        return Err(AppError::QuestionnaireNotOpen("问卷已过截止时间".to_string()));
+    }
+
+    Ok(())
+}
+
+pub fn parse_status(status: &str) -> AppResult<QuestionnaireStatus> {
+    QuestionnaireStatus::parse(status)
+        .ok_or_else(|| AppError::InternalServerError(format!("未知的问卷状态: {}", status)))
+}
@@ -1,3 +1,22 @@
 pub mod user_service;
 pub mod questionnaire_service;
-pub mod response_service; 
\ No newline at end of file
+pub mod response_service;
+pub mod version_service;
+pub mod answer_validator;
+pub mod question_logic;pub mod lifecycle_service;
+pub mod quota_service;
+pub mod export_service;
+pub mod definition_service;
+pub mod statistics_service;
+pub mod text_analysis_service;
+pub mod session_service;
+pub mod permission_service;
+pub mod collaborator_service;
+pub mod admin_service;
+pub mod organization_service;
+pub mod two_factor_service;
+pub mod sso_service;
+pub mod api_token_service;
+pub mod webhook_service;
+pub mod notification_service;
+pub mod campaign_service;
\ No newline at end of file
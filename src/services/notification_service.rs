@@ -0,0 +1,638 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Timelike, Utc};
+use chrono_tz::Tz;
+use sqlx::{MySql, Pool, Transaction};
+
+use crate::config::{MailConfig, NotificationConfig};
+use crate::models::error::AppResult;
+use crate::models::notification::{
+    NotificationKind, NotificationMode, NotificationPreferences, QuestionnaireNotificationSetting,
+    UpdateNotificationPreferencesRequest, UpdateQuestionnaireNotificationRequest,
+};
+use crate::models::response::AnswerDetail;
+use crate::services::permission_service::{Access, PermissionService};
+use crate::services::response_service::ResponseService;
+use crate::services::user_service::field_error;
+use crate::utils::mail::{mail_sender, MailMessage, MailTemplate};
+
+// 发送中的邮件占用的时间（秒），超过后其他实例可以重新发送
+const SEND_LEASE: i64 = 300;
+// 每日摘要最多统计的天数，避免重新开启摘要时把很久以前的回答也算进去
+const MAX_DIGEST_DAYS: i64 = 7;
+const MAX_SUBJECT_LENGTH: usize = 255;
+const MAX_ERROR_LENGTH: usize = 500;
+
+const RESPONSE_MAIL: MailTemplate = MailTemplate {
+    subject: "新回答：{title}",
+    body: "{nickname}，你好：\n\n问卷「{title}」收到了一份新回答。\n\n回答编号：{response_id}\n提交人：{respondent}\n提交时间：{submitted_at}\n\n{answers}\n\n如需改为每日摘要或关闭通知，请修改通知设置。",
+};
+
+const RECEIPT_MAIL: MailTemplate = MailTemplate {
+    subject: "回答副本：{title}",
+    body: "{nickname}，你好：\n\n感谢你填写问卷「{title}」，以下是你在{submitted_at}提交的回答：\n\n{answers}\n\n如不需要回答副本，可以在通知设置中关闭。",
+};
+
+const DIGEST_MAIL: MailTemplate = MailTemplate {
+    subject: "每日摘要：{count}份新回答",
+    body: "{nickname}，你好：\n\n{from}至{to}期间，你订阅的问卷共收到{count}份新回答：\n\n{questionnaires}\n\n如需改为逐份通知或关闭通知，请修改通知设置。",
+};
+
+pub struct NotificationService {
+    db: Arc<Pool<MySql>>,
+}
+
+impl NotificationService {
+    pub fn new(db: Arc<Pool<MySql>>) -> Self {
+        Self { db }
+    }
+
+    // 获取通知设置，没有保存过时返回默认设置
+    pub async fn get_preferences(&self, user_id: i32) -> AppResult<NotificationPreferences> {
+        let row = sqlx::query!(
+            r#"
+            SELECT response_mode, receipt as "receipt: bool", digest_hour, timezone
+            FROM notification_preferences WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(&*self.db)
+        .await?;
+
+        let questionnaires = sqlx::query!(
+            r#"
+            SELECT s.questionnaire_id, q.title, s.response_mode
+            FROM questionnaire_notification_settings s
+            JOIN questionnaires q ON s.questionnaire_id = q.id
+            WHERE s.user_id = ?
+            ORDER BY s.questionnaire_id
+            "#,
+            user_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|setting| QuestionnaireNotificationSetting {
+            questionnaire_id: setting.questionnaire_id,
+            title: setting.title,
+            response_mode: NotificationMode::parse(&setting.response_mode),
+            overridden: true,
+        })
+        .collect();
+
+        Ok(match row {
+            Some(row) => NotificationPreferences {
+                response_mode: NotificationMode::parse(&row.response_mode),
+                receipt: row.receipt,
+                digest_hour: row.digest_hour as u8,
+                timezone: row.timezone,
+                questionnaires,
+            },
+            None => NotificationPreferences {
+                response_mode: NotificationMode::Off,
+                receipt: true,
+                digest_hour: 8,
+                timezone: "UTC".to_string(),
+                questionnaires,
+            },
+        })
+    }
+
+    // 修改通知设置，未提供的字段保持不变
+    pub async fn update_preferences(
+        &self,
+        user_id: i32,
+        req: UpdateNotificationPreferencesRequest,
+    ) -> AppResult<NotificationPreferences> {
+        if let Some(timezone) = &req.timezone {
+            if timezone.parse::<Tz>().is_err() {
+                return Err(field_error("timezone", &format!("无效的时区: {}", timezone)));
+            }
+        }
+
+        let current = self.get_preferences(user_id).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO notification_preferences
+                (user_id, response_mode, receipt, digest_hour, timezone)
+            VALUES (?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                response_mode = VALUES(response_mode), receipt = VALUES(receipt),
+                digest_hour = VALUES(digest_hour), timezone = VALUES(timezone)
+            "#,
+            user_id,
+            req.response_mode.unwrap_or(current.response_mode).as_str(),
+            req.receipt.unwrap_or(current.receipt),
+            req.digest_hour.unwrap_or(current.digest_hour) as i8,
+            req.timezone.unwrap_or(current.timezone)
+        )
+        .execute(&*self.db)
+        .await?;
+
+        self.get_preferences(user_id).await
+    }
+
+    // 获取对某份问卷实际生效的通知方式
+    pub async fn get_questionnaire_setting(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+    ) -> AppResult<QuestionnaireNotificationSetting> {
+        self.authorize(user_id, questionnaire_id).await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT q.title, q.creator_id,
+                   s.response_mode as "override_mode?",
+                   p.response_mode as "default_mode?"
+            FROM questionnaires q
+            LEFT JOIN questionnaire_notification_settings s
+                ON s.questionnaire_id = q.id AND s.user_id = ?
+            LEFT JOIN notification_preferences p ON p.user_id = ?
+            WHERE q.id = ?
+            "#,
+            user_id,
+            user_id,
+            questionnaire_id
+        )
+        .fetch_one(&*self.db)
+        .await?;
+
+        // 没有单独设置时，自己创建的问卷使用默认的通知方式，其他问卷不通知
+        let response_mode = match (&row.override_mode, &row.default_mode) {
+            (Some(mode), _) => NotificationMode::parse(mode),
+            (None, Some(mode)) if row.creator_id == Some(user_id) => NotificationMode::parse(mode),
+            _ => NotificationMode::Off,
+        };
+
+        Ok(QuestionnaireNotificationSetting {
+            questionnaire_id,
+            title: row.title,
+            response_mode,
+            overridden: row.override_mode.is_some(),
+        })
+    }
+
+    // 单独设置某份问卷的通知方式，可以查看问卷的用户都可以订阅
+    pub async fn update_questionnaire_setting(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        req: UpdateQuestionnaireNotificationRequest,
+    ) -> AppResult<QuestionnaireNotificationSetting> {
+        self.authorize(user_id, questionnaire_id).await?;
+
+        let mut tx = self.db.begin().await?;
+
+        // 生成每日摘要时从通知设置表中查找订阅了摘要的用户
+        sqlx::query!(
+            "INSERT IGNORE INTO notification_preferences (user_id) VALUES (?)",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO questionnaire_notification_settings (user_id, questionnaire_id, response_mode)
+            VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE response_mode = VALUES(response_mode)
+            "#,
+            user_id,
+            questionnaire_id,
+            req.response_mode.as_str()
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get_questionnaire_setting(user_id, questionnaire_id).await
+    }
+
+    // 删除单独的设置，恢复使用默认的通知方式
+    pub async fn reset_questionnaire_setting(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+    ) -> AppResult<QuestionnaireNotificationSetting> {
+        self.authorize(user_id, questionnaire_id).await?;
+
+        sqlx::query!(
+            "DELETE FROM questionnaire_notification_settings WHERE user_id = ? AND questionnaire_id = ?",
+            user_id,
+            questionnaire_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        self.get_questionnaire_setting(user_id, questionnaire_id).await
+    }
+
+    // 提交回答时写入发件箱：给选择逐份通知的用户发送新回答邮件，给提交人发送回答副本
+    pub(crate) async fn enqueue_response(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        response_id: i32,
+        respondent_id: Option<i32>,
+    ) -> AppResult<()> {
+        // 单独设置的订阅需要用户仍然可以查看问卷，协作者被移除后不再收到通知
+        let recipients = sqlx::query!(
+            r#"
+            SELECT u.id as "id!: i32", u.nickname as "nickname!: String",
+                   u.email as "email!: String", p.timezone as "timezone?: String"
+            FROM questionnaires q
+            JOIN users u ON u.id = q.creator_id
+            LEFT JOIN notification_preferences p ON p.user_id = u.id
+            LEFT JOIN questionnaire_notification_settings s
+                ON s.user_id = u.id AND s.questionnaire_id = q.id
+            WHERE q.id = ? AND u.email IS NOT NULL
+              AND COALESCE(s.response_mode, p.response_mode) = 'instant'
+            UNION
+            SELECT u.id, u.nickname, u.email, p.timezone
+            FROM questionnaire_notification_settings s
+            JOIN questionnaires q ON q.id = s.questionnaire_id
+            JOIN users u ON u.id = s.user_id
+            LEFT JOIN notification_preferences p ON p.user_id = u.id
+            WHERE s.questionnaire_id = ? AND s.response_mode = 'instant' AND u.email IS NOT NULL
+              AND (u.role = 'admin'
+                   OR EXISTS (SELECT 1 FROM questionnaire_collaborators c
+                              WHERE c.questionnaire_id = q.id AND c.user_id = u.id
+                              AND c.status = 'accepted')
+                   OR EXISTS (SELECT 1 FROM organization_members m
+                              WHERE m.organization_id = q.organization_id AND m.user_id = u.id))
+            "#,
+            questionnaire_id,
+            questionnaire_id
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let respondent = match respondent_id {
+            Some(respondent_id) => sqlx::query!(
+                r#"
+                SELECT u.nickname, u.email, p.receipt as "receipt?: bool",
+                       p.timezone as "timezone?"
+                FROM users u
+                LEFT JOIN notification_preferences p ON p.user_id = u.id
+                WHERE u.id = ?
+                "#,
+                respondent_id
+            )
+            .fetch_optional(&mut **tx)
+            .await?
+            .filter(|user| user.receipt.unwrap_or(true))
+            .and_then(|user| Some((respondent_id, user.nickname, user.email?, user.timezone))),
+            None => None,
+        };
+
+        if recipients.is_empty() && respondent.is_none() {
+            return Ok(());
+        }
+
+        // 匿名问卷的回答详情中不含提交人
+        let response = ResponseService::load_response_detail(&mut **tx, response_id).await?;
+        let submitted_at = response.submitted_at.unwrap_or(response.created_at);
+        let answers = format_answers(&response.answers);
+        let response_id = response_id.to_string();
+        let respondent_name = response.respondent.as_deref().unwrap_or("匿名");
+
+        for recipient in recipients {
+            let submitted_at = local_time(submitted_at, recipient.timezone.as_deref());
+            let message = RESPONSE_MAIL.render(
+                recipient.email,
+                &[
+                    ("nickname", &recipient.nickname),
+                    ("title", &response.questionnaire_title),
+                    ("response_id", &response_id),
+                    ("respondent", respondent_name),
+                    ("submitted_at", &submitted_at),
+                    ("answers", &answers),
+                ],
+            );
+            Self::enqueue(tx, Some(recipient.id), NotificationKind::Response, message).await?;
+        }
+
+        if let Some((user_id, nickname, email, timezone)) = respondent {
+            let submitted_at = local_time(submitted_at, timezone.as_deref());
+            let message = RECEIPT_MAIL.render(
+                email,
+                &[
+                    ("nickname", &nickname),
+                    ("title", &response.questionnaire_title),
+                    ("submitted_at", &submitted_at),
+                    ("answers", &answers),
+                ],
+            );
+            Self::enqueue(tx, Some(user_id), NotificationKind::Receipt, message).await?;
+        }
+
+        Ok(())
+    }
+
+    // 写入发件箱，由后台任务发送
+    pub(crate) async fn enqueue(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: Option<i32>,
+        kind: NotificationKind,
+        message: MailMessage,
+    ) -> AppResult<()> {
+        let subject: String = message.subject.chars().take(MAX_SUBJECT_LENGTH).collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO notification_outbox
+                (user_id, kind, recipient, subject, body, next_attempt_at)
+            VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+            "#,
+            user_id,
+            kind.as_str(),
+            message.to,
+            subject,
+            message.body
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // 为到达发送时间的用户生成每日摘要，返回生成的摘要数量，由后台任务定时调用
+    pub async fn enqueue_digests(db: &Pool<MySql>) -> AppResult<u64> {
+        let candidates = sqlx::query!(
+            r#"
+            SELECT p.user_id, p.response_mode, p.digest_hour, p.timezone,
+                   p.last_digest_at as "last_digest_at: chrono::DateTime<chrono::Utc>",
+                   u.nickname, u.email as "email!"
+            FROM notification_preferences p
+            JOIN users u ON u.id = p.user_id
+            WHERE u.email IS NOT NULL
+              AND (p.response_mode = 'daily'
+                   OR EXISTS (SELECT 1 FROM questionnaire_notification_settings s
+                              WHERE s.user_id = p.user_id AND s.response_mode = 'daily'))
+            "#
+        )
+        .fetch_all(db)
+        .await?;
+
+        let now = Utc::now();
+        let mut enqueued = 0;
+
+        for user in candidates {
+            let timezone: Tz = user.timezone.parse().unwrap_or(Tz::UTC);
+            let local_now = now.with_timezone(&timezone);
+            let sent_today = user.last_digest_at.is_some_and(|last| {
+                last.with_timezone(&timezone).date_naive() == local_now.date_naive()
+            });
+            if sent_today || local_now.hour() < user.digest_hour as u32 {
+                continue;
+            }
+
+            let since = user
+                .last_digest_at
+                .unwrap_or(now - Duration::days(1))
+                .max(now - Duration::days(MAX_DIGEST_DAYS));
+
+            let mut tx = db.begin().await?;
+
+            // 先更新摘要时间占用这次摘要，多个实例同时运行时不会重复生成
+            let claimed = sqlx::query!(
+                r#"
+                UPDATE notification_preferences SET last_digest_at = ?
+                WHERE user_id = ? AND last_digest_at <=> ?
+                "#,
+                now,
+                user.user_id,
+                user.last_digest_at
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if claimed.rows_affected() == 0 {
+                continue;
+            }
+
+            // 与逐份通知相同，单独设置的订阅需要用户仍然可以查看问卷
+            let counts = sqlx::query!(
+                r#"
+                SELECT q.id, q.title, COUNT(*) as count
+                FROM questionnaires q
+                JOIN questionnaire_responses r ON r.questionnaire_id = q.id
+                LEFT JOIN questionnaire_notification_settings s
+                    ON s.questionnaire_id = q.id AND s.user_id = ?
+                WHERE r.status = 'completed' AND r.submitted_at > ? AND r.submitted_at <= ?
+                  AND COALESCE(s.response_mode, IF(q.creator_id = ?, ?, 'off')) = 'daily'
+                  AND (q.creator_id = ?
+                       OR EXISTS (SELECT 1 FROM users a WHERE a.id = ? AND a.role = 'admin')
+                       OR EXISTS (SELECT 1 FROM questionnaire_collaborators c
+                                  WHERE c.questionnaire_id = q.id AND c.user_id = ?
+                                  AND c.status = 'accepted')
+                       OR EXISTS (SELECT 1 FROM organization_members m
+                                  WHERE m.organization_id = q.organization_id AND m.user_id = ?))
+                GROUP BY q.id, q.title
+                ORDER BY count DESC, q.id
+                "#,
+                user.user_id,
+                since,
+                now,
+                user.user_id,
+                user.response_mode,
+                user.user_id,
+                user.user_id,
+                user.user_id,
+                user.user_id
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+
+            // 期间没有新回答时不发送邮件
+            let total: i64 = counts.iter().map(|row| row.count).sum();
+            if total > 0 {
+                let questionnaires = counts
+                    .iter()
+                    .map(|row| format!("- 「{}」：{}份", row.title, row.count))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let message = DIGEST_MAIL.render(
+                    user.email,
+                    &[
+                        ("nickname", &user.nickname),
+                        ("count", &total.to_string()),
+                        ("from", &local_time(since, Some(&user.timezone))),
+                        ("to", &local_time(now, Some(&user.timezone))),
+                        ("questionnaires", &questionnaires),
+                    ],
+                );
+                Self::enqueue(&mut tx, Some(user.user_id), NotificationKind::Digest, message)
+                    .await?;
+                enqueued += 1;
+            }
+
+            tx.commit().await?;
+        }
+
+        Ok(enqueued)
+    }
+
+    // 发送发件箱中已到时间的邮件，返回发送成功的数量，由后台任务定时调用
+    pub async fn send_due(
+        db: &Pool<MySql>,
+        mail: &MailConfig,
+        config: &NotificationConfig,
+    ) -> AppResult<u64> {
+        let due = sqlx::query!(
+            r#"
+            SELECT id FROM notification_outbox
+            WHERE status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+            ORDER BY next_attempt_at, id
+            LIMIT ?
+            "#,
+            config.batch_size
+        )
+        .fetch_all(db)
+        .await?;
+
+        let sender = mail_sender(mail);
+        let mut sent = 0;
+
+        for notification in due {
+            // 先顺延下次发送时间占用该邮件，多个实例同时运行时不会重复发送
+            let claimed = sqlx::query!(
+                r#"
+                UPDATE notification_outbox SET next_attempt_at = ?
+                WHERE id = ? AND status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+                "#,
+                Utc::now() + Duration::seconds(SEND_LEASE),
+                notification.id
+            )
+            .execute(db)
+            .await?;
+
+            if claimed.rows_affected() == 0 {
+                continue;
+            }
+
+            let row = sqlx::query!(
+                "SELECT recipient, subject, body, attempts FROM notification_outbox WHERE id = ?",
+                notification.id
+            )
+            .fetch_one(db)
+            .await?;
+
+            let attempts = row.attempts + 1;
+            let message = MailMessage {
+                to: row.recipient,
+                subject: row.subject,
+                body: row.body,
+            };
+
+            // 正文可能包含邀请链接中的令牌和回答内容，发送成功或放弃重试后清除
+            match sender.send(message).await {
+                Ok(()) => {
+                    sqlx::query!(
+                        r#"
+                        UPDATE notification_outbox
+                        SET status = 'sent', attempts = ?, last_error = NULL, body = '',
+                            next_attempt_at = NULL, sent_at = CURRENT_TIMESTAMP
+                        WHERE id = ?
+                        "#,
+                        attempts,
+                        notification.id
+                    )
+                    .execute(db)
+                    .await?;
+                    sent += 1;
+                }
+                // 失败时按指数退避安排重试，次数用完后标记为失败
+                Err(e) => {
+                    let (status, next_attempt_at) = if attempts >= config.max_attempts {
+                        ("failed", None)
+                    } else {
+                        let delay = retry_delay(config, attempts);
+                        ("pending", Some(Utc::now() + Duration::seconds(delay as i64)))
+                    };
+                    let error: String = e.to_string().chars().take(MAX_ERROR_LENGTH).collect();
+
+                    sqlx::query!(
+                        r#"
+                        UPDATE notification_outbox
+                        SET status = ?, attempts = ?, last_error = ?, next_attempt_at = ?,
+                            body = IF(? = 'failed', '', body)
+                        WHERE id = ?
+                        "#,
+                        status,
+                        attempts,
+                        error,
+                        next_attempt_at,
+                        status,
+                        notification.id
+                    )
+                    .execute(db)
+                    .await?;
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+
+    async fn authorize(&self, user_id: i32, questionnaire_id: i32) -> AppResult<()> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::View)
+            .await?;
+        Ok(())
+    }
+}
+
+// 邮件中的回答列表，每个问题一行标题、一行回答
+fn format_answers(answers: &[AnswerDetail]) -> String {
+    if answers.is_empty() {
+        return "（没有作答的问题）".to_string();
+    }
+
+    answers
+        .iter()
+        .enumerate()
+        .map(|(index, answer)| {
+            format!("{}. {}\n   {}", index + 1, answer.question_title, format_answer(answer))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_answer(answer: &AnswerDetail) -> String {
+    if let Some(rows) = &answer.matrix_answers {
+        return rows
+            .iter()
+            .map(|row| format!("{}：{}", row.row, row.selected_options.join("、")))
+            .collect::<Vec<_>>()
+            .join("；");
+    }
+
+    // 选项附带的补充文字写在括号中
+    match (&answer.selected_options, &answer.text_value, answer.numeric_value) {
+        (Some(options), Some(text), _) => format!("{}（{}）", options.join("、"), text),
+        (Some(options), None, _) => options.join("、"),
+        (None, Some(text), _) => text.clone(),
+        (None, None, Some(value)) => value.to_string(),
+        (None, None, None) => "（未作答）".to_string(),
+    }
+}
+
+// 按收件人的时区显示时间，未设置或无效时使用UTC
+fn local_time(time: DateTime<Utc>, timezone: Option<&str>) -> String {
+    let timezone: Tz = timezone
+        .and_then(|timezone| timezone.parse().ok())
+        .unwrap_or(Tz::UTC);
+    time.with_timezone(&timezone)
+        .format("%Y-%m-%d %H:%M %Z")
+        .to_string()
+}
+
+// 第n次失败后的等待时间：基础间隔每次翻倍，不超过最长间隔
+fn retry_delay(config: &NotificationConfig, attempts: i32) -> u64 {
+    let exponent = (attempts - 1).clamp(0, 20) as u32;
+    config
+        .retry_base_delay
+        .saturating_mul(1 << exponent)
+        .min(config.max_retry_delay)
+}
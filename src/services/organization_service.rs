@@ -0,0 +1,436 @@
+use std::sync::Arc;
+use sqlx::{MySql, Pool, Transaction};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult};
+use crate::models::organization::{
+    AddMemberRequest, Organization, OrganizationMember, OrganizationRequest, OrganizationRole,
+    UpdateMemberRequest,
+};
+use crate::services::permission_service::PermissionService;
+
+pub struct OrganizationService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl OrganizationService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 创建组织，创建人成为所有者
+    pub async fn create(&self, user_id: i32, req: OrganizationRequest) -> AppResult<Organization> {
+        let mut tx = self.db.begin().await?;
+
+        let organization_id = sqlx::query!(
+            r#"
+            INSERT INTO organizations (name, anonymous_responses, response_retention_days)
+            VALUES (?, ?, ?)
+            "#,
+            req.name.trim(),
+            req.anonymous_responses,
+            req.response_retention_days
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id() as i32;
+
+        sqlx::query!(
+            "INSERT INTO organization_members (organization_id, user_id, role) VALUES (?, ?, ?)",
+            organization_id,
+            user_id,
+            OrganizationRole::Owner.as_str()
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.find(user_id, organization_id).await
+    }
+
+    // 获取我加入的组织
+    pub async fn list(&self, user_id: i32) -> AppResult<Vec<Organization>> {
+        let organizations = sqlx::query!(
+            r#"
+            SELECT o.id, o.name,
+                   o.anonymous_responses as "anonymous_responses: bool",
+                   o.response_retention_days, m.role,
+                   (SELECT COUNT(*) FROM organization_members WHERE organization_id = o.id)
+                       as "member_count!: i64",
+                   (SELECT COUNT(*) FROM questionnaires WHERE organization_id = o.id)
+                       as "questionnaire_count!: i64",
+                   o.created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM organization_members m
+            JOIN organizations o ON m.organization_id = o.id
+            WHERE m.user_id = ?
+            ORDER BY o.name
+            "#,
+            user_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(Organization {
+                id: row.id,
+                name: row.name,
+                anonymous_responses: row.anonymous_responses,
+                response_retention_days: row.response_retention_days,
+                role: parse_role(&row.role)?,
+                member_count: row.member_count,
+                questionnaire_count: row.questionnaire_count,
+                created_at: row.created_at.expect("创建时间不应为空"),
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(organizations)
+    }
+
+    // 获取组织详情
+    pub async fn get(&self, user_id: i32, organization_id: i32) -> AppResult<Organization> {
+        self.permissions()
+            .require_member(user_id, organization_id, OrganizationRole::Member)
+            .await?;
+
+        self.find(user_id, organization_id).await
+    }
+
+    // 修改组织名称和设置，新的匿名设置只作用于此后创建的问卷
+    pub async fn update(
+        &self,
+        user_id: i32,
+        organization_id: i32,
+        req: OrganizationRequest,
+    ) -> AppResult<Organization> {
+        self.permissions()
+            .require_member(user_id, organization_id, OrganizationRole::Admin)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE organizations
+            SET name = ?, anonymous_responses = ?, response_retention_days = ?
+            WHERE id = ?
+            "#,
+            req.name.trim(),
+            req.anonymous_responses,
+            req.response_retention_days,
+            organization_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        self.find(user_id, organization_id).await
+    }
+
+    // 删除组织，组织中还有问卷时需要先删除或转移
+    pub async fn delete(&self, user_id: i32, organization_id: i32) -> AppResult<()> {
+        self.permissions()
+            .require_member(user_id, organization_id, OrganizationRole::Owner)
+            .await?;
+
+        let questionnaire_count = sqlx::query!(
+            "SELECT COUNT(*) as count FROM questionnaires WHERE organization_id = ?",
+            organization_id
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        if questionnaire_count > 0 {
+            return Err(AppError::validation(format!(
+                "组织中还有{}份问卷，请先删除或转移",
+                questionnaire_count
+            )));
+        }
+
+        // 成员随组织一同删除
+        sqlx::query!("DELETE FROM organizations WHERE id = ?", organization_id)
+            .execute(&*self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    // 获取组织成员
+    pub async fn members(
+        &self,
+        user_id: i32,
+        organization_id: i32,
+    ) -> AppResult<Vec<OrganizationMember>> {
+        self.permissions()
+            .require_member(user_id, organization_id, OrganizationRole::Member)
+            .await?;
+
+        let members = sqlx::query!(
+            r#"
+            SELECT m.user_id, u.username, u.nickname, m.role,
+                   m.created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM organization_members m
+            JOIN users u ON m.user_id = u.id
+            WHERE m.organization_id = ?
+            ORDER BY m.created_at
+            "#,
+            organization_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(OrganizationMember {
+                user_id: row.user_id,
+                username: row.username,
+                nickname: row.nickname,
+                role: parse_role(&row.role)?,
+                joined_at: row.created_at.expect("加入时间不应为空"),
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(members)
+    }
+
+    // 按用户名或邮箱添加成员，只有所有者可以添加其他所有者
+    pub async fn add_member(
+        &self,
+        user_id: i32,
+        organization_id: i32,
+        req: AddMemberRequest,
+    ) -> AppResult<OrganizationMember> {
+        let actor_role = self
+            .permissions()
+            .require_member(user_id, organization_id, OrganizationRole::Admin)
+            .await?;
+        ensure_can_assign(actor_role, req.role)?;
+
+        let username = req.username.as_deref().map(str::trim).filter(|v| !v.is_empty());
+        let email = req.email.as_deref().map(str::trim).filter(|v| !v.is_empty());
+
+        let member_id = match (username, email) {
+            (Some(username), None) => {
+                sqlx::query!("SELECT id FROM users WHERE username = ?", username)
+                    .fetch_optional(&*self.db)
+                    .await?
+                    .map(|user| user.id)
+            }
+            (None, Some(email)) => {
+                sqlx::query!("SELECT id FROM users WHERE email = ?", email)
+                    .fetch_optional(&*self.db)
+                    .await?
+                    .map(|user| user.id)
+            }
+            _ => return Err(AppError::validation("请提供成员的用户名或邮箱之一")),
+        }
+        .ok_or_else(|| AppError::NotFoundError("要添加的用户不存在".to_string()))?;
+
+        let existing = sqlx::query!(
+            "SELECT id FROM organization_members WHERE organization_id = ? AND user_id = ?",
+            organization_id,
+            member_id
+        )
+        .fetch_optional(&*self.db)
+        .await?;
+
+        if existing.is_some() {
+            return Err(AppError::validation("该用户已是组织成员"));
+        }
+
+        sqlx::query!(
+            "INSERT INTO organization_members (organization_id, user_id, role) VALUES (?, ?, ?)",
+            organization_id,
+            member_id,
+            req.role.as_str()
+        )
+        .execute(&*self.db)
+        .await?;
+
+        self.find_member(organization_id, member_id).await
+    }
+
+    // 修改成员角色，组织至少保留一名所有者
+    pub async fn update_member(
+        &self,
+        user_id: i32,
+        organization_id: i32,
+        member_id: i32,
+        req: UpdateMemberRequest,
+    ) -> AppResult<OrganizationMember> {
+        let actor_role = self
+            .permissions()
+            .require_member(user_id, organization_id, OrganizationRole::Admin)
+            .await?;
+
+        let mut tx = self.db.begin().await?;
+
+        let current_role = Self::lock_member(&mut tx, organization_id, member_id).await?;
+        ensure_can_assign(actor_role, current_role)?;
+        ensure_can_assign(actor_role, req.role)?;
+
+        if current_role == OrganizationRole::Owner && req.role != OrganizationRole::Owner {
+            Self::ensure_other_owner(&mut tx, organization_id, member_id).await?;
+        }
+
+        sqlx::query!(
+            "UPDATE organization_members SET role = ? WHERE organization_id = ? AND user_id = ?",
+            req.role.as_str(),
+            organization_id,
+            member_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.find_member(organization_id, member_id).await
+    }
+
+    // 移除成员；成员也可以自行退出
+    pub async fn remove_member(
+        &self,
+        user_id: i32,
+        organization_id: i32,
+        member_id: i32,
+    ) -> AppResult<()> {
+        let actor_role = if user_id == member_id {
+            None
+        } else {
+            Some(
+                self.permissions()
+                    .require_member(user_id, organization_id, OrganizationRole::Admin)
+                    .await?,
+            )
+        };
+
+        let mut tx = self.db.begin().await?;
+
+        let current_role = Self::lock_member(&mut tx, organization_id, member_id).await?;
+        if let Some(actor_role) = actor_role {
+            ensure_can_assign(actor_role, current_role)?;
+        }
+
+        if current_role == OrganizationRole::Owner {
+            Self::ensure_other_owner(&mut tx, organization_id, member_id).await?;
+        }
+
+        sqlx::query!(
+            "DELETE FROM organization_members WHERE organization_id = ? AND user_id = ?",
+            organization_id,
+            member_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // 锁定成员记录并返回其当前角色
+    async fn lock_member(
+        tx: &mut Transaction<'_, MySql>,
+        organization_id: i32,
+        member_id: i32,
+    ) -> AppResult<OrganizationRole> {
+        let member = sqlx::query!(
+            r#"
+            SELECT role FROM organization_members
+            WHERE organization_id = ? AND user_id = ?
+            FOR UPDATE
+            "#,
+            organization_id,
+            member_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("该用户不是组织成员".to_string()))?;
+
+        parse_role(&member.role)
+    }
+
+    // 检查除该成员外组织是否还有其他所有者
+    async fn ensure_other_owner(
+        tx: &mut Transaction<'_, MySql>,
+        organization_id: i32,
+        member_id: i32,
+    ) -> AppResult<()> {
+        let owners = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM organization_members
+            WHERE organization_id = ? AND role = 'owner' AND user_id <> ?
+            "#,
+            organization_id,
+            member_id
+        )
+        .fetch_one(&mut **tx)
+        .await?
+        .count;
+
+        if owners == 0 {
+            return Err(AppError::validation(
+                "组织至少需要一名所有者，请先任命其他所有者",
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn find(&self, user_id: i32, organization_id: i32) -> AppResult<Organization> {
+        self.list(user_id)
+            .await?
+            .into_iter()
+            .find(|organization| organization.id == organization_id)
+            .ok_or_else(|| AppError::NotFoundError(format!("组织ID {} 不存在", organization_id)))
+    }
+
+    async fn find_member(
+        &self,
+        organization_id: i32,
+        member_id: i32,
+    ) -> AppResult<OrganizationMember> {
+        let row = sqlx::query!(
+            r#"
+            SELECT m.user_id, u.username, u.nickname, m.role,
+                   m.created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM organization_members m
+            JOIN users u ON m.user_id = u.id
+            WHERE m.organization_id = ? AND m.user_id = ?
+            "#,
+            organization_id,
+            member_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("该用户不是组织成员".to_string()))?;
+
+        Ok(OrganizationMember {
+            user_id: row.user_id,
+            username: row.username,
+            nickname: row.nickname,
+            role: parse_role(&row.role)?,
+            joined_at: row.created_at.expect("加入时间不应为空"),
+        })
+    }
+
+    fn permissions(&self) -> PermissionService {
+        PermissionService::new(self.db.clone())
+    }
+}
+
+// 管理员可以管理普通成员和管理员，涉及所有者的变更只能由所有者进行
+fn ensure_can_assign(actor_role: OrganizationRole, role: OrganizationRole) -> AppResult<()> {
+    if role == OrganizationRole::Owner && actor_role != OrganizationRole::Owner {
+        return Err(AppError::PermissionError(
+            "只有所有者可以任命或变更所有者".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_role(role: &str) -> AppResult<OrganizationRole> {
+    OrganizationRole::parse(role)
+        .ok_or_else(|| AppError::InternalServerError(format!("未知的组织角色: {}", role)))
+}
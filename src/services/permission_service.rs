@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use sqlx::{MySql, Pool};
+
+use crate::models::collaborator::CollaboratorRole;
+use crate::models::error::{AppError, AppResult};
+use crate::models::organization::OrganizationRole;
+use crate::models::user::UserRole;
+
+// 对问卷的操作级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    View,   // 查看问卷结构、回答、统计和版本
+    Edit,   // 修改问卷内容和设置、变更状态、标记回答
+    Manage, // 删除问卷、管理协作者
+}
+
+// 用户对某份问卷拥有的身份
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionnaireAccess {
+    Admin,
+    Owner,
+    Member(OrganizationRole), // 问卷所属组织的成员
+    Collaborator(CollaboratorRole),
+}
+
+impl QuestionnaireAccess {
+    pub fn allows(&self, access: Access) -> bool {
+        match self {
+            Self::Admin | Self::Owner => true,
+            Self::Member(OrganizationRole::Member) => access != Access::Manage,
+            Self::Member(_) => true,
+            Self::Collaborator(CollaboratorRole::Editor) => access != Access::Manage,
+            Self::Collaborator(CollaboratorRole::Viewer) => access == Access::View,
+        }
+    }
+}
+
+pub struct PermissionService {
+    db: Arc<Pool<MySql>>,
+}
+
+impl PermissionService {
+    pub fn new(db: Arc<Pool<MySql>>) -> Self {
+        Self { db }
+    }
+
+    // 检查问卷是否存在以及用户是否可以进行指定的操作
+    pub async fn authorize(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        access: Access,
+    ) -> AppResult<QuestionnaireAccess> {
+        let row = sqlx::query!(
+            r#"
+            SELECT q.creator_id,
+                   (SELECT role FROM users WHERE id = ?) as "user_role?: String",
+                   m.role as "member_role?",
+                   c.role as "collaborator_role?"
+            FROM questionnaires q
+            LEFT JOIN organization_members m
+                ON m.organization_id = q.organization_id AND m.user_id = ?
+            LEFT JOIN questionnaire_collaborators c
+                ON c.questionnaire_id = q.id AND c.user_id = ? AND c.status = 'accepted'
+            WHERE q.id = ?
+            "#,
+            user_id,
+            user_id,
+            user_id,
+            questionnaire_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("问卷ID {} 不存在", questionnaire_id)))?;
+
+        // 用户可能同时拥有多个身份，取第一个允许该操作的
+        let mut roles = Vec::new();
+        if row.user_role.as_deref().map(UserRole::parse) == Some(UserRole::Admin) {
+            roles.push(QuestionnaireAccess::Admin);
+        }
+        if row.creator_id == Some(user_id) {
+            roles.push(QuestionnaireAccess::Owner);
+        }
+        if let Some(role) = row.member_role.as_deref().and_then(OrganizationRole::parse) {
+            roles.push(QuestionnaireAccess::Member(role));
+        }
+        if let Some(role) = row.collaborator_role.as_deref().and_then(CollaboratorRole::parse) {
+            roles.push(QuestionnaireAccess::Collaborator(role));
+        }
+
+        match roles.into_iter().find(|role| role.allows(access)) {
+            Some(role) => Ok(role),
+            None => Err(AppError::PermissionError(
+                match access {
+                    Access::View => "你无权查看此问卷",
+                    Access::Edit => "你无权修改此问卷",
+                    Access::Manage => "你无权管理此问卷",
+                }
+                .to_string(),
+            )),
+        }
+    }
+
+    // 检查用户是否为组织成员且角色不低于min_role，返回其实际角色
+    pub async fn require_member(
+        &self,
+        user_id: i32,
+        organization_id: i32,
+        min_role: OrganizationRole,
+    ) -> AppResult<OrganizationRole> {
+        let row = sqlx::query!(
+            r#"
+            SELECT m.role as "role?"
+            FROM organizations o
+            LEFT JOIN organization_members m ON m.organization_id = o.id AND m.user_id = ?
+            WHERE o.id = ?
+            "#,
+            user_id,
+            organization_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("组织ID {} 不存在", organization_id)))?;
+
+        match row.role.as_deref().and_then(OrganizationRole::parse) {
+            Some(role) if role >= min_role => Ok(role),
+            Some(_) => Err(AppError::PermissionError("你在该组织中的权限不足".to_string())),
+            None => Err(AppError::PermissionError("你不是该组织的成员".to_string())),
+        }
+    }
+
+    // 检查用户是否为管理员
+    pub async fn require_admin(&self, user_id: i32) -> AppResult<()> {
+        let role = sqlx::query!("SELECT role FROM users WHERE id = ?", user_id)
+            .fetch_optional(&*self.db)
+            .await?
+            .map(|user| UserRole::parse(&user.role));
+
+        if role != Some(UserRole::Admin) {
+            return Err(AppError::PermissionError("需要管理员权限".to_string()));
+        }
+
+        Ok(())
+    }
+}
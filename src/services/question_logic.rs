@@ -0,0 +1,425 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::models::logic::{
+    Condition, JumpRule, JumpTarget, OptionRef, QuestionLogic, QuestionRef, SectionRef,
+};
+use crate::services::answer_validator::ValidatedAnswer;
+
+// 解析引用时使用的问卷结构
+pub struct LogicContext<'a> {
+    pub question_ids: &'a [i32],              // 按问卷中的顺序排列
+    pub question_types: &'a [String],         // 与question_ids一一对应
+    pub question_sections: &'a [Option<i32>], // 与question_ids一一对应，问题所属的分页ID
+    pub section_ids: &'a [i32],               // 按问卷中的顺序排列
+    pub options: &'a [(i32, i32, String)],    // (选项ID, 问题ID, 选项文本)
+}
+
+impl LogicContext<'_> {
+    // 将问题引用解析为问题在问卷中的位置
+    fn position(&self, question: &QuestionRef) -> Result<usize, String> {
+        match (question.question_id, question.question_index) {
+            (Some(id), _) => self
+                .question_ids
+                .iter()
+                .position(|q| *q == id)
+                .ok_or_else(|| format!("引用的问题ID {} 不属于该问卷", id)),
+            (None, Some(index)) if index < self.question_ids.len() => Ok(index),
+            (None, Some(index)) => Err(format!("引用的问题位置 {} 超出范围", index)),
+            (None, None) => Err("问题引用需要question_id或question_index".to_string()),
+        }
+    }
+
+    fn resolve_question(&self, question: &QuestionRef) -> Result<(usize, QuestionRef), String> {
+        let position = self.position(question)?;
+        Ok((
+            position,
+            QuestionRef {
+                question_id: Some(self.question_ids[position]),
+                question_index: None,
+            },
+        ))
+    }
+
+    // 将分页引用解析为分页ID
+    fn resolve_section(&self, section: &SectionRef) -> Result<i32, String> {
+        match (section.section_id, section.section_index) {
+            (Some(id), _) if self.section_ids.contains(&id) => Ok(id),
+            (Some(id), _) => Err(format!("引用的分页ID {} 不属于该问卷", id)),
+            (None, Some(index)) => self
+                .section_ids
+                .get(index)
+                .copied()
+                .ok_or_else(|| format!("引用的分页位置 {} 超出范围", index)),
+            (None, None) => Err("分页引用需要section_id或section_index".to_string()),
+        }
+    }
+
+    fn resolve_option(&self, question_id: i32, option: &OptionRef) -> Result<OptionRef, String> {
+        let found = self.options.iter().find(|(id, qid, text)| {
+            *qid == question_id
+                && match (option.option_id, &option.option_text) {
+                    (Some(option_id), _) => *id == option_id,
+                    (None, Some(option_text)) => text == option_text,
+                    (None, None) => false,
+                }
+        });
+
+        found
+            .map(|(id, _, _)| OptionRef {
+                option_id: Some(*id),
+                option_text: None,
+            })
+            .ok_or_else(|| format!("引用的选项不属于问题 {}", question_id))
+    }
+
+    // 解析条件中的引用，条件只能引用排在before之前的问题
+    pub fn resolve_condition(&self, condition: &Condition, before: usize) -> Result<Condition, String> {
+        let earlier = |question: &QuestionRef| -> Result<(usize, QuestionRef), String> {
+            let (position, resolved) = self.resolve_question(question)?;
+            if position >= before {
+                return Err(format!("条件只能引用排在前面的问题（第{}题）", position + 1));
+            }
+            Ok((position, resolved))
+        };
+
+        Ok(match condition {
+            Condition::All { conditions } => Condition::All {
+                conditions: conditions
+                    .iter()
+                    .map(|c| self.resolve_condition(c, before))
+                    .collect::<Result<_, _>>()?,
+            },
+            Condition::Any { conditions } => Condition::Any {
+                conditions: conditions
+                    .iter()
+                    .map(|c| self.resolve_condition(c, before))
+                    .collect::<Result<_, _>>()?,
+            },
+            Condition::Not { condition } => Condition::Not {
+                condition: Box::new(self.resolve_condition(condition, before)?),
+            },
+            Condition::Answered { question } => Condition::Answered {
+                question: earlier(question)?.1,
+            },
+            Condition::Selected { question, option } => {
+                let (_, question) = earlier(question)?;
+                let option = self.resolve_option(question.question_id.unwrap_or_default(), option)?;
+                Condition::Selected { question, option }
+            }
+            Condition::Compare {
+                question,
+                operator,
+                value,
+            } => {
+                let (position, question) = earlier(question)?;
+                if !matches!(
+                    self.question_types[position].as_str(),
+                    "rating" | "nps" | "number"
+                ) {
+                    return Err("数值比较只能用于评分、NPS或数字题".to_string());
+                }
+                Condition::Compare {
+                    question,
+                    operator: *operator,
+                    value: *value,
+                }
+            }
+        })
+    }
+
+    // 解析位于position的问题的逻辑
+    pub fn resolve(&self, logic: &QuestionLogic, position: usize) -> Result<QuestionLogic, String> {
+        let show_if = logic
+            .show_if
+            .as_ref()
+            .map(|condition| self.resolve_condition(condition, position))
+            .transpose()?;
+
+        let jumps = logic
+            .jumps
+            .iter()
+            .map(|jump| {
+                // 跳转条件可以引用本题
+                let when = self.resolve_condition(&jump.when, position + 1)?;
+                let to = match &jump.to {
+                    JumpTarget::End => JumpTarget::End,
+                    JumpTarget::Question(question) => {
+                        let (target, resolved) = self.resolve_question(question)?;
+                        if target <= position {
+                            return Err("只能跳转到排在后面的问题".to_string());
+                        }
+                        JumpTarget::Question(resolved)
+                    }
+                    // 跳转到分页即跳转到该分页的第一个问题
+                    JumpTarget::Section(section) => {
+                        let section_id = self.resolve_section(section)?;
+                        let first = self
+                            .question_sections
+                            .iter()
+                            .position(|section| *section == Some(section_id))
+                            .ok_or("跳转目标分页中没有问题")?;
+                        if first <= position {
+                            return Err("只能跳转到排在后面的分页".to_string());
+                        }
+                        JumpTarget::Section(SectionRef {
+                            section_id: Some(section_id),
+                            section_index: None,
+                        })
+                    }
+                };
+                Ok(JumpRule { when, to })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(QuestionLogic { show_if, jumps })
+    }
+}
+
+// 按问卷顺序计算显示的问题，questions为(问题ID, 所属分页ID, 条件逻辑)。
+// 显示条件不满足或被跳转跳过的问题视为隐藏，隐藏问题的回答不参与之后的条件判断
+pub fn visible_questions(
+    questions: &[(i32, Option<i32>, Option<&QuestionLogic>)],
+    answers: &HashMap<i32, &ValidatedAnswer>,
+) -> HashSet<i32> {
+    let mut visible = HashSet::new();
+    let mut visible_answers: HashMap<i32, &ValidatedAnswer> = HashMap::new();
+    let mut skip_to: Option<usize> = None;
+
+    for (position, &(question_id, _, logic)) in questions.iter().enumerate() {
+        if skip_to.is_some_and(|target| position < target) {
+            continue;
+        }
+        skip_to = None;
+
+        if let Some(condition) = logic.and_then(|logic| logic.show_if.as_ref()) {
+            if !evaluate(condition, &visible_answers) {
+                continue;
+            }
+        }
+
+        visible.insert(question_id);
+        if let Some(answer) = answers.get(&question_id) {
+            visible_answers.insert(question_id, answer);
+        }
+
+        let jump = logic
+            .into_iter()
+            .flat_map(|logic| &logic.jumps)
+            .find(|jump| evaluate(&jump.when, &visible_answers));
+
+        skip_to = jump.map(|jump| match &jump.to {
+            JumpTarget::End => questions.len(),
+            // 目标问题已被停用时不跳过任何问题
+            JumpTarget::Question(target) => questions
+                .iter()
+                .position(|(id, _, _)| Some(*id) == target.question_id)
+                .unwrap_or(position + 1),
+            // 跳转到分页中第一个仍在使用的问题，分页已被移除或没有问题时不跳过任何问题
+            JumpTarget::Section(target) => questions
+                .iter()
+                .enumerate()
+                .skip(position + 1)
+                .find(|(_, (_, section, _))| section.is_some() && *section == target.section_id)
+                .map_or(position + 1, |(target, _)| target),
+        });
+    }
+
+    visible
+}
+
+// 根据已作答的内容判断条件是否成立，未作答的问题不满足任何条件
+pub fn evaluate(condition: &Condition, answers: &HashMap<i32, &ValidatedAnswer>) -> bool {
+    let answer = |question: &QuestionRef| question.question_id.and_then(|id| answers.get(&id));
+
+    match condition {
+        Condition::All { conditions } => conditions.iter().all(|c| evaluate(c, answers)),
+        Condition::Any { conditions } => conditions.iter().any(|c| evaluate(c, answers)),
+        Condition::Not { condition } => !evaluate(condition, answers),
+        Condition::Answered { question } => answer(question).is_some(),
+        Condition::Selected { question, option } => answer(question)
+            .zip(option.option_id)
+            .is_some_and(|(answer, option_id)| {
+                answer.selections.iter().any(|s| s.option_id == option_id)
+            }),
+        Condition::Compare {
+            question,
+            operator,
+            value,
+        } => answer(question)
+            .and_then(|answer| answer.numeric_value)
+            .is_some_and(|numeric| operator.apply(numeric, *value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::logic::CompareOperator;
+    use crate::services::answer_validator::Selection;
+
+    fn number(question_id: i32, value: f64) -> ValidatedAnswer {
+        ValidatedAnswer {
+            question_id,
+            text_value: None,
+            numeric_value: Some(value),
+            selections: Vec::new(),
+        }
+    }
+
+    fn choice(question_id: i32, option_id: i32) -> ValidatedAnswer {
+        ValidatedAnswer {
+            question_id,
+            text_value: None,
+            numeric_value: None,
+            selections: vec![Selection {
+                option_id,
+                row_option_id: None,
+                rank_position: None,
+            }],
+        }
+    }
+
+    fn question(question_id: i32) -> QuestionRef {
+        QuestionRef {
+            question_id: Some(question_id),
+            question_index: None,
+        }
+    }
+
+    fn jump(when: Condition, to: JumpTarget) -> QuestionLogic {
+        QuestionLogic {
+            show_if: None,
+            jumps: vec![JumpRule { when, to }],
+        }
+    }
+
+    fn less_than(question_id: i32, value: f64) -> Condition {
+        Condition::Compare {
+            question: question(question_id),
+            operator: CompareOperator::Lt,
+            value,
+        }
+    }
+
+    fn visible(
+        questions: &[(i32, Option<i32>, Option<&QuestionLogic>)],
+        answers: &[ValidatedAnswer],
+    ) -> Vec<i32> {
+        let answers = answers.iter().map(|answer| (answer.question_id, answer)).collect();
+        let mut visible: Vec<_> = visible_questions(questions, &answers).into_iter().collect();
+        visible.sort();
+        visible
+    }
+
+    #[test]
+    fn show_if_chain_ignores_answers_to_hidden_questions() {
+        let second = QuestionLogic {
+            show_if: Some(Condition::Selected {
+                question: question(1),
+                option: OptionRef {
+                    option_id: Some(10),
+                    option_text: None,
+                },
+            }),
+            jumps: Vec::new(),
+        };
+        let third = QuestionLogic {
+            show_if: Some(Condition::Answered { question: question(2) }),
+            jumps: Vec::new(),
+        };
+        let questions = [(1, None, None), (2, None, Some(&second)), (3, None, Some(&third))];
+
+        assert_eq!(visible(&questions, &[choice(1, 10), number(2, 1.0)]), vec![1, 2, 3]);
+        // 第2题被隐藏后，即使提交了它的回答，依赖它的第3题也被隐藏
+        assert_eq!(visible(&questions, &[choice(1, 11), number(2, 1.0)]), vec![1]);
+    }
+
+    #[test]
+    fn jump_skips_questions_in_between() {
+        let first = jump(less_than(1, 5.0), JumpTarget::Question(question(4)));
+        let questions = [
+            (1, None, Some(&first)),
+            (2, None, None),
+            (3, None, None),
+            (4, None, None),
+            (5, None, None),
+        ];
+
+        assert_eq!(visible(&questions, &[number(1, 3.0)]), vec![1, 4, 5]);
+        assert_eq!(visible(&questions, &[number(1, 7.0)]), vec![1, 2, 3, 4, 5]);
+        // 未作答时条件不成立
+        assert_eq!(visible(&questions, &[]), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn jump_to_end_hides_remaining_questions() {
+        let second = jump(less_than(2, 5.0), JumpTarget::End);
+        let questions = [(1, None, None), (2, None, Some(&second)), (3, None, None)];
+
+        assert_eq!(visible(&questions, &[number(2, 1.0)]), vec![1, 2]);
+    }
+
+    #[test]
+    fn retired_jump_target_skips_nothing() {
+        let first = jump(less_than(1, 5.0), JumpTarget::Question(question(99)));
+        let questions = [(1, None, Some(&first)), (2, None, None), (3, None, None)];
+
+        assert_eq!(visible(&questions, &[number(1, 1.0)]), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn jump_to_section_lands_on_its_first_active_question() {
+        let section = |section_id| {
+            JumpTarget::Section(SectionRef {
+                section_id: Some(section_id),
+                section_index: None,
+            })
+        };
+        let first = jump(less_than(1, 5.0), section(300));
+        let questions = [
+            (1, Some(100), Some(&first)),
+            (2, Some(100), None),
+            (3, Some(200), None),
+            (5, Some(300), None),
+            (6, Some(300), None),
+        ];
+        assert_eq!(visible(&questions, &[number(1, 1.0)]), vec![1, 5, 6]);
+
+        // 分页已被移除时不跳过任何问题
+        let retired = jump(less_than(1, 5.0), section(400));
+        let questions = [(1, Some(100), Some(&retired)), (2, Some(100), None)];
+        assert_eq!(visible(&questions, &[number(1, 1.0)]), vec![1, 2]);
+    }
+
+    #[test]
+    fn resolves_section_target_after_question() {
+        let types = vec!["number".to_string(); 3];
+        let context = LogicContext {
+            question_ids: &[1, 2, 3],
+            question_types: &types,
+            question_sections: &[Some(100), Some(100), Some(200)],
+            section_ids: &[100, 200],
+            options: &[],
+        };
+        let to_section = |index| {
+            jump(
+                less_than(1, 5.0),
+                JumpTarget::Section(SectionRef {
+                    section_id: None,
+                    section_index: Some(index),
+                }),
+            )
+        };
+
+        let resolved = context.resolve(&to_section(1), 0).unwrap();
+        assert_eq!(
+            resolved.jumps[0].to,
+            JumpTarget::Section(SectionRef {
+                section_id: Some(200),
+                section_index: None,
+            })
+        );
+        assert!(context.resolve(&to_section(0), 0).is_err());
+        assert!(context.resolve(&to_section(2), 0).is_err());
+    }
+}
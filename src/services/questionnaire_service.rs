@@ -1,12 +1,23 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use sqlx::{MySql, Pool, Transaction};
 
-use crate::models::error::{AppError, AppResult};
+use crate::models::error::{AppError, AppResult, FieldErrors};
 use crate::models::questionnaire::{
-    CreateQuestionnaireRequest, Question, QuestionResponse, Questionnaire,
-    QuestionnaireListItem, QuestionnaireListResponse, QuestionnaireResponse,
+    is_matrix, uses_options, AnonymityRequest, CreateQuestionnaireRequest, OptionItem, OptionRequest, Question,
+    QuestionConfig, QuestionRequest, QuestionResponse, Questionnaire, QuestionnaireListItem,
+    QuestionnaireListResponse, QuestionnaireResponse, QuestionnaireStatus, SectionRequest,
+    SectionResponse, SubmissionLimits, TransferQuestionnaireRequest,
+    MAX_RATING_STEPS, QUESTION_TYPES,
 };
 use crate::config::Config;
+use crate::models::logic::QuestionLogic;
+use crate::models::organization::OrganizationRole;
+use crate::services::campaign_service::CampaignService;
+use crate::services::lifecycle_service::parse_status;
+use crate::services::permission_service::{Access, PermissionService};
+use crate::services::question_logic::LogicContext;
+use crate::services::version_service::VersionService;
 
 pub struct QuestionnaireService {
     db: Arc<Pool<MySql>>,
@@ -22,66 +33,75 @@ impl QuestionnaireService {
     pub async fn create_questionnaire(
         &self,
         user_id: i32,
+        organization_id: Option<i32>,
         req: CreateQuestionnaireRequest,
     ) -> AppResult<QuestionnaireResponse> {
+        // 在组织中创建问卷需要是组织成员
+        if let Some(organization_id) = organization_id {
+            PermissionService::new(self.db.clone())
+                .require_member(user_id, organization_id, OrganizationRole::Member)
+                .await?;
+        }
+
         let mut tx = self.db.begin().await?;
+        let questionnaire_id =
+            Self::insert_definition(&mut tx, user_id, organization_id, &req).await?;
+        tx.commit().await?;
+
+        // 返回创建的问卷
+        self.get_questionnaire(questionnaire_id).await
+    }
+
+    // 在事务中创建问卷及其分页、问题和选项，返回问卷ID
+    pub(crate) async fn insert_definition(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i32,
+        organization_id: Option<i32>,
+        req: &CreateQuestionnaireRequest,
+    ) -> AppResult<i32> {
+        Self::validate_questions(req)?;
 
-        // 创建问卷
+        // 创建问卷，组织中的问卷按组织设置决定是否匿名收集回答
         let questionnaire_id = sqlx::query!(
             r#"
-            INSERT INTO questionnaires (title, description, is_public, creator_id)
-            VALUES (?, ?, ?, ?)
+            INSERT INTO questionnaires
+                (title, description, is_public, creator_id, organization_id, anonymous)
+            VALUES (?, ?, ?, ?, ?,
+                    COALESCE((SELECT anonymous_responses FROM organizations WHERE id = ?), FALSE))
             "#,
             req.title,
             req.description,
             req.is_public,
-            user_id
+            user_id,
+            organization_id,
+            organization_id
         )
-        .execute(&mut *tx)
+        .execute(&mut **tx)
         .await?
         .last_insert_id() as i32;
 
-        // 创建问题和选项
-        for (index, question) in req.questions.iter().enumerate() {
-            let question_id = sqlx::query!(
-                r#"
-                INSERT INTO questions 
-                (questionnaire_id, title, question_type, required, display_order)
-                VALUES (?, ?, ?, ?, ?)
-                "#,
-                questionnaire_id,
-                question.title,
-                question.question_type,
-                question.required,
-                (index + 1) as i32
-            )
-            .execute(&mut *tx)
-            .await?
-            .last_insert_id() as i32;
+        // 创建分页、问题和选项
+        let section_ids = Self::sync_sections(tx, questionnaire_id, &req.sections).await?;
 
-            // 如果是单选或多选题，创建选项
-            if question.question_type == "radio" || question.question_type == "checkbox" {
-                for (opt_index, option_text) in question.options.iter().enumerate() {
-                    sqlx::query!(
-                        r#"
-                        INSERT INTO question_options
-                        (question_id, option_text, display_order)
-                        VALUES (?, ?, ?)
-                        "#,
-                        question_id,
-                        option_text,
-                        (opt_index + 1) as i32
-                    )
-                    .execute(&mut *tx)
-                    .await?;
-                }
-            }
+        let mut question_ids = Vec::with_capacity(req.questions.len());
+        for (index, question) in req.questions.iter().enumerate() {
+            let section_id = question.section.map(|section| section_ids[section]);
+            question_ids.push(
+                Self::insert_question(
+                    tx,
+                    questionnaire_id,
+                    question,
+                    (index + 1) as i32,
+                    section_id,
+                )
+                .await?,
+            );
         }
 
-        tx.commit().await?;
+        // 问题和选项都创建后才能解析条件逻辑中的引用
+        Self::save_logic(tx, questionnaire_id, &req.questions, &question_ids, &section_ids).await?;
 
-        // 返回创建的问卷
-        self.get_questionnaire(questionnaire_id).await
+        Ok(questionnaire_id)
     }
 
     // 更新问卷
@@ -91,20 +111,52 @@ impl QuestionnaireService {
         questionnaire_id: i32,
         req: CreateQuestionnaireRequest,
     ) -> AppResult<QuestionnaireResponse> {
-        // 先检查问卷是否存在且属于该用户
+        // 先检查问卷是否存在且用户可以修改
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::Edit)
+            .await?;
+
         let questionnaire = sqlx::query!(
-            "SELECT creator_id FROM questionnaires WHERE id = ?",
+            "SELECT current_version_id FROM questionnaires WHERE id = ?",
             questionnaire_id
         )
-        .fetch_optional(&*self.db)
-        .await?
-        .ok_or_else(|| AppError::NotFoundError(format!("问卷ID {} 不存在", questionnaire_id)))?;
+        .fetch_one(&*self.db)
+        .await?;
 
-        if questionnaire.creator_id != user_id {
-            return Err(AppError::PermissionError("你无权修改此问卷".to_string()));
+        let mut tx = self.db.begin().await?;
+        Self::apply_definition(&mut tx, questionnaire_id, &req).await?;
+        tx.commit().await?;
+
+        // 已发布的问卷在修改后生成新版本，使之后的回答关联到新的问卷结构
+        if questionnaire.current_version_id.is_some() {
+            VersionService::new(self.db.clone(), self.config.clone())
+                .create_version(user_id, questionnaire_id)
+                .await?;
         }
 
-        let mut tx = self.db.begin().await?;
+        // 返回更新后的问卷
+        self.get_questionnaire(questionnaire_id).await
+    }
+
+    // 在事务中将问卷结构更新为请求中的定义：按ID比对原地更新问题和选项，新增缺失的，停用被移除的
+    pub(crate) async fn apply_definition(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        req: &CreateQuestionnaireRequest,
+    ) -> AppResult<()> {
+        Self::validate_questions(req)?;
+
+        // 已归档的问卷不能再修改
+        let status = sqlx::query!(
+            "SELECT status FROM questionnaires WHERE id = ? FOR UPDATE",
+            questionnaire_id
+        )
+        .fetch_one(&mut **tx)
+        .await?
+        .status;
+        if parse_status(&status)? == QuestionnaireStatus::Archived {
+            return Err(AppError::validation("已归档的问卷不能修改"));
+        }
 
         // 更新问卷基本信息
         sqlx::query!(
@@ -118,55 +170,506 @@ impl QuestionnaireService {
             req.is_public,
             questionnaire_id
         )
-        .execute(&mut *tx)
+        .execute(&mut **tx)
         .await?;
 
-        // 删除旧的问题和选项
-        sqlx::query!("DELETE FROM questions WHERE questionnaire_id = ?", questionnaire_id)
-            .execute(&mut *tx)
-            .await?;
+        // 加载现有的问题，按ID与请求中的问题进行比对
+        let mut existing_questions: HashMap<i32, String> = sqlx::query!(
+            r#"
+            SELECT id, question_type FROM questions
+            WHERE questionnaire_id = ? AND retired_at IS NULL
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.id, row.question_type))
+        .collect();
+
+        let section_ids = Self::sync_sections(tx, questionnaire_id, &req.sections).await?;
+        let mut question_ids = Vec::with_capacity(req.questions.len());
 
-        // 创建新的问题和选项
         for (index, question) in req.questions.iter().enumerate() {
-            let question_id = sqlx::query!(
+            let display_order = (index + 1) as i32;
+            let section_id = question.section.map(|section| section_ids[section]);
+
+            let question_id = match question.id {
+                Some(question_id) => question_id,
+                None => {
+                    question_ids.push(
+                        Self::insert_question(
+                            tx,
+                            questionnaire_id,
+                            question,
+                            display_order,
+                            section_id,
+                        )
+                        .await?,
+                    );
+                    continue;
+                }
+            };
+
+            let old_type = existing_questions.remove(&question_id).ok_or_else(|| {
+                AppError::validation(format!(
+                    "问题ID {} 不属于该问卷或在请求中重复出现",
+                    question_id
+                ))
+            })?;
+
+            if old_type != question.question_type {
+                // 题型变化后旧回答无法按新题型解读，停用旧问题并新建一个问题
+                Self::retire_question(tx, question_id).await?;
+                question_ids.push(
+                    Self::insert_question(tx, questionnaire_id, question, display_order, section_id)
+                        .await?,
+                );
+                continue;
+            }
+
+            // 原地更新问题，保留已有的回答
+            let config = Self::config_json(question)?;
+            sqlx::query!(
                 r#"
-                INSERT INTO questions 
-                (questionnaire_id, title, question_type, required, display_order)
-                VALUES (?, ?, ?, ?, ?)
+                UPDATE questions
+                SET title = ?, required = ?, display_order = ?, config = ?, section_id = ?
+                WHERE id = ?
                 "#,
-                questionnaire_id,
                 question.title,
-                question.question_type,
                 question.required,
-                (index + 1) as i32
+                display_order,
+                config,
+                section_id,
+                question_id
             )
-            .execute(&mut *tx)
-            .await?
-            .last_insert_id() as i32;
+            .execute(&mut **tx)
+            .await?;
+
+            Self::sync_question_options(tx, question_id, question).await?;
+            question_ids.push(question_id);
+        }
+
+        // 请求中不再包含的问题只做停用处理，历史回答和统计仍然可用
+        for question_id in existing_questions.into_keys() {
+            Self::retire_question(tx, question_id).await?;
+        }
+
+        Self::save_logic(tx, questionnaire_id, &req.questions, &question_ids, &section_ids).await
+    }
+
+    // 按ID原地更新分页，新增缺失的分页，停用被移除的分页，返回与请求顺序对应的分页ID
+    async fn sync_sections(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        sections: &[SectionRequest],
+    ) -> AppResult<Vec<i32>> {
+        let mut existing_sections: HashSet<i32> = sqlx::query!(
+            r#"
+            SELECT id FROM question_sections
+            WHERE questionnaire_id = ? AND retired_at IS NULL
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
+
+        let mut section_ids = Vec::with_capacity(sections.len());
+
+        for (index, section) in sections.iter().enumerate() {
+            let display_order = (index + 1) as i32;
+
+            let section_id = match section.id {
+                Some(section_id) => {
+                    if !existing_sections.remove(&section_id) {
+                        return Err(AppError::validation(format!(
+                            "分页ID {} 不属于该问卷或在请求中重复出现",
+                            section_id
+                        )));
+                    }
 
-            // 如果是单选或多选题，创建选项
-            if question.question_type == "radio" || question.question_type == "checkbox" {
-                for (opt_index, option_text) in question.options.iter().enumerate() {
+                    sqlx::query!(
+                        r#"
+                        UPDATE question_sections
+                        SET title = ?, description = ?, display_order = ?
+                        WHERE id = ?
+                        "#,
+                        section.title,
+                        section.description,
+                        display_order,
+                        section_id
+                    )
+                    .execute(&mut **tx)
+                    .await?;
+
+                    section_id
+                }
+                None => sqlx::query!(
+                    r#"
+                    INSERT INTO question_sections
+                    (questionnaire_id, title, description, display_order)
+                    VALUES (?, ?, ?, ?)
+                    "#,
+                    questionnaire_id,
+                    section.title,
+                    section.description,
+                    display_order
+                )
+                .execute(&mut **tx)
+                .await?
+                .last_insert_id() as i32,
+            };
+
+            section_ids.push(section_id);
+        }
+
+        for section_id in existing_sections {
+            sqlx::query!(
+                "UPDATE question_sections SET retired_at = CURRENT_TIMESTAMP WHERE id = ?",
+                section_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(section_ids)
+    }
+
+    // 解析并保存问题的条件逻辑，引用统一转换为问题ID和选项ID，需在问题和选项保存后调用
+    async fn save_logic(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        questions: &[QuestionRequest],
+        question_ids: &[i32],
+        section_ids: &[i32],
+    ) -> AppResult<()> {
+        let options: Vec<(i32, i32, String)> = sqlx::query!(
+            r#"
+            SELECT qo.id, qo.question_id, qo.option_text
+            FROM question_options qo
+            JOIN questions q ON qo.question_id = q.id
+            WHERE q.questionnaire_id = ? AND q.retired_at IS NULL
+            AND qo.retired_at IS NULL AND qo.role = 'option'
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.id, row.question_id, row.option_text))
+        .collect();
+
+        let question_types: Vec<String> = questions
+            .iter()
+            .map(|question| question.question_type.clone())
+            .collect();
+        let question_sections: Vec<Option<i32>> = questions
+            .iter()
+            .map(|question| question.section.map(|section| section_ids[section]))
+            .collect();
+        let context = LogicContext {
+            question_ids,
+            question_types: &question_types,
+            question_sections: &question_sections,
+            section_ids,
+            options: &options,
+        };
+
+        let mut errors = FieldErrors::new();
+
+        for (index, (question, question_id)) in questions.iter().zip(question_ids).enumerate() {
+            let logic = match question.logic.as_ref() {
+                Some(logic) if *logic != QuestionLogic::default() => {
+                    match context.resolve(logic, index) {
+                        Ok(resolved) => Some(serde_json::to_string(&resolved).map_err(|e| {
+                            AppError::InternalServerError(format!("序列化条件逻辑失败: {}", e))
+                        })?),
+                        Err(message) => {
+                            errors.insert(format!("questions[{}].logic", index), vec![message]);
+                            continue;
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            sqlx::query!(
+                "UPDATE questions SET logic = ? WHERE id = ?",
+                logic,
+                question_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        if !errors.is_empty() {
+            return Err(AppError::validation_fields("条件逻辑不正确", errors));
+        }
+
+        Ok(())
+    }
+
+    // 检查问题定义：题型是否支持，选项和题型配置是否完整有效，分页是否连续
+    fn validate_questions(req: &CreateQuestionnaireRequest) -> AppResult<()> {
+        let mut errors = FieldErrors::new();
+
+        for (index, section) in req.sections.iter().enumerate() {
+            if section.title.trim().is_empty() {
+                errors.insert(
+                    format!("sections[{}]", index),
+                    vec!["分页标题不能为空".to_string()],
+                );
+            }
+        }
+
+        let mut previous_section = 0;
+
+        for (index, question) in req.questions.iter().enumerate() {
+            let mut messages = Vec::new();
+            let config = question.config.clone().unwrap_or_default();
+
+            if question.title.trim().is_empty() {
+                messages.push("问题标题不能为空".to_string());
+            }
+
+            // 分页按顺序展示，同一分页的问题必须连续排列
+            match question.section {
+                None if !req.sections.is_empty() => {
+                    messages.push("问卷已分页，问题必须指定所属分页".to_string());
+                }
+                Some(_) if req.sections.is_empty() => {
+                    messages.push("问卷未设置分页".to_string());
+                }
+                Some(section) if section >= req.sections.len() => {
+                    messages.push(format!("分页 {} 不存在", section));
+                }
+                Some(section) if section < previous_section => {
+                    messages.push("同一分页的问题必须连续排列".to_string());
+                }
+                Some(section) => previous_section = section,
+                None => {}
+            }
+
+            if !QUESTION_TYPES.contains(&question.question_type.as_str()) {
+                messages.push(format!("不支持的题型: {}", question.question_type));
+            }
+
+            if uses_options(&question.question_type) && question.options.is_empty() {
+                messages.push("该题型至少需要一个选项".to_string());
+            }
+
+            if is_matrix(&question.question_type) && question.rows.is_empty() {
+                messages.push("矩阵题至少需要一行".to_string());
+            }
+
+            match question.question_type.as_str() {
+                "rating" => {
+                    let (min, max, step) = config.rating_scale();
+                    let steps = (max - min) / step;
+                    if min >= max || step <= 0.0 {
+                        messages.push("评分题的最小值必须小于最大值，步长必须大于0".to_string());
+                    } else if (steps - steps.round()).abs() > 1e-9 {
+                        messages.push("评分题的取值范围必须是步长的整数倍".to_string());
+                    } else if steps.round() > MAX_RATING_STEPS as f64 {
+                        messages.push(format!("评分题最多{}档", MAX_RATING_STEPS + 1));
+                    }
+                    if !matches!(config.style.as_deref(), None | Some("scale") | Some("star")) {
+                        messages.push("评分题样式只能是scale或star".to_string());
+                    }
+                }
+                "number" => {
+                    if let (Some(min), Some(max)) = (config.min, config.max) {
+                        if min > max {
+                            messages.push("数字题的最小值不能大于最大值".to_string());
+                        }
+                    }
+                }
+                "date" => {
+                    if !matches!(config.date_mode(), "date" | "time" | "datetime") {
+                        messages.push("日期题的类型只能是date、time或datetime".to_string());
+                    }
+                }
+                _ => {}
+            }
+
+            if !messages.is_empty() {
+                errors.insert(format!("questions[{}]", index), messages);
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(AppError::validation_fields("问题定义不正确", errors));
+        }
+
+        Ok(())
+    }
+
+    // 将题型配置序列化为JSON
+    fn config_json(question: &QuestionRequest) -> AppResult<Option<String>> {
+        question
+            .config
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| AppError::InternalServerError(format!("序列化题型配置失败: {}", e)))
+    }
+
+    // 在事务中插入问题及其选项
+    async fn insert_question(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        question: &QuestionRequest,
+        display_order: i32,
+        section_id: Option<i32>,
+    ) -> AppResult<i32> {
+        let config = Self::config_json(question)?;
+
+        let question_id = sqlx::query!(
+            r#"
+            INSERT INTO questions 
+            (questionnaire_id, section_id, title, question_type, required, display_order, config)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            questionnaire_id,
+            section_id,
+            question.title,
+            question.question_type,
+            question.required,
+            display_order,
+            config
+        )
+        .execute(&mut **tx)
+        .await?
+        .last_insert_id() as i32;
+
+        Self::sync_question_options(tx, question_id, question).await?;
+
+        Ok(question_id)
+    }
+
+    // 同步问题的选项和矩阵题的行
+    async fn sync_question_options(
+        tx: &mut Transaction<'_, MySql>,
+        question_id: i32,
+        question: &QuestionRequest,
+    ) -> AppResult<()> {
+        let no_items: &[OptionRequest] = &[];
+
+        let options = if uses_options(&question.question_type) {
+            question.options.as_slice()
+        } else {
+            no_items
+        };
+        let rows = if is_matrix(&question.question_type) {
+            question.rows.as_slice()
+        } else {
+            no_items
+        };
+
+        Self::sync_options(tx, question_id, "option", options).await?;
+        Self::sync_options(tx, question_id, "row", rows).await
+    }
+
+    // 按ID（其次按文本）匹配已有选项并原地更新，新增缺失的选项，停用被移除的选项
+    async fn sync_options(
+        tx: &mut Transaction<'_, MySql>,
+        question_id: i32,
+        role: &str,
+        items: &[OptionRequest],
+    ) -> AppResult<()> {
+        let mut remaining: Vec<(i32, String)> = sqlx::query!(
+            r#"
+            SELECT id, option_text FROM question_options
+            WHERE question_id = ? AND role = ? AND retired_at IS NULL
+            ORDER BY display_order
+            "#,
+            question_id,
+            role
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.id, row.option_text))
+        .collect();
+
+        for (opt_index, option) in items.iter().enumerate() {
+            let display_order = (opt_index + 1) as i32;
+
+            let matched = match option.id() {
+                Some(option_id) => Some(
+                    remaining
+                        .iter()
+                        .position(|(id, _)| *id == option_id)
+                        .ok_or_else(|| {
+                            AppError::validation(format!(
+                                "选项ID {} 不属于问题 {} 或在请求中重复出现",
+                                option_id, question_id
+                            ))
+                        })?,
+                ),
+                None => remaining.iter().position(|(_, text)| text == option.text()),
+            };
+
+            match matched {
+                Some(pos) => {
+                    let (option_id, _) = remaining.remove(pos);
+                    sqlx::query!(
+                        r#"
+                        UPDATE question_options
+                        SET option_text = ?, display_order = ?
+                        WHERE id = ?
+                        "#,
+                        option.text(),
+                        display_order,
+                        option_id
+                    )
+                    .execute(&mut **tx)
+                    .await?;
+                }
+                None => {
                     sqlx::query!(
                         r#"
                         INSERT INTO question_options
-                        (question_id, option_text, display_order)
-                        VALUES (?, ?, ?)
+                        (question_id, option_text, role, display_order)
+                        VALUES (?, ?, ?, ?)
                         "#,
                         question_id,
-                        option_text,
-                        (opt_index + 1) as i32
+                        option.text(),
+                        role,
+                        display_order
                     )
-                    .execute(&mut *tx)
+                    .execute(&mut **tx)
                     .await?;
                 }
             }
         }
 
-        tx.commit().await?;
+        // 停用请求中不再包含的选项
+        for (option_id, _) in remaining {
+            sqlx::query!(
+                "UPDATE question_options SET retired_at = CURRENT_TIMESTAMP WHERE id = ?",
+                option_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
 
-        // 返回更新后的问卷
-        self.get_questionnaire(questionnaire_id).await
+        Ok(())
+    }
+
+    // 停用问题：不再出现在问卷中，但其历史回答保留
+    async fn retire_question(tx: &mut Transaction<'_, MySql>, question_id: i32) -> AppResult<()> {
+        sqlx::query!(
+            "UPDATE questions SET retired_at = CURRENT_TIMESTAMP WHERE id = ?",
+            question_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
     }
 
     // 获取问卷详情
@@ -174,7 +677,15 @@ impl QuestionnaireService {
         // 获取问卷基本信息
         let questionnaire = sqlx::query!(
             r#"
-            SELECT id, title, description, is_public, creator_id, 
+            SELECT id, title, description, is_public, status, creator_id, organization_id,
+                   current_version_id, anonymous as "anonymous: bool",
+                   opens_at as "opens_at: chrono::DateTime<chrono::Utc>",
+                   closes_at as "closes_at: chrono::DateTime<chrono::Utc>",
+                   max_responses,
+                   one_per_account as "one_per_account: bool",
+                   one_per_device as "one_per_device: bool",
+                   one_per_ip as "one_per_ip: bool",
+                   invite_only as "invite_only: bool",
                    created_at as "created_at: chrono::DateTime<chrono::Utc>", 
                    updated_at as "updated_at: chrono::DateTime<chrono::Utc>"
             FROM questionnaires
@@ -190,6 +701,7 @@ impl QuestionnaireService {
         let title = questionnaire.title.clone();
         let description = questionnaire.description.clone().expect("问卷描述不应为空");
         let is_public = questionnaire.is_public.expect("is_public状态不应为空") != 0;
+        let status = parse_status(&questionnaire.status)?;
         let creator_id = questionnaire.creator_id;
         let created_at = questionnaire.created_at.expect("创建时间不应为空");
         let updated_at = questionnaire.updated_at.expect("更新时间不应为空");
@@ -200,12 +712,22 @@ impl QuestionnaireService {
             title: title.clone(),
             description: description.clone(),
             is_public,
+            status: questionnaire.status.clone(),
+            opens_at: questionnaire.opens_at,
+            closes_at: questionnaire.closes_at,
+            max_responses: questionnaire.max_responses,
+            one_per_account: questionnaire.one_per_account,
+            one_per_device: questionnaire.one_per_device,
+            one_per_ip: questionnaire.one_per_ip,
+            anonymous: questionnaire.anonymous,
             creator_id,
+            organization_id: questionnaire.organization_id,
             created_at,
             updated_at,
         };
 
-        // 获取问题列表
+        // 获取分页和问题列表
+        let sections = self.get_questionnaire_sections(questionnaire.id).await?;
         let questions = self.get_questionnaire_questions(&questionnaire_model).await?;
 
         Ok(QuestionnaireResponse {
@@ -213,13 +735,54 @@ impl QuestionnaireService {
             title,
             description,
             is_public,
+            status,
+            opens_at: questionnaire.opens_at,
+            closes_at: questionnaire.closes_at,
+            limits: SubmissionLimits {
+                max_responses: questionnaire.max_responses,
+                one_per_account: questionnaire.one_per_account,
+                one_per_device: questionnaire.one_per_device,
+                one_per_ip: questionnaire.one_per_ip,
+                invite_only: questionnaire.invite_only,
+            },
+            anonymous: questionnaire.anonymous,
             creator_id,
+            organization_id: questionnaire.organization_id,
+            current_version_id: questionnaire.current_version_id,
             created_at,
             updated_at,
+            sections,
             questions,
         })
     }
 
+    // 获取问卷当前启用的分页
+    async fn get_questionnaire_sections(
+        &self,
+        questionnaire_id: i32,
+    ) -> AppResult<Vec<SectionResponse>> {
+        let sections = sqlx::query!(
+            r#"
+            SELECT id, title, description
+            FROM question_sections
+            WHERE questionnaire_id = ? AND retired_at IS NULL
+            ORDER BY display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| SectionResponse {
+            id: row.id,
+            title: row.title,
+            description: row.description.unwrap_or_default(),
+        })
+        .collect();
+
+        Ok(sections)
+    }
+
     // 获取问卷的所有问题和选项
     async fn get_questionnaire_questions(
         &self,
@@ -227,11 +790,13 @@ impl QuestionnaireService {
     ) -> AppResult<Vec<QuestionResponse>> {
         let questions = sqlx::query!(
             r#"
-            SELECT id, questionnaire_id, title, question_type, required, display_order, 
+            SELECT id, questionnaire_id, section_id, title, question_type, required, display_order,
+                   config, logic,
+                   retired_at as "retired_at: chrono::DateTime<chrono::Utc>",
                    created_at as "created_at: chrono::DateTime<chrono::Utc>", 
                    updated_at as "updated_at: chrono::DateTime<chrono::Utc>"
             FROM questions
-            WHERE questionnaire_id = ?
+            WHERE questionnaire_id = ? AND retired_at IS NULL
             ORDER BY display_order
             "#,
             questionnaire.id
@@ -243,28 +808,34 @@ impl QuestionnaireService {
 
         for question_record in questions {
             let mut options = Vec::new();
+            let mut option_items = Vec::new();
+            let mut rows = Vec::new();
             
             // 转换为问题模型
             let question = Question {
                 id: question_record.id,
                 questionnaire_id: question_record.questionnaire_id,
+                section_id: question_record.section_id,
                 title: question_record.title,
                 question_type: question_record.question_type.clone(),
                 required: question_record.required.expect("必填标志不应为空") != 0,
                 display_order: question_record.display_order,
+                config: question_record.config,
+                logic: question_record.logic,
+                retired_at: question_record.retired_at,
                 created_at: question_record.created_at.expect("创建时间不应为空"),
                 updated_at: question_record.updated_at.expect("更新时间不应为空"),
             };
 
-            // 如果是单选或多选题，获取选项
-            if question.question_type == "radio" || question.question_type == "checkbox" {
+            // 如果是带选项的题型，获取选项（矩阵题还包括行）
+            if uses_options(&question.question_type) {
                 let question_options = sqlx::query!(
                     r#"
-                    SELECT id, question_id, option_text, display_order,
+                    SELECT id, question_id, option_text, role, display_order,
                            created_at as "created_at: chrono::DateTime<chrono::Utc>", 
                            updated_at as "updated_at: chrono::DateTime<chrono::Utc>"
                     FROM question_options
-                    WHERE question_id = ?
+                    WHERE question_id = ? AND retired_at IS NULL
                     ORDER BY display_order
                     "#,
                     question.id
@@ -273,33 +844,74 @@ impl QuestionnaireService {
                 .await?;
 
                 for option_record in question_options {
-                    options.push(option_record.option_text);
+                    let item = OptionItem {
+                        id: option_record.id,
+                        text: option_record.option_text,
+                    };
+
+                    if option_record.role == "row" {
+                        rows.push(item);
+                    } else {
+                        options.push(item.text.clone());
+                        option_items.push(item);
+                    }
                 }
             }
 
+            let config = question
+                .config
+                .as_deref()
+                .map(serde_json::from_str::<QuestionConfig>)
+                .transpose()
+                .map_err(|e| AppError::InternalServerError(format!("解析题型配置失败: {}", e)))?;
+            let logic = question
+                .logic
+                .as_deref()
+                .map(serde_json::from_str::<QuestionLogic>)
+                .transpose()
+                .map_err(|e| AppError::InternalServerError(format!("解析条件逻辑失败: {}", e)))?;
+
             result.push(QuestionResponse {
                 id: question.id,
                 title: question.title,
                 question_type: question.question_type,
                 required: question.required,
                 options,
+                option_items,
+                rows,
+                config,
+                logic,
+                section_id: question.section_id,
             });
         }
 
         Ok(result)
     }
 
-    // 获取用户的问卷列表
+    // 获取用户工作区中的问卷列表，未指定组织时返回个人问卷
     pub async fn get_user_questionnaires(
         &self,
         user_id: i32,
+        organization_id: Option<i32>,
         page: i64,
         page_size: i64,
     ) -> AppResult<QuestionnaireListResponse> {
+        if let Some(organization_id) = organization_id {
+            PermissionService::new(self.db.clone())
+                .require_member(user_id, organization_id, OrganizationRole::Member)
+                .await?;
+        }
+
         // 获取总数
         let total = sqlx::query!(
-            "SELECT COUNT(*) as count FROM questionnaires WHERE creator_id = ?",
-            user_id
+            r#"
+            SELECT COUNT(*) as count FROM questionnaires
+            WHERE (? IS NULL AND creator_id = ? AND organization_id IS NULL)
+               OR organization_id = ?
+            "#,
+            organization_id,
+            user_id,
+            organization_id
         )
         .fetch_one(&*self.db)
         .await?
@@ -312,16 +924,23 @@ impl QuestionnaireService {
                 q.id, 
                 q.title, 
                 q.description, 
-                u.username as creator,
+                q.status as "status!: String",
+                COALESCE(u.username, '') as "creator!: String",
+                o.name as "organization?",
                 q.created_at as "created_at: chrono::DateTime<chrono::Utc>",
-                (SELECT COUNT(*) FROM questionnaire_responses WHERE questionnaire_id = q.id) as response_count
+                (SELECT COUNT(*) FROM questionnaire_responses
+                 WHERE questionnaire_id = q.id AND status = 'completed') as response_count
             FROM questionnaires q
-            JOIN users u ON q.creator_id = u.id
-            WHERE q.creator_id = ?
+            LEFT JOIN users u ON q.creator_id = u.id
+            LEFT JOIN organizations o ON q.organization_id = o.id
+            WHERE (? IS NULL AND q.creator_id = ? AND q.organization_id IS NULL)
+               OR q.organization_id = ?
             ORDER BY q.created_at DESC
             LIMIT ? OFFSET ?
             "#,
+            organization_id,
             user_id,
+            organization_id,
             page_size,
             (page - 1) * page_size
         )
@@ -330,15 +949,19 @@ impl QuestionnaireService {
 
         let items = items
             .into_iter()
-            .map(|row| QuestionnaireListItem {
-                id: row.id.expect("问卷ID不应为空"),
-                title: row.title.expect("问卷标题不应为空"),
-                description: row.description.expect("问卷描述不应为空"),
-                creator: row.creator,
-                created_at: row.created_at.expect("创建时间不应为空"),
-                response_count: row.response_count.unwrap_or(0) as i32,
+            .map(|row| {
+                Ok(QuestionnaireListItem {
+                    id: row.id.expect("问卷ID不应为空"),
+                    title: row.title.expect("问卷标题不应为空"),
+                    description: row.description.expect("问卷描述不应为空"),
+                    status: parse_status(&row.status)?,
+                    creator: row.creator,
+                    organization: row.organization,
+                    created_at: row.created_at.expect("创建时间不应为空"),
+                    response_count: row.response_count.unwrap_or(0) as i32,
+                })
             })
-            .collect();
+            .collect::<AppResult<Vec<_>>>()?;
 
         Ok(QuestionnaireListResponse {
             items,
@@ -360,7 +983,9 @@ impl QuestionnaireService {
             id: Option<i32>,
             title: Option<String>,
             description: Option<String>,
+            status: String,
             creator: String,
+            organization: Option<String>,
             created_at: Option<chrono::DateTime<chrono::Utc>>,
             response_count: Option<i64>,
         }
@@ -379,12 +1004,16 @@ impl QuestionnaireService {
                     q.id, 
                     q.title, 
                     q.description, 
-                    u.username as creator,
+                    q.status as "status!: String",
+                    COALESCE(u.username, '') as "creator!: String",
+                    o.name as "organization?",
                     q.created_at as "created_at: chrono::DateTime<chrono::Utc>",
-                    (SELECT COUNT(*) FROM questionnaire_responses WHERE questionnaire_id = q.id) as response_count
+                    (SELECT COUNT(*) FROM questionnaire_responses
+                     WHERE questionnaire_id = q.id AND status = 'completed') as response_count
                 FROM questionnaires q
-                JOIN users u ON q.creator_id = u.id
-                WHERE q.is_public = 1
+                LEFT JOIN users u ON q.creator_id = u.id
+                LEFT JOIN organizations o ON q.organization_id = o.id
+                WHERE q.is_public = 1 AND q.status = 'published'
                 AND (q.title LIKE ? OR q.description LIKE ?)
                 ORDER BY q.created_at DESC
                 LIMIT ? OFFSET ?
@@ -403,7 +1032,9 @@ impl QuestionnaireService {
                     id: row.id,
                     title: row.title,
                     description: row.description,
+                    status: row.status,
                     creator: row.creator,
+                    organization: row.organization,
                     created_at: row.created_at,
                     response_count: row.response_count,
                 })
@@ -414,7 +1045,7 @@ impl QuestionnaireService {
                 "
                 SELECT COUNT(*) as count 
                 FROM questionnaires q
-                WHERE q.is_public = 1
+                WHERE q.is_public = 1 AND q.status = 'published'
                 AND (q.title LIKE ? OR q.description LIKE ?)
                 ",
                 search_pattern,
@@ -431,12 +1062,16 @@ impl QuestionnaireService {
                     q.id, 
                     q.title, 
                     q.description, 
-                    u.username as creator,
+                    q.status as "status!: String",
+                    COALESCE(u.username, '') as "creator!: String",
+                    o.name as "organization?",
                     q.created_at as "created_at: chrono::DateTime<chrono::Utc>",
-                    (SELECT COUNT(*) FROM questionnaire_responses WHERE questionnaire_id = q.id) as response_count
+                    (SELECT COUNT(*) FROM questionnaire_responses
+                     WHERE questionnaire_id = q.id AND status = 'completed') as response_count
                 FROM questionnaires q
-                JOIN users u ON q.creator_id = u.id
-                WHERE q.is_public = 1
+                LEFT JOIN users u ON q.creator_id = u.id
+                LEFT JOIN organizations o ON q.organization_id = o.id
+                WHERE q.is_public = 1 AND q.status = 'published'
                 ORDER BY q.created_at DESC
                 LIMIT ? OFFSET ?
                 "#,
@@ -452,7 +1087,9 @@ impl QuestionnaireService {
                     id: row.id,
                     title: row.title,
                     description: row.description,
+                    status: row.status,
                     creator: row.creator,
+                    organization: row.organization,
                     created_at: row.created_at,
                     response_count: row.response_count,
                 })
@@ -463,7 +1100,7 @@ impl QuestionnaireService {
                 "
                 SELECT COUNT(*) as count 
                 FROM questionnaires q
-                WHERE q.is_public = 1
+                WHERE q.is_public = 1 AND q.status = 'published'
                 "
             )
             .fetch_one(&*self.db)
@@ -474,15 +1111,19 @@ impl QuestionnaireService {
         // 转换为列表项
         let items = raw_items
             .into_iter()
-            .map(|row| QuestionnaireListItem {
-                id: row.id.expect("问卷ID不应为空"),
-                title: row.title.expect("问卷标题不应为空"),
-                description: row.description.expect("问卷描述不应为空"),
-                creator: row.creator,
-                created_at: row.created_at.expect("创建时间不应为空"),
-                response_count: row.response_count.unwrap_or(0) as i32,
+            .map(|row| {
+                Ok(QuestionnaireListItem {
+                    id: row.id.expect("问卷ID不应为空"),
+                    title: row.title.expect("问卷标题不应为空"),
+                    description: row.description.expect("问卷描述不应为空"),
+                    status: parse_status(&row.status)?,
+                    creator: row.creator,
+                    organization: row.organization,
+                    created_at: row.created_at.expect("创建时间不应为空"),
+                    response_count: row.response_count.unwrap_or(0) as i32,
+                })
             })
-            .collect();
+            .collect::<AppResult<Vec<_>>>()?;
 
         Ok(QuestionnaireListResponse {
             items,
@@ -492,21 +1133,141 @@ impl QuestionnaireService {
         })
     }
 
-    // 删除问卷
-    pub async fn delete_questionnaire(&self, user_id: i32, questionnaire_id: i32) -> AppResult<()> {
-        // 先检查问卷是否存在且属于该用户
-        let questionnaire = sqlx::query!(
-            "SELECT creator_id FROM questionnaires WHERE id = ?",
+    // 将问卷转移到组织或其他用户的个人工作区
+    pub async fn transfer(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        req: TransferQuestionnaireRequest,
+    ) -> AppResult<QuestionnaireResponse> {
+        let permissions = PermissionService::new(self.db.clone());
+        permissions
+            .authorize(user_id, questionnaire_id, Access::Manage)
+            .await?;
+
+        let username = req.username.as_deref().map(str::trim).filter(|v| !v.is_empty());
+
+        match (req.organization_id, username) {
+            (Some(organization_id), None) => {
+                // 只能转移到自己所在的组织
+                permissions
+                    .require_member(user_id, organization_id, OrganizationRole::Member)
+                    .await?;
+
+                sqlx::query!(
+                    "UPDATE questionnaires SET organization_id = ? WHERE id = ?",
+                    organization_id,
+                    questionnaire_id
+                )
+                .execute(&*self.db)
+                .await?;
+            }
+            (None, Some(username)) => {
+                let recipient = sqlx::query!("SELECT id FROM users WHERE username = ?", username)
+                    .fetch_optional(&*self.db)
+                    .await?
+                    .ok_or_else(|| AppError::NotFoundError("接收问卷的用户不存在".to_string()))?;
+
+                let mut tx = self.db.begin().await?;
+
+                sqlx::query!(
+                    r#"
+                    UPDATE questionnaires SET creator_id = ?, organization_id = NULL
+                    WHERE id = ?
+                    "#,
+                    recipient.id,
+                    questionnaire_id
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                // 接收人成为所有者后不再需要协作者身份
+                sqlx::query!(
+                    "DELETE FROM questionnaire_collaborators WHERE questionnaire_id = ? AND user_id = ?",
+                    questionnaire_id,
+                    recipient.id
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                tx.commit().await?;
+            }
+            _ => {
+                return Err(AppError::validation(
+                    "请提供接收问卷的组织ID或用户名之一",
+                ))
+            }
+        }
+
+        self.get_questionnaire(questionnaire_id).await
+    }
+
+    // 设置问卷是否匿名收集回答，匿名问卷不向创建者展示回答者身份；
+    // 已经匿名收集到回答后不能取消匿名，否则这些回答的提交人会重新显示出来
+    pub async fn update_anonymity(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        req: AnonymityRequest,
+    ) -> AppResult<QuestionnaireResponse> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::Edit)
+            .await?;
+
+        let mut tx = self.db.begin().await?;
+
+        // 锁定问卷，避免检查期间有新的回答提交
+        let anonymous = sqlx::query!(
+            r#"SELECT anonymous as "anonymous: bool" FROM questionnaires WHERE id = ? FOR UPDATE"#,
             questionnaire_id
         )
-        .fetch_optional(&*self.db)
+        .fetch_one(&mut *tx)
         .await?
-        .ok_or_else(|| AppError::NotFoundError(format!("问卷ID {} 不存在", questionnaire_id)))?;
+        .anonymous;
+
+        if anonymous && !req.anonymous {
+            let responses = sqlx::query!(
+                r#"
+                SELECT COUNT(*) as count FROM questionnaire_responses
+                WHERE questionnaire_id = ? AND status = 'completed'
+                "#,
+                questionnaire_id
+            )
+            .fetch_one(&mut *tx)
+            .await?
+            .count;
+
+            if responses > 0 {
+                return Err(AppError::validation(
+                    "问卷已经匿名收集到回答，不能再取消匿名",
+                ));
+            }
+        }
+
+        sqlx::query!(
+            "UPDATE questionnaires SET anonymous = ? WHERE id = ?",
+            req.anonymous,
+            questionnaire_id
+        )
+        .execute(&mut *tx)
+        .await?;
 
-        if questionnaire.creator_id != user_id {
-            return Err(AppError::PermissionError("你无权删除此问卷".to_string()));
+        if req.anonymous {
+            CampaignService::detach_responses(&mut tx, questionnaire_id).await?;
         }
 
+        tx.commit().await?;
+
+        self.get_questionnaire(questionnaire_id).await
+    }
+
+    // 删除问卷
+    pub async fn delete_questionnaire(&self, user_id: i32, questionnaire_id: i32) -> AppResult<()> {
+        // 先检查问卷是否存在，只有所有者和管理员可以删除
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::Manage)
+            .await?;
+
         // 开始事务
         let mut tx = self.db.begin().await?;
 
@@ -520,12 +1281,12 @@ impl QuestionnaireService {
     }
 
     // 在事务中删除问卷及相关数据
-    async fn delete_questionnaire_transaction(
+    pub(crate) async fn delete_questionnaire_transaction(
         tx: &mut Transaction<'_, MySql>,
         questionnaire_id: i32,
     ) -> AppResult<()> {
         // 删除问卷的回答
-        // 先删除问题回答的选项和文本
+        // 先删除问题回答的选项、文本和数值
         sqlx::query!(
             r#"
             DELETE tr
@@ -552,6 +1313,19 @@ impl QuestionnaireService {
         .execute(&mut **tx)
         .await?;
 
+        sqlx::query!(
+            r#"
+            DELETE nr
+            FROM numeric_responses nr
+            JOIN question_responses qr ON nr.question_response_id = qr.id
+            JOIN questionnaire_responses qnr ON qr.questionnaire_response_id = qnr.id
+            WHERE qnr.questionnaire_id = ?
+            "#,
+            questionnaire_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
         // 删除问题回答
         sqlx::query!(
             r#"
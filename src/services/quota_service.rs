@@ -0,0 +1,337 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use sqlx::{Executor, MySql, Pool, Transaction};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult, FieldErrors};
+use crate::models::questionnaire::{QuestionnaireResponse, SubmissionLimits};
+use crate::models::quota::{QuotaStatus, UpdateQuotasRequest};
+use crate::models::response::Respondent;
+use crate::services::answer_validator::ValidatedAnswer;
+use crate::services::permission_service::{Access, PermissionService};
+use crate::services::questionnaire_service::QuestionnaireService;
+
+pub struct QuotaService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl QuotaService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 更新问卷的提交限制
+    pub async fn update_limits(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        limits: SubmissionLimits,
+    ) -> AppResult<QuestionnaireResponse> {
+        self.check_access(user_id, questionnaire_id, Access::Edit).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE questionnaires
+            SET max_responses = ?, one_per_account = ?, one_per_device = ?, one_per_ip = ?,
+                invite_only = ?
+            WHERE id = ?
+            "#,
+            limits.max_responses,
+            limits.one_per_account,
+            limits.one_per_device,
+            limits.one_per_ip,
+            limits.invite_only,
+            questionnaire_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        QuestionnaireService::new(self.db.clone(), self.config.clone())
+            .get_questionnaire(questionnaire_id)
+            .await
+    }
+
+    // 获取问卷的配额及完成情况
+    pub async fn get_quotas(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+    ) -> AppResult<Vec<QuotaStatus>> {
+        self.check_access(user_id, questionnaire_id, Access::View).await?;
+        Self::quota_status(&*self.db, questionnaire_id).await
+    }
+
+    // 替换问卷的全部配额，配额只能设置在单选题和多选题的选项上
+    pub async fn update_quotas(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        req: UpdateQuotasRequest,
+    ) -> AppResult<Vec<QuotaStatus>> {
+        self.check_access(user_id, questionnaire_id, Access::Edit).await?;
+
+        let options = sqlx::query!(
+            r#"
+            SELECT qo.id, qo.question_id
+            FROM question_options qo
+            JOIN questions q ON qo.question_id = q.id
+            WHERE q.questionnaire_id = ? AND q.retired_at IS NULL AND qo.retired_at IS NULL
+            AND q.question_type IN ('radio', 'checkbox') AND qo.role = 'option'
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        let mut errors = FieldErrors::new();
+        let mut seen = HashSet::new();
+
+        for (index, quota) in req.quotas.iter().enumerate() {
+            let mut messages = Vec::new();
+
+            if quota.limit < 1 {
+                messages.push("配额必须大于0".to_string());
+            }
+            if !options
+                .iter()
+                .any(|option| option.id == quota.option_id && option.question_id == quota.question_id)
+            {
+                messages.push("选项不属于该问卷的单选题或多选题".to_string());
+            }
+            if !seen.insert(quota.option_id) {
+                messages.push("同一选项只能设置一个配额".to_string());
+            }
+
+            if !messages.is_empty() {
+                errors.insert(format!("quotas[{}]", index), messages);
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(AppError::validation_fields("配额设置不正确", errors));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM response_quotas WHERE questionnaire_id = ?",
+            questionnaire_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for quota in &req.quotas {
+            sqlx::query!(
+                r#"
+                INSERT INTO response_quotas (questionnaire_id, question_id, option_id, quota_limit)
+                VALUES (?, ?, ?, ?)
+                "#,
+                questionnaire_id,
+                quota.question_id,
+                quota.option_id,
+                quota.limit
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Self::quota_status(&*self.db, questionnaire_id).await
+    }
+
+    // 锁定问卷行并检查提交人是否已提交过，同一问卷的提交在此之后串行执行
+    pub(crate) async fn lock_and_check_respondent(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        respondent: &Respondent,
+    ) -> AppResult<SubmissionLimits> {
+        let row = sqlx::query!(
+            r#"
+            SELECT max_responses,
+                   one_per_account as "one_per_account: bool",
+                   one_per_device as "one_per_device: bool",
+                   one_per_ip as "one_per_ip: bool",
+                   invite_only as "invite_only: bool"
+            FROM questionnaires
+            WHERE id = ?
+            FOR UPDATE
+            "#,
+            questionnaire_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        let limits = SubmissionLimits {
+            max_responses: row.max_responses,
+            one_per_account: row.one_per_account,
+            one_per_device: row.one_per_device,
+            one_per_ip: row.one_per_ip,
+            invite_only: row.invite_only,
+        };
+
+        let by_account = limits.one_per_account && respondent.user_id.is_some();
+        let by_device = limits.one_per_device && respondent.device_id.is_some();
+        let by_ip = limits.one_per_ip && respondent.ip_address.is_some();
+
+        if by_account || by_device || by_ip {
+            let submitted = sqlx::query!(
+                r#"
+                SELECT COUNT(*) as count FROM questionnaire_responses
+                WHERE questionnaire_id = ? AND status = 'completed'
+                AND ((? AND respondent_id = ?) OR (? AND device_id = ?) OR (? AND ip_address = ?))
+                "#,
+                questionnaire_id,
+                by_account,
+                respondent.user_id,
+                by_device,
+                respondent.device_id,
+                by_ip,
+                respondent.ip_address
+            )
+            .fetch_one(&mut **tx)
+            .await?
+            .count;
+
+            if submitted > 0 {
+                return Err(AppError::validation(
+                    "您已经提交过该问卷，不能重复提交".to_string()
+                ));
+            }
+        }
+
+        Ok(limits)
+    }
+
+    // 检查回答总数上限和所选选项的配额，需在lock_and_check_respondent之后调用
+    pub(crate) async fn check_capacity(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        limits: &SubmissionLimits,
+        answers: &[ValidatedAnswer],
+    ) -> AppResult<()> {
+        if let Some(max_responses) = limits.max_responses {
+            if Self::completed_count(tx, questionnaire_id).await? >= max_responses as i64 {
+                return Err(AppError::QuestionnaireNotOpen(
+                    "问卷已达到回答数量上限".to_string(),
+                ));
+            }
+        }
+
+        let selected: HashSet<i32> = answers
+            .iter()
+            .flat_map(|answer| &answer.selections)
+            .filter(|selection| selection.row_option_id.is_none())
+            .map(|selection| selection.option_id)
+            .collect();
+
+        for quota in Self::quota_status(&mut **tx, questionnaire_id).await? {
+            if selected.contains(&quota.option_id) && quota.remaining <= 0 {
+                return Err(AppError::QuestionnaireNotOpen(format!(
+                    "选项“{}”的名额已满",
+                    quota.option_text
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // 回答数达到上限后关闭问卷，返回问卷是否由本次提交关闭
+    pub(crate) async fn close_if_full(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        limits: &SubmissionLimits,
+    ) -> AppResult<bool> {
+        let Some(max_responses) = limits.max_responses else {
+            return Ok(false);
+        };
+
+        if Self::completed_count(tx, questionnaire_id).await? < max_responses as i64 {
+            return Ok(false);
+        }
+
+        let result = sqlx::query!(
+            "UPDATE questionnaires SET status = 'closed' WHERE id = ? AND status = 'published'",
+            questionnaire_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn completed_count(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+    ) -> AppResult<i64> {
+        let count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM questionnaire_responses
+            WHERE questionnaire_id = ? AND status = 'completed'
+            "#,
+            questionnaire_id
+        )
+        .fetch_one(&mut **tx)
+        .await?
+        .count;
+
+        Ok(count)
+    }
+
+    // 统计每个配额已完成的回答数
+    async fn quota_status<'e, E>(executor: E, questionnaire_id: i32) -> AppResult<Vec<QuotaStatus>>
+    where
+        E: Executor<'e, Database = MySql>,
+    {
+        let quotas = sqlx::query!(
+            r#"
+            SELECT
+                rq.id,
+                rq.question_id,
+                q.title as question_title,
+                rq.option_id,
+                qo.option_text,
+                rq.quota_limit,
+                (
+                    SELECT COUNT(DISTINCT qr.questionnaire_response_id)
+                    FROM option_responses o
+                    JOIN question_responses qr ON o.question_response_id = qr.id
+                    JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+                    WHERE o.option_id = rq.option_id AND r.status = 'completed'
+                ) as "filled!: i64"
+            FROM response_quotas rq
+            JOIN questions q ON rq.question_id = q.id
+            JOIN question_options qo ON rq.option_id = qo.id
+            WHERE rq.questionnaire_id = ?
+            ORDER BY q.display_order, qo.display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(|row| QuotaStatus {
+            id: row.id,
+            question_id: row.question_id,
+            question_title: row.question_title,
+            option_id: row.option_id,
+            option_text: row.option_text,
+            limit: row.quota_limit,
+            filled: row.filled as i32,
+            remaining: (row.quota_limit - row.filled as i32).max(0),
+        })
+        .collect();
+
+        Ok(quotas)
+    }
+
+    async fn check_access(&self, user_id: i32, questionnaire_id: i32, access: Access) -> AppResult<()> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, access)
+            .await?;
+
+        Ok(())
+    }
+}
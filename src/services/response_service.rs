@@ -1,12 +1,31 @@
 use std::sync::Arc;
-use sqlx::{MySql, Pool};
+use sqlx::{MySql, MySqlConnection, Pool, Transaction};
+use uuid::Uuid;
 
 use crate::models::error::{AppError, AppResult};
+use crate::models::questionnaire::{is_matrix, QuestionnaireStatus};
 use crate::models::response::{
-    AnswerDetail, OptionCount, QuestionStatistics, QuestionnaireStatistics,
-    ResponseDetails, ResponseListItem, SubmitResponseRequest, SubmitResponseResponse,
+    AnswerDetail, MatrixAnswerDetail, DraftResponse, QuestionAnswer, Respondent, ResponseDetails,
+    ResponseListItem, SaveDraftRequest, SubmitResponseRequest, SubmitResponseResponse,
 };
+use crate::models::webhook::{QuestionnaireEventData, ResponseSubmittedData, WebhookEvent};
 use crate::config::Config;
+use crate::services::answer_validator::{QuestionnaireDefinition, ValidatedAnswer};
+use crate::services::campaign_service::CampaignService;
+use crate::services::lifecycle_service::ensure_accepting;
+use crate::services::notification_service::NotificationService;
+use crate::services::permission_service::{Access, PermissionService};
+use crate::services::quota_service::QuotaService;
+use crate::services::statistics_service::StatisticsService;
+use crate::services::version_service::VersionService;
+use crate::services::webhook_service::WebhookService;
+
+// 尚未提交的草稿
+struct Draft {
+    id: i32,
+    resume_token: String,
+    answers: Vec<QuestionAnswer>,
+}
 
 pub struct ResponseService {
     db: Arc<Pool<MySql>>,
@@ -18,15 +37,44 @@ impl ResponseService {
         Self { db, config }
     }
 
-    // 提交问卷回答
+    // 提交问卷回答，携带续答凭证时提交对应的草稿
     pub async fn submit_response(
         &self,
-        user_id: Option<i32>,
+        respondent: Respondent,
+        req: SubmitResponseRequest,
+    ) -> AppResult<SubmitResponseResponse> {
+        self.submit(respondent, req, None).await
+    }
+
+    // 通过邀请链接提交，回答关联到受邀人，每个链接只能提交一次
+    pub async fn submit_invited_response(
+        &self,
+        token: &str,
         req: SubmitResponseRequest,
     ) -> AppResult<SubmitResponseResponse> {
-        // 检查问卷是否存在
-        let _questionnaire = sqlx::query!(
-            "SELECT id, is_public FROM questionnaires WHERE id = ?",
+        // 由链接识别提交人，不再按账号、设备和IP限制，也不记录这些信息
+        let respondent = Respondent {
+            user_id: None,
+            device_id: None,
+            ip_address: None,
+        };
+        self.submit(respondent, req, Some(token)).await
+    }
+
+    async fn submit(
+        &self,
+        respondent: Respondent,
+        req: SubmitResponseRequest,
+        invitation: Option<&str>,
+    ) -> AppResult<SubmitResponseResponse> {
+        // 检查问卷是否存在且正在接受回答
+        let questionnaire = sqlx::query!(
+            r#"
+            SELECT id, title, current_version_id, status,
+                   opens_at as "opens_at: chrono::DateTime<chrono::Utc>",
+                   closes_at as "closes_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaires WHERE id = ?
+            "#,
             req.questionnaire_id
         )
         .fetch_optional(&*self.db)
@@ -34,281 +82,431 @@ impl ResponseService {
         .ok_or_else(|| {
             AppError::NotFoundError(format!("问卷ID {} 不存在", req.questionnaire_id))
         })?;
-        
-        // 如果是已登录用户，检查是否已经提交过该问卷
-        if let Some(uid) = user_id {
-            let existing_response = sqlx::query!(
-                r#"
-                SELECT id FROM questionnaire_responses 
-                WHERE questionnaire_id = ? AND respondent_id = ?
-                "#,
-                req.questionnaire_id,
-                uid
-            )
-            .fetch_optional(&*self.db)
-            .await?;
-            
-            // 如果已经提交过，返回错误
-            if existing_response.is_some() {
-                return Err(AppError::ValidationError(
-                    "您已经提交过该问卷，不能重复提交".to_string()
-                ));
-            }
-        }
+        ensure_accepting(&questionnaire.status, questionnaire.opens_at, questionnaire.closes_at)?;
 
         // 开始事务
         let mut tx = self.db.begin().await?;
 
-        // 创建问卷回答记录
-        let questionnaire_response_id = sqlx::query!(
-            r#"
-            INSERT INTO questionnaire_responses (questionnaire_id, respondent_id)
-            VALUES (?, ?)
-            "#,
-            req.questionnaire_id,
-            user_id
-        )
-        .execute(&mut *tx)
-        .await?
-        .last_insert_id() as i32;
+        // 锁定问卷后检查提交限制，保证并发提交时不会超过上限和配额
+        let limits =
+            QuotaService::lock_and_check_respondent(&mut tx, req.questionnaire_id, &respondent)
+                .await?;
 
-        // 处理每个问题的回答
-        for answer in &req.answers {
-            // 创建问题回答记录
-            let question_response_id = sqlx::query!(
+        let recipient_id = match invitation {
+            Some(token) => {
+                Some(CampaignService::lock_recipient(&mut tx, token, req.questionnaire_id).await?)
+            }
+            None if limits.invite_only => {
+                return Err(AppError::PermissionError(
+                    "该问卷只接受通过邀请链接提交的回答".to_string(),
+                ));
+            }
+            None => None,
+        };
+
+        let draft = match &req.resume_token {
+            Some(token) => Some(
+                Self::load_draft(&mut tx, token, respondent.user_id, req.questionnaire_id).await?,
+            ),
+            None => None,
+        };
+
+        // 按问卷定义验证回答（包括草稿中已保存的回答），未通过时返回各问题的错误信息
+        let definition = QuestionnaireDefinition::load(&mut tx, req.questionnaire_id).await?;
+        let (draft_id, answers) = match draft {
+            Some(draft) => (
+                Some(draft.id),
+                merge_answers(&definition, draft.answers, req.answers),
+            ),
+            None => (None, req.answers),
+        };
+        let answers = definition.validate(&answers)?;
+
+        QuotaService::check_capacity(&mut tx, req.questionnaire_id, &limits, &answers).await?;
+
+        // 草稿分多次填写时，以最早的开始时间为准
+        let started_at = reported_start(req.started_at);
+
+        // 创建或完成问卷回答记录，并关联回答时问卷所处的版本
+        let questionnaire_response_id = match draft_id {
+            Some(draft_id) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE questionnaire_responses
+                    SET status = 'completed', version_id = ?,
+                        respondent_id = COALESCE(respondent_id, ?),
+                        device_id = ?, ip_address = ?,
+                        resume_token = NULL, draft_answers = NULL, current_section_id = NULL,
+                        started_at = LEAST(COALESCE(?, started_at), COALESCE(started_at, ?)),
+                        submitted_at = CURRENT_TIMESTAMP
+                    WHERE id = ?
+                    "#,
+                    questionnaire.current_version_id,
+                    respondent.user_id,
+                    respondent.device_id,
+                    respondent.ip_address,
+                    started_at,
+                    started_at,
+                    draft_id
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                draft_id
+            }
+            None => sqlx::query!(
                 r#"
-                INSERT INTO question_responses (questionnaire_response_id, question_id)
-                VALUES (?, ?)
+                INSERT INTO questionnaire_responses
+                (questionnaire_id, version_id, respondent_id, device_id, ip_address,
+                 status, started_at, submitted_at)
+                VALUES (?, ?, ?, ?, ?, 'completed', ?, CURRENT_TIMESTAMP)
                 "#,
-                questionnaire_response_id,
-                answer.question_id
+                req.questionnaire_id,
+                questionnaire.current_version_id,
+                respondent.user_id,
+                respondent.device_id,
+                respondent.ip_address,
+                started_at
             )
             .execute(&mut *tx)
             .await?
-            .last_insert_id() as i32;
+            .last_insert_id() as i32,
+        };
 
-            // 根据问题类型保存回答内容
-            match answer.answer_type.as_str() {
-                "text" => {
-                    if let Some(text_value) = &answer.text_value {
-                        sqlx::query!(
-                            r#"
-                            INSERT INTO text_responses (question_response_id, text_value)
-                            VALUES (?, ?)
-                            "#,
-                            question_response_id,
-                            text_value
-                        )
-                        .execute(&mut *tx)
-                        .await?;
-                    }
-                }
-                "option" | "options" => {
-                    // 处理选项回答
-                    if let Some(option_ids) = &answer.option_ids {
-                        for option_id in option_ids {
-                            sqlx::query!(
-                                r#"
-                                INSERT INTO option_responses (question_response_id, option_id)
-                                VALUES (?, ?)
-                                "#,
-                                question_response_id,
-                                option_id
-                            )
-                            .execute(&mut *tx)
-                            .await?;
-                        }
-                    } else if let Some(option_values) = &answer.option_values {
-                        // 如果提交的是选项文本而非ID，需要查找对应的选项ID
-                        for option_value in option_values {
-                            // 查找选项ID
-                            let option = sqlx::query!(
-                                r#"
-                                SELECT id FROM question_options 
-                                WHERE question_id = ? AND option_text = ?
-                                "#,
-                                answer.question_id,
-                                option_value
-                            )
-                            .fetch_optional(&mut *tx)
-                            .await?;
-
-                            if let Some(option_row) = option {
-                                sqlx::query!(
-                                    r#"
-                                    INSERT INTO option_responses (question_response_id, option_id)
-                                    VALUES (?, ?)
-                                    "#,
-                                    question_response_id,
-                                    option_row.id
-                                )
-                                .execute(&mut *tx)
-                                .await?;
-                            }
-                        }
-                    }
-                }
-                _ => {
-                    return Err(AppError::ValidationError(format!(
-                        "不支持的回答类型: {}",
-                        answer.answer_type
-                    )));
-                }
-            }
+        Self::insert_answers(&mut tx, questionnaire_response_id, &answers).await?;
+        if let Some(recipient_id) = recipient_id {
+            CampaignService::complete_recipient(&mut tx, recipient_id, questionnaire_response_id)
+                .await?;
         }
+        StatisticsService::record_response(&mut tx, req.questionnaire_id, questionnaire_response_id)
+            .await?;
+        let closed = QuotaService::close_if_full(&mut tx, req.questionnaire_id, &limits).await?;
 
-        // 提交事务
-        tx.commit().await?;
-
-        Ok(SubmitResponseResponse {
+        let submission = SubmitResponseResponse {
             id: questionnaire_response_id,
             questionnaire_id: req.questionnaire_id,
             success: true,
             created_at: chrono::Utc::now(),
-        })
+        };
+
+        // Webhook推送与回答在同一事务中写入，没有订阅时不生成回答详情
+        let webhook_ids = WebhookService::subscribers(
+            &mut tx,
+            req.questionnaire_id,
+            WebhookEvent::ResponseSubmitted,
+        )
+        .await?;
+        if !webhook_ids.is_empty() {
+            let response = Self::load_response_detail(&mut tx, questionnaire_response_id).await?;
+            WebhookService::enqueue(
+                &mut tx,
+                &webhook_ids,
+                req.questionnaire_id,
+                WebhookEvent::ResponseSubmitted,
+                &ResponseSubmittedData {
+                    submission: &submission,
+                    response: &response,
+                },
+            )
+            .await?;
+        }
+        // 通知邮件同样先写入发件箱，发送失败不影响已提交的回答
+        NotificationService::enqueue_response(
+            &mut tx,
+            req.questionnaire_id,
+            questionnaire_response_id,
+            respondent.user_id,
+        )
+        .await?;
+        if closed {
+            WebhookService::notify(
+                &mut tx,
+                req.questionnaire_id,
+                WebhookEvent::QuestionnaireClosed,
+                &QuestionnaireEventData {
+                    id: req.questionnaire_id,
+                    title: questionnaire.title,
+                    status: QuestionnaireStatus::Closed,
+                    trigger: "response_cap",
+                },
+            )
+            .await?;
+        }
+
+        // 提交事务
+        tx.commit().await?;
+
+        Ok(submission)
     }
 
-    // 获取问卷的统计信息
-    pub async fn get_questionnaire_statistics(
+    // 保存草稿：与已保存的回答合并，只检查已作答问题的格式
+    pub async fn save_draft(
         &self,
-        user_id: i32,
-        questionnaire_id: i32,
-    ) -> AppResult<QuestionnaireStatistics> {
-        // 检查问卷是否存在且用户是否有权限查看
+        respondent: Respondent,
+        req: SaveDraftRequest,
+    ) -> AppResult<DraftResponse> {
         let questionnaire = sqlx::query!(
-            "SELECT id, title, creator_id FROM questionnaires WHERE id = ?",
-            questionnaire_id
+            r#"
+            SELECT id, current_version_id, status,
+                   opens_at as "opens_at: chrono::DateTime<chrono::Utc>",
+                   closes_at as "closes_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaires WHERE id = ?
+            "#,
+            req.questionnaire_id
         )
         .fetch_optional(&*self.db)
         .await?
         .ok_or_else(|| {
-            AppError::NotFoundError(format!("问卷ID {} 不存在", questionnaire_id))
+            AppError::NotFoundError(format!("问卷ID {} 不存在", req.questionnaire_id))
         })?;
+        ensure_accepting(&questionnaire.status, questionnaire.opens_at, questionnaire.closes_at)?;
 
-        // 检查权限
-        if questionnaire.creator_id != user_id {
-            return Err(AppError::PermissionError(
-                "你无权查看此问卷的统计信息".to_string(),
-            ));
+        let mut tx = self.db.begin().await?;
+
+        // 已提交过的人不能再开始填写
+        QuotaService::lock_and_check_respondent(&mut tx, req.questionnaire_id, &respondent).await?;
+        let user_id = respondent.user_id;
+
+        let definition = QuestionnaireDefinition::load(&mut tx, req.questionnaire_id).await?;
+        definition.validate_partial(&req.answers)?;
+
+        // 当前分页必须是问卷中启用的分页
+        if let Some(section_id) = req.current_section_id {
+            sqlx::query!(
+                r#"
+                SELECT id FROM question_sections
+                WHERE id = ? AND questionnaire_id = ? AND retired_at IS NULL
+                "#,
+                section_id,
+                req.questionnaire_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::validation(format!("分页ID {} 不属于该问卷", section_id))
+            })?;
         }
 
-        // 获取问卷回答总数
-        let response_count = sqlx::query!(
-            "SELECT COUNT(*) as count FROM questionnaire_responses WHERE questionnaire_id = ?",
-            questionnaire_id
+        // 未携带续答凭证时，已登录用户继续使用其未提交的草稿
+        let resume_token = match (&req.resume_token, user_id) {
+            (Some(token), _) => Some(token.clone()),
+            (None, Some(uid)) => sqlx::query!(
+                r#"
+                SELECT resume_token FROM questionnaire_responses
+                WHERE questionnaire_id = ? AND respondent_id = ? AND status = 'in_progress'
+                ORDER BY updated_at DESC
+                LIMIT 1
+                "#,
+                req.questionnaire_id,
+                uid
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .and_then(|row| row.resume_token),
+            (None, None) => None,
+        };
+
+        let draft = match &resume_token {
+            Some(token) => {
+                Some(Self::load_draft(&mut tx, token, user_id, req.questionnaire_id).await?)
+            }
+            None => None,
+        };
+
+        let (draft_id, resume_token, saved_answers) = match draft {
+            Some(draft) => (Some(draft.id), draft.resume_token, draft.answers),
+            None => (None, Uuid::new_v4().simple().to_string(), Vec::new()),
+        };
+        let answers = merge_answers(&definition, saved_answers, req.answers);
+        let draft_answers = serde_json::to_string(&answers)
+            .map_err(|e| AppError::InternalServerError(format!("序列化草稿失败: {}", e)))?;
+
+        let draft_id = match draft_id {
+            Some(draft_id) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE questionnaire_responses
+                    SET draft_answers = ?, current_section_id = ?,
+                        respondent_id = COALESCE(respondent_id, ?)
+                    WHERE id = ?
+                    "#,
+                    draft_answers,
+                    req.current_section_id,
+                    user_id,
+                    draft_id
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                draft_id
+            }
+            None => sqlx::query!(
+                r#"
+                INSERT INTO questionnaire_responses
+                (questionnaire_id, version_id, respondent_id, device_id, ip_address, status,
+                 resume_token, draft_answers, current_section_id, started_at)
+                VALUES (?, ?, ?, ?, ?, 'in_progress', ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP))
+                "#,
+                req.questionnaire_id,
+                questionnaire.current_version_id,
+                user_id,
+                respondent.device_id,
+                respondent.ip_address,
+                resume_token,
+                draft_answers,
+                req.current_section_id,
+                reported_start(req.started_at)
+            )
+            .execute(&mut *tx)
+            .await?
+            .last_insert_id() as i32,
+        };
+
+        tx.commit().await?;
+
+        Ok(DraftResponse {
+            id: draft_id,
+            questionnaire_id: req.questionnaire_id,
+            resume_token,
+            current_section_id: req.current_section_id,
+            answers,
+            updated_at: chrono::Utc::now(),
+        })
+    }
+
+    // 通过续答凭证获取草稿
+    pub async fn get_draft(&self, resume_token: &str) -> AppResult<DraftResponse> {
+        let draft = sqlx::query!(
+            r#"
+            SELECT id, questionnaire_id, draft_answers, current_section_id,
+                   updated_at as "updated_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaire_responses
+            WHERE resume_token = ? AND status = 'in_progress'
+            "#,
+            resume_token
         )
-        .fetch_one(&*self.db)
+        .fetch_optional(&*self.db)
         .await?
-        .count as i32;
+        .ok_or_else(|| AppError::NotFoundError("草稿不存在或已提交".to_string()))?;
+
+        Ok(DraftResponse {
+            id: draft.id,
+            questionnaire_id: draft.questionnaire_id,
+            resume_token: resume_token.to_string(),
+            current_section_id: draft.current_section_id,
+            answers: parse_draft_answers(draft.draft_answers.as_deref())?,
+            updated_at: draft.updated_at.expect("更新时间不应为空"),
+        })
+    }
 
-        // 获取问卷的所有问题
-        let questions = sqlx::query!(
+    // 在事务中锁定并加载未提交的草稿
+    async fn load_draft(
+        tx: &mut Transaction<'_, MySql>,
+        resume_token: &str,
+        user_id: Option<i32>,
+        questionnaire_id: i32,
+    ) -> AppResult<Draft> {
+        let draft = sqlx::query!(
             r#"
-            SELECT id, title, question_type
-            FROM questions
-            WHERE questionnaire_id = ?
-            ORDER BY display_order
+            SELECT id, questionnaire_id, respondent_id, draft_answers
+            FROM questionnaire_responses
+            WHERE resume_token = ? AND status = 'in_progress'
+            FOR UPDATE
             "#,
-            questionnaire_id
+            resume_token
         )
-        .fetch_all(&*self.db)
-        .await?;
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("草稿不存在或已提交".to_string()))?;
 
-        let mut question_stats = Vec::new();
-
-        for question in questions {
-            match question.question_type.as_str() {
-                "text" => {
-                    // 获取文本回答
-                    let text_responses = sqlx::query!(
-                        r#"
-                        SELECT tr.text_value
-                        FROM text_responses tr
-                        JOIN question_responses qr ON tr.question_response_id = qr.id
-                        JOIN questionnaire_responses qnr ON qr.questionnaire_response_id = qnr.id
-                        WHERE qnr.questionnaire_id = ? AND qr.question_id = ?
-                        "#,
-                        questionnaire_id,
-                        question.id
-                    )
-                    .fetch_all(&*self.db)
-                    .await?
-                    .into_iter()
-                    .map(|row| row.text_value)
-                    .collect();
+        if draft.questionnaire_id != questionnaire_id {
+            return Err(AppError::validation("草稿不属于该问卷"));
+        }
 
-                    question_stats.push(QuestionStatistics {
-                        question_id: question.id,
-                        title: question.title,
-                        question_type: question.question_type,
-                        text_responses: Some(text_responses),
-                        option_counts: None,
-                    });
-                }
-                "radio" | "checkbox" => {
-                    // 获取选项及其选择次数
-                    let options = sqlx::query!(
-                        r#"
-                        SELECT 
-                            qo.id as option_id, 
-                            qo.option_text,
-                            COUNT(opt_resp.id) as count
-                        FROM question_options qo
-                        LEFT JOIN option_responses opt_resp ON qo.id = opt_resp.option_id
-                        LEFT JOIN question_responses qr ON opt_resp.question_response_id = qr.id
-                        WHERE qo.question_id = ?
-                        GROUP BY qo.id, qo.option_text
-                        ORDER BY qo.display_order
-                        "#,
-                        question.id
-                    )
-                    .fetch_all(&*self.db)
-                    .await?;
-
-                    let total_responses = options
-                        .iter()
-                        .map(|opt| opt.count as i32)
-                        .sum::<i32>();
-
-                    let option_counts = options
-                        .into_iter()
-                        .map(|opt| {
-                            let count = opt.count as i32;
-                            let percentage = if total_responses > 0 {
-                                (count as f64 / total_responses as f64) * 100.0
-                            } else {
-                                0.0
-                            };
-
-                            OptionCount {
-                                option_id: opt.option_id,
-                                option_text: opt.option_text,
-                                count,
-                                percentage,
-                            }
-                        })
-                        .collect();
-
-                    question_stats.push(QuestionStatistics {
-                        question_id: question.id,
-                        title: question.title,
-                        question_type: question.question_type,
-                        text_responses: None,
-                        option_counts: Some(option_counts),
-                    });
-                }
-                _ => {} // 其他类型暂不处理
+        // 已关联用户的草稿不能被其他登录用户继续填写
+        if let (Some(owner), Some(uid)) = (draft.respondent_id, user_id) {
+            if owner != uid {
+                return Err(AppError::PermissionError("你无权继续填写此草稿".to_string()));
             }
         }
 
-        Ok(QuestionnaireStatistics {
-            questionnaire_id,
-            title: questionnaire.title,
-            response_count,
-            questions: question_stats,
+        Ok(Draft {
+            id: draft.id,
+            resume_token: resume_token.to_string(),
+            answers: parse_draft_answers(draft.draft_answers.as_deref())?,
         })
     }
 
+    // 保存通过验证的回答
+    async fn insert_answers(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_response_id: i32,
+        answers: &[ValidatedAnswer],
+    ) -> AppResult<()> {
+        // 处理每个问题的回答
+        for answer in answers {
+            // 创建问题回答记录
+            let question_response_id = sqlx::query!(
+                r#"
+                INSERT INTO question_responses (questionnaire_response_id, question_id)
+                VALUES (?, ?)
+                "#,
+                questionnaire_response_id,
+                answer.question_id
+            )
+            .execute(&mut **tx)
+            .await?
+            .last_insert_id() as i32;
+
+            // 保存文本回答
+            if let Some(text_value) = &answer.text_value {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO text_responses (question_response_id, text_value)
+                    VALUES (?, ?)
+                    "#,
+                    question_response_id,
+                    text_value
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+
+            // 保存数值回答
+            if let Some(numeric_value) = answer.numeric_value {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO numeric_responses (question_response_id, numeric_value)
+                    VALUES (?, ?)
+                    "#,
+                    question_response_id,
+                    numeric_value
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+
+            // 保存选项回答（包括矩阵题的行和排序题的名次）
+            for selection in &answer.selections {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO option_responses
+                    (question_response_id, option_id, row_option_id, rank_position)
+                    VALUES (?, ?, ?, ?)
+                    "#,
+                    question_response_id,
+                    selection.option_id,
+                    selection.row_option_id,
+                    selection.rank_position
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
     // 获取问卷的回答列表
     pub async fn get_questionnaire_responses(
         &self,
@@ -318,35 +516,25 @@ impl ResponseService {
         page_size: i64,
     ) -> AppResult<Vec<ResponseListItem>> {
         // 检查问卷是否存在且用户是否有权限查看
-        let questionnaire = sqlx::query!(
-            "SELECT creator_id FROM questionnaires WHERE id = ?",
-            questionnaire_id
-        )
-        .fetch_optional(&*self.db)
-        .await?
-        .ok_or_else(|| {
-            AppError::NotFoundError(format!("问卷ID {} 不存在", questionnaire_id))
-        })?;
-
-        // 检查权限
-        if questionnaire.creator_id != user_id {
-            return Err(AppError::PermissionError(
-                "你无权查看此问卷的回答".to_string(),
-            ));
-        }
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::View)
+            .await?;
 
-        // 获取问卷回答列表
+        // 获取问卷回答列表，匿名问卷不返回回答者
         let responses = sqlx::query!(
             r#"
             SELECT 
                 qr.id, 
                 qr.questionnaire_id,
                 qr.created_at as "created_at: chrono::DateTime<chrono::Utc>",
-                u.username as respondent
+                qr.submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
+                COALESCE(u.username, cr.email) as "respondent?"
             FROM questionnaire_responses qr
-            LEFT JOIN users u ON qr.respondent_id = u.id
-            WHERE qr.questionnaire_id = ?
-            ORDER BY qr.created_at DESC
+            JOIN questionnaires q ON qr.questionnaire_id = q.id
+            LEFT JOIN users u ON qr.respondent_id = u.id AND q.anonymous = FALSE
+            LEFT JOIN campaign_recipients cr ON cr.response_id = qr.id AND q.anonymous = FALSE
+            WHERE qr.questionnaire_id = ? AND qr.status = 'completed'
+            ORDER BY qr.submitted_at DESC
             LIMIT ? OFFSET ?
             "#,
             questionnaire_id,
@@ -361,6 +549,7 @@ impl ResponseService {
             questionnaire_id: row.questionnaire_id,
             respondent: row.respondent,
             created_at: row.created_at.expect("创建时间不应为空"),
+            submitted_at: row.submitted_at,
         })
         .collect();
 
@@ -373,83 +562,228 @@ impl ResponseService {
         user_id: i32,
         response_id: i32,
     ) -> AppResult<ResponseDetails> {
-        // 获取回答基本信息
+        let response = sqlx::query!(
+            r#"
+            SELECT questionnaire_id FROM questionnaire_responses
+            WHERE id = ? AND status = 'completed'
+            "#,
+            response_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("回答ID {} 不存在", response_id)))?;
+
+        // 检查权限
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, response.questionnaire_id, Access::View)
+            .await?;
+
+        let mut conn = self.db.acquire().await?;
+        Self::load_response_detail(&mut conn, response_id).await
+    }
+
+    // 读取回答详情，不检查权限；也用于在提交回答的事务中生成Webhook推送内容
+    pub(crate) async fn load_response_detail(
+        conn: &mut MySqlConnection,
+        response_id: i32,
+    ) -> AppResult<ResponseDetails> {
+        // 获取回答基本信息及其所回答的版本
         let response = sqlx::query!(
             r#"
             SELECT 
                 qr.id, 
                 qr.questionnaire_id,
+                qr.version_id,
                 qr.created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                qr.submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
                 q.title as questionnaire_title,
-                q.creator_id,
-                u.username as respondent
+                COALESCE(u.username, cr.email) as "respondent?",
+                v.version_number as "version_number?",
+                v.title as "version_title?",
+                v.snapshot as "snapshot?"
             FROM questionnaire_responses qr
             JOIN questionnaires q ON qr.questionnaire_id = q.id
-            LEFT JOIN users u ON qr.respondent_id = u.id
-            WHERE qr.id = ?
+            LEFT JOIN users u ON qr.respondent_id = u.id AND q.anonymous = FALSE
+            LEFT JOIN campaign_recipients cr ON cr.response_id = qr.id AND q.anonymous = FALSE
+            LEFT JOIN questionnaire_versions v ON qr.version_id = v.id
+            WHERE qr.id = ? AND qr.status = 'completed'
             "#,
             response_id
         )
-        .fetch_optional(&*self.db)
+        .fetch_optional(&mut *conn)
         .await?
         .ok_or_else(|| AppError::NotFoundError(format!("回答ID {} 不存在", response_id)))?;
 
-        // 检查权限
-        if response.creator_id != user_id {
-            return Err(AppError::PermissionError(
-                "你无权查看此回答".to_string(),
-            ));
-        }
+        // 有版本快照时按快照中的问题标题和选项文本展示回答
+        let snapshot = match &response.snapshot {
+            Some(snapshot) => VersionService::parse_snapshot(snapshot)?,
+            None => Vec::new(),
+        };
 
-        // 获取回答详情
-        let answers = sqlx::query!(
+        // 获取每个问题的回答
+        let rows = sqlx::query!(
             r#"
             SELECT 
+                qr.id as question_response_id,
                 q.id as question_id,
                 q.title as question_title,
                 q.question_type,
-                tr.text_value,
-                GROUP_CONCAT(qo.option_text SEPARATOR '||') as selected_options
+                tr.text_value as "text_value?",
+                nr.numeric_value as "numeric_value?"
             FROM question_responses qr
             JOIN questions q ON qr.question_id = q.id
             LEFT JOIN text_responses tr ON tr.question_response_id = qr.id
-            LEFT JOIN option_responses orsp ON orsp.question_response_id = qr.id
-            LEFT JOIN question_options qo ON orsp.option_id = qo.id
+            LEFT JOIN numeric_responses nr ON nr.question_response_id = qr.id
             WHERE qr.questionnaire_response_id = ?
-            GROUP BY q.id, q.title, q.question_type, tr.text_value
+            ORDER BY q.display_order
             "#,
             response_id
         )
-        .fetch_all(&*self.db)
-        .await?
-        .into_iter()
-        .map(|row| {
-            let selected_options = row
-                .selected_options
-                .map(|opts| {
-                    opts.split("||")
-                        .map(|s| s.to_string())
-                        .filter(|s| !s.is_empty())
-                        .collect()
-                });
-
-            AnswerDetail {
-                question_id: row.question_id,
-                question_title: row.question_title,
-                question_type: row.question_type,
-                text_value: row.text_value,
-                selected_options,
-            }
-        })
-        .collect();
+        .fetch_all(&mut *conn)
+        .await?;
+
+        // 获取选择的选项，排序题按名次、矩阵题按行排列
+        let selections = sqlx::query!(
+            r#"
+            SELECT
+                orsp.question_response_id,
+                orsp.option_id,
+                qo.option_text,
+                orsp.row_option_id,
+                r.option_text as "row_text?"
+            FROM option_responses orsp
+            JOIN question_responses qr ON orsp.question_response_id = qr.id
+            JOIN question_options qo ON orsp.option_id = qo.id
+            LEFT JOIN question_options r ON orsp.row_option_id = r.id
+            WHERE qr.questionnaire_response_id = ?
+            ORDER BY orsp.rank_position, r.display_order, qo.display_order
+            "#,
+            response_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut answers: Vec<AnswerDetail> = rows
+            .into_iter()
+            .map(|row| {
+                let versioned = snapshot.iter().find(|q| q.id == row.question_id);
+
+                // 有版本快照时优先使用快照中的选项文本
+                let option_text = |option_id: i32, text: &str| {
+                    versioned
+                        .and_then(|question| {
+                            question
+                                .option_items
+                                .iter()
+                                .chain(&question.rows)
+                                .find(|option| option.id == option_id)
+                        })
+                        .map(|option| option.text.clone())
+                        .unwrap_or_else(|| text.to_string())
+                };
+
+                let selected: Vec<_> = selections
+                    .iter()
+                    .filter(|s| s.question_response_id == row.question_response_id)
+                    .collect();
+
+                let mut selected_options = None;
+                let mut matrix_answers = None;
+
+                if is_matrix(&row.question_type) {
+                    let mut matrix: Vec<(i32, MatrixAnswerDetail)> = Vec::new();
+                    for s in &selected {
+                        let row_id = s.row_option_id.unwrap_or_default();
+                        let text = option_text(s.option_id, &s.option_text);
+                        match matrix.iter_mut().find(|(id, _)| *id == row_id) {
+                            Some((_, detail)) => detail.selected_options.push(text),
+                            None => matrix.push((
+                                row_id,
+                                MatrixAnswerDetail {
+                                    row: option_text(row_id, s.row_text.as_deref().unwrap_or_default()),
+                                    selected_options: vec![text],
+                                },
+                            )),
+                        }
+                    }
+                    matrix_answers = Some(matrix.into_iter().map(|(_, detail)| detail).collect());
+                } else if !selected.is_empty() {
+                    selected_options = Some(
+                        selected
+                            .iter()
+                            .map(|s| option_text(s.option_id, &s.option_text))
+                            .collect(),
+                    );
+                }
+
+                AnswerDetail {
+                    question_id: row.question_id,
+                    question_title: versioned
+                        .map(|question| question.title.clone())
+                        .unwrap_or(row.question_title),
+                    question_type: versioned
+                        .map(|question| question.question_type.clone())
+                        .unwrap_or(row.question_type),
+                    text_value: row.text_value,
+                    numeric_value: row.numeric_value,
+                    selected_options,
+                    matrix_answers,
+                }
+            })
+            .collect();
+
+        // 按版本中的问题顺序排列
+        if !snapshot.is_empty() {
+            answers.sort_by_key(|answer| {
+                snapshot
+                    .iter()
+                    .position(|q| q.id == answer.question_id)
+                    .unwrap_or(usize::MAX)
+            });
+        }
 
         Ok(ResponseDetails {
             id: response.id,
             questionnaire_id: response.questionnaire_id,
-            questionnaire_title: response.questionnaire_title,
+            questionnaire_title: response
+                .version_title
+                .unwrap_or(response.questionnaire_title),
+            version_id: response.version_id,
+            version_number: response.version_number,
             respondent: response.respondent,
             created_at: response.created_at.expect("创建时间不应为空"),
+            submitted_at: response.submitted_at,
             answers,
         })
     }
-} 
\ No newline at end of file
+}
+
+// 合并草稿中已保存的回答和新提交的回答，同一问题以新回答为准，已从问卷中移除的问题被丢弃
+fn merge_answers(
+    definition: &QuestionnaireDefinition,
+    saved: Vec<QuestionAnswer>,
+    answers: Vec<QuestionAnswer>,
+) -> Vec<QuestionAnswer> {
+    let mut merged: Vec<QuestionAnswer> = saved
+        .into_iter()
+        .filter(|answer| definition.contains(answer.question_id))
+        .filter(|answer| !answers.iter().any(|a| a.question_id == answer.question_id))
+        .collect();
+    merged.extend(answers);
+    merged
+}
+
+// 前端上报的开始时间，晚于当前时间的视为无效
+fn reported_start(
+    started_at: Option<chrono::DateTime<chrono::Utc>>,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+    started_at.filter(|started_at| *started_at <= chrono::Utc::now())
+}
+
+fn parse_draft_answers(draft_answers: Option<&str>) -> AppResult<Vec<QuestionAnswer>> {
+    draft_answers
+        .map(serde_json::from_str)
+        .transpose()
+        .map_err(|e| AppError::InternalServerError(format!("解析草稿失败: {}", e)))
+        .map(Option::unwrap_or_default)
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool, Transaction};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult};
+use crate::models::user::TokenResponse;
+use crate::utils::auth::{generate_jwt, generate_secret_token, hash_token};
+
+pub struct SessionService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl SessionService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 登录时创建会话，返回访问令牌和刷新令牌
+    pub async fn create_session(
+        &self,
+        user_id: i32,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> AppResult<TokenResponse> {
+        let refresh_token = generate_secret_token();
+        let expires_at = self.refresh_expires_at();
+        let user_agent = user_agent.map(|agent| agent.chars().take(255).collect::<String>());
+
+        let mut tx = self.db.begin().await?;
+
+        let session_id = sqlx::query!(
+            r#"
+            INSERT INTO user_sessions (user_id, user_agent, ip_address, expires_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+            user_id,
+            user_agent,
+            ip_address,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id() as i32;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (session_id, token_hash, expires_at)
+            VALUES (?, ?, ?)
+            "#,
+            session_id,
+            hash_token(&refresh_token),
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(TokenResponse {
+            token: generate_jwt(&self.config, user_id, session_id)?,
+            refresh_token,
+            expires_in: self.config.jwt.expiration,
+        })
+    }
+
+    // 用刷新令牌换取新的访问令牌和刷新令牌，旧的刷新令牌随即作废
+    pub async fn refresh(&self, refresh_token: &str) -> AppResult<TokenResponse> {
+        let mut tx = self.db.begin().await?;
+
+        let token = sqlx::query!(
+            r#"
+            SELECT
+                rt.id, rt.session_id, s.user_id,
+                rt.used_at IS NOT NULL as "used: bool",
+                rt.expires_at > CURRENT_TIMESTAMP as "active!: bool",
+                s.revoked_at IS NULL as "session_active: bool"
+            FROM refresh_tokens rt
+            JOIN user_sessions s ON rt.session_id = s.id
+            WHERE rt.token_hash = ?
+            FOR UPDATE
+            "#,
+            hash_token(refresh_token)
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::AuthError("无效的刷新令牌".to_string()))?;
+
+        // 已使用过的刷新令牌再次出现，说明令牌可能已被盗用，吊销整个会话
+        if token.used {
+            sqlx::query!(
+                r#"
+                UPDATE user_sessions SET revoked_at = CURRENT_TIMESTAMP
+                WHERE id = ? AND revoked_at IS NULL
+                "#,
+                token.session_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            return Err(AppError::AuthError(
+                "刷新令牌已被使用，该会话已失效，请重新登录".to_string(),
+            ));
+        }
+
+        if !token.active || !token.session_active {
+            return Err(AppError::AuthError("登录已失效，请重新登录".to_string()));
+        }
+
+        let new_refresh_token = generate_secret_token();
+        let expires_at = self.refresh_expires_at();
+
+        sqlx::query!(
+            "UPDATE refresh_tokens SET used_at = CURRENT_TIMESTAMP WHERE id = ?",
+            token.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (session_id, token_hash, expires_at)
+            VALUES (?, ?, ?)
+            "#,
+            token.session_id,
+            hash_token(&new_refresh_token),
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE user_sessions SET expires_at = ?, last_used_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#,
+            expires_at,
+            token.session_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(TokenResponse {
+            token: generate_jwt(&self.config, token.user_id, token.session_id)?,
+            refresh_token: new_refresh_token,
+            expires_in: self.config.jwt.expiration,
+        })
+    }
+
+    // 退出当前会话，该会话的访问令牌和刷新令牌立即失效
+    pub async fn revoke(&self, user_id: i32, session_id: i32) -> AppResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE user_sessions SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND user_id = ? AND revoked_at IS NULL
+            "#,
+            session_id,
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // 退出用户的所有会话，返回被吊销的会话数
+    pub async fn revoke_all(&self, user_id: i32) -> AppResult<u64> {
+        let mut tx = self.db.begin().await?;
+        let revoked = Self::revoke_user_sessions(&mut tx, user_id).await?;
+        tx.commit().await?;
+
+        Ok(revoked)
+    }
+
+    // 在事务中吊销用户的所有会话，修改密码等操作与之一同提交
+    pub(crate) async fn revoke_user_sessions(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i32,
+    ) -> AppResult<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_sessions SET revoked_at = CURRENT_TIMESTAMP
+            WHERE user_id = ? AND revoked_at IS NULL
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    fn refresh_expires_at(&self) -> chrono::DateTime<Utc> {
+        Utc::now() + Duration::seconds(self.config.jwt.refresh_expiration as i64)
+    }
+}
@@ -0,0 +1,515 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use serde_json::{Map, Value};
+use sqlx::{MySql, Pool};
+
+use crate::config::{Config, OidcConfig};
+use crate::models::error::{AppError, AppResult};
+use crate::models::user::{
+    SsoAuthorizeResponse, SsoCallbackRequest, SsoCallbackResponse, UserIdentity,
+};
+use crate::services::two_factor_service::TwoFactorService;
+use crate::services::user_service::UserService;
+use crate::utils::auth::{generate_secret_token, hash_password, hash_token};
+use crate::utils::oidc::OidcClient;
+
+// 自动创建的用户名长度与注册时的限制一致
+const MAX_USERNAME_LENGTH: usize = 20;
+const MAX_NICKNAME_LENGTH: usize = 50;
+
+pub struct SsoService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+// ID令牌中与账号相关的声明
+struct ExternalIdentity {
+    subject: String,
+    username: Option<String>,
+    nickname: Option<String>,
+    email: Option<String>,
+    email_verified: bool,
+}
+
+impl SsoService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 生成跳转到身份提供方的地址，link_user_id不为空时回调后关联到该账号
+    pub async fn authorize(&self, link_user_id: Option<i32>) -> AppResult<SsoAuthorizeResponse> {
+        let oidc = self.oidc_config()?;
+        let client = OidcClient::new(oidc);
+
+        let state = generate_secret_token();
+        let nonce = generate_secret_token();
+        let code_verifier = generate_secret_token();
+        let authorization_url = client
+            .authorization_url(&state, &nonce, &code_verifier)
+            .await?;
+
+        // 顺带清理已过期的登录状态
+        sqlx::query!("DELETE FROM oidc_login_states WHERE expires_at < CURRENT_TIMESTAMP")
+            .execute(&*self.db)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO oidc_login_states (state_hash, code_verifier, nonce, link_user_id, expires_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            hash_token(&state),
+            code_verifier,
+            nonce,
+            link_user_id,
+            Utc::now() + Duration::seconds(oidc.state_expiration as i64)
+        )
+        .execute(&*self.db)
+        .await?;
+
+        Ok(SsoAuthorizeResponse {
+            authorization_url,
+            expires_in: oidc.state_expiration,
+        })
+    }
+
+    // 处理身份提供方的回调：登录时返回令牌，关联时返回关联的身份
+    // current_user_id为发起回调请求的已登录用户，关联外部身份时必须与发起关联的用户一致
+    pub async fn callback(
+        &self,
+        req: SsoCallbackRequest,
+        current_user_id: Option<i32>,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> AppResult<SsoCallbackResponse> {
+        let oidc = self.oidc_config()?;
+        let client = OidcClient::new(oidc);
+
+        // 登录状态只能使用一次，先删除再向身份提供方换取令牌
+        let mut tx = self.db.begin().await?;
+
+        let login_state = sqlx::query!(
+            r#"
+            SELECT id, code_verifier, nonce, link_user_id,
+                expires_at > CURRENT_TIMESTAMP as "valid!: bool"
+            FROM oidc_login_states
+            WHERE state_hash = ?
+            FOR UPDATE
+            "#,
+            hash_token(&req.state)
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::AuthError("单点登录已过期，请重新登录".to_string()))?;
+
+        sqlx::query!("DELETE FROM oidc_login_states WHERE id = ?", login_state.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        if !login_state.valid {
+            return Err(AppError::AuthError("单点登录已过期，请重新登录".to_string()));
+        }
+
+        if login_state.link_user_id.is_some() && login_state.link_user_id != current_user_id {
+            return Err(AppError::PermissionError(
+                "请使用发起关联的账号完成关联".to_string(),
+            ));
+        }
+
+        let id_token = client
+            .exchange_code(&req.code, &login_state.code_verifier)
+            .await?;
+        let claims = client.validate_id_token(&id_token, &login_state.nonce).await?;
+        let identity = ExternalIdentity::from_claims(&claims, oidc)?;
+        let issuer = client.issuer();
+
+        if let Some(user_id) = login_state.link_user_id {
+            let identity = self.link(user_id, issuer, &identity).await?;
+            return Ok(SsoCallbackResponse::Linked(identity));
+        }
+
+        let user_id = match self.find_linked_user(issuer, &identity.subject).await? {
+            Some(user_id) => user_id,
+            None => self.link_or_provision(oidc, issuer, &identity).await?,
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE user_identities SET last_login_at = CURRENT_TIMESTAMP, email = ?
+            WHERE issuer = ? AND subject = ?
+            "#,
+            identity.email,
+            issuer,
+            identity.subject
+        )
+        .execute(&*self.db)
+        .await?;
+
+        let user_service = UserService::new(self.db.clone(), self.config.clone());
+
+        // 开启了两步验证的账号默认仍需输入验证码，避免身份提供方或邮箱被冒用时直接登录；
+        // 只有明确信任身份提供方的多因素认证时才跳过
+        let two_factor = TwoFactorService::new(self.db.clone(), self.config.clone());
+        if !oidc.skip_two_factor && two_factor.is_enabled(user_id).await? {
+            let challenge_token = user_service
+                .create_login_challenge(user_id, user_agent.as_deref(), ip_address.as_deref())
+                .await?;
+            return Ok(SsoCallbackResponse::TwoFactorRequired {
+                challenge_token,
+                expires_in: self.config.login.challenge_expiration,
+            });
+        }
+
+        let username = sqlx::query!("SELECT username FROM users WHERE id = ?", user_id)
+            .fetch_one(&*self.db)
+            .await?
+            .username;
+        user_service
+            .audit_login(
+                Some(user_id),
+                &username,
+                None,
+                user_agent.as_deref(),
+                ip_address.as_deref(),
+            )
+            .await?;
+
+        let auth = user_service
+            .start_session(user_id, user_agent, ip_address)
+            .await?;
+        Ok(SsoCallbackResponse::Authenticated(auth))
+    }
+
+    // 获取关联到账号的外部身份
+    pub async fn list_identities(&self, user_id: i32) -> AppResult<Vec<UserIdentity>> {
+        let identities = sqlx::query!(
+            r#"
+            SELECT id, issuer, subject, email,
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                   last_login_at as "last_login_at: chrono::DateTime<chrono::Utc>"
+            FROM user_identities
+            WHERE user_id = ?
+            ORDER BY id
+            "#,
+            user_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| UserIdentity {
+            id: row.id,
+            issuer: row.issuer,
+            subject: row.subject,
+            email: row.email,
+            created_at: row.created_at.expect("关联时间不应为空"),
+            last_login_at: row.last_login_at,
+        })
+        .collect();
+
+        Ok(identities)
+    }
+
+    // 取消关联外部身份
+    pub async fn unlink(&self, user_id: i32, identity_id: i32) -> AppResult<()> {
+        let result = sqlx::query!(
+            "DELETE FROM user_identities WHERE id = ? AND user_id = ?",
+            identity_id,
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFoundError(format!(
+                "外部身份ID {} 不存在",
+                identity_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    // 将外部身份关联到已登录的账号
+    async fn link(
+        &self,
+        user_id: i32,
+        issuer: &str,
+        identity: &ExternalIdentity,
+    ) -> AppResult<UserIdentity> {
+        match self.find_linked_user(issuer, &identity.subject).await? {
+            Some(linked_user_id) if linked_user_id == user_id => {}
+            Some(_) => {
+                return Err(AppError::validation("该外部身份已关联到其他账号"));
+            }
+            None => {
+                self.insert_identity(user_id, issuer, identity).await?;
+            }
+        }
+
+        self.list_identities(user_id)
+            .await?
+            .into_iter()
+            .find(|linked| linked.issuer == issuer && linked.subject == identity.subject)
+            .ok_or_else(|| AppError::InternalServerError("关联外部身份失败".to_string()))
+    }
+
+    // 首次登录的外部身份：按已验证的邮箱关联已有账号，或自动创建账号
+    async fn link_or_provision(
+        &self,
+        oidc: &OidcConfig,
+        issuer: &str,
+        identity: &ExternalIdentity,
+    ) -> AppResult<i32> {
+        let existing = match &identity.email {
+            Some(email) => sqlx::query!("SELECT id FROM users WHERE email = ?", email)
+                .fetch_optional(&*self.db)
+                .await?
+                .map(|user| user.id),
+            None => None,
+        };
+
+        if let Some(user_id) = existing {
+            if oidc.link_by_email && identity.email_verified {
+                self.insert_identity(user_id, issuer, identity).await?;
+                return Ok(user_id);
+            }
+            if !oidc.auto_provision {
+                return Err(AppError::PermissionError(
+                    "该邮箱已有账号，请使用密码登录后在个人设置中关联单点登录".to_string(),
+                ));
+            }
+        }
+
+        if !oidc.auto_provision {
+            return Err(AppError::PermissionError(
+                "没有与该身份关联的账号，请联系管理员开通".to_string(),
+            ));
+        }
+
+        // 邮箱已被其他账号使用时新账号不绑定邮箱
+        let email = identity.email.clone().filter(|_| existing.is_none());
+        let username = self
+            .available_username(identity.username.as_deref().unwrap_or(&identity.subject))
+            .await?;
+        let nickname = identity.nickname.clone().unwrap_or_else(|| username.clone());
+        let nickname: String = nickname.chars().take(MAX_NICKNAME_LENGTH).collect();
+
+        // 自动创建的账号使用随机密码，需要时可以通过邮件重置密码
+        let password_hash = hash_password(&generate_secret_token())?;
+
+        let mut tx = self.db.begin().await?;
+
+        let user_id = sqlx::query!(
+            r#"
+            INSERT INTO users (username, nickname, password_hash, email)
+            VALUES (?, ?, ?, ?)
+            "#,
+            username,
+            nickname,
+            password_hash,
+            email
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id() as i32;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_identities (user_id, issuer, subject, email)
+            VALUES (?, ?, ?, ?)
+            "#,
+            user_id,
+            issuer,
+            identity.subject,
+            identity.email
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user_id)
+    }
+
+    async fn insert_identity(
+        &self,
+        user_id: i32,
+        issuer: &str,
+        identity: &ExternalIdentity,
+    ) -> AppResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_identities (user_id, issuer, subject, email)
+            VALUES (?, ?, ?, ?)
+            "#,
+            user_id,
+            issuer,
+            identity.subject,
+            identity.email
+        )
+        .execute(&*self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_linked_user(&self, issuer: &str, subject: &str) -> AppResult<Option<i32>> {
+        let identity = sqlx::query!(
+            "SELECT user_id FROM user_identities WHERE issuer = ? AND subject = ?",
+            issuer,
+            subject
+        )
+        .fetch_optional(&*self.db)
+        .await?;
+
+        Ok(identity.map(|identity| identity.user_id))
+    }
+
+    // 用户名被占用时依次追加数字后缀
+    async fn available_username(&self, preferred: &str) -> AppResult<String> {
+        let base: String = preferred
+            .split('@')
+            .next()
+            .unwrap_or(preferred)
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .take(MAX_USERNAME_LENGTH)
+            .collect();
+        let base = if base.chars().count() < 3 {
+            format!("sso_{}", base)
+        } else {
+            base
+        };
+
+        for suffix in 0..100 {
+            let candidate = if suffix == 0 {
+                base.clone()
+            } else {
+                let suffix = suffix.to_string();
+                let prefix: String = base
+                    .chars()
+                    .take(MAX_USERNAME_LENGTH - suffix.len())
+                    .collect();
+                format!("{}{}", prefix, suffix)
+            };
+
+            let existing = sqlx::query!("SELECT id FROM users WHERE username = ?", candidate)
+                .fetch_optional(&*self.db)
+                .await?;
+            if existing.is_none() {
+                return Ok(candidate);
+            }
+        }
+
+        Err(AppError::InternalServerError(format!(
+            "无法为单点登录用户{}生成可用的用户名",
+            preferred
+        )))
+    }
+
+    fn oidc_config(&self) -> AppResult<&OidcConfig> {
+        self.config
+            .oidc
+            .as_ref()
+            .ok_or_else(|| AppError::NotFoundError("未启用单点登录".to_string()))
+    }
+}
+
+impl ExternalIdentity {
+    // 按配置的声明名称读取用户信息，声明名称可以用.访问嵌套的对象
+    fn from_claims(claims: &Map<String, Value>, oidc: &OidcConfig) -> AppResult<Self> {
+        let subject = claim_str(claims, "sub")
+            .ok_or_else(|| AppError::AuthError("ID令牌中缺少sub".to_string()))?;
+
+        Ok(Self {
+            subject,
+            username: claim_str(claims, &oidc.username_claim),
+            nickname: claim_str(claims, &oidc.nickname_claim),
+            email: claim_str(claims, &oidc.email_claim),
+            email_verified: claims
+                .get("email_verified")
+                .and_then(|value| {
+                    value
+                        .as_bool()
+                        .or_else(|| value.as_str().map(|value| value == "true"))
+                })
+                .unwrap_or(false),
+        })
+    }
+}
+
+fn claim_str(claims: &Map<String, Value>, path: &str) -> Option<String> {
+    let mut parts = path.split('.');
+    let mut value = claims.get(parts.next()?)?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+
+    match value {
+        Value::String(value) => Some(value.trim().to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+    .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::MySqlPool;
+    use url::Url;
+
+    use super::*;
+    use crate::utils::oidc::mock_idp::MockIdp;
+
+    // 使用DATABASE_URL指向的数据库（需已执行database/init.sql，编译查询宏时同样需要）
+    async fn service(idp: &MockIdp) -> SsoService {
+        if env::var("JWT_SECRET").is_err() {
+            env::set_var("JWT_SECRET", "test-secret");
+        }
+        let mut config = Config::from_env().unwrap();
+        config.oidc = Some(idp.config());
+
+        let db = MySqlPool::connect(&config.database.url).await.unwrap();
+        SsoService::new(Arc::new(db), Arc::new(config))
+    }
+
+    #[tokio::test]
+    async fn login_state_can_only_be_used_once() {
+        let idp = MockIdp::start().await;
+        let subject = format!("mock-{}", generate_secret_token());
+        idp.set_claim("sub", subject.clone().into());
+        let service = service(&idp).await;
+
+        let authorization = service.authorize(None).await.unwrap();
+        let url = Url::parse(&authorization.authorization_url).unwrap();
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+                .unwrap()
+        };
+        // 模拟的身份提供方把授权码作为nonce写入ID令牌
+        let request = || SsoCallbackRequest {
+            code: param("nonce"),
+            state: param("state"),
+        };
+
+        let first = service.callback(request(), None, None, None).await;
+        let second = service.callback(request(), None, None, None).await;
+
+        // 删除自动创建的账号，外部身份和会话随之删除
+        if let Some(user_id) = service.find_linked_user(&idp.issuer, &subject).await.unwrap() {
+            sqlx::query!("DELETE FROM users WHERE id = ?", user_id)
+                .execute(&*service.db)
+                .await
+                .unwrap();
+        }
+
+        assert!(matches!(first, Ok(SsoCallbackResponse::Authenticated(_))));
+        assert!(matches!(second, Err(AppError::AuthError(_))));
+    }
+}
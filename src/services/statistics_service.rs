@@ -0,0 +1,1655 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+};
+use chrono_tz::Tz;
+use sqlx::{MySql, Pool, Transaction};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult, FieldErrors};
+use crate::models::questionnaire::QuestionConfig;
+use crate::models::response::{
+    DateSummary, MatrixRowStatistics, NpsSummary, NumericSummary, OptionCount,
+    QuestionStatistics, QuestionnaireStatistics, RankingStatistics, TextAnswer, TextAnswerPage,
+    ValueCount,
+};
+use crate::models::statistics::{
+    AnswerFilter, Category, ChiSquareTest, ContingencyTable, CrossTabAxis, CrossTabCell,
+    OptionTrend, ResponseTimeline, StatisticsQuery, StatisticsQueryResult, TimeInterval,
+    TimelineBucket, TimelineOption, TimelineQuery,
+};
+use crate::services::permission_service::{Access, PermissionService};
+use crate::services::text_analysis_service::{parse_tags, tag_count};
+
+pub struct StatisticsService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+struct QuestionInfo {
+    id: i32,
+    title: String,
+    question_type: String,
+    categories: Vec<Category>,
+}
+
+// 一份回答中某个问题的答案
+#[derive(Default)]
+struct Answer {
+    options: Vec<i32>,
+    value: Option<f64>,
+}
+
+impl StatisticsService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 获取问卷的统计信息，各题结果读取自统计汇总表
+    pub async fn get_questionnaire_statistics(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        refresh: bool,
+    ) -> AppResult<QuestionnaireStatistics> {
+        self.ensure_viewer(user_id, questionnaire_id).await?;
+
+        let questionnaire = sqlx::query!(
+            "SELECT title FROM questionnaires WHERE id = ?",
+            questionnaire_id
+        )
+        .fetch_one(&*self.db)
+        .await?;
+
+        // 尚未生成汇总或要求重新计算时，由已有回答重新生成
+        let summary = sqlx::query!(
+            "SELECT response_count FROM questionnaire_statistics WHERE questionnaire_id = ?",
+            questionnaire_id
+        )
+        .fetch_optional(&*self.db)
+        .await?;
+        let response_count = match summary {
+            Some(summary) if !refresh => summary.response_count,
+            _ => self.rebuild_aggregates(questionnaire_id).await?,
+        };
+
+        let draft_count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM questionnaire_responses
+            WHERE questionnaire_id = ? AND status = 'in_progress'
+            "#,
+            questionnaire_id
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count as i32;
+
+        // 获取问卷的所有问题，已停用但仍有历史回答的问题也保留在统计中
+        let questions = sqlx::query!(
+            r#"
+            SELECT q.id, q.title, q.question_type, q.config,
+                   q.retired_at as "retired_at: chrono::DateTime<chrono::Utc>"
+            FROM questions q
+            WHERE q.questionnaire_id = ?
+            AND (
+                q.retired_at IS NULL
+                OR EXISTS (SELECT 1 FROM question_responses qr WHERE qr.question_id = q.id)
+            )
+            ORDER BY q.retired_at IS NOT NULL, q.display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        let options = sqlx::query!(
+            r#"
+            SELECT qo.id, qo.question_id, qo.option_text, qo.role,
+                   qo.retired_at IS NOT NULL as "retired: bool"
+            FROM question_options qo
+            JOIN questions q ON qo.question_id = q.id
+            WHERE q.questionnaire_id = ?
+            ORDER BY qo.display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        let answered: HashMap<(i32, i32), (i32, Option<String>, Option<String>)> = sqlx::query!(
+            r#"
+            SELECT question_id, row_option_id, answer_count, earliest_text, latest_text
+            FROM question_statistics
+            WHERE questionnaire_id = ?
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                (row.question_id, row.row_option_id),
+                (row.answer_count, row.earliest_text, row.latest_text),
+            )
+        })
+        .collect();
+
+        let selected: HashMap<(i32, i32), (i32, i64, i32)> = sqlx::query!(
+            r#"
+            SELECT option_id, row_option_id, selection_count, rank_sum, first_place_count
+            FROM option_statistics
+            WHERE questionnaire_id = ?
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                (row.option_id, row.row_option_id),
+                (row.selection_count, row.rank_sum, row.first_place_count),
+            )
+        })
+        .collect();
+
+        let mut values: HashMap<i32, Vec<(f64, i32)>> = HashMap::new();
+        for row in sqlx::query!(
+            r#"
+            SELECT question_id, numeric_value, value_count
+            FROM value_statistics
+            WHERE questionnaire_id = ?
+            ORDER BY question_id, numeric_value
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        {
+            values
+                .entry(row.question_id)
+                .or_default()
+                .push((row.numeric_value, row.value_count));
+        }
+
+        let mut tags: HashMap<i32, Vec<(String, i32)>> = HashMap::new();
+        for row in sqlx::query!(
+            r#"
+            SELECT question_id, tag, COUNT(*) as count
+            FROM text_answer_tags
+            WHERE questionnaire_id = ?
+            GROUP BY question_id, tag
+            ORDER BY count DESC, tag
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        {
+            tags.entry(row.question_id)
+                .or_default()
+                .push((row.tag, row.count as i32));
+        }
+
+        let selection_count = |option_id: i32, row_id: i32| {
+            selected.get(&(option_id, row_id)).map_or(0, |(count, _, _)| *count)
+        };
+        // 已停用的选项和矩阵行只在有人选择过时保留
+        let used: HashSet<i32> = selected
+            .keys()
+            .flat_map(|(option_id, row_id)| [*option_id, *row_id])
+            .collect();
+        let used = &used;
+        let visible = |question_id: i32, role: &'static str| {
+            options.iter().filter(move |option| {
+                option.question_id == question_id
+                    && option.role == role
+                    && (!option.retired || used.contains(&option.id))
+            })
+        };
+
+        let mut question_stats = Vec::new();
+
+        for question in questions {
+            let config: QuestionConfig = question
+                .config
+                .as_deref()
+                .and_then(|config| serde_json::from_str(config).ok())
+                .unwrap_or_default();
+            let (answer_count, earliest, latest) = answered
+                .get(&(question.id, 0))
+                .cloned()
+                .unwrap_or((0, None, None));
+
+            let mut stats = QuestionStatistics {
+                question_id: question.id,
+                title: question.title,
+                question_type: question.question_type,
+                retired: question.retired_at.is_some(),
+                answer_count,
+                option_counts: None,
+                numeric_summary: None,
+                nps: None,
+                date_summary: None,
+                matrix_counts: None,
+                ranking: None,
+                tag_counts: None,
+            };
+
+            match stats.question_type.as_str() {
+                // 文本回答通过单独的接口分页获取，这里只汇总人工标签
+                "text" => {
+                    stats.tag_counts = Some(
+                        tags.remove(&question.id)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|(tag, count)| tag_count(tag, count, answer_count))
+                            .collect(),
+                    );
+                }
+                // 日期已规范化为可排序的格式
+                "date" => {
+                    stats.date_summary = Some(DateSummary {
+                        count: answer_count,
+                        earliest,
+                        latest,
+                    });
+                }
+                "radio" | "checkbox" => {
+                    stats.option_counts = Some(option_counts(
+                        visible(question.id, "option")
+                            .map(|option| {
+                                (option.id, option.option_text.clone(), selection_count(option.id, 0))
+                            })
+                            .collect(),
+                        answer_count,
+                    ));
+                }
+                "rating" | "nps" | "number" => {
+                    let distribution = values.remove(&question.id).unwrap_or_default();
+
+                    // 评分题和NPS题列出所有可选分值，数字题只列出出现过的值
+                    let scale = match stats.question_type.as_str() {
+                        "rating" => {
+                            let (min, _, step) = config.rating_scale();
+                            (0..=config.rating_steps()).map(|i| min + i as f64 * step).collect()
+                        }
+                        "nps" => (0..=10).map(f64::from).collect(),
+                        _ => Vec::new(),
+                    };
+
+                    if stats.question_type == "nps" {
+                        stats.nps = Some(nps_summary(&distribution));
+                    }
+                    stats.numeric_summary = Some(numeric_summary(distribution, scale));
+                }
+                "matrix_radio" | "matrix_checkbox" => {
+                    // 按行统计每一列的选择人数，以回答了该行的人数为基数
+                    let columns: Vec<_> = visible(question.id, "option").collect();
+                    stats.matrix_counts = Some(
+                        visible(question.id, "row")
+                            .map(|row| {
+                                let row_answers = answered
+                                    .get(&(question.id, row.id))
+                                    .map_or(0, |(count, _, _)| *count);
+                                MatrixRowStatistics {
+                                    row_id: row.id,
+                                    row_text: row.option_text.clone(),
+                                    answer_count: row_answers,
+                                    option_counts: option_counts(
+                                        columns
+                                            .iter()
+                                            .map(|column| {
+                                                (
+                                                    column.id,
+                                                    column.option_text.clone(),
+                                                    selection_count(column.id, row.id),
+                                                )
+                                            })
+                                            .collect(),
+                                        row_answers,
+                                    ),
+                                }
+                            })
+                            .collect(),
+                    );
+                }
+                "ranking" => {
+                    // 统计每个选项的平均名次和排第一的次数
+                    stats.ranking = Some(
+                        visible(question.id, "option")
+                            .map(|option| {
+                                let (count, rank_sum, first_place_count) =
+                                    selected.get(&(option.id, 0)).copied().unwrap_or((0, 0, 0));
+                                RankingStatistics {
+                                    option_id: option.id,
+                                    option_text: option.option_text.clone(),
+                                    count,
+                                    average_rank: if count > 0 {
+                                        rank_sum as f64 / count as f64
+                                    } else {
+                                        0.0
+                                    },
+                                    first_place_count,
+                                }
+                            })
+                            .collect(),
+                    );
+                }
+                _ => {}
+            }
+
+            question_stats.push(stats);
+        }
+
+        Ok(QuestionnaireStatistics {
+            questionnaire_id,
+            title: questionnaire.title,
+            response_count,
+            draft_count,
+            questions: question_stats,
+        })
+    }
+
+    // 分页获取文本、日期、邮箱、电话题的回答，最新提交的在前
+    pub async fn get_text_answers(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        question_id: i32,
+        page: i64,
+        page_size: i64,
+    ) -> AppResult<TextAnswerPage> {
+        self.ensure_viewer(user_id, questionnaire_id).await?;
+
+        let question = sqlx::query!(
+            "SELECT question_type FROM questions WHERE id = ? AND questionnaire_id = ?",
+            question_id,
+            questionnaire_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("问题ID {} 不存在", question_id)))?;
+
+        if !matches!(question.question_type.as_str(), "text" | "date" | "email" | "phone") {
+            return Err(AppError::validation("只有文本、日期、邮箱和电话题可以获取回答列表"));
+        }
+
+        let page = page.max(1);
+        let page_size = page_size.clamp(1, 100);
+
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM text_responses tr
+            JOIN question_responses qr ON tr.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            WHERE qr.question_id = ? AND r.status = 'completed'
+            "#,
+            question_id
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        let answers = sqlx::query!(
+            r#"
+            SELECT
+                tr.id as text_response_id,
+                r.id as response_id,
+                tr.text_value,
+                r.submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
+                (
+                    SELECT CAST(JSON_ARRAYAGG(t.tag) AS CHAR)
+                    FROM text_answer_tags t WHERE t.text_response_id = tr.id
+                ) as "tags?: String"
+            FROM text_responses tr
+            JOIN question_responses qr ON tr.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            WHERE qr.question_id = ? AND r.status = 'completed'
+            ORDER BY r.submitted_at DESC, r.id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            question_id,
+            page_size,
+            (page - 1) * page_size
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| TextAnswer {
+            text_response_id: row.text_response_id,
+            response_id: row.response_id,
+            text: row.text_value,
+            tags: parse_tags(row.tags),
+            submitted_at: row.submitted_at,
+        })
+        .collect();
+
+        Ok(TextAnswerPage {
+            question_id,
+            total,
+            page,
+            page_size,
+            answers,
+        })
+    }
+
+    // 由已有回答重新生成问卷的统计汇总，返回已提交的回答数
+    async fn rebuild_aggregates(&self, questionnaire_id: i32) -> AppResult<i32> {
+        let mut tx = self.db.begin().await?;
+
+        // 锁定问卷，避免与提交回答时的增量更新交错
+        sqlx::query!(
+            "SELECT id FROM questionnaires WHERE id = ? FOR UPDATE",
+            questionnaire_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM question_statistics WHERE questionnaire_id = ?",
+            questionnaire_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM option_statistics WHERE questionnaire_id = ?",
+            questionnaire_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM value_statistics WHERE questionnaire_id = ?",
+            questionnaire_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        Self::accumulate(&mut tx, questionnaire_id, 0, i32::MAX).await?;
+
+        let response_count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM questionnaire_responses
+            WHERE questionnaire_id = ? AND status = 'completed'
+            "#,
+            questionnaire_id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .count as i32;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO questionnaire_statistics (questionnaire_id, response_count)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+                response_count = VALUES(response_count),
+                rebuilt_at = CURRENT_TIMESTAMP
+            "#,
+            questionnaire_id,
+            response_count
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(response_count)
+    }
+
+    // 提交回答后累加到统计汇总，需在提交回答的事务中调用
+    pub(crate) async fn record_response(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        questionnaire_response_id: i32,
+    ) -> AppResult<()> {
+        let updated = sqlx::query!(
+            r#"
+            UPDATE questionnaire_statistics
+            SET response_count = response_count + 1
+            WHERE questionnaire_id = ?
+            "#,
+            questionnaire_id
+        )
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+
+        // 尚未生成汇总时无需更新，首次查看统计时会完整生成
+        if updated == 0 {
+            return Ok(());
+        }
+
+        Self::accumulate(
+            tx,
+            questionnaire_id,
+            questionnaire_response_id,
+            questionnaire_response_id,
+        )
+        .await
+    }
+
+    // 将ID在指定范围内的已提交回答累加到统计汇总：重新生成时为全部回答，提交时只有该回答
+    async fn accumulate(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        first_response_id: i32,
+        last_response_id: i32,
+    ) -> AppResult<()> {
+        // 各问题的作答人数，日期题同时记录最早和最晚的日期
+        sqlx::query!(
+            r#"
+            INSERT INTO question_statistics
+            (question_id, row_option_id, questionnaire_id, answer_count, earliest_text, latest_text)
+            SELECT
+                qr.question_id, 0, r.questionnaire_id, COUNT(*),
+                MIN(CASE WHEN q.question_type = 'date' THEN tr.text_value END),
+                MAX(CASE WHEN q.question_type = 'date' THEN tr.text_value END)
+            FROM question_responses qr
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            JOIN questions q ON qr.question_id = q.id
+            LEFT JOIN text_responses tr ON tr.question_response_id = qr.id
+            WHERE r.questionnaire_id = ? AND r.status = 'completed'
+            AND r.id BETWEEN ? AND ?
+            GROUP BY qr.question_id, r.questionnaire_id
+            ON DUPLICATE KEY UPDATE
+                answer_count = answer_count + VALUES(answer_count),
+                earliest_text = LEAST(
+                    COALESCE(earliest_text, VALUES(earliest_text)),
+                    COALESCE(VALUES(earliest_text), earliest_text)
+                ),
+                latest_text = GREATEST(
+                    COALESCE(latest_text, VALUES(latest_text)),
+                    COALESCE(VALUES(latest_text), latest_text)
+                )
+            "#,
+            questionnaire_id,
+            first_response_id,
+            last_response_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        // 矩阵题各行的作答人数
+        sqlx::query!(
+            r#"
+            INSERT INTO question_statistics
+            (question_id, row_option_id, questionnaire_id, answer_count)
+            SELECT qr.question_id, o.row_option_id, r.questionnaire_id, COUNT(DISTINCT qr.id)
+            FROM option_responses o
+            JOIN question_responses qr ON o.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            WHERE r.questionnaire_id = ? AND r.status = 'completed'
+            AND r.id BETWEEN ? AND ?
+            AND o.row_option_id IS NOT NULL
+            GROUP BY qr.question_id, o.row_option_id, r.questionnaire_id
+            ON DUPLICATE KEY UPDATE answer_count = answer_count + VALUES(answer_count)
+            "#,
+            questionnaire_id,
+            first_response_id,
+            last_response_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        // 各选项（矩阵题按行）的选择人数，排序题的名次之和与排第一的次数
+        sqlx::query!(
+            r#"
+            INSERT INTO option_statistics
+            (option_id, row_option_id, question_id, questionnaire_id,
+             selection_count, rank_sum, first_place_count)
+            SELECT
+                o.option_id, COALESCE(o.row_option_id, 0), qr.question_id, r.questionnaire_id,
+                COUNT(*),
+                COALESCE(SUM(o.rank_position), 0),
+                COALESCE(SUM(o.rank_position = 1), 0)
+            FROM option_responses o
+            JOIN question_responses qr ON o.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            WHERE r.questionnaire_id = ? AND r.status = 'completed'
+            AND r.id BETWEEN ? AND ?
+            GROUP BY o.option_id, COALESCE(o.row_option_id, 0), qr.question_id, r.questionnaire_id
+            ON DUPLICATE KEY UPDATE
+                selection_count = selection_count + VALUES(selection_count),
+                rank_sum = rank_sum + VALUES(rank_sum),
+                first_place_count = first_place_count + VALUES(first_place_count)
+            "#,
+            questionnaire_id,
+            first_response_id,
+            last_response_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        // 数值题各分值的回答人数
+        sqlx::query!(
+            r#"
+            INSERT INTO value_statistics (question_id, numeric_value, questionnaire_id, value_count)
+            SELECT qr.question_id, nr.numeric_value, r.questionnaire_id, COUNT(*)
+            FROM numeric_responses nr
+            JOIN question_responses qr ON nr.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            WHERE r.questionnaire_id = ? AND r.status = 'completed'
+            AND r.id BETWEEN ? AND ?
+            GROUP BY qr.question_id, nr.numeric_value, r.questionnaire_id
+            ON DUPLICATE KEY UPDATE value_count = value_count + VALUES(value_count)
+            "#,
+            questionnaire_id,
+            first_response_id,
+            last_response_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // 按回答筛选后统计，并按维度问题生成列联表
+    pub async fn query_statistics(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        query: StatisticsQuery,
+    ) -> AppResult<StatisticsQueryResult> {
+        self.ensure_viewer(user_id, questionnaire_id).await?;
+
+        let questions = self.load_questions(questionnaire_id).await?;
+        Self::validate_query(&query, &questions)?;
+
+        // 提交时间范围内的全部回答
+        let response_ids: Vec<i32> = sqlx::query!(
+            r#"
+            SELECT id FROM questionnaire_responses
+            WHERE questionnaire_id = ? AND status = 'completed'
+            AND (? IS NULL OR submitted_at >= ?)
+            AND (? IS NULL OR submitted_at < ?)
+            ORDER BY id
+            "#,
+            questionnaire_id,
+            query.submitted_from,
+            query.submitted_from,
+            query.submitted_to,
+            query.submitted_to
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
+
+        // 可用于筛选和分类的答案：选项和数值
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                qr.questionnaire_response_id as response_id,
+                qr.question_id,
+                o.option_id as "option_id?",
+                nr.numeric_value as "numeric_value?"
+            FROM question_responses qr
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            JOIN questions q ON qr.question_id = q.id
+            LEFT JOIN option_responses o
+                ON o.question_response_id = qr.id AND o.row_option_id IS NULL
+            LEFT JOIN numeric_responses nr ON nr.question_response_id = qr.id
+            WHERE r.questionnaire_id = ? AND r.status = 'completed'
+            AND q.question_type IN ('radio', 'checkbox', 'rating', 'nps', 'number')
+            AND (? IS NULL OR r.submitted_at >= ?)
+            AND (? IS NULL OR r.submitted_at < ?)
+            "#,
+            questionnaire_id,
+            query.submitted_from,
+            query.submitted_from,
+            query.submitted_to,
+            query.submitted_to
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        let mut answers: HashMap<i32, HashMap<i32, Answer>> = HashMap::new();
+        for row in rows {
+            let answer = answers
+                .entry(row.response_id)
+                .or_default()
+                .entry(row.question_id)
+                .or_default();
+            if let Some(option_id) = row.option_id {
+                answer.options.push(option_id);
+            }
+            if row.numeric_value.is_some() {
+                answer.value = row.numeric_value;
+            }
+        }
+
+        let empty = HashMap::new();
+        let matching: Vec<&HashMap<i32, Answer>> = response_ids
+            .iter()
+            .map(|id| answers.get(id).unwrap_or(&empty))
+            .filter(|response| {
+                query
+                    .filters
+                    .iter()
+                    .all(|filter| passes(filter, response.get(&filter.question_id)))
+            })
+            .collect();
+
+        let dimension = query
+            .dimension_question_id
+            .and_then(|id| questions.iter().find(|question| question.id == id));
+
+        let targets: Vec<&QuestionInfo> = if query.question_ids.is_empty() {
+            questions
+                .iter()
+                .filter(|question| !question.categories.is_empty())
+                .filter(|question| Some(question.id) != query.dimension_question_id)
+                .collect()
+        } else {
+            query
+                .question_ids
+                .iter()
+                .filter_map(|id| questions.iter().find(|question| question.id == *id))
+                .collect()
+        };
+
+        let tables = targets
+            .into_iter()
+            .map(|question| contingency_table(question, dimension, &matching))
+            .collect();
+
+        Ok(StatisticsQueryResult {
+            questionnaire_id,
+            response_count: matching.len() as i32,
+            dimension: dimension.map(|question| CrossTabAxis {
+                question_id: question.id,
+                title: question.title.clone(),
+                question_type: question.question_type.clone(),
+            }),
+            tables,
+        })
+    }
+
+    // 按小时、天或周统计回答数量的变化，以及选项选择比例和完成用时的趋势
+    pub async fn response_timeline(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        query: TimelineQuery,
+    ) -> AppResult<ResponseTimeline> {
+        self.ensure_viewer(user_id, questionnaire_id).await?;
+
+        let mut errors = FieldErrors::new();
+        let timezone: Tz = match query.timezone.parse() {
+            Ok(timezone) => timezone,
+            Err(_) => {
+                errors.insert(
+                    "timezone".to_string(),
+                    vec![format!("无效的时区: {}", query.timezone)],
+                );
+                Tz::UTC
+            }
+        };
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if from >= to {
+                errors.insert("from".to_string(), vec!["开始时间必须早于结束时间".to_string()]);
+            }
+        }
+
+        let option = match query.option_id {
+            Some(option_id) => {
+                let option = sqlx::query!(
+                    r#"
+                    SELECT qo.id, qo.question_id, qo.option_text, q.question_type
+                    FROM question_options qo
+                    JOIN questions q ON qo.question_id = q.id
+                    WHERE qo.id = ? AND q.questionnaire_id = ? AND qo.role = 'option'
+                    "#,
+                    option_id,
+                    questionnaire_id
+                )
+                .fetch_optional(&*self.db)
+                .await?;
+
+                match option {
+                    Some(option) if matches!(option.question_type.as_str(), "radio" | "checkbox") => {
+                        Some(TimelineOption {
+                            question_id: option.question_id,
+                            option_id: option.id,
+                            option_text: option.option_text,
+                        })
+                    }
+                    Some(_) => {
+                        errors.insert(
+                            "option_id".to_string(),
+                            vec!["只能跟踪单选题和多选题的选项".to_string()],
+                        );
+                        None
+                    }
+                    None => {
+                        errors.insert(
+                            "option_id".to_string(),
+                            vec![format!("选项ID {} 不属于该问卷", option_id)],
+                        );
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        if !errors.is_empty() {
+            return Err(AppError::validation_fields("统计条件不正确", errors));
+        }
+
+        // 草稿续答的回答按提交时间归入时间段，而不是草稿的创建时间
+        let responses: Vec<(i32, DateTime<Utc>, Option<f64>)> = sqlx::query!(
+            r#"
+            SELECT id,
+                   COALESCE(submitted_at, created_at) as "received_at: chrono::DateTime<chrono::Utc>",
+                   started_at as "started_at: chrono::DateTime<chrono::Utc>",
+                   submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaire_responses
+            WHERE questionnaire_id = ? AND status = 'completed'
+            AND (? IS NULL OR COALESCE(submitted_at, created_at) >= ?)
+            AND (? IS NULL OR COALESCE(submitted_at, created_at) < ?)
+            ORDER BY COALESCE(submitted_at, created_at), id
+            "#,
+            questionnaire_id,
+            query.from,
+            query.from,
+            query.to,
+            query.to
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .filter_map(|row| {
+            let duration = match (row.started_at, row.submitted_at) {
+                (Some(started_at), Some(submitted_at)) if submitted_at >= started_at => {
+                    Some((submitted_at - started_at).num_milliseconds() as f64 / 1000.0)
+                }
+                _ => None,
+            };
+            row.received_at.map(|received_at| (row.id, received_at, duration))
+        })
+        .collect();
+
+        // 回答了所跟踪问题的回答，以及是否选择了该选项
+        let selections: HashMap<i32, bool> = match &option {
+            Some(option) => sqlx::query!(
+                r#"
+                SELECT
+                    qr.questionnaire_response_id as response_id,
+                    EXISTS (
+                        SELECT 1 FROM option_responses o
+                        WHERE o.question_response_id = qr.id AND o.option_id = ?
+                    ) as "selected: bool"
+                FROM question_responses qr
+                JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+                WHERE qr.question_id = ? AND r.status = 'completed'
+                "#,
+                option.option_id,
+                option.question_id
+            )
+            .fetch_all(&*self.db)
+            .await?
+            .into_iter()
+            .map(|row| (row.response_id, row.selected))
+            .collect(),
+            None => HashMap::new(),
+        };
+
+        // 没有指定范围时从第一份回答统计到最后一份回答
+        let first = query.from.or(responses.first().map(|response| response.1));
+        let last = query
+            .to
+            .map(|to| to - Duration::nanoseconds(1))
+            .or(responses.last().map(|response| response.1));
+
+        let mut starts = Vec::new();
+        if let (Some(first), Some(last)) = (first, last) {
+            let estimated = (last - first).num_hours() / interval_hours(query.interval) + 2;
+            if estimated > MAX_BUCKETS {
+                return Err(AppError::validation_fields(
+                    "统计条件不正确",
+                    FieldErrors::from([(
+                        "interval".to_string(),
+                        vec!["时间范围内的时间段过多，请选择更大的粒度或缩小范围".to_string()],
+                    )]),
+                ));
+            }
+
+            let end = bucket_start(last, query.interval, timezone);
+            let mut start = bucket_start(first, query.interval, timezone);
+            while start <= end {
+                starts.push(start);
+                start = next_bucket(start, query.interval);
+            }
+        }
+
+        let index: HashMap<NaiveDateTime, usize> = starts
+            .iter()
+            .enumerate()
+            .map(|(i, start)| (*start, i))
+            .collect();
+        let mut counts = vec![0i32; starts.len()];
+        let mut durations = vec![Vec::new(); starts.len()];
+        let mut selected = vec![0i32; starts.len()];
+        let mut answered = vec![0i32; starts.len()];
+
+        for (response_id, received_at, duration) in &responses {
+            let Some(i) = index
+                .get(&bucket_start(*received_at, query.interval, timezone))
+                .copied()
+            else {
+                continue;
+            };
+
+            counts[i] += 1;
+            if let Some(duration) = duration {
+                durations[i].push(*duration);
+            }
+            if let Some(is_selected) = selections.get(response_id) {
+                answered[i] += 1;
+                if *is_selected {
+                    selected[i] += 1;
+                }
+            }
+        }
+
+        let mut buckets = Vec::new();
+        let (mut cumulative_count, mut cumulative_selected, mut cumulative_answered) = (0, 0, 0);
+        for (i, start) in starts.iter().enumerate() {
+            cumulative_count += counts[i];
+            cumulative_selected += selected[i];
+            cumulative_answered += answered[i];
+
+            // 夏令时跳过的整点不存在，也不会有回答
+            let Some(start) = localize(*start, query.interval, timezone) else {
+                continue;
+            };
+
+            buckets.push(TimelineBucket {
+                start,
+                count: counts[i],
+                cumulative_count,
+                median_completion_seconds: median(std::mem::take(&mut durations[i])),
+                option: option.as_ref().map(|_| OptionTrend {
+                    selected: selected[i],
+                    answered: answered[i],
+                    cumulative_selected,
+                    cumulative_answered,
+                    cumulative_percentage: if cumulative_answered > 0 {
+                        cumulative_selected as f64 / cumulative_answered as f64 * 100.0
+                    } else {
+                        0.0
+                    },
+                }),
+            });
+        }
+
+        let all_durations: Vec<f64> = responses.iter().filter_map(|response| response.2).collect();
+
+        Ok(ResponseTimeline {
+            questionnaire_id,
+            interval: query.interval,
+            timezone: timezone.name().to_string(),
+            total: responses.len() as i32,
+            timed_count: all_durations.len() as i32,
+            median_completion_seconds: median(all_durations),
+            option,
+            buckets,
+        })
+    }
+
+    // 检查问卷是否存在且用户是否有权限查看
+    async fn ensure_viewer(&self, user_id: i32, questionnaire_id: i32) -> AppResult<()> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::View)
+            .await?;
+
+        Ok(())
+    }
+
+    // 读取问卷的问题及其分类，已停用但仍有历史回答的问题和选项也保留
+    async fn load_questions(&self, questionnaire_id: i32) -> AppResult<Vec<QuestionInfo>> {
+        let questions = sqlx::query!(
+            r#"
+            SELECT q.id, q.title, q.question_type, q.config
+            FROM questions q
+            WHERE q.questionnaire_id = ?
+            AND (
+                q.retired_at IS NULL
+                OR EXISTS (SELECT 1 FROM question_responses qr WHERE qr.question_id = q.id)
+            )
+            ORDER BY q.retired_at IS NOT NULL, q.display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        let options = sqlx::query!(
+            r#"
+            SELECT qo.id, qo.question_id, qo.option_text
+            FROM question_options qo
+            JOIN questions q ON qo.question_id = q.id
+            WHERE q.questionnaire_id = ? AND qo.role = 'option'
+            AND (
+                qo.retired_at IS NULL
+                OR EXISTS (SELECT 1 FROM option_responses o WHERE o.option_id = qo.id)
+            )
+            ORDER BY qo.display_order
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        Ok(questions
+            .into_iter()
+            .map(|question| {
+                let config: QuestionConfig = question
+                    .config
+                    .as_deref()
+                    .and_then(|config| serde_json::from_str(config).ok())
+                    .unwrap_or_default();
+
+                let categories = match question.question_type.as_str() {
+                    "radio" | "checkbox" => options
+                        .iter()
+                        .filter(|option| option.question_id == question.id)
+                        .map(|option| Category {
+                            option_id: Some(option.id),
+                            value: None,
+                            label: option.option_text.clone(),
+                        })
+                        .collect(),
+                    "rating" => {
+                        let (min, _, step) = config.rating_scale();
+                        (0..=config.rating_steps())
+                            .map(|i| value_category(min + i as f64 * step))
+                            .collect()
+                    }
+                    "nps" => (0..=10).map(|value| value_category(value as f64)).collect(),
+                    // 数字题只能用于筛选，其他题型不参与统计
+                    _ => Vec::new(),
+                };
+
+                QuestionInfo {
+                    id: question.id,
+                    title: question.title,
+                    question_type: question.question_type,
+                    categories,
+                }
+            })
+            .collect())
+    }
+
+    fn validate_query(query: &StatisticsQuery, questions: &[QuestionInfo]) -> AppResult<()> {
+        let mut errors = FieldErrors::new();
+        let find = |id: i32| questions.iter().find(|question| question.id == id);
+
+        for (index, filter) in query.filters.iter().enumerate() {
+            let mut messages = Vec::new();
+
+            match find(filter.question_id) {
+                None => messages.push(format!("问题ID {} 不属于该问卷", filter.question_id)),
+                Some(question) => {
+                    let choice = matches!(question.question_type.as_str(), "radio" | "checkbox");
+                    let numeric = matches!(question.question_type.as_str(), "rating" | "nps" | "number");
+
+                    if !choice && !numeric {
+                        messages.push("只能按单选、多选、评分、NPS和数字题筛选".to_string());
+                    }
+                    if !filter.option_ids.is_empty() {
+                        if !choice {
+                            messages.push("只有单选题和多选题可以按选项筛选".to_string());
+                        } else if filter.option_ids.iter().any(|id| {
+                            !question.categories.iter().any(|category| category.option_id == Some(*id))
+                        }) {
+                            messages.push("选项不属于该问题".to_string());
+                        }
+                    }
+                    if (filter.min.is_some() || filter.max.is_some()) && !numeric {
+                        messages.push("只有评分、NPS和数字题可以按数值范围筛选".to_string());
+                    }
+                    if filter.option_ids.is_empty() && filter.min.is_none() && filter.max.is_none() {
+                        messages.push("筛选条件需要option_ids或数值范围".to_string());
+                    }
+                }
+            }
+
+            if !messages.is_empty() {
+                errors.insert(format!("filters[{}]", index), messages);
+            }
+        }
+
+        if let Some(id) = query.dimension_question_id {
+            if !find(id).is_some_and(|question| !question.categories.is_empty()) {
+                errors.insert(
+                    "dimension_question_id".to_string(),
+                    vec!["维度只能是该问卷的单选、多选、评分或NPS题".to_string()],
+                );
+            }
+        }
+
+        for (index, id) in query.question_ids.iter().enumerate() {
+            if !find(*id).is_some_and(|question| !question.categories.is_empty()) {
+                errors.insert(
+                    format!("question_ids[{}]", index),
+                    vec!["只能统计该问卷的单选、多选、评分或NPS题".to_string()],
+                );
+            }
+        }
+
+        if let (Some(from), Some(to)) = (query.submitted_from, query.submitted_to) {
+            if from >= to {
+                errors.insert(
+                    "submitted_from".to_string(),
+                    vec!["开始时间必须早于结束时间".to_string()],
+                );
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(AppError::validation_fields("统计条件不正确", errors));
+        }
+
+        Ok(())
+    }
+}
+
+// 计算选项的选择比例，base为作答人数
+fn option_counts(counts: Vec<(i32, String, i32)>, base: i32) -> Vec<OptionCount> {
+    counts
+        .into_iter()
+        .map(|(option_id, option_text, count)| OptionCount {
+            option_id,
+            option_text,
+            count,
+            percentage: if base > 0 {
+                count as f64 / base as f64 * 100.0
+            } else {
+                0.0
+            },
+        })
+        .collect()
+}
+
+// 由各分值的人数计算汇总统计，scale为需要完整列出的分值（可为空）
+fn numeric_summary(distribution: Vec<(f64, i32)>, scale: Vec<f64>) -> NumericSummary {
+    let count: i32 = distribution.iter().map(|(_, n)| n).sum();
+    let total = count as f64;
+    let mean = if count > 0 {
+        distribution.iter().map(|(v, n)| v * *n as f64).sum::<f64>() / total
+    } else {
+        0.0
+    };
+    let std_dev = if count > 0 {
+        (distribution
+            .iter()
+            .map(|(v, n)| (v - mean).powi(2) * *n as f64)
+            .sum::<f64>()
+            / total)
+            .sqrt()
+    } else {
+        0.0
+    };
+
+    // 按位置取排序后的第index个值（从0开始）
+    let nth = |index: i32| {
+        let mut seen = 0;
+        distribution
+            .iter()
+            .find(|(_, n)| {
+                seen += n;
+                seen > index
+            })
+            .map_or(0.0, |(v, _)| *v)
+    };
+    let median = match count {
+        0 => 0.0,
+        n if n % 2 == 0 => (nth(n / 2 - 1) + nth(n / 2)) / 2.0,
+        n => nth(n / 2),
+    };
+
+    let mut distinct = scale;
+    for (value, _) in &distribution {
+        if !distinct.iter().any(|v| (v - value).abs() < 1e-9) {
+            distinct.push(*value);
+        }
+    }
+    distinct.sort_by(|a, b| a.total_cmp(b));
+
+    let values = distinct
+        .into_iter()
+        .map(|value| {
+            let value_count: i32 = distribution
+                .iter()
+                .filter(|(v, _)| (v - value).abs() < 1e-9)
+                .map(|(_, n)| n)
+                .sum();
+            ValueCount {
+                value,
+                count: value_count,
+                percentage: if count > 0 {
+                    value_count as f64 / total * 100.0
+                } else {
+                    0.0
+                },
+            }
+        })
+        .collect();
+
+    NumericSummary {
+        count,
+        mean,
+        median,
+        min: distribution.first().map_or(0.0, |(v, _)| *v),
+        max: distribution.last().map_or(0.0, |(v, _)| *v),
+        std_dev,
+        distribution: values,
+    }
+}
+
+// 计算净推荐值：9-10分为推荐者，7-8分为被动者，0-6分为贬损者
+fn nps_summary(distribution: &[(f64, i32)]) -> NpsSummary {
+    let count_where = |matches: fn(f64) -> bool| -> i32 {
+        distribution
+            .iter()
+            .filter(|(v, _)| matches(*v))
+            .map(|(_, n)| n)
+            .sum()
+    };
+    let total = count_where(|_| true);
+    let promoters = count_where(|v| v >= 9.0);
+    let detractors = count_where(|v| v <= 6.0);
+    let passives = total - promoters - detractors;
+    let score = if total == 0 {
+        0.0
+    } else {
+        (promoters - detractors) as f64 / total as f64 * 100.0
+    };
+
+    NpsSummary {
+        promoters,
+        passives,
+        detractors,
+        score,
+    }
+}
+
+// 时间序列最多包含的时间段数
+const MAX_BUCKETS: i64 = 5000;
+
+fn interval_hours(interval: TimeInterval) -> i64 {
+    match interval {
+        TimeInterval::Hour => 1,
+        TimeInterval::Day => 24,
+        TimeInterval::Week => 24 * 7,
+    }
+}
+
+// 所在时间段的本地起始时间
+fn bucket_start(time: DateTime<Utc>, interval: TimeInterval, timezone: Tz) -> NaiveDateTime {
+    let local = time.with_timezone(&timezone).naive_local();
+    match interval {
+        TimeInterval::Hour => local.date().and_time(NaiveTime::MIN) + Duration::hours(local.hour() as i64),
+        TimeInterval::Day => local.date().and_time(NaiveTime::MIN),
+        TimeInterval::Week => {
+            let monday = local.date() - Duration::days(local.weekday().num_days_from_monday() as i64);
+            monday.and_time(NaiveTime::MIN)
+        }
+    }
+}
+
+fn next_bucket(start: NaiveDateTime, interval: TimeInterval) -> NaiveDateTime {
+    start + Duration::hours(interval_hours(interval))
+}
+
+// 转换为带时区偏移的时间；零点因夏令时不存在时顺延到当天第一个存在的整点
+fn localize(
+    start: NaiveDateTime,
+    interval: TimeInterval,
+    timezone: Tz,
+) -> Option<DateTime<FixedOffset>> {
+    let shifts = if interval == TimeInterval::Hour { 0..1 } else { 0..3 };
+    shifts
+        .filter_map(|hours| {
+            timezone
+                .from_local_datetime(&(start + Duration::hours(hours)))
+                .earliest()
+        })
+        .next()
+        .map(|time| time.fixed_offset())
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    Some(if values.len() % 2 == 0 {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    })
+}
+
+fn value_category(value: f64) -> Category {
+    Category {
+        option_id: None,
+        value: Some(value),
+        label: value.to_string(),
+    }
+}
+
+fn passes(filter: &AnswerFilter, answer: Option<&Answer>) -> bool {
+    let Some(answer) = answer else {
+        return false;
+    };
+
+    if !filter.option_ids.is_empty()
+        && !filter.option_ids.iter().any(|id| answer.options.contains(id))
+    {
+        return false;
+    }
+
+    if filter.min.is_some() || filter.max.is_some() {
+        let Some(value) = answer.value else {
+            return false;
+        };
+        if filter.min.is_some_and(|min| value < min) || filter.max.is_some_and(|max| value > max) {
+            return false;
+        }
+    }
+
+    true
+}
+
+// 答案命中的分类，多选题可能命中多个
+fn matched_categories(categories: &[Category], answer: Option<&Answer>) -> Vec<usize> {
+    let Some(answer) = answer else {
+        return Vec::new();
+    };
+
+    categories
+        .iter()
+        .enumerate()
+        .filter(|(_, category)| match (category.option_id, category.value) {
+            (Some(option_id), _) => answer.options.contains(&option_id),
+            (None, Some(value)) => answer.value.is_some_and(|v| (v - value).abs() < 1e-9),
+            (None, None) => false,
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn is_single_choice(question_type: &str) -> bool {
+    matches!(question_type, "radio" | "rating" | "nps")
+}
+
+fn contingency_table(
+    question: &QuestionInfo,
+    dimension: Option<&QuestionInfo>,
+    responses: &[&HashMap<i32, Answer>],
+) -> ContingencyTable {
+    let rows = match dimension {
+        Some(dimension) => dimension.categories.clone(),
+        None => vec![Category {
+            option_id: None,
+            value: None,
+            label: "全部".to_string(),
+        }],
+    };
+    let columns = question.categories.clone();
+
+    let mut counts = vec![vec![0i32; columns.len()]; rows.len()];
+    let mut row_totals = vec![0i32; rows.len()];
+    let mut column_totals = vec![0i32; columns.len()];
+    let mut total = 0;
+
+    for response in responses {
+        let row_hits = match dimension {
+            Some(dimension) => matched_categories(&dimension.categories, response.get(&dimension.id)),
+            None => vec![0],
+        };
+        let column_hits = matched_categories(&columns, response.get(&question.id));
+        if row_hits.is_empty() || column_hits.is_empty() {
+            continue;
+        }
+
+        total += 1;
+        for row in &row_hits {
+            row_totals[*row] += 1;
+            for column in &column_hits {
+                counts[*row][*column] += 1;
+            }
+        }
+        for column in &column_hits {
+            column_totals[*column] += 1;
+        }
+    }
+
+    let percentage = |count: i32, base: i32| {
+        if base > 0 {
+            count as f64 / base as f64 * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    let cells = counts
+        .iter()
+        .enumerate()
+        .map(|(row, row_counts)| {
+            row_counts
+                .iter()
+                .enumerate()
+                .map(|(column, count)| CrossTabCell {
+                    count: *count,
+                    row_percentage: percentage(*count, row_totals[row]),
+                    column_percentage: percentage(*count, column_totals[column]),
+                    total_percentage: percentage(*count, total),
+                })
+                .collect()
+        })
+        .collect();
+
+    // 多选题中一人可计入多个单元格，不满足卡方检验的独立性前提
+    let chi_square = dimension
+        .filter(|dimension| {
+            is_single_choice(&dimension.question_type) && is_single_choice(&question.question_type)
+        })
+        .and_then(|_| chi_square_test(&counts));
+
+    ContingencyTable {
+        question_id: question.id,
+        title: question.title.clone(),
+        question_type: question.question_type.clone(),
+        rows,
+        columns,
+        cells,
+        row_totals,
+        column_totals,
+        total,
+        chi_square,
+    }
+}
+
+// 卡方独立性检验，忽略全为0的行和列
+fn chi_square_test(counts: &[Vec<i32>]) -> Option<ChiSquareTest> {
+    let row_sums: Vec<f64> = counts
+        .iter()
+        .map(|row| row.iter().sum::<i32>() as f64)
+        .collect();
+    let column_count = counts.first().map_or(0, Vec::len);
+    let column_sums: Vec<f64> = (0..column_count)
+        .map(|column| counts.iter().map(|row| row[column]).sum::<i32>() as f64)
+        .collect();
+    let n: f64 = row_sums.iter().sum();
+
+    let rows: Vec<usize> = (0..row_sums.len()).filter(|r| row_sums[*r] > 0.0).collect();
+    let columns: Vec<usize> = (0..column_count).filter(|c| column_sums[*c] > 0.0).collect();
+    if rows.len() < 2 || columns.len() < 2 {
+        return None;
+    }
+
+    let mut statistic = 0.0;
+    let mut low_expected = 0;
+    for row in &rows {
+        for column in &columns {
+            let expected = row_sums[*row] * column_sums[*column] / n;
+            let observed = counts[*row][*column] as f64;
+            statistic += (observed - expected).powi(2) / expected;
+            if expected < 5.0 {
+                low_expected += 1;
+            }
+        }
+    }
+
+    let degrees_of_freedom = (rows.len() - 1) * (columns.len() - 1);
+    let smaller = rows.len().min(columns.len()) as f64;
+
+    Some(ChiSquareTest {
+        statistic,
+        degrees_of_freedom: degrees_of_freedom as i32,
+        p_value: upper_incomplete_gamma(degrees_of_freedom as f64 / 2.0, statistic / 2.0),
+        cramers_v: (statistic / (n * (smaller - 1.0))).sqrt(),
+        low_expected_frequency: low_expected as f64 > (rows.len() * columns.len()) as f64 * 0.2,
+    })
+}
+
+// 正则化上不完全伽马函数Q(a, x)，卡方分布的p值为Q(df/2, χ²/2)
+fn upper_incomplete_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    let prefix = (-x + a * x.ln() - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        // 级数展开
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        for _ in 0..500 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-14 {
+                break;
+            }
+        }
+        (1.0 - sum * prefix).clamp(0.0, 1.0)
+    } else {
+        // 连分式展开（Lentz方法）
+        const TINY: f64 = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..500 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-14 {
+                break;
+            }
+        }
+        (prefix * h).clamp(0.0, 1.0)
+    }
+}
+
+// Lanczos近似计算ln Γ(x)，x > 0
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let mut sum = COEFFICIENTS[0];
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + i as f64);
+    }
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn ln_gamma_matches_known_values() {
+        assert_close(ln_gamma(1.0), 0.0);
+        assert_close(ln_gamma(5.0), 24f64.ln());
+        assert_close(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln());
+    }
+
+    #[test]
+    fn p_value_matches_chi_square_distribution() {
+        // 自由度1、2、10时0.05显著性水平的临界值
+        assert_close(upper_incomplete_gamma(0.5, 3.841_458_820_694_124 / 2.0), 0.05);
+        assert_close(upper_incomplete_gamma(1.0, 5.991_464_547_107_979 / 2.0), 0.05);
+        assert_close(upper_incomplete_gamma(5.0, 18.307_038_053_275_146 / 2.0), 0.05);
+        // 自由度2时Q(1, x/2) = e^(-x/2)
+        assert_close(upper_incomplete_gamma(1.0, 0.5), (-0.5f64).exp());
+        // x < a + 1时使用级数展开
+        assert_close(upper_incomplete_gamma(5.0, 3.940_299_136_119_060_5 / 2.0), 0.95);
+        assert_close(upper_incomplete_gamma(5.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn chi_square_test_on_two_by_two_table() {
+        let test = chi_square_test(&[vec![10, 20], vec![30, 40]]).unwrap();
+
+        assert_close(test.statistic, 0.793_650_793_650_793_7);
+        assert_eq!(test.degrees_of_freedom, 1);
+        assert_close(test.p_value, 0.372_998_483_613_487_1);
+        assert_close(test.cramers_v, 0.089_087_080_637_474_81);
+        assert!(!test.low_expected_frequency);
+    }
+
+    #[test]
+    fn chi_square_test_ignores_empty_rows_and_columns() {
+        let test = chi_square_test(&[vec![10, 0, 20], vec![0, 0, 0], vec![30, 0, 40]]).unwrap();
+
+        assert_close(test.statistic, 0.793_650_793_650_793_7);
+        assert_eq!(test.degrees_of_freedom, 1);
+        assert_close(test.p_value, 0.372_998_483_613_487_1);
+
+        // 去掉全为0的行和列后不足2×2时无法检验
+        assert!(chi_square_test(&[vec![10, 20], vec![0, 0]]).is_none());
+        assert!(chi_square_test(&[vec![10, 0], vec![5, 0], vec![7, 0]]).is_none());
+    }
+}
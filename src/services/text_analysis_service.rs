@@ -0,0 +1,359 @@
+use std::sync::Arc;
+use sqlx::{MySql, Pool};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult, FieldErrors};
+use crate::models::text_analysis::{
+    TagCount, TermFrequency, TextAnalysis, TextAnalysisQuery, TextAnswerTags, TextSearchMatch,
+    TextSearchQuery, TextSearchResult, UpdateTagsRequest,
+};
+use crate::services::permission_service::{Access, PermissionService};
+use crate::utils::text::{self, TermCounter};
+
+// 每个回答最多的标签数和标签长度
+const MAX_TAGS: usize = 20;
+const MAX_TAG_LENGTH: usize = 50;
+
+pub struct TextAnalysisService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl TextAnalysisService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 统计文本题回答的词频和短语频率，以及各标签的数量
+    pub async fn analyze(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        question_id: i32,
+        query: TextAnalysisQuery,
+    ) -> AppResult<TextAnalysis> {
+        self.ensure_viewer(user_id, questionnaire_id).await?;
+
+        let question = sqlx::query!(
+            "SELECT question_type FROM questions WHERE id = ? AND questionnaire_id = ?",
+            question_id,
+            questionnaire_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("问题ID {} 不存在", question_id)))?;
+
+        if question.question_type != "text" {
+            return Err(AppError::validation("只有文本题可以进行文本分析"));
+        }
+
+        let texts: Vec<String> = sqlx::query!(
+            r#"
+            SELECT tr.text_value
+            FROM text_responses tr
+            JOIN question_responses qr ON tr.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            WHERE qr.question_id = ? AND r.status = 'completed'
+            "#,
+            question_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| row.text_value)
+        .collect();
+        let answer_count = texts.len() as i32;
+
+        // 分词较耗时，放到阻塞线程中执行
+        let min_length = query.min_length.max(1);
+        let top = query.top.clamp(1, 500);
+        let (words, phrases) = tokio::task::spawn_blocking(move || {
+            let mut counter = TermCounter::new(min_length);
+            for text in &texts {
+                counter.add(text);
+            }
+            counter.top(top)
+        })
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("文本分析失败: {}", e)))?;
+
+        let to_frequencies = |terms: Vec<(String, text::TermCount)>| {
+            terms
+                .into_iter()
+                .map(|(term, count)| TermFrequency {
+                    term,
+                    count: count.count,
+                    answer_count: count.text_count,
+                })
+                .collect()
+        };
+
+        let tags = sqlx::query!(
+            r#"
+            SELECT tag, COUNT(*) as count
+            FROM text_answer_tags
+            WHERE questionnaire_id = ? AND question_id = ?
+            GROUP BY tag
+            ORDER BY count DESC, tag
+            "#,
+            questionnaire_id,
+            question_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| tag_count(row.tag, row.count as i32, answer_count))
+        .collect();
+
+        Ok(TextAnalysis {
+            question_id,
+            answer_count,
+            words: to_frequencies(words),
+            phrases: to_frequencies(phrases),
+            tags,
+        })
+    }
+
+    // 在问卷的文本题回答中全文搜索，也可以按标签筛选
+    pub async fn search(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        query: TextSearchQuery,
+    ) -> AppResult<TextSearchResult> {
+        self.ensure_viewer(user_id, questionnaire_id).await?;
+
+        let mut errors = FieldErrors::new();
+        let keywords = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+        let tag = query.tag.as_deref().map(str::trim).filter(|tag| !tag.is_empty());
+
+        // ngram索引按两个字切分，单个字无法检索
+        if keywords.is_some_and(|q| q.split_whitespace().any(|term| term.chars().count() < 2)) {
+            errors.insert("q".to_string(), vec!["每个搜索词至少需要2个字".to_string()]);
+        }
+        if keywords.is_none() && tag.is_none() {
+            errors.insert("q".to_string(), vec!["请输入搜索词或标签".to_string()]);
+        }
+        if !errors.is_empty() {
+            return Err(AppError::validation_fields("搜索条件不正确", errors));
+        }
+
+        let against = keywords.and_then(text::boolean_query);
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM text_responses tr
+            JOIN question_responses qr ON tr.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            JOIN questions q ON qr.question_id = q.id
+            WHERE r.questionnaire_id = ? AND r.status = 'completed' AND q.question_type = 'text'
+            AND (? IS NULL OR qr.question_id = ?)
+            AND (? IS NULL OR MATCH (tr.text_value) AGAINST (? IN BOOLEAN MODE))
+            AND (? IS NULL OR EXISTS (
+                SELECT 1 FROM text_answer_tags t WHERE t.text_response_id = tr.id AND t.tag = ?
+            ))
+            "#,
+            questionnaire_id,
+            query.question_id,
+            query.question_id,
+            against,
+            against,
+            tag,
+            tag
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        let matches = sqlx::query!(
+            r#"
+            SELECT
+                tr.id as text_response_id,
+                r.id as response_id,
+                qr.question_id,
+                q.title as question_title,
+                tr.text_value,
+                r.submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
+                (
+                    SELECT CAST(JSON_ARRAYAGG(t.tag) AS CHAR)
+                    FROM text_answer_tags t WHERE t.text_response_id = tr.id
+                ) as "tags?: String"
+            FROM text_responses tr
+            JOIN question_responses qr ON tr.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            JOIN questions q ON qr.question_id = q.id
+            WHERE r.questionnaire_id = ? AND r.status = 'completed' AND q.question_type = 'text'
+            AND (? IS NULL OR qr.question_id = ?)
+            AND (? IS NULL OR MATCH (tr.text_value) AGAINST (? IN BOOLEAN MODE))
+            AND (? IS NULL OR EXISTS (
+                SELECT 1 FROM text_answer_tags t WHERE t.text_response_id = tr.id AND t.tag = ?
+            ))
+            ORDER BY r.submitted_at DESC, tr.id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            questionnaire_id,
+            query.question_id,
+            query.question_id,
+            against,
+            against,
+            tag,
+            tag,
+            page_size,
+            (page - 1) * page_size
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| TextSearchMatch {
+            text_response_id: row.text_response_id,
+            response_id: row.response_id,
+            question_id: row.question_id,
+            question_title: row.question_title,
+            text: row.text_value,
+            tags: parse_tags(row.tags),
+            submitted_at: row.submitted_at,
+        })
+        .collect();
+
+        Ok(TextSearchResult {
+            total,
+            page,
+            page_size,
+            matches,
+        })
+    }
+
+    // 替换文本回答的全部标签
+    pub async fn update_tags(
+        &self,
+        user_id: i32,
+        text_response_id: i32,
+        req: UpdateTagsRequest,
+    ) -> AppResult<TextAnswerTags> {
+        let answer = sqlx::query!(
+            r#"
+            SELECT qr.question_id, r.questionnaire_id, q.question_type
+            FROM text_responses tr
+            JOIN question_responses qr ON tr.question_response_id = qr.id
+            JOIN questionnaire_responses r ON qr.questionnaire_response_id = r.id
+            JOIN questions q ON qr.question_id = q.id
+            WHERE tr.id = ?
+            "#,
+            text_response_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFoundError(format!("文本回答ID {} 不存在", text_response_id))
+        })?;
+
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, answer.questionnaire_id, Access::Edit)
+            .await?;
+
+        if answer.question_type != "text" {
+            return Err(AppError::validation("只有文本题的回答可以添加标签"));
+        }
+
+        let tags = normalize_tags(req.tags)?;
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM text_answer_tags WHERE text_response_id = ?",
+            text_response_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for tag in &tags {
+            sqlx::query!(
+                r#"
+                INSERT INTO text_answer_tags
+                (text_response_id, questionnaire_id, question_id, tag, created_by)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+                text_response_id,
+                answer.questionnaire_id,
+                answer.question_id,
+                tag,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(TextAnswerTags {
+            text_response_id,
+            tags,
+        })
+    }
+
+    // 检查问卷是否存在且用户是否有权限查看
+    async fn ensure_viewer(&self, user_id: i32, questionnaire_id: i32) -> AppResult<()> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::View)
+            .await?;
+
+        Ok(())
+    }
+}
+
+// 去掉首尾空白和重复的标签，并检查数量和长度
+fn normalize_tags(tags: Vec<String>) -> AppResult<Vec<String>> {
+    let mut errors = FieldErrors::new();
+    let mut normalized: Vec<String> = Vec::new();
+
+    for (index, tag) in tags.iter().enumerate() {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            errors.insert(format!("tags[{}]", index), vec!["标签不能为空".to_string()]);
+        } else if tag.chars().count() > MAX_TAG_LENGTH {
+            errors.insert(
+                format!("tags[{}]", index),
+                vec![format!("标签不能超过{}个字符", MAX_TAG_LENGTH)],
+            );
+        } else if !normalized.iter().any(|existing| existing == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+
+    if normalized.len() > MAX_TAGS {
+        errors.insert(
+            "tags".to_string(),
+            vec![format!("每个回答最多{}个标签", MAX_TAGS)],
+        );
+    }
+
+    if !errors.is_empty() {
+        return Err(AppError::validation_fields("标签不正确", errors));
+    }
+
+    Ok(normalized)
+}
+
+// 解析JSON_ARRAYAGG返回的标签数组
+pub(crate) fn parse_tags(tags: Option<String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .and_then(|tags| serde_json::from_str(&tags).ok())
+        .unwrap_or_default();
+    tags.sort();
+    tags
+}
+
+pub(crate) fn tag_count(tag: String, count: i32, answer_count: i32) -> TagCount {
+    TagCount {
+        tag,
+        count,
+        percentage: if answer_count > 0 {
+            count as f64 / answer_count as f64 * 100.0
+        } else {
+            0.0
+        },
+    }
+}
@@ -0,0 +1,271 @@
+use std::sync::Arc;
+use chrono::Utc;
+use sqlx::{MySql, Pool, Transaction};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult};
+use crate::models::user::{
+    DisableTwoFactorRequest, RecoveryCodesResponse, TwoFactorSetupRequest,
+    TwoFactorSetupResponse, TwoFactorStatus,
+};
+use crate::services::user_service::field_error;
+use crate::utils::auth::{hash_token, verify_password};
+use crate::utils::totp;
+
+pub struct TwoFactorService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl TwoFactorService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 获取两步验证的状态
+    pub async fn status(&self, user_id: i32) -> AppResult<TwoFactorStatus> {
+        let enabled_at = sqlx::query!(
+            r#"
+            SELECT enabled_at as "enabled_at: chrono::DateTime<chrono::Utc>"
+            FROM user_totp WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .and_then(|row| row.enabled_at);
+
+        let recovery_codes_remaining = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM totp_recovery_codes
+            WHERE user_id = ? AND used_at IS NULL
+            "#,
+            user_id
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        Ok(TwoFactorStatus {
+            enabled: enabled_at.is_some(),
+            enabled_at,
+            recovery_codes_remaining: if enabled_at.is_some() {
+                recovery_codes_remaining
+            } else {
+                0
+            },
+        })
+    }
+
+    // 生成新的密钥，验证第一个验证码后才会开启
+    pub async fn setup(
+        &self,
+        user_id: i32,
+        req: TwoFactorSetupRequest,
+    ) -> AppResult<TwoFactorSetupResponse> {
+        let user = sqlx::query!(
+            "SELECT username, password_hash FROM users WHERE id = ?",
+            user_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("用户ID {} 不存在", user_id)))?;
+
+        if !verify_password(&req.password, &user.password_hash)? {
+            return Err(field_error("password", "密码不正确"));
+        }
+
+        if self.is_enabled(user_id).await? {
+            return Err(AppError::validation("已开启两步验证，如需更换密钥请先关闭"));
+        }
+
+        let secret = totp::generate_secret();
+        sqlx::query!(
+            r#"
+            INSERT INTO user_totp (user_id, secret) VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+                secret = VALUES(secret),
+                last_used_step = NULL,
+                created_at = CURRENT_TIMESTAMP
+            "#,
+            user_id,
+            secret
+        )
+        .execute(&*self.db)
+        .await?;
+
+        Ok(TwoFactorSetupResponse {
+            otpauth_uri: totp::otpauth_uri(&self.config.login.totp_issuer, &user.username, &secret),
+            secret,
+        })
+    }
+
+    // 验证第一个验证码并开启两步验证，返回恢复码
+    pub async fn enable(&self, user_id: i32, code: &str) -> AppResult<RecoveryCodesResponse> {
+        let mut tx = self.db.begin().await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT secret, last_used_step, enabled_at IS NOT NULL as "enabled!: bool"
+            FROM user_totp WHERE user_id = ?
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::validation("请先生成两步验证密钥"))?;
+
+        if row.enabled {
+            return Err(AppError::validation("已开启两步验证"));
+        }
+
+        let step = totp::verify_code(&row.secret, code, Utc::now().timestamp(), row.last_used_step)
+            .ok_or_else(|| field_error("code", "验证码不正确"))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE user_totp SET enabled_at = CURRENT_TIMESTAMP, last_used_step = ?
+            WHERE user_id = ?
+            "#,
+            step,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let recovery_codes = Self::replace_recovery_codes(&mut tx, user_id).await?;
+
+        tx.commit().await?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    // 关闭两步验证，需要密码和验证码（或恢复码）
+    pub async fn disable(&self, user_id: i32, req: DisableTwoFactorRequest) -> AppResult<()> {
+        let user = sqlx::query!("SELECT password_hash FROM users WHERE id = ?", user_id)
+            .fetch_optional(&*self.db)
+            .await?
+            .ok_or_else(|| AppError::NotFoundError(format!("用户ID {} 不存在", user_id)))?;
+
+        if !verify_password(&req.password, &user.password_hash)? {
+            return Err(field_error("password", "密码不正确"));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        if !Self::verify(&mut tx, user_id, &req.code).await? {
+            return Err(field_error("code", "验证码不正确"));
+        }
+
+        sqlx::query!("DELETE FROM totp_recovery_codes WHERE user_id = ?", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM user_totp WHERE user_id = ?", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // 重新生成恢复码，原有的恢复码全部作废
+    pub async fn regenerate_recovery_codes(
+        &self,
+        user_id: i32,
+        code: &str,
+    ) -> AppResult<RecoveryCodesResponse> {
+        let mut tx = self.db.begin().await?;
+
+        if !Self::verify(&mut tx, user_id, code).await? {
+            return Err(field_error("code", "验证码不正确"));
+        }
+
+        let recovery_codes = Self::replace_recovery_codes(&mut tx, user_id).await?;
+
+        tx.commit().await?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    // 用户是否已开启两步验证
+    pub(crate) async fn is_enabled(&self, user_id: i32) -> AppResult<bool> {
+        let row = sqlx::query!(
+            "SELECT user_id FROM user_totp WHERE user_id = ? AND enabled_at IS NOT NULL",
+            user_id
+        )
+        .fetch_optional(&*self.db)
+        .await?;
+
+        Ok(row.is_some())
+    }
+
+    // 在事务中验证动态验证码或恢复码，通过后记录使用，避免同一验证码被重复使用
+    pub(crate) async fn verify(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i32,
+        code: &str,
+    ) -> AppResult<bool> {
+        let row = sqlx::query!(
+            r#"
+            SELECT secret, last_used_step FROM user_totp
+            WHERE user_id = ? AND enabled_at IS NOT NULL
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::validation("尚未开启两步验证"))?;
+
+        if let Some(step) =
+            totp::verify_code(&row.secret, code, Utc::now().timestamp(), row.last_used_step)
+        {
+            sqlx::query!(
+                "UPDATE user_totp SET last_used_step = ? WHERE user_id = ?",
+                step,
+                user_id
+            )
+            .execute(&mut **tx)
+            .await?;
+            return Ok(true);
+        }
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_recovery_codes SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = ? AND code_hash = ? AND used_at IS NULL
+            "#,
+            user_id,
+            hash_token(&totp::normalize_recovery_code(code))
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn replace_recovery_codes(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i32,
+    ) -> AppResult<Vec<String>> {
+        sqlx::query!("DELETE FROM totp_recovery_codes WHERE user_id = ?", user_id)
+            .execute(&mut **tx)
+            .await?;
+
+        let codes = totp::generate_recovery_codes();
+        for code in &codes {
+            sqlx::query!(
+                "INSERT INTO totp_recovery_codes (user_id, code_hash) VALUES (?, ?)",
+                user_id,
+                hash_token(&totp::normalize_recovery_code(code))
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(codes)
+    }
+}
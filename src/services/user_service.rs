@@ -1,13 +1,26 @@
 use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
 use sqlx::{MySql, Pool};
+use tracing::error;
 
-use crate::models::error::{AppError, AppResult};
+use crate::models::error::{AppError, AppResult, FieldErrors};
 use crate::models::user::{
-    AuthResponse, CreateUserRequest, LoginRequest, User, UserResponse,
+    AuthResponse, ChangePasswordRequest, CreateUserRequest, DeleteAccountRequest,
+    DeleteAccountResponse, ForgotPasswordRequest, LoginRecord, LoginRequest, LoginResponse,
+    OwnedQuestionnaireAction, ResetPasswordRequest, TokenResponse, TwoFactorLoginRequest,
+    UpdateProfileRequest, User, UserResponse,
 };
-use crate::utils::auth::{generate_jwt, hash_password, verify_password};
+use crate::services::questionnaire_service::QuestionnaireService;
+use crate::services::session_service::SessionService;
+use crate::services::two_factor_service::TwoFactorService;
+use crate::utils::auth::{generate_secret_token, hash_password, hash_token, verify_password};
+use crate::utils::lockout::{lockout_store, LoginBlock, LoginGuard};
+use crate::utils::mail::{mail_sender, MailMessage};
 use crate::config::Config;
 
+// 同一登录挑战允许输错验证码的次数
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
 pub struct UserService {
     db: Arc<Pool<MySql>>,
     config: Arc<Config>,
@@ -28,7 +41,11 @@ impl UserService {
         .await?;
 
         if existing_user.is_some() {
-            return Err(AppError::ValidationError("用户名已被使用".to_string()));
+            return Err(AppError::validation("用户名已被使用".to_string()));
+        }
+
+        if let Some(email) = &req.email {
+            self.ensure_email_available(email, None).await?;
         }
 
         // 哈希密码
@@ -55,53 +72,253 @@ impl UserService {
         Ok(UserResponse::from(user))
     }
 
-    pub async fn login(&self, req: LoginRequest) -> AppResult<AuthResponse> {
+    // 登录，开启了两步验证的账号在密码正确后返回登录挑战
+    pub async fn login(
+        &self,
+        req: LoginRequest,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> AppResult<LoginResponse> {
+        let guard = self.login_guard();
+        let now = Utc::now();
+
+        // 失败次数过多时先拒绝，不再验证密码
+        self.ensure_not_blocked(
+            &guard,
+            &req.username,
+            user_agent.as_deref(),
+            ip_address.as_deref(),
+            now,
+        )
+        .await?;
+
         // 查找用户
         let user = sqlx::query!(
-            r#"
-            SELECT id, username, nickname, password_hash, email, 
-                   created_at as "created_at: chrono::DateTime<chrono::Utc>", 
-                   updated_at as "updated_at: chrono::DateTime<chrono::Utc>"
-            FROM users
-            WHERE username = ?
-            "#,
+            "SELECT id, username, password_hash FROM users WHERE username = ?",
             req.username
         )
         .fetch_optional(&*self.db)
-        .await?
-        .ok_or_else(|| AppError::AuthError("用户名或密码不正确".to_string()))?;
+        .await?;
 
-        // 验证密码
-        let is_valid = verify_password(&req.password, &user.password_hash)?;
+        // 验证密码，用户不存在和密码错误同样计入失败次数
+        let is_valid = match &user {
+            Some(user) => verify_password(&req.password, &user.password_hash)?,
+            None => false,
+        };
         if !is_valid {
+            guard
+                .record_failure(&req.username, ip_address.as_deref(), now)
+                .await?;
+            self.audit_login(
+                user.as_ref().map(|user| user.id),
+                &req.username,
+                Some("invalid_credentials"),
+                user_agent.as_deref(),
+                ip_address.as_deref(),
+            )
+            .await?;
             return Err(AppError::AuthError("用户名或密码不正确".to_string()));
         }
+        let user = user.expect("密码验证通过时用户一定存在");
 
-        // 转换为用户模型
-        let user = User {
-            id: user.id,
-            username: user.username,
-            nickname: user.nickname,
-            password_hash: user.password_hash,
-            email: user.email,
-            created_at: user.created_at.expect("创建时间不应为空"),
-            updated_at: user.updated_at.expect("更新时间不应为空"),
-        };
+        // 开启两步验证时，验证码通过后才清除失败计数并创建会话
+        let two_factor = TwoFactorService::new(self.db.clone(), self.config.clone());
+        if two_factor.is_enabled(user.id).await? {
+            let challenge_token = self
+                .create_login_challenge(user.id, user_agent.as_deref(), ip_address.as_deref())
+                .await?;
+
+            return Ok(LoginResponse::TwoFactorRequired {
+                challenge_token,
+                expires_in: self.config.login.challenge_expiration,
+            });
+        }
+
+        guard.clear_account(&req.username).await?;
+        self.audit_login(
+            Some(user.id),
+            &user.username,
+            None,
+            user_agent.as_deref(),
+            ip_address.as_deref(),
+        )
+        .await?;
+
+        let auth = self.start_session(user.id, user_agent, ip_address).await?;
+        Ok(LoginResponse::Authenticated(auth))
+    }
+
+    // 使用登录挑战和验证码（或恢复码）完成两步验证登录
+    pub async fn complete_two_factor(
+        &self,
+        req: TwoFactorLoginRequest,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> AppResult<AuthResponse> {
+        let guard = self.login_guard();
+        let now = Utc::now();
+
+        let mut tx = self.db.begin().await?;
+
+        let challenge = sqlx::query!(
+            r#"
+            SELECT c.id, c.user_id, u.username,
+                c.used_at IS NULL AND c.expires_at > CURRENT_TIMESTAMP
+                    AND c.failed_attempts < ? as "valid!: bool"
+            FROM login_challenges c
+            JOIN users u ON c.user_id = u.id
+            WHERE c.token_hash = ?
+            FOR UPDATE
+            "#,
+            MAX_CHALLENGE_ATTEMPTS,
+            hash_token(&req.challenge_token)
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|challenge| challenge.valid)
+        .ok_or_else(|| AppError::AuthError("登录已过期，请重新输入用户名和密码".to_string()))?;
+
+        self.ensure_not_blocked(
+            &guard,
+            &challenge.username,
+            user_agent.as_deref(),
+            ip_address.as_deref(),
+            now,
+        )
+        .await?;
+
+        // 验证码错误同样计入登录失败次数，同一挑战错误次数过多后作废
+        if !TwoFactorService::verify(&mut tx, challenge.user_id, &req.code).await? {
+            sqlx::query!(
+                "UPDATE login_challenges SET failed_attempts = failed_attempts + 1 WHERE id = ?",
+                challenge.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            guard
+                .record_failure(&challenge.username, ip_address.as_deref(), now)
+                .await?;
+            self.audit_login(
+                Some(challenge.user_id),
+                &challenge.username,
+                Some("invalid_two_factor"),
+                user_agent.as_deref(),
+                ip_address.as_deref(),
+            )
+            .await?;
+            return Err(AppError::AuthError("验证码不正确".to_string()));
+        }
+
+        sqlx::query!(
+            "UPDATE login_challenges SET used_at = CURRENT_TIMESTAMP WHERE id = ?",
+            challenge.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        guard.clear_account(&challenge.username).await?;
+        self.audit_login(
+            Some(challenge.user_id),
+            &challenge.username,
+            None,
+            user_agent.as_deref(),
+            ip_address.as_deref(),
+        )
+        .await?;
+
+        self.start_session(challenge.user_id, user_agent, ip_address)
+            .await
+    }
 
-        // 生成JWT令牌
-        let token = generate_jwt(&self.config, user.id, None)?;
+    // 创建两步验证的登录挑战，返回挑战令牌，有效期为challenge_expiration
+    pub(crate) async fn create_login_challenge(
+        &self,
+        user_id: i32,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> AppResult<String> {
+        let challenge_token = generate_secret_token();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO login_challenges (user_id, token_hash, user_agent, ip_address, expires_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            user_id,
+            hash_token(&challenge_token),
+            user_agent.map(|agent| agent.chars().take(255).collect::<String>()),
+            ip_address,
+            Utc::now() + Duration::seconds(self.config.login.challenge_expiration as i64)
+        )
+        .execute(&*self.db)
+        .await?;
+
+        Ok(challenge_token)
+    }
+
+    // 创建登录会话，生成访问令牌和刷新令牌
+    pub(crate) async fn start_session(
+        &self,
+        user_id: i32,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> AppResult<AuthResponse> {
+        let user = self.get_user_by_id(user_id).await?;
+
+        let session_service = SessionService::new(self.db.clone(), self.config.clone());
+        let tokens = session_service
+            .create_session(user.id, user_agent, ip_address)
+            .await?;
 
         // 返回认证信息
         Ok(AuthResponse {
-            token,
+            token: tokens.token,
+            refresh_token: tokens.refresh_token,
+            expires_in: tokens.expires_in,
             user: UserResponse::from(user),
         })
     }
 
+    // 账号或IP因失败次数过多受限时记录本次尝试并拒绝
+    async fn ensure_not_blocked(
+        &self,
+        guard: &LoginGuard,
+        username: &str,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> AppResult<()> {
+        let Some(block) = guard.check(username, ip_address, now).await? else {
+            return Ok(());
+        };
+
+        let user_id = self.find_user_id(username).await?;
+        self.audit_login(user_id, username, Some(block.reason()), user_agent, ip_address)
+            .await?;
+
+        let message = match block {
+            LoginBlock::Locked { .. } => format!(
+                "登录失败次数过多，账号已被暂时锁定，请在{}后重试",
+                wait_label(block.retry_after())
+            ),
+            LoginBlock::Throttled { .. } => {
+                format!("登录尝试过于频繁，请在{}后重试", wait_label(block.retry_after()))
+            }
+        };
+        Err(AppError::TooManyRequests {
+            message,
+            retry_after: block.retry_after(),
+        })
+    }
+
     pub async fn get_user_by_id(&self, user_id: i32) -> AppResult<User> {
         let user = sqlx::query!(
             r#"
-            SELECT id, username, nickname, password_hash, email, 
+            SELECT id, username, nickname, password_hash, email, role,
                    created_at as "created_at: chrono::DateTime<chrono::Utc>", 
                    updated_at as "updated_at: chrono::DateTime<chrono::Utc>"
             FROM users
@@ -119,6 +336,7 @@ impl UserService {
             nickname: user.nickname,
             password_hash: user.password_hash,
             email: user.email,
+            role: user.role,
             created_at: user.created_at.expect("创建时间不应为空"),
             updated_at: user.updated_at.expect("更新时间不应为空"),
         })
@@ -128,4 +346,414 @@ impl UserService {
         let user = self.get_user_by_id(user_id).await?;
         Ok(UserResponse::from(user))
     }
-} 
\ No newline at end of file
+
+    // 修改昵称和邮箱
+    pub async fn update_profile(
+        &self,
+        user_id: i32,
+        req: UpdateProfileRequest,
+    ) -> AppResult<UserResponse> {
+        let user = self.get_user_by_id(user_id).await?;
+
+        if let Some(email) = &req.email {
+            self.ensure_email_available(email, Some(user_id)).await?;
+        }
+
+        sqlx::query!(
+            "UPDATE users SET nickname = ?, email = ? WHERE id = ?",
+            req.nickname.unwrap_or(user.nickname),
+            req.email.or(user.email),
+            user_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        self.get_user_response_by_id(user_id).await
+    }
+
+    // 修改密码，其他设备上的登录全部失效，当前设备获得新的令牌
+    pub async fn change_password(
+        &self,
+        user_id: i32,
+        req: ChangePasswordRequest,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> AppResult<TokenResponse> {
+        let user = self.get_user_by_id(user_id).await?;
+
+        if !verify_password(&req.current_password, &user.password_hash)? {
+            return Err(field_error("current_password", "当前密码不正确"));
+        }
+
+        let password_hash = hash_password(&req.new_password)?;
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            "UPDATE users SET password_hash = ? WHERE id = ?",
+            password_hash,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // 尚未使用的重置密码链接一并作废
+        sqlx::query!(
+            r#"
+            UPDATE password_reset_tokens SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = ? AND used_at IS NULL
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        SessionService::revoke_user_sessions(&mut tx, user_id).await?;
+
+        tx.commit().await?;
+
+        SessionService::new(self.db.clone(), self.config.clone())
+            .create_session(user_id, user_agent, ip_address)
+            .await
+    }
+
+    // 发送重置密码邮件；为避免泄露邮箱是否注册，邮箱不存在时同样视为成功
+    pub async fn request_password_reset(&self, req: ForgotPasswordRequest) -> AppResult<()> {
+        let user = sqlx::query!(
+            "SELECT id, nickname, email FROM users WHERE email = ?",
+            req.email
+        )
+        .fetch_optional(&*self.db)
+        .await?;
+
+        let Some(user) = user else {
+            return Ok(());
+        };
+
+        let token = generate_secret_token();
+        let expiration = self.config.account.password_reset_expiration;
+        let expires_at = Utc::now() + Duration::seconds(expiration as i64);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+            VALUES (?, ?, ?)
+            "#,
+            user.id,
+            hash_token(&token),
+            expires_at
+        )
+        .execute(&*self.db)
+        .await?;
+
+        let url = &self.config.account.password_reset_url;
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let message = MailMessage {
+            to: user.email.unwrap_or(req.email),
+            subject: "重置密码".to_string(),
+            body: format!(
+                "{}，你好：\n\n请打开以下链接重置密码，链接在{}分钟内有效且只能使用一次：\n{}{}token={}\n\n如果这不是你本人的操作，请忽略此邮件。",
+                user.nickname,
+                expiration / 60,
+                url,
+                separator,
+                token
+            ),
+        };
+
+        // 发送失败只记录日志，返回结果与邮箱未注册时一致
+        if let Err(e) = mail_sender(&self.config.mail).send(message).await {
+            error!("Failed to send password reset mail to user {}: {}", user.id, e);
+        }
+
+        Ok(())
+    }
+
+    // 使用邮件中的令牌设置新密码，所有登录随之失效
+    pub async fn reset_password(&self, req: ResetPasswordRequest) -> AppResult<()> {
+        let mut tx = self.db.begin().await?;
+
+        let token = sqlx::query!(
+            r#"
+            SELECT user_id,
+                used_at IS NULL AND expires_at > CURRENT_TIMESTAMP as "valid!: bool"
+            FROM password_reset_tokens
+            WHERE token_hash = ?
+            FOR UPDATE
+            "#,
+            hash_token(&req.token)
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|token| token.valid)
+        .ok_or_else(|| field_error("token", "重置链接无效或已过期"))?;
+
+        let password_hash = hash_password(&req.new_password)?;
+
+        sqlx::query!(
+            "UPDATE users SET password_hash = ? WHERE id = ?",
+            password_hash,
+            token.user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE password_reset_tokens SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = ? AND used_at IS NULL
+            "#,
+            token.user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        SessionService::revoke_user_sessions(&mut tx, token.user_id).await?;
+
+        let user = sqlx::query!("SELECT username FROM users WHERE id = ?", token.user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        // 通过邮箱重置密码后解除账号的登录锁定
+        self.login_guard().clear_account(&user.username).await?;
+
+        Ok(())
+    }
+
+    // 获取最近的登录记录，包括失败的尝试
+    pub async fn login_history(&self, user_id: i32, limit: i64) -> AppResult<Vec<LoginRecord>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT success as "success: bool", failure_reason, user_agent, ip_address,
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM login_attempts
+            WHERE user_id = ?
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?
+            "#,
+            user_id,
+            limit
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| LoginRecord {
+            success: row.success,
+            failure_reason: row.failure_reason,
+            user_agent: row.user_agent,
+            ip_address: row.ip_address,
+            created_at: row.created_at.expect("登录时间不应为空"),
+        })
+        .collect();
+
+        Ok(records)
+    }
+
+    // 记录一次登录尝试，failure_reason为空表示登录成功
+    pub(crate) async fn audit_login(
+        &self,
+        user_id: Option<i32>,
+        username: &str,
+        failure_reason: Option<&str>,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> AppResult<()> {
+        let username: String = username.chars().take(50).collect();
+        let user_agent = user_agent.map(|agent| agent.chars().take(255).collect::<String>());
+
+        sqlx::query!(
+            r#"
+            INSERT INTO login_attempts
+                (user_id, username, success, failure_reason, user_agent, ip_address)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            user_id,
+            username,
+            failure_reason.is_none(),
+            failure_reason,
+            user_agent,
+            ip_address
+        )
+        .execute(&*self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_user_id(&self, username: &str) -> AppResult<Option<i32>> {
+        let user = sqlx::query!("SELECT id FROM users WHERE username = ?", username)
+            .fetch_optional(&*self.db)
+            .await?;
+
+        Ok(user.map(|user| user.id))
+    }
+
+    fn login_guard(&self) -> LoginGuard {
+        LoginGuard::new(
+            lockout_store(&self.config.login, self.db.clone()),
+            self.config.login.clone(),
+        )
+    }
+
+    // 注销账号，名下的问卷按请求删除或转交给其他用户
+    pub async fn delete_account(
+        &self,
+        user_id: i32,
+        req: DeleteAccountRequest,
+    ) -> AppResult<DeleteAccountResponse> {
+        let user = self.get_user_by_id(user_id).await?;
+
+        if !verify_password(&req.password, &user.password_hash)? {
+            return Err(field_error("password", "密码不正确"));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        // 组织不能失去最后一个所有者
+        let sole_owned = sqlx::query!(
+            r#"
+            SELECT o.name FROM organization_members m
+            JOIN organizations o ON m.organization_id = o.id
+            WHERE m.user_id = ? AND m.role = 'owner'
+            AND NOT EXISTS (
+                SELECT 1 FROM organization_members other
+                WHERE other.organization_id = m.organization_id
+                AND other.role = 'owner' AND other.user_id <> m.user_id
+            )
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !sole_owned.is_empty() {
+            let names: Vec<String> = sole_owned.into_iter().map(|row| row.name).collect();
+            return Err(AppError::validation(format!(
+                "你是组织「{}」唯一的所有者，请先转让所有权或删除组织",
+                names.join("」「")
+            )));
+        }
+
+        // 组织中的问卷归组织所有，只需处理个人问卷
+        let questionnaire_ids: Vec<i32> = sqlx::query!(
+            "SELECT id FROM questionnaires WHERE creator_id = ? AND organization_id IS NULL FOR UPDATE",
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
+
+        let mut result = DeleteAccountResponse {
+            deleted_questionnaires: 0,
+            transferred_questionnaires: 0,
+        };
+
+        if !questionnaire_ids.is_empty() {
+            match req.questionnaires {
+                None => {
+                    return Err(field_error(
+                        "questionnaires",
+                        &format!(
+                            "名下还有{}份问卷，请选择删除（delete）或转交（transfer）",
+                            questionnaire_ids.len()
+                        ),
+                    ));
+                }
+                Some(OwnedQuestionnaireAction::Delete) => {
+                    for questionnaire_id in &questionnaire_ids {
+                        QuestionnaireService::delete_questionnaire_transaction(
+                            &mut tx,
+                            *questionnaire_id,
+                        )
+                        .await?;
+                    }
+                    result.deleted_questionnaires = questionnaire_ids.len() as u64;
+                }
+                Some(OwnedQuestionnaireAction::Transfer) => {
+                    let username = req
+                        .transfer_to
+                        .as_deref()
+                        .map(str::trim)
+                        .filter(|username| !username.is_empty())
+                        .ok_or_else(|| field_error("transfer_to", "请指定接收问卷的用户名"))?;
+
+                    let recipient = sqlx::query!(
+                        "SELECT id FROM users WHERE username = ?",
+                        username
+                    )
+                    .fetch_optional(&mut *tx)
+                    .await?
+                    .filter(|recipient| recipient.id != user_id)
+                    .ok_or_else(|| field_error("transfer_to", "接收问卷的用户不存在"))?;
+
+                    // 接收人成为所有者后不再需要协作者身份
+                    sqlx::query!(
+                        r#"
+                        DELETE c FROM questionnaire_collaborators c
+                        JOIN questionnaires q ON c.questionnaire_id = q.id
+                        WHERE q.creator_id = ? AND q.organization_id IS NULL AND c.user_id = ?
+                        "#,
+                        user_id,
+                        recipient.id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+
+                    result.transferred_questionnaires = sqlx::query!(
+                        r#"
+                        UPDATE questionnaires SET creator_id = ?
+                        WHERE creator_id = ? AND organization_id IS NULL
+                        "#,
+                        recipient.id,
+                        user_id
+                    )
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+                }
+            }
+        }
+
+        // 会话、令牌和组织成员身份随用户一同删除，回答保留为匿名回答，
+        // 组织问卷的创建人置空
+        sqlx::query!("DELETE FROM users WHERE id = ?", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result)
+    }
+
+    // 检查邮箱是否已被其他账号使用
+    async fn ensure_email_available(&self, email: &str, user_id: Option<i32>) -> AppResult<()> {
+        let existing = sqlx::query!("SELECT id FROM users WHERE email = ?", email)
+            .fetch_optional(&*self.db)
+            .await?;
+
+        if existing.is_some_and(|existing| Some(existing.id) != user_id) {
+            return Err(field_error("email", "邮箱已被其他账号使用"));
+        }
+
+        Ok(())
+    }
+}
+
+// 将等待时间格式化为便于阅读的文字
+fn wait_label(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{}秒", seconds.max(1))
+    } else {
+        format!("{}分钟", seconds.div_ceil(60))
+    }
+}
+
+pub(crate) fn field_error(field: &str, message: &str) -> AppError {
+    let mut errors = FieldErrors::new();
+    errors.insert(field.to_string(), vec![message.to_string()]);
+    AppError::validation_fields(message, errors)
+}
@@ -0,0 +1,550 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use sqlx::{MySql, Pool};
+
+use crate::config::Config;
+use crate::models::error::{AppError, AppResult};
+use crate::models::questionnaire::{
+    CreateQuestionnaireRequest, OptionItem, OptionRequest, QuestionRequest, QuestionResponse,
+    QuestionnaireResponse, SectionRequest, SectionResponse,
+};
+use crate::models::version::{
+    FieldChange, OptionRename, QuestionDiff, QuestionnaireVersion, VersionDetail, VersionDiff,
+    VersionListItem,
+};
+use crate::services::questionnaire_service::QuestionnaireService;
+use crate::services::permission_service::{Access, PermissionService};
+
+pub struct VersionService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl VersionService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 保存问卷当前的问题和选项快照，并将其设为当前版本
+    pub async fn create_version(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+    ) -> AppResult<VersionDetail> {
+        let questionnaire = QuestionnaireService::new(self.db.clone(), self.config.clone())
+            .get_questionnaire(questionnaire_id)
+            .await?;
+
+        let snapshot = serde_json::to_string(&questionnaire.questions)
+            .map_err(|e| AppError::InternalServerError(format!("生成版本快照失败: {}", e)))?;
+        let sections = if questionnaire.sections.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&questionnaire.sections).map_err(|e| {
+                AppError::InternalServerError(format!("生成版本快照失败: {}", e))
+            })?)
+        };
+
+        let mut tx = self.db.begin().await?;
+
+        // 锁定问卷行，保证并发发布时版本号连续
+        sqlx::query!(
+            "SELECT id FROM questionnaires WHERE id = ? FOR UPDATE",
+            questionnaire_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let version_number = sqlx::query!(
+            r#"
+            SELECT MAX(version_number) as "max_version?: i32"
+            FROM questionnaire_versions
+            WHERE questionnaire_id = ?
+            "#,
+            questionnaire_id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .max_version
+        .unwrap_or(0)
+            + 1;
+
+        let version_id = sqlx::query!(
+            r#"
+            INSERT INTO questionnaire_versions
+            (questionnaire_id, version_number, title, description, snapshot, sections, created_by)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            questionnaire_id,
+            version_number,
+            questionnaire.title,
+            questionnaire.description,
+            snapshot,
+            sections,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id() as i32;
+
+        sqlx::query!(
+            "UPDATE questionnaires SET current_version_id = ? WHERE id = ?",
+            version_id,
+            questionnaire_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get_version(user_id, questionnaire_id, version_number).await
+    }
+
+    // 获取问卷的版本列表
+    pub async fn list_versions(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+    ) -> AppResult<Vec<VersionListItem>> {
+        let current_version_id = self
+            .check_access(user_id, questionnaire_id, Access::View)
+            .await?;
+
+        let versions = sqlx::query!(
+            r#"
+            SELECT
+                v.id,
+                v.questionnaire_id,
+                v.version_number,
+                v.title,
+                u.username as created_by,
+                v.created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                (SELECT COUNT(*) FROM questionnaire_responses
+                 WHERE version_id = v.id AND status = 'completed') as response_count
+            FROM questionnaire_versions v
+            LEFT JOIN users u ON v.created_by = u.id
+            WHERE v.questionnaire_id = ?
+            ORDER BY v.version_number DESC
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| VersionListItem {
+            id: row.id,
+            questionnaire_id: row.questionnaire_id,
+            version_number: row.version_number,
+            title: row.title,
+            is_current: current_version_id == Some(row.id),
+            created_by: row.created_by,
+            created_at: row.created_at.expect("创建时间不应为空"),
+            response_count: row.response_count.unwrap_or(0) as i32,
+        })
+        .collect();
+
+        Ok(versions)
+    }
+
+    // 获取指定版本的问卷结构
+    pub async fn get_version(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        version_number: i32,
+    ) -> AppResult<VersionDetail> {
+        self.check_access(user_id, questionnaire_id, Access::View)
+            .await?;
+
+        let version = self.load_version(questionnaire_id, version_number).await?;
+        Self::into_detail(version)
+    }
+
+    // 比较两个版本之间的差异
+    pub async fn diff_versions(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        from: i32,
+        to: i32,
+    ) -> AppResult<VersionDiff> {
+        self.check_access(user_id, questionnaire_id, Access::View)
+            .await?;
+
+        let from = Self::into_detail(self.load_version(questionnaire_id, from).await?)?;
+        let to = Self::into_detail(self.load_version(questionnaire_id, to).await?)?;
+
+        Ok(Self::diff(&from, &to))
+    }
+
+    // 将问卷恢复为指定版本的结构，已有回答保持不变
+    pub async fn restore_version(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        version_number: i32,
+    ) -> AppResult<QuestionnaireResponse> {
+        let current_version_id = self
+            .check_access(user_id, questionnaire_id, Access::Edit)
+            .await?;
+
+        let is_public = sqlx::query!(
+            "SELECT is_public FROM questionnaires WHERE id = ?",
+            questionnaire_id
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .is_public
+        .map(|v| v != 0)
+        .unwrap_or(false);
+
+        let version = Self::into_detail(self.load_version(questionnaire_id, version_number).await?)?;
+
+        let req = CreateQuestionnaireRequest {
+            title: version.title,
+            description: version.description,
+            is_public,
+            sections: version
+                .sections
+                .iter()
+                .map(|section| SectionRequest {
+                    id: Some(section.id),
+                    title: section.title.clone(),
+                    description: section.description.clone(),
+                })
+                .collect(),
+            questions: version
+                .questions
+                .iter()
+                .map(|question| QuestionRequest {
+                    id: Some(question.id),
+                    title: question.title.clone(),
+                    question_type: question.question_type.clone(),
+                    required: question.required,
+                    options: to_option_requests(&question.option_items),
+                    rows: to_option_requests(&question.rows),
+                    config: question.config.clone(),
+                    logic: question.logic.clone(),
+                    section: question.section_id.and_then(|section_id| {
+                        version.sections.iter().position(|section| section.id == section_id)
+                    }),
+                })
+                .collect(),
+        };
+
+        let mut tx = self.db.begin().await?;
+
+        // 先重新启用快照中在之后的编辑里被停用的分页、问题和选项，再按快照原地更新
+        for section in &version.sections {
+            sqlx::query!(
+                r#"
+                UPDATE question_sections SET retired_at = NULL
+                WHERE id = ? AND questionnaire_id = ?
+                "#,
+                section.id,
+                questionnaire_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for question in &version.questions {
+            sqlx::query!(
+                r#"
+                UPDATE questions SET retired_at = NULL
+                WHERE id = ? AND questionnaire_id = ?
+                "#,
+                question.id,
+                questionnaire_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            for option in question.option_items.iter().chain(&question.rows) {
+                sqlx::query!(
+                    r#"
+                    UPDATE question_options SET retired_at = NULL
+                    WHERE id = ? AND question_id = ?
+                    "#,
+                    option.id,
+                    question.id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        QuestionnaireService::apply_definition(&mut tx, questionnaire_id, &req).await?;
+
+        tx.commit().await?;
+
+        // 已发布的问卷恢复后生成新版本
+        if current_version_id.is_some() {
+            self.create_version(user_id, questionnaire_id).await?;
+        }
+
+        QuestionnaireService::new(self.db.clone(), self.config.clone())
+            .get_questionnaire(questionnaire_id)
+            .await
+    }
+
+    // 检查用户对问卷的权限，返回问卷当前的版本ID
+    async fn check_access(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        access: Access,
+    ) -> AppResult<Option<i32>> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, access)
+            .await?;
+
+        let questionnaire = sqlx::query!(
+            "SELECT current_version_id FROM questionnaires WHERE id = ?",
+            questionnaire_id
+        )
+        .fetch_one(&*self.db)
+        .await?;
+
+        Ok(questionnaire.current_version_id)
+    }
+
+    async fn load_version(
+        &self,
+        questionnaire_id: i32,
+        version_number: i32,
+    ) -> AppResult<QuestionnaireVersion> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, questionnaire_id, version_number, title, description, snapshot, sections,
+                   created_by,
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM questionnaire_versions
+            WHERE questionnaire_id = ? AND version_number = ?
+            "#,
+            questionnaire_id,
+            version_number
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFoundError(format!(
+                "问卷ID {} 的版本 {} 不存在",
+                questionnaire_id, version_number
+            ))
+        })?;
+
+        Ok(QuestionnaireVersion {
+            id: row.id,
+            questionnaire_id: row.questionnaire_id,
+            version_number: row.version_number,
+            title: row.title,
+            description: row.description.unwrap_or_default(),
+            snapshot: row.snapshot,
+            sections: row.sections,
+            created_by: row.created_by,
+            created_at: row.created_at.expect("创建时间不应为空"),
+        })
+    }
+
+    // 解析版本快照中的问题列表
+    pub fn parse_snapshot(snapshot: &str) -> AppResult<Vec<QuestionResponse>> {
+        serde_json::from_str(snapshot)
+            .map_err(|e| AppError::InternalServerError(format!("解析版本快照失败: {}", e)))
+    }
+
+    fn into_detail(version: QuestionnaireVersion) -> AppResult<VersionDetail> {
+        let questions = Self::parse_snapshot(&version.snapshot)?;
+        let sections: Vec<SectionResponse> = version
+            .sections
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| AppError::InternalServerError(format!("解析版本快照失败: {}", e)))?
+            .unwrap_or_default();
+
+        Ok(VersionDetail {
+            id: version.id,
+            questionnaire_id: version.questionnaire_id,
+            version_number: version.version_number,
+            title: version.title,
+            description: version.description,
+            created_at: version.created_at,
+            sections,
+            questions,
+        })
+    }
+
+    // 按问题ID和选项ID比较两个版本的结构
+    fn diff(from: &VersionDetail, to: &VersionDetail) -> VersionDiff {
+        let mut changes = Vec::new();
+        push_change(&mut changes, "title", &from.title, &to.title);
+        push_change(&mut changes, "description", &from.description, &to.description);
+        push_change(
+            &mut changes,
+            "sections",
+            &join_section_titles(&from.sections),
+            &join_section_titles(&to.sections),
+        );
+
+        let from_questions: HashMap<i32, (usize, &QuestionResponse)> = from
+            .questions
+            .iter()
+            .enumerate()
+            .map(|(index, question)| (question.id, (index, question)))
+            .collect();
+        let to_ids: Vec<i32> = to.questions.iter().map(|question| question.id).collect();
+
+        let mut added_questions = Vec::new();
+        let mut modified_questions = Vec::new();
+
+        for (index, question) in to.questions.iter().enumerate() {
+            let Some((old_index, old)) = from_questions.get(&question.id) else {
+                added_questions.push(question.clone());
+                continue;
+            };
+
+            let mut question_changes = Vec::new();
+            push_change(&mut question_changes, "title", &old.title, &question.title);
+            push_change(&mut question_changes, "type", &old.question_type, &question.question_type);
+            push_change(
+                &mut question_changes,
+                "required",
+                &old.required.to_string(),
+                &question.required.to_string(),
+            );
+            push_change(
+                &mut question_changes,
+                "position",
+                &(old_index + 1).to_string(),
+                &(index + 1).to_string(),
+            );
+            push_change(
+                &mut question_changes,
+                "config",
+                &serde_json::to_string(&old.config).unwrap_or_default(),
+                &serde_json::to_string(&question.config).unwrap_or_default(),
+            );
+            push_change(
+                &mut question_changes,
+                "logic",
+                &serde_json::to_string(&old.logic).unwrap_or_default(),
+                &serde_json::to_string(&question.logic).unwrap_or_default(),
+            );
+            push_change(
+                &mut question_changes,
+                "section",
+                section_title(&from.sections, old.section_id),
+                section_title(&to.sections, question.section_id),
+            );
+            push_change(
+                &mut question_changes,
+                "rows",
+                &join_texts(&old.rows),
+                &join_texts(&question.rows),
+            );
+
+            let old_options: HashMap<i32, &str> = old
+                .option_items
+                .iter()
+                .map(|option| (option.id, option.text.as_str()))
+                .collect();
+            let new_option_ids: Vec<i32> = question.option_items.iter().map(|o| o.id).collect();
+
+            let mut added_options = Vec::new();
+            let mut renamed_options = Vec::new();
+            for option in &question.option_items {
+                match old_options.get(&option.id) {
+                    None => added_options.push(option.clone()),
+                    Some(old_text) if *old_text != option.text => renamed_options.push(OptionRename {
+                        option_id: option.id,
+                        from: old_text.to_string(),
+                        to: option.text.clone(),
+                    }),
+                    Some(_) => {}
+                }
+            }
+            let removed_options: Vec<_> = old
+                .option_items
+                .iter()
+                .filter(|option| !new_option_ids.contains(&option.id))
+                .cloned()
+                .collect();
+
+            if !question_changes.is_empty()
+                || !added_options.is_empty()
+                || !removed_options.is_empty()
+                || !renamed_options.is_empty()
+            {
+                modified_questions.push(QuestionDiff {
+                    question_id: question.id,
+                    title: question.title.clone(),
+                    changes: question_changes,
+                    added_options,
+                    removed_options,
+                    renamed_options,
+                });
+            }
+        }
+
+        let removed_questions = from
+            .questions
+            .iter()
+            .filter(|question| !to_ids.contains(&question.id))
+            .cloned()
+            .collect();
+
+        VersionDiff {
+            questionnaire_id: to.questionnaire_id,
+            from_version: from.version_number,
+            to_version: to.version_number,
+            changes,
+            added_questions,
+            removed_questions,
+            modified_questions,
+        }
+    }
+}
+
+fn to_option_requests(items: &[OptionItem]) -> Vec<OptionRequest> {
+    items
+        .iter()
+        .map(|option| OptionRequest::Detailed {
+            id: Some(option.id),
+            text: option.text.clone(),
+        })
+        .collect()
+}
+
+fn join_texts(items: &[OptionItem]) -> String {
+    items
+        .iter()
+        .map(|item| item.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn join_section_titles(sections: &[SectionResponse]) -> String {
+    sections
+        .iter()
+        .map(|section| section.title.as_str())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn section_title(sections: &[SectionResponse], section_id: Option<i32>) -> &str {
+    section_id
+        .and_then(|section_id| sections.iter().find(|section| section.id == section_id))
+        .map(|section| section.title.as_str())
+        .unwrap_or_default()
+}
+
+fn push_change(changes: &mut Vec<FieldChange>, field: &str, from: &str, to: &str) {
+    if from != to {
+        changes.push(FieldChange {
+            field: field.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+}
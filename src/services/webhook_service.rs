@@ -0,0 +1,605 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use sqlx::{MySql, Pool, Transaction};
+use uuid::Uuid;
+
+use crate::config::{Config, WebhookConfig};
+use crate::models::error::{AppError, AppResult};
+use crate::models::webhook::{
+    CreateWebhookRequest, CreatedWebhook, DeliveryAttempt, DeliveryPage, DeliveryQuery,
+    DeliveryStatus, UpdateWebhookRequest, Webhook, WebhookDelivery, WebhookDeliveryDetail,
+    WebhookEvent, WebhookPayload,
+};
+use crate::services::permission_service::{Access, PermissionService};
+use crate::services::user_service::field_error;
+use crate::utils::auth::generate_secret_token;
+use crate::utils::webhook::{resolve_target, DeliveryOutcome, WebhookClient};
+
+// 每份问卷最多的订阅数量
+const MAX_WEBHOOKS_PER_QUESTIONNAIRE: i64 = 10;
+
+pub struct WebhookService {
+    db: Arc<Pool<MySql>>,
+    config: Arc<Config>,
+}
+
+impl WebhookService {
+    pub fn new(db: Arc<Pool<MySql>>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+
+    // 获取问卷的Webhook订阅
+    pub async fn list(&self, user_id: i32, questionnaire_id: i32) -> AppResult<Vec<Webhook>> {
+        self.authorize(user_id, questionnaire_id).await?;
+
+        let webhooks = sqlx::query!(
+            r#"
+            SELECT id, questionnaire_id, url, events, active as "active: bool",
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                   updated_at as "updated_at: chrono::DateTime<chrono::Utc>"
+            FROM webhooks
+            WHERE questionnaire_id = ?
+            ORDER BY id
+            "#,
+            questionnaire_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| Webhook {
+            id: row.id,
+            questionnaire_id: row.questionnaire_id,
+            url: row.url,
+            events: WebhookEvent::parse_list(&row.events),
+            active: row.active,
+            created_at: row.created_at.expect("创建时间不应为空"),
+            updated_at: row.updated_at.expect("更新时间不应为空"),
+        })
+        .collect();
+
+        Ok(webhooks)
+    }
+
+    // 创建订阅，签名密钥只在创建时返回
+    pub async fn create(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        req: CreateWebhookRequest,
+    ) -> AppResult<CreatedWebhook> {
+        self.authorize(user_id, questionnaire_id).await?;
+
+        let url = self.validate_url(&req.url).await?;
+
+        let count = sqlx::query!(
+            "SELECT COUNT(*) as count FROM webhooks WHERE questionnaire_id = ?",
+            questionnaire_id
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        if count >= MAX_WEBHOOKS_PER_QUESTIONNAIRE {
+            return Err(AppError::validation(format!(
+                "每份问卷最多只能创建{}个Webhook",
+                MAX_WEBHOOKS_PER_QUESTIONNAIRE
+            )));
+        }
+
+        let secret = req.secret.unwrap_or_else(generate_secret_token);
+
+        let id = sqlx::query!(
+            r#"
+            INSERT INTO webhooks (questionnaire_id, url, secret, events, created_by)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            questionnaire_id,
+            url,
+            secret,
+            WebhookEvent::join(&req.events),
+            user_id
+        )
+        .execute(&*self.db)
+        .await?
+        .last_insert_id() as i32;
+
+        let webhook = self.find(questionnaire_id, id).await?;
+
+        Ok(CreatedWebhook { secret, webhook })
+    }
+
+    // 修改订阅地址、事件或启用状态
+    pub async fn update(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        webhook_id: i32,
+        req: UpdateWebhookRequest,
+    ) -> AppResult<Webhook> {
+        self.authorize(user_id, questionnaire_id).await?;
+        let webhook = self.find(questionnaire_id, webhook_id).await?;
+
+        let url = match &req.url {
+            Some(url) => self.validate_url(url).await?,
+            None => webhook.url,
+        };
+        let events = req.events.unwrap_or(webhook.events);
+
+        sqlx::query!(
+            "UPDATE webhooks SET url = ?, events = ?, active = ? WHERE id = ?",
+            url,
+            WebhookEvent::join(&events),
+            req.active.unwrap_or(webhook.active),
+            webhook_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        self.find(questionnaire_id, webhook_id).await
+    }
+
+    // 删除订阅，推送记录一并删除
+    pub async fn delete(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        webhook_id: i32,
+    ) -> AppResult<()> {
+        self.authorize(user_id, questionnaire_id).await?;
+        self.find(questionnaire_id, webhook_id).await?;
+
+        sqlx::query!("DELETE FROM webhooks WHERE id = ?", webhook_id)
+            .execute(&*self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    // 分页获取推送记录，最新的在前
+    pub async fn list_deliveries(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        webhook_id: i32,
+        query: DeliveryQuery,
+    ) -> AppResult<DeliveryPage> {
+        self.authorize(user_id, questionnaire_id).await?;
+        self.find(questionnaire_id, webhook_id).await?;
+
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+        let status = query.status.map(|status| status.as_str());
+
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM webhook_deliveries
+            WHERE webhook_id = ? AND (? IS NULL OR status = ?)
+            "#,
+            webhook_id,
+            status,
+            status
+        )
+        .fetch_one(&*self.db)
+        .await?
+        .count;
+
+        let deliveries = sqlx::query!(
+            r#"
+            SELECT id, delivery_uuid, event, status, attempts, last_status_code, last_error,
+                   next_attempt_at as "next_attempt_at: chrono::DateTime<chrono::Utc>",
+                   delivered_at as "delivered_at: chrono::DateTime<chrono::Utc>",
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM webhook_deliveries
+            WHERE webhook_id = ? AND (? IS NULL OR status = ?)
+            ORDER BY id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            webhook_id,
+            status,
+            status,
+            page_size,
+            (page - 1) * page_size
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|row| WebhookDelivery {
+            id: row.id,
+            delivery_uuid: row.delivery_uuid,
+            event: row.event,
+            status: DeliveryStatus::parse(&row.status).unwrap_or(DeliveryStatus::Pending),
+            attempts: row.attempts,
+            next_attempt_at: row.next_attempt_at,
+            last_status_code: row.last_status_code,
+            last_error: row.last_error,
+            delivered_at: row.delivered_at,
+            created_at: row.created_at.expect("创建时间不应为空"),
+        })
+        .collect();
+
+        Ok(DeliveryPage {
+            total,
+            page,
+            page_size,
+            deliveries,
+        })
+    }
+
+    // 获取推送详情，包括推送内容和每次投递的结果
+    pub async fn get_delivery(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        webhook_id: i32,
+        delivery_id: i32,
+    ) -> AppResult<WebhookDeliveryDetail> {
+        self.authorize(user_id, questionnaire_id).await?;
+        self.find(questionnaire_id, webhook_id).await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT id, delivery_uuid, event, payload, status, attempts, last_status_code, last_error,
+                   next_attempt_at as "next_attempt_at: chrono::DateTime<chrono::Utc>",
+                   delivered_at as "delivered_at: chrono::DateTime<chrono::Utc>",
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM webhook_deliveries
+            WHERE id = ? AND webhook_id = ?
+            "#,
+            delivery_id,
+            webhook_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("推送ID {} 不存在", delivery_id)))?;
+
+        let attempt_log = sqlx::query!(
+            r#"
+            SELECT status_code, error, response_body, duration_ms,
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>"
+            FROM webhook_delivery_attempts
+            WHERE delivery_id = ?
+            ORDER BY id
+            "#,
+            delivery_id
+        )
+        .fetch_all(&*self.db)
+        .await?
+        .into_iter()
+        .map(|attempt| DeliveryAttempt {
+            status_code: attempt.status_code,
+            error: attempt.error,
+            response_body: attempt.response_body,
+            duration_ms: attempt.duration_ms,
+            created_at: attempt.created_at.expect("投递时间不应为空"),
+        })
+        .collect();
+
+        let payload = serde_json::from_str(&row.payload)
+            .map_err(|e| AppError::InternalServerError(format!("解析推送内容失败: {}", e)))?;
+
+        Ok(WebhookDeliveryDetail {
+            delivery: WebhookDelivery {
+                id: row.id,
+                delivery_uuid: row.delivery_uuid,
+                event: row.event,
+                status: DeliveryStatus::parse(&row.status).unwrap_or(DeliveryStatus::Pending),
+                attempts: row.attempts,
+                next_attempt_at: row.next_attempt_at,
+                last_status_code: row.last_status_code,
+                last_error: row.last_error,
+                delivered_at: row.delivered_at,
+                created_at: row.created_at.expect("创建时间不应为空"),
+            },
+            payload,
+            attempt_log,
+        })
+    }
+
+    // 重新投递：以相同的推送ID和内容重新排队，投递次数重新计算
+    pub async fn redeliver(
+        &self,
+        user_id: i32,
+        questionnaire_id: i32,
+        webhook_id: i32,
+        delivery_id: i32,
+    ) -> AppResult<WebhookDeliveryDetail> {
+        self.authorize(user_id, questionnaire_id).await?;
+        let webhook = self.find(questionnaire_id, webhook_id).await?;
+
+        if !webhook.active {
+            return Err(AppError::validation("Webhook已停用，请先启用后再重新投递"));
+        }
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'pending', attempts = 0, next_attempt_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND webhook_id = ? AND status <> 'pending'
+            "#,
+            delivery_id,
+            webhook_id
+        )
+        .execute(&*self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            // 区分推送不存在和正在等待投递
+            self.get_delivery(user_id, questionnaire_id, webhook_id, delivery_id)
+                .await?;
+            return Err(AppError::validation("该推送正在等待投递"));
+        }
+
+        self.get_delivery(user_id, questionnaire_id, webhook_id, delivery_id)
+            .await
+    }
+
+    // 在触发事件的事务中为订阅了该事件的Webhook写入待投递的推送
+    pub(crate) async fn notify<T: Serialize>(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        event: WebhookEvent,
+        data: &T,
+    ) -> AppResult<()> {
+        let webhook_ids = Self::subscribers(tx, questionnaire_id, event).await?;
+        Self::enqueue(tx, &webhook_ids, questionnaire_id, event, data).await
+    }
+
+    // 订阅了该事件且已启用的Webhook，没有订阅时可以省去构建推送内容
+    pub(crate) async fn subscribers(
+        tx: &mut Transaction<'_, MySql>,
+        questionnaire_id: i32,
+        event: WebhookEvent,
+    ) -> AppResult<Vec<i32>> {
+        let webhook_ids = sqlx::query!(
+            "SELECT id, events FROM webhooks WHERE questionnaire_id = ? AND active = TRUE",
+            questionnaire_id
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .filter(|row| WebhookEvent::parse_list(&row.events).contains(&event))
+        .map(|row| row.id)
+        .collect();
+
+        Ok(webhook_ids)
+    }
+
+    pub(crate) async fn enqueue<T: Serialize>(
+        tx: &mut Transaction<'_, MySql>,
+        webhook_ids: &[i32],
+        questionnaire_id: i32,
+        event: WebhookEvent,
+        data: &T,
+    ) -> AppResult<()> {
+        for webhook_id in webhook_ids {
+            let delivery_uuid = Uuid::new_v4().to_string();
+            let payload = serde_json::to_string(&WebhookPayload {
+                id: &delivery_uuid,
+                event,
+                questionnaire_id,
+                created_at: Utc::now(),
+                data,
+            })
+            .map_err(|e| AppError::InternalServerError(format!("生成推送内容失败: {}", e)))?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO webhook_deliveries
+                    (webhook_id, delivery_uuid, event, payload, next_attempt_at)
+                VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)
+                "#,
+                webhook_id,
+                delivery_uuid,
+                event.as_str(),
+                payload
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    // 投递已到时间的推送，返回投递成功的数量，由后台任务定时调用
+    pub async fn deliver_due(db: &Pool<MySql>, config: &WebhookConfig) -> AppResult<u64> {
+        let due = sqlx::query!(
+            r#"
+            SELECT id FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+            ORDER BY next_attempt_at, id
+            LIMIT ?
+            "#,
+            config.batch_size
+        )
+        .fetch_all(db)
+        .await?;
+
+        let client = WebhookClient::new(config.timeout, config.allow_private_targets);
+        let mut delivered = 0;
+
+        for delivery in due {
+            // 先顺延下次投递时间占用该推送，多个实例同时运行时不会重复投递
+            let lease_until = Utc::now() + Duration::seconds((config.timeout * 2 + 60) as i64);
+            let claimed = sqlx::query!(
+                r#"
+                UPDATE webhook_deliveries SET next_attempt_at = ?
+                WHERE id = ? AND status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+                "#,
+                lease_until,
+                delivery.id
+            )
+            .execute(db)
+            .await?;
+
+            if claimed.rows_affected() == 0 {
+                continue;
+            }
+
+            let row = sqlx::query!(
+                r#"
+                SELECT d.delivery_uuid, d.event, d.payload, d.attempts,
+                       w.url, w.secret, w.active as "active: bool"
+                FROM webhook_deliveries d
+                JOIN webhooks w ON d.webhook_id = w.id
+                WHERE d.id = ?
+                "#,
+                delivery.id
+            )
+            .fetch_one(db)
+            .await?;
+
+            // 停用期间的推送不再投递，启用后可以手动重新投递
+            if !row.active {
+                sqlx::query!(
+                    r#"
+                    UPDATE webhook_deliveries
+                    SET status = 'failed', next_attempt_at = NULL, last_error = 'Webhook已停用'
+                    WHERE id = ?
+                    "#,
+                    delivery.id
+                )
+                .execute(db)
+                .await?;
+                continue;
+            }
+
+            let outcome = client
+                .deliver(&row.url, &row.secret, &row.event, &row.delivery_uuid, &row.payload)
+                .await;
+            Self::record_attempt(db, config, delivery.id, row.attempts + 1, &outcome).await?;
+
+            if outcome.is_success() {
+                delivered += 1;
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    // 记录投递结果：成功后结束，失败时按指数退避安排重试，次数用完后标记为失败
+    async fn record_attempt(
+        db: &Pool<MySql>,
+        config: &WebhookConfig,
+        delivery_id: i32,
+        attempts: i32,
+        outcome: &DeliveryOutcome,
+    ) -> AppResult<()> {
+        let mut tx = db.begin().await?;
+
+        let status_code = outcome.status_code.map(i32::from);
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_delivery_attempts
+                (delivery_id, status_code, error, response_body, duration_ms)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            delivery_id,
+            status_code,
+            outcome.error,
+            outcome.response_body,
+            outcome.duration_ms
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if outcome.is_success() {
+            sqlx::query!(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = 'delivered', attempts = ?, last_status_code = ?, last_error = NULL,
+                    next_attempt_at = NULL, delivered_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+                "#,
+                attempts,
+                status_code,
+                delivery_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        } else {
+            let (status, next_attempt_at) = if attempts >= config.max_attempts {
+                (DeliveryStatus::Failed, None)
+            } else {
+                let delay = retry_delay(config, attempts);
+                (
+                    DeliveryStatus::Pending,
+                    Some(Utc::now() + Duration::seconds(delay as i64)),
+                )
+            };
+
+            sqlx::query!(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = ?, attempts = ?, last_status_code = ?, last_error = ?,
+                    next_attempt_at = ?
+                WHERE id = ?
+                "#,
+                status.as_str(),
+                attempts,
+                status_code,
+                outcome.error,
+                next_attempt_at,
+                delivery_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn find(&self, questionnaire_id: i32, webhook_id: i32) -> AppResult<Webhook> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, questionnaire_id, url, events, active as "active: bool",
+                   created_at as "created_at: chrono::DateTime<chrono::Utc>",
+                   updated_at as "updated_at: chrono::DateTime<chrono::Utc>"
+            FROM webhooks
+            WHERE id = ? AND questionnaire_id = ?
+            "#,
+            webhook_id,
+            questionnaire_id
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError(format!("Webhook ID {} 不存在", webhook_id)))?;
+
+        Ok(Webhook {
+            id: row.id,
+            questionnaire_id: row.questionnaire_id,
+            url: row.url,
+            events: WebhookEvent::parse_list(&row.events),
+            active: row.active,
+            created_at: row.created_at.expect("创建时间不应为空"),
+            updated_at: row.updated_at.expect("更新时间不应为空"),
+        })
+    }
+
+    // Webhook的签名密钥和推送内容包含回答数据，需要管理问卷的权限
+    async fn authorize(&self, user_id: i32, questionnaire_id: i32) -> AppResult<()> {
+        PermissionService::new(self.db.clone())
+            .authorize(user_id, questionnaire_id, Access::Manage)
+            .await?;
+        Ok(())
+    }
+
+    // 保存前解析订阅地址，拒绝指向内网的地址
+    async fn validate_url(&self, url: &str) -> AppResult<String> {
+        let target = resolve_target(url, self.config.webhook.allow_private_targets)
+            .await
+            .map_err(|message| field_error("url", &message))?;
+        Ok(target.url.into())
+    }
+}
+
+// 第n次失败后的等待时间：基础间隔每次翻倍，不超过最长间隔
+fn retry_delay(config: &WebhookConfig, attempts: i32) -> u64 {
+    let exponent = (attempts - 1).clamp(0, 20) as u32;
+    config
+        .retry_base_delay
+        .saturating_mul(1 << exponent)
+        .min(config.max_retry_delay)
+}
@@ -3,7 +3,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use axum::{
     extract::{FromRef, FromRequestParts, State},
-    http::{header, request::Parts, Request},
+    http::{header, request::Parts, HeaderMap, Request},
     middleware::Next,
     response::Response,
     body::Body,
@@ -11,11 +11,18 @@ use axum::{
 use async_trait::async_trait;
 use bcrypt::{hash, verify, DEFAULT_COST};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::MySqlPool;
 
 use crate::config::Config;
+use crate::models::api_token::{ApiScope, ApiScopes};
 use crate::models::error::{AppError, AppResult};
 use crate::models::user::Claims;
 
+// 个人访问令牌的前缀，用于与JWT访问令牌区分
+pub const API_TOKEN_PREFIX: &str = "qpat_";
+
 // 密码加密
 pub fn hash_password(password: &str) -> AppResult<String> {
     hash(password, DEFAULT_COST)
@@ -28,13 +35,8 @@ pub fn verify_password(password: &str, hash: &str) -> AppResult<bool> {
         .map_err(|e| AppError::InternalServerError(format!("密码验证失败: {}", e)))
 }
 
-// 生成JWT令牌
-pub fn generate_jwt(
-    config: &Arc<Config>,
-    user_id: i32,
-    expiration_hours: Option<u64>,
-) -> AppResult<String> {
-    let expiration = expiration_hours.unwrap_or(24);
+// 生成访问令牌，有效期由JWT_EXPIRATION配置
+pub fn generate_jwt(config: &Arc<Config>, user_id: i32, session_id: i32) -> AppResult<String> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("获取当前时间失败")
@@ -42,8 +44,9 @@ pub fn generate_jwt(
     
     let claims = Claims {
         sub: user_id.to_string(),
+        sid: session_id,
         iat: now as usize,
-        exp: (now + expiration * 3600) as usize,
+        exp: (now + config.jwt.expiration) as usize,
     };
 
     let token = encode(
@@ -68,62 +71,215 @@ pub fn verify_jwt(config: &Arc<Config>, token: &str) -> AppResult<Claims> {
     Ok(token_data.claims)
 }
 
-// JWT中间件，用于保护需要认证的路由
+// 生成随机令牌，用于刷新令牌和重置密码链接
+pub fn generate_secret_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// 数据库中只保存令牌的摘要
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// 通过验证的登录会话
+#[derive(Debug, Clone, Copy)]
+pub struct AuthSession {
+    pub user_id: i32,
+    pub session_id: i32,
+}
+
+// 通过验证的请求身份：登录会话或个人访问令牌
+#[derive(Debug, Clone, Copy)]
+pub enum Principal {
+    Session(AuthSession),
+    ApiToken { user_id: i32, scopes: ApiScopes },
+}
+
+impl Principal {
+    pub fn user_id(&self) -> i32 {
+        match self {
+            Self::Session(session) => session.user_id,
+            Self::ApiToken { user_id, .. } => *user_id,
+        }
+    }
+
+    // 登录会话可以访问所有接口；个人访问令牌只能访问声明了权限范围的接口，且须拥有该权限
+    pub fn authorize(&self, required: Option<ApiScope>) -> AppResult<()> {
+        let Self::ApiToken { scopes, .. } = self else {
+            return Ok(());
+        };
+
+        match required {
+            Some(scope) if scopes.contains(scope) => Ok(()),
+            Some(scope) => Err(AppError::PermissionError(format!(
+                "个人访问令牌缺少{}权限",
+                scope.as_str()
+            ))),
+            None => Err(AppError::PermissionError(
+                "该接口不支持使用个人访问令牌".to_string(),
+            )),
+        }
+    }
+}
+
+// 验证访问令牌或个人访问令牌，并检查其所属的会话是否已退出登录或被吊销
+pub async fn authenticate(
+    config: &Arc<Config>,
+    db: &MySqlPool,
+    token: &str,
+) -> AppResult<Principal> {
+    if token.starts_with(API_TOKEN_PREFIX) {
+        return authenticate_api_token(db, token).await;
+    }
+
+    let claims = verify_jwt(config, token)?;
+    let user_id = claims
+        .sub
+        .parse::<i32>()
+        .map_err(|_| AppError::AuthError("无效的用户标识".to_string()))?;
+
+    let session = sqlx::query!(
+        r#"
+        SELECT user_id FROM user_sessions
+        WHERE id = ? AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        "#,
+        claims.sid
+    )
+    .fetch_optional(db)
+    .await?;
+
+    match session {
+        Some(session) if session.user_id == user_id => Ok(Principal::Session(AuthSession {
+            user_id,
+            session_id: claims.sid,
+        })),
+        _ => Err(AppError::AuthError("登录已失效，请重新登录".to_string())),
+    }
+}
+
+async fn authenticate_api_token(db: &MySqlPool, token: &str) -> AppResult<Principal> {
+    let token_hash = hash_token(token);
+
+    let api_token = sqlx::query!(
+        r#"
+        SELECT id, user_id, scopes FROM api_tokens
+        WHERE token_hash = ? AND revoked_at IS NULL
+        AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        "#,
+        token_hash
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::AuthError("个人访问令牌无效、已过期或已被撤销".to_string()))?;
+
+    sqlx::query!(
+        "UPDATE api_tokens SET last_used_at = CURRENT_TIMESTAMP WHERE id = ?",
+        api_token.id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(Principal::ApiToken {
+        user_id: api_token.user_id,
+        scopes: ApiScopes::parse(&api_token.scopes),
+    })
+}
+
+fn bearer_token(headers: &HeaderMap) -> AppResult<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .filter(|auth| auth.starts_with("Bearer "))
+        .map(|auth| auth.trim_start_matches("Bearer ").trim())
+        .ok_or_else(|| AppError::AuthError("请提供有效的认证令牌".to_string()))
+}
+
+// 认证中间件所需的状态
+#[derive(Clone)]
+pub struct AuthState {
+    pub config: Arc<Config>,
+    pub db: Arc<MySqlPool>,
+}
+
+// JWT中间件，用于保护需要认证的路由；个人访问令牌的权限范围由CurrentUser按路由检查
 pub async fn auth_middleware(
-    State(config): State<Arc<Config>>,
-    request: Request<Body>,
+    State(state): State<AuthState>,
+    mut request: Request<Body>,
     next: Next<Body>,
 ) -> Result<Response, AppError> {
-    let auth_header = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|header| header.to_str().ok());
-
-    match auth_header {
-        Some(auth) if auth.starts_with("Bearer ") => {
-            let token = auth.trim_start_matches("Bearer ").trim();
-            let _claims = verify_jwt(&config, token)?;
-            // 通过验证，继续请求
-            let response = next.run(request).await;
-            Ok(response)
+    let token = bearer_token(request.headers())?.to_string();
+    let principal = authenticate(&state.config, &state.db, &token).await?;
+
+    // 通过验证，记录身份供后续的提取器使用，继续请求
+    request.extensions_mut().insert(principal);
+    let response = next.run(request).await;
+    Ok(response)
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Principal
+where
+    Arc<Config>: FromRef<S>,
+    Arc<MySqlPool>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    // 使用axum 0.6.x的签名格式
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // 已经过认证中间件时直接使用其结果
+        if let Some(principal) = parts.extensions.get::<Principal>() {
+            return Ok(*principal);
+        }
+
+        let config = Arc::<Config>::from_ref(state);
+        let db = Arc::<MySqlPool>::from_ref(state);
+        let token = bearer_token(&parts.headers)?;
+        let principal = authenticate(&config, &db, token).await?;
+
+        parts.extensions.insert(principal);
+        Ok(principal)
+    }
+}
+
+// 只接受登录会话，用于退出登录等与会话相关的接口
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthSession
+where
+    Arc<Config>: FromRef<S>,
+    Arc<MySqlPool>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Principal::from_request_parts(parts, state).await? {
+            Principal::Session(session) => Ok(session),
+            Principal::ApiToken { .. } => Err(AppError::PermissionError(
+                "该接口不支持使用个人访问令牌".to_string(),
+            )),
         }
-        _ => Err(AppError::AuthError("请提供有效的认证令牌".to_string())),
     }
 }
 
 // 用于从请求中提取当前用户ID的提取器
+// 路由通过Extension(ApiScope)声明个人访问令牌所需的权限范围，未声明的路由只接受登录会话
 pub struct CurrentUser(pub i32);
 
 #[async_trait]
 impl<S> FromRequestParts<S> for CurrentUser
 where
     Arc<Config>: FromRef<S>,
+    Arc<MySqlPool>: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = AppError;
 
-    // 使用axum 0.6.x的签名格式
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let config = Arc::from_ref(state);
-
-        let auth_header = parts
-            .headers
-            .get(header::AUTHORIZATION)
-            .and_then(|header| header.to_str().ok());
-
-        match auth_header {
-            Some(auth) if auth.starts_with("Bearer ") => {
-                let token = auth.trim_start_matches("Bearer ").trim();
-                let claims = verify_jwt(&config, token)?;
-                
-                let user_id = claims
-                    .sub
-                    .parse::<i32>()
-                    .map_err(|_| AppError::AuthError("无效的用户标识".to_string()))?;
-                
-                Ok(CurrentUser(user_id))
-            }
-            _ => Err(AppError::AuthError("请提供有效的认证令牌".to_string())),
-        }
+        let principal = Principal::from_request_parts(parts, state).await?;
+        principal.authorize(parts.extensions.get::<ApiScope>().copied())?;
+        Ok(CurrentUser(principal.user_id()))
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,122 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{header, request::Parts, HeaderName},
+    response::AppendHeaders,
+};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::models::error::AppError;
+use crate::models::response::Respondent;
+
+// 标识设备的Cookie名称
+const DEVICE_COOKIE: &str = "device_id";
+
+// 从请求中提取的客户端信息：设备Cookie和IP地址
+pub struct ClientInfo {
+    pub device_id: String,
+    pub ip_address: Option<String>,
+    new_device: bool, // 请求未携带设备Cookie，需要在响应中下发
+}
+
+impl ClientInfo {
+    pub fn respondent(&self, user_id: Option<i32>) -> Respondent {
+        Respondent {
+            user_id,
+            device_id: Some(self.device_id.clone()),
+            ip_address: self.ip_address.clone(),
+        }
+    }
+
+    // 首次访问时下发设备Cookie
+    pub fn cookie_header(&self) -> AppendHeaders<Vec<(HeaderName, String)>> {
+        let mut headers = Vec::new();
+        if self.new_device {
+            headers.push((
+                header::SET_COOKIE,
+                format!(
+                    "{}={}; Path=/; Max-Age=31536000; HttpOnly; SameSite=Lax",
+                    DEVICE_COOKIE, self.device_id
+                ),
+            ));
+        }
+        AppendHeaders(headers)
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    Arc<Config>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::from_ref(state);
+
+        let device_id = parts
+            .headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(name, _)| *name == DEVICE_COOKIE)
+            .map(|(_, value)| value.to_string())
+            .filter(|value| {
+                !value.is_empty()
+                    && value.len() <= 64
+                    && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+
+        // 部署在反向代理之后时，从X-Forwarded-For中读取客户端IP
+        let forwarded_ip = if config.server.trust_proxy {
+            forwarded_client_ip(parts, config.server.trusted_proxy_hops)
+        } else {
+            None
+        };
+        let ip_address = forwarded_ip.or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        });
+
+        Ok(match device_id {
+            Some(device_id) => Self {
+                device_id,
+                ip_address,
+                new_device: false,
+            },
+            None => Self {
+                device_id: Uuid::new_v4().simple().to_string(),
+                ip_address,
+                new_device: true,
+            },
+        })
+    }
+}
+
+// X-Forwarded-For最左边的部分由客户端自行填写，不可信；每层代理都在末尾追加它看到的来源地址，
+// 因此从右往左数第hops个才是最外层代理记录的真实客户端IP，层数不足时返回None
+fn forwarded_client_ip(parts: &Parts, hops: usize) -> Option<String> {
+    let entries: Vec<&str> = parts
+        .headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    entries
+        .len()
+        .checked_sub(hops)
+        .and_then(|index| entries[index].parse::<IpAddr>().ok())
+        .map(|ip| ip.to_string())
+}
@@ -0,0 +1,544 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Number, Value};
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+use crate::models::error::FieldErrors;
+use crate::models::questionnaire::{
+    CreateQuestionnaireRequest, OptionRequest, QuestionConfig, QuestionRequest, SectionRequest,
+};
+
+// 解析得到的问卷定义，lines记录各字段（如questions[2]）在源文本中的行号
+pub struct ParsedDefinition {
+    pub definition: CreateQuestionnaireRequest,
+    pub lines: BTreeMap<String, usize>,
+}
+
+impl ParsedDefinition {
+    // 查找字段所在的行，字段本身没有记录时使用最近的上级字段
+    pub fn line_of(&self, field: &str) -> Option<usize> {
+        let mut field = field;
+        loop {
+            if let Some(line) = self.lines.get(field) {
+                return Some(*line);
+            }
+            field = &field[..field.rfind(['.', '['])?];
+        }
+    }
+
+    // 在字段错误的键前加上所在行，如"line 12: questions[3]"
+    pub fn locate(&self, errors: FieldErrors) -> FieldErrors {
+        let mut located = FieldErrors::new();
+        for (field, messages) in errors {
+            let key = match self.line_of(&field) {
+                Some(line) => format!("line {}: {}", line, field),
+                None => field,
+            };
+            located.entry(key).or_default().extend(messages);
+        }
+        located
+    }
+}
+
+fn line_error(line: usize, message: impl Into<String>) -> FieldErrors {
+    let mut errors = FieldErrors::new();
+    errors.insert(format!("line {}", line), vec![message.into()]);
+    errors
+}
+
+fn document_error(message: impl Into<String>) -> FieldErrors {
+    let mut errors = FieldErrors::new();
+    errors.insert("content".to_string(), vec![message.into()]);
+    errors
+}
+
+pub fn parse_json(content: &str) -> Result<ParsedDefinition, FieldErrors> {
+    let definition = serde_json::from_str(content)
+        .map_err(|e| line_error(e.line(), e.to_string()))?;
+
+    // JSON也是合法的YAML，借助YAML解析器定位各字段所在的行
+    Ok(ParsedDefinition {
+        definition,
+        lines: locate_lines(content),
+    })
+}
+
+pub fn parse_yaml(content: &str) -> Result<ParsedDefinition, FieldErrors> {
+    let document = YamlLoader::load_from_str(content)
+        .map_err(|e| line_error(e.marker().line(), e.info()))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| document_error("文档为空"))?;
+
+    let value = yaml_to_json(document).map_err(document_error)?;
+    let definition = serde_json::from_value(value).map_err(|e| document_error(e.to_string()))?;
+
+    Ok(ParsedDefinition {
+        definition,
+        lines: locate_lines(content),
+    })
+}
+
+pub fn to_yaml(definition: &CreateQuestionnaireRequest) -> Result<String, String> {
+    let value = serde_json::to_value(definition).map_err(|e| e.to_string())?;
+    let mut out = String::new();
+    YamlEmitter::new(&mut out)
+        .dump(&json_to_yaml(value))
+        .map_err(|e| e.to_string())?;
+    out.push('\n');
+    Ok(out)
+}
+
+fn yaml_to_json(yaml: Yaml) -> Result<Value, String> {
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Boolean(value) => Value::Bool(value),
+        Yaml::Integer(value) => Value::Number(value.into()),
+        Yaml::Real(value) => value
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| format!("无效的数字: {}", value))?,
+        Yaml::String(value) => Value::String(value),
+        Yaml::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(yaml_to_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        Yaml::Hash(hash) => {
+            let mut map = Map::new();
+            for (key, value) in hash {
+                let key = match key {
+                    Yaml::String(key) => key,
+                    Yaml::Integer(key) => key.to_string(),
+                    key => return Err(format!("不支持的键: {:?}", key)),
+                };
+                map.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(map)
+        }
+        Yaml::Alias(_) | Yaml::BadValue => return Err("不支持YAML别名".to_string()),
+    })
+}
+
+fn json_to_yaml(value: Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(value) => Yaml::Boolean(value),
+        Value::Number(value) => match value.as_i64() {
+            Some(value) => Yaml::Integer(value),
+            None => Yaml::Real(value.to_string()),
+        },
+        Value::String(value) => Yaml::String(value),
+        Value::Array(items) => Yaml::Array(items.into_iter().map(json_to_yaml).collect()),
+        Value::Object(map) => {
+            let mut hash = Hash::new();
+            for (key, value) in map {
+                hash.insert(Yaml::String(key), json_to_yaml(value));
+            }
+            Yaml::Hash(hash)
+        }
+    }
+}
+
+// 遍历YAML事件，记录每个值的路径（与字段错误的键格式相同）及其起始行
+fn locate_lines(content: &str) -> BTreeMap<String, usize> {
+    let mut locator = LineLocator::default();
+    if Parser::new_from_str(content).load(&mut locator, false).is_err() {
+        return BTreeMap::new();
+    }
+    locator.lines
+}
+
+enum Frame {
+    Mapping { key: Option<String>, expecting_key: bool },
+    Sequence { index: usize },
+}
+
+#[derive(Default)]
+struct LineLocator {
+    stack: Vec<Frame>,
+    lines: BTreeMap<String, usize>,
+}
+
+impl LineLocator {
+    fn path(&self) -> String {
+        let mut path = String::new();
+        for frame in &self.stack {
+            match frame {
+                Frame::Mapping { key: Some(key), .. } => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(key);
+                }
+                Frame::Mapping { key: None, .. } => {}
+                Frame::Sequence { index } => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        path
+    }
+
+    fn expecting_key(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Mapping { expecting_key: true, .. }))
+    }
+
+    // 一个值结束后，上级映射等待下一个键，上级序列移到下一项
+    fn value_done(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Mapping { expecting_key, .. }) => *expecting_key = true,
+            Some(Frame::Sequence { index }) => *index += 1,
+            None => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for LineLocator {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(value, ..) => {
+                if let Some(Frame::Mapping { key, expecting_key }) = self.stack.last_mut() {
+                    if *expecting_key {
+                        *key = Some(value);
+                        *expecting_key = false;
+                        return;
+                    }
+                }
+                self.lines.insert(self.path(), mark.line());
+                self.value_done();
+            }
+            Event::MappingStart(..) | Event::SequenceStart(..) => {
+                if !self.expecting_key() {
+                    self.lines.insert(self.path(), mark.line());
+                }
+                self.stack.push(match event {
+                    Event::MappingStart(..) => Frame::Mapping { key: None, expecting_key: true },
+                    _ => Frame::Sequence { index: 0 },
+                });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.value_done();
+            }
+            Event::Alias(_) => self.value_done(),
+            _ => {}
+        }
+    }
+}
+
+// 文本格式：
+//   # 问卷标题
+//   > 问卷描述（可以有多行）
+//   @public
+//   ## 分页标题
+//   > 分页描述
+//   1. 问题标题 [题型] * {题型配置JSON}
+//   - 选项（矩阵题的列）
+//   + 矩阵题的行
+// 题型省略时有选项为radio，否则为text；*表示必答；以//开头的行为注释
+pub fn parse_text(content: &str) -> Result<ParsedDefinition, FieldErrors> {
+    let mut errors = FieldErrors::new();
+    let mut lines = BTreeMap::new();
+
+    let mut title = None;
+    let mut description: Vec<&str> = Vec::new();
+    let mut is_public = false;
+    let mut sections: Vec<SectionRequest> = Vec::new();
+    let mut questions: Vec<QuestionRequest> = Vec::new();
+    // 描述写在最近的标题之后，出现问题后不能再写描述
+    let mut after_heading = true;
+
+    for (index, raw) in content.lines().enumerate() {
+        let number = index + 1;
+        let line = raw.trim();
+
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+
+        if let Some(text) = line.strip_prefix("## ") {
+            lines.insert(format!("sections[{}]", sections.len()), number);
+            sections.push(SectionRequest {
+                id: None,
+                title: text.trim().to_string(),
+                description: String::new(),
+            });
+            after_heading = true;
+        } else if let Some(text) = line.strip_prefix("# ") {
+            if title.is_some() || !questions.is_empty() || !sections.is_empty() {
+                errors.insert(format!("line {}", number), vec!["问卷标题只能写在开头".to_string()]);
+                continue;
+            }
+            lines.insert("title".to_string(), number);
+            title = Some(text.trim().to_string());
+        } else if let Some(text) = line.strip_prefix('>') {
+            if !after_heading {
+                errors.insert(
+                    format!("line {}", number),
+                    vec!["描述只能写在问卷标题或分页标题之后".to_string()],
+                );
+                continue;
+            }
+            match sections.last_mut() {
+                Some(section) => {
+                    if !section.description.is_empty() {
+                        section.description.push('\n');
+                    }
+                    section.description.push_str(text.trim());
+                }
+                None => {
+                    lines.entry("description".to_string()).or_insert(number);
+                    description.push(text.trim());
+                }
+            }
+        } else if line == "@public" {
+            is_public = true;
+        } else if let Some(text) = line.strip_prefix("- ").or_else(|| line.strip_prefix("+ ")) {
+            let Some(question) = questions.last_mut() else {
+                errors.insert(format!("line {}", number), vec!["选项必须写在问题之后".to_string()]);
+                continue;
+            };
+            let option = OptionRequest::Text(text.trim().to_string());
+            if line.starts_with('+') {
+                question.rows.push(option);
+            } else {
+                question.options.push(option);
+            }
+        } else if let Some(text) = strip_question_number(line) {
+            match parse_question_line(text) {
+                Ok((question_title, question_type, required, config)) => {
+                    lines.insert(format!("questions[{}]", questions.len()), number);
+                    questions.push(QuestionRequest {
+                        id: None,
+                        title: question_title,
+                        question_type: question_type.unwrap_or_default(),
+                        required,
+                        options: Vec::new(),
+                        rows: Vec::new(),
+                        config,
+                        logic: None,
+                        section: sections.len().checked_sub(1),
+                    });
+                    after_heading = false;
+                }
+                Err(message) => {
+                    errors.insert(format!("line {}", number), vec![message]);
+                }
+            }
+        } else {
+            errors.insert(
+                format!("line {}", number),
+                vec!["无法识别的行，问题应以“1.”开头，选项以“- ”开头".to_string()],
+            );
+        }
+    }
+
+    let Some(title) = title else {
+        errors.insert("line 1".to_string(), vec!["缺少问卷标题（以“# ”开头）".to_string()]);
+        return Err(errors);
+    };
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // 未写题型的问题按是否有选项推断
+    for question in &mut questions {
+        if question.question_type.is_empty() {
+            question.question_type = if question.options.is_empty() { "text" } else { "radio" }.to_string();
+        }
+    }
+
+    Ok(ParsedDefinition {
+        definition: CreateQuestionnaireRequest {
+            title,
+            description: description.join("\n"),
+            is_public,
+            sections,
+            questions,
+        },
+        lines,
+    })
+}
+
+// 去掉问题开头的编号，如"1."、"2)"、"3、"
+fn strip_question_number(line: &str) -> Option<&str> {
+    let digits = line.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 {
+        return None;
+    }
+    let rest = &line[digits..];
+    let rest = rest
+        .strip_prefix('.')
+        .or_else(|| rest.strip_prefix(')'))
+        .or_else(|| rest.strip_prefix('、'))?;
+    Some(rest.trim())
+}
+
+// 按“标题 [题型] * {题型配置}”的格式从行尾依次解析题型配置、必答标记和题型，剩余部分为问题标题。
+// 顺序固定，因此写出题型时，标题末尾的“*”、“[”和“{”都会保留在标题中
+fn parse_question_line(
+    text: &str,
+) -> Result<(String, Option<String>, bool, Option<QuestionConfig>), String> {
+    let mut rest = text.trim_end();
+
+    let mut config = None;
+    if rest.ends_with('}') {
+        // 标题中也可能有“{”，从左往右找到第一个能解析为JSON的位置
+        let mut first_error = None;
+        for (start, _) in rest.match_indices('{') {
+            match serde_json::from_str(&rest[start..]) {
+                Ok(parsed) => {
+                    config = Some(parsed);
+                    rest = rest[..start].trim_end();
+                    break;
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if config.is_none() {
+            return Err(match first_error {
+                Some(e) => format!("题型配置不是有效的JSON: {}", e),
+                None => "题型配置缺少“{”".to_string(),
+            });
+        }
+    }
+
+    let required = match rest.strip_suffix('*') {
+        Some(stripped) => {
+            rest = stripped.trim_end();
+            true
+        }
+        None => false,
+    };
+
+    let mut question_type = None;
+    if rest.ends_with(']') {
+        let start = rest.rfind('[').ok_or("题型缺少“[”")?;
+        question_type = Some(rest[start + 1..rest.len() - 1].trim().to_string());
+        rest = rest[..start].trim_end();
+    }
+
+    Ok((rest.to_string(), question_type, required, config))
+}
+
+// 生成文本格式，条件逻辑无法用文本格式表示，会以注释标出
+pub fn to_text(definition: &CreateQuestionnaireRequest) -> Result<String, String> {
+    let mut out = format!("# {}\n", single_line(&definition.title));
+    for line in definition.description.lines() {
+        out.push_str(&format!("> {}\n", line));
+    }
+    if definition.is_public {
+        out.push_str("@public\n");
+    }
+
+    // 分页按顺序输出，没有问题的分页也保留
+    let mut next_section = 0;
+    for (index, question) in definition.questions.iter().enumerate() {
+        if let Some(section) = question.section {
+            while next_section <= section && next_section < definition.sections.len() {
+                write_section(&mut out, &definition.sections[next_section]);
+                next_section += 1;
+            }
+        }
+
+        out.push('\n');
+        if question.logic.is_some() {
+            out.push_str("// 本题的条件逻辑未导出，请使用JSON或YAML格式\n");
+        }
+        out.push_str(&format!(
+            "{}. {} [{}]",
+            index + 1,
+            single_line(&question.title),
+            question.question_type
+        ));
+        if question.required {
+            out.push_str(" *");
+        }
+        if let Some(config) = &question.config {
+            out.push(' ');
+            out.push_str(&serde_json::to_string(config).map_err(|e| e.to_string())?);
+        }
+        out.push('\n');
+
+        for option in &question.options {
+            out.push_str(&format!("- {}\n", single_line(option.text())));
+        }
+        for row in &question.rows {
+            out.push_str(&format!("+ {}\n", single_line(row.text())));
+        }
+    }
+
+    for section in definition.sections.iter().skip(next_section) {
+        write_section(&mut out, section);
+    }
+
+    Ok(out)
+}
+
+fn write_section(out: &mut String, section: &SectionRequest) {
+    out.push_str(&format!("\n## {}\n", single_line(&section.title)));
+    for line in section.description.lines() {
+        out.push_str(&format!("> {}\n", line));
+    }
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_format_round_trips() {
+        let source = r#"{
+            "title": "满意度调查",
+            "description": "第一行\n第二行",
+            "is_public": true,
+            "sections": [
+                {"title": "基本信息", "description": "请如实填写"},
+                {"title": "空分页", "description": ""}
+            ],
+            "questions": [
+                {"title": "Rate 5*", "type": "radio", "required": false,
+                 "options": ["好", "- 不好"], "section": 0},
+                {"title": "打分 {满分5}", "type": "rating", "required": true,
+                 "config": {"min": 1, "max": 5, "min_label": "差]"}, "section": 0},
+                {"title": "选择 [A] 或 [B]", "type": "matrix_radio", "required": true,
+                 "options": ["是", "否"], "rows": ["A", "B"], "section": 0},
+                {"title": "其他意见*", "type": "text", "required": true, "section": 0}
+            ]
+        }"#;
+        let definition = parse_json(source).unwrap().definition;
+
+        let text = to_text(&definition).unwrap();
+        let parsed = parse_text(&text).unwrap().definition;
+
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&definition).unwrap(),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn suffixes_are_parsed_in_fixed_order() {
+        let (title, question_type, required, config) =
+            parse_question_line(r#"Rate 5* [radio] * {"max": 10}"#).unwrap();
+
+        assert_eq!(title, "Rate 5*");
+        assert_eq!(question_type.as_deref(), Some("radio"));
+        assert!(required);
+        assert_eq!(config.and_then(|config| config.max), Some(10.0));
+    }
+}
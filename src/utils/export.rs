@@ -0,0 +1,366 @@
+use crate::utils::zip::ZipWriter;
+
+// 导出文件中的一列
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: String,  // 变量名，如Q1、Q3_2
+    pub label: String, // 列标题
+    pub kind: ColumnKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ColumnKind {
+    Text,
+    Number,
+    // 数字编码及其含义
+    Coded(Vec<(i32, String)>),
+}
+
+// 单元格的值
+#[derive(Debug, Clone)]
+pub enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+}
+
+// 按行写出导出文件，每个方法把要输出的字节追加到out中
+pub trait ExportWriter: Send {
+    fn begin(&mut self, columns: &[Column], out: &mut Vec<u8>);
+    fn row(&mut self, cells: &[Cell], out: &mut Vec<u8>);
+    fn finish(&mut self, out: &mut Vec<u8>);
+}
+
+// CSV，表头使用列标题，带BOM以便Excel正确识别UTF-8
+pub struct CsvWriter;
+
+impl ExportWriter for CsvWriter {
+    fn begin(&mut self, columns: &[Column], out: &mut Vec<u8>) {
+        out.extend_from_slice("\u{feff}".as_bytes());
+        write_csv_line(columns.iter().map(|column| column.label.as_str()), out);
+    }
+
+    fn row(&mut self, cells: &[Cell], out: &mut Vec<u8>) {
+        write_csv_cells(cells, out);
+    }
+
+    fn finish(&mut self, _out: &mut Vec<u8>) {}
+}
+
+// XLSX，只包含一个工作表，单元格使用内联字符串
+pub struct XlsxWriter {
+    zip: ZipWriter,
+    row_number: usize,
+}
+
+const CONTENT_TYPES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const ROOT_RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="responses" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+const WORKBOOK_RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+// 样式1带有quotePrefix，Excel始终把单元格内容当作文本，编辑后也不会变成公式
+const STYLES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="1"><font><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0" quotePrefix="1"/></cellXfs></styleSheet>"#;
+
+const SHEET_HEADER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#;
+
+const SHEET_FOOTER_XML: &str = "</sheetData></worksheet>";
+
+impl XlsxWriter {
+    pub fn new() -> Self {
+        Self {
+            zip: ZipWriter::new(),
+            row_number: 0,
+        }
+    }
+
+    fn write_row(&mut self, cells: &[Cell], out: &mut Vec<u8>) {
+        self.row_number += 1;
+
+        let mut xml = format!("<row r=\"{}\">", self.row_number);
+        for (index, cell) in cells.iter().enumerate() {
+            let reference = format!("{}{}", column_letters(index), self.row_number);
+            match cell {
+                Cell::Empty => {}
+                Cell::Number(value) => {
+                    xml.push_str(&format!("<c r=\"{}\"><v>{}</v></c>", reference, value));
+                }
+                Cell::Text(value) => {
+                    let style = if is_formula_like(value) { " s=\"1\"" } else { "" };
+                    xml.push_str(&format!(
+                        "<c r=\"{}\"{} t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                        reference,
+                        style,
+                        escape_xml(value)
+                    ));
+                }
+            }
+        }
+        xml.push_str("</row>");
+
+        out.extend_from_slice(self.zip.write(xml.as_bytes()));
+    }
+}
+
+impl Default for XlsxWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportWriter for XlsxWriter {
+    fn begin(&mut self, columns: &[Column], out: &mut Vec<u8>) {
+        out.extend(self.zip.entry("[Content_Types].xml", CONTENT_TYPES_XML.as_bytes()));
+        out.extend(self.zip.entry("_rels/.rels", ROOT_RELS_XML.as_bytes()));
+        out.extend(self.zip.entry("xl/workbook.xml", WORKBOOK_XML.as_bytes()));
+        out.extend(self.zip.entry("xl/_rels/workbook.xml.rels", WORKBOOK_RELS_XML.as_bytes()));
+        out.extend(self.zip.entry("xl/styles.xml", STYLES_XML.as_bytes()));
+
+        // 工作表的内容随数据库查询逐行写出
+        out.extend(self.zip.start_entry("xl/worksheets/sheet1.xml"));
+        out.extend_from_slice(self.zip.write(SHEET_HEADER_XML.as_bytes()));
+
+        let header: Vec<Cell> = columns
+            .iter()
+            .map(|column| Cell::Text(column.label.clone()))
+            .collect();
+        self.write_row(&header, out);
+    }
+
+    fn row(&mut self, cells: &[Cell], out: &mut Vec<u8>) {
+        self.write_row(cells, out);
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.zip.write(SHEET_FOOTER_XML.as_bytes()));
+        out.extend(self.zip.finish_entry());
+        out.extend(std::mem::take(&mut self.zip).finish());
+    }
+}
+
+// 供统计软件使用的zip包：
+// data.csv 使用变量名作为表头、选项使用数字编码；
+// codebook.csv 列出每个变量的标签和编码含义；
+// import.sps 为SPSS的导入语法，包含变量标签和值标签
+pub struct SpssWriter {
+    zip: ZipWriter,
+}
+
+impl SpssWriter {
+    pub fn new() -> Self {
+        Self { zip: ZipWriter::new() }
+    }
+}
+
+impl Default for SpssWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportWriter for SpssWriter {
+    fn begin(&mut self, columns: &[Column], out: &mut Vec<u8>) {
+        out.extend(self.zip.entry("codebook.csv", &codebook(columns)));
+        out.extend(self.zip.entry("import.sps", spss_syntax(columns).as_bytes()));
+
+        out.extend(self.zip.start_entry("data.csv"));
+        let mut header = Vec::new();
+        write_csv_line(columns.iter().map(|column| column.name.as_str()), &mut header);
+        out.extend_from_slice(self.zip.write(&header));
+    }
+
+    fn row(&mut self, cells: &[Cell], out: &mut Vec<u8>) {
+        let mut line = Vec::new();
+        write_csv_cells(cells, &mut line);
+        out.extend_from_slice(self.zip.write(&line));
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        out.extend(self.zip.finish_entry());
+        out.extend(std::mem::take(&mut self.zip).finish());
+    }
+}
+
+fn codebook(columns: &[Column]) -> Vec<u8> {
+    let mut out = "\u{feff}".as_bytes().to_vec();
+    write_csv_line(["variable", "label", "type", "values"].into_iter(), &mut out);
+
+    for column in columns {
+        let (kind, values) = match &column.kind {
+            ColumnKind::Text => ("string", String::new()),
+            ColumnKind::Number => ("numeric", String::new()),
+            ColumnKind::Coded(codes) => (
+                "coded",
+                codes
+                    .iter()
+                    .map(|(code, label)| format!("{}={}", code, label))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+        };
+        write_csv_line(
+            [column.name.as_str(), column.label.as_str(), kind, values.as_str()].into_iter(),
+            &mut out,
+        );
+    }
+
+    out
+}
+
+fn spss_syntax(columns: &[Column]) -> String {
+    let mut sps = String::from(
+        "GET DATA\n  /TYPE=TXT\n  /FILE='data.csv'\n  /ENCODING='UTF8'\n  /DELIMITERS=\",\"\n  /QUALIFIER='\"'\n  /ARRANGEMENT=DELIMITED\n  /FIRSTCASE=2\n  /VARIABLES=\n",
+    );
+    for column in columns {
+        let format = match column.kind {
+            ColumnKind::Text => "A2000",
+            ColumnKind::Number => "F12.2",
+            ColumnKind::Coded(_) => "F8.0",
+        };
+        sps.push_str(&format!("    {} {}\n", column.name, format));
+    }
+    sps.push_str(".\n\nVARIABLE LABELS\n");
+    for column in columns {
+        sps.push_str(&format!("  {} '{}'\n", column.name, escape_spss(&column.label)));
+    }
+    sps.push_str(".\n");
+
+    let coded: Vec<_> = columns
+        .iter()
+        .filter_map(|column| match &column.kind {
+            ColumnKind::Coded(codes) => Some((column, codes)),
+            _ => None,
+        })
+        .collect();
+    if !coded.is_empty() {
+        sps.push_str("\nVALUE LABELS\n");
+        for (index, (column, codes)) in coded.iter().enumerate() {
+            let prefix = if index == 0 { "  " } else { "  /" };
+            let labels: Vec<String> = codes
+                .iter()
+                .map(|(code, label)| format!("{} '{}'", code, escape_spss(label)))
+                .collect();
+            sps.push_str(&format!("{}{} {}\n", prefix, column.name, labels.join(" ")));
+        }
+        sps.push_str(".\n");
+    }
+
+    sps.push_str("\nEXECUTE.\n");
+    sps
+}
+
+fn write_csv_cells(cells: &[Cell], out: &mut Vec<u8>) {
+    let values: Vec<String> = cells
+        .iter()
+        .map(|cell| match cell {
+            Cell::Empty => String::new(),
+            // 在可能被当作公式的文本前加单引号，防止打开文件时执行回答中的公式
+            Cell::Text(value) if is_formula_like(value) => format!("'{}", value),
+            Cell::Text(value) => value.clone(),
+            Cell::Number(value) => value.to_string(),
+        })
+        .collect();
+    write_csv_line(values.iter().map(String::as_str), out);
+}
+
+// 以这些字符开头的文本会被Excel等表格软件当作公式
+fn is_formula_like(value: &str) -> bool {
+    value.starts_with(['=', '+', '-', '@', '\t', '\r'])
+}
+
+fn write_csv_line<'a>(values: impl Iterator<Item = &'a str>, out: &mut Vec<u8>) {
+    for (index, value) in values.enumerate() {
+        if index > 0 {
+            out.push(b',');
+        }
+        if value.contains([',', '"', '\n', '\r']) {
+            out.push(b'"');
+            out.extend_from_slice(value.replace('"', "\"\"").as_bytes());
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(value.as_bytes());
+        }
+    }
+    out.extend_from_slice(b"\r\n");
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // XML 1.0不允许除制表符和换行外的控制字符
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            ch if (ch as u32) < 0x20 => {}
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn escape_spss(value: &str) -> String {
+    value.replace('\'', "''").replace(['\n', '\r'], " ")
+}
+
+// 列序号转换为Excel的列名：0 -> A，26 -> AA
+fn column_letters(mut index: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_prefixes_formula_like_text() {
+        let mut out = Vec::new();
+        let cells = [
+            Cell::Text("=HYPERLINK(\"http://example.com\")".to_string()),
+            Cell::Text("-1+2".to_string()),
+            Cell::Text("@SUM(A1)".to_string()),
+            Cell::Text("\tcmd".to_string()),
+            Cell::Text("正常回答".to_string()),
+            Cell::Number(-1.5),
+        ];
+        write_csv_cells(&cells, &mut out);
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\"'=HYPERLINK(\"\"http://example.com\"\")\",'-1+2,'@SUM(A1),'\tcmd,正常回答,-1.5\r\n"
+        );
+    }
+
+    #[test]
+    fn xlsx_marks_formula_like_text_as_quoted() {
+        let mut writer = XlsxWriter::new();
+        let mut out = Vec::new();
+        writer.write_row(
+            &[Cell::Text("=1+1".to_string()), Cell::Text("text".to_string())],
+            &mut out,
+        );
+        let xml = String::from_utf8(out).unwrap();
+
+        assert!(xml.contains("<c r=\"A1\" s=\"1\" t=\"inlineStr\">"));
+        assert!(xml.contains("<c r=\"B1\" t=\"inlineStr\">"));
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::config::{LockoutStoreKind, LoginConfig};
+use crate::models::error::AppResult;
+
+// 失败计数的对象：账号按小写用户名统计，与用户是否存在无关，避免泄露账号是否存在
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LockoutKey {
+    Account(String),
+    Ip(String),
+}
+
+impl LockoutKey {
+    pub fn account(username: &str) -> Self {
+        Self::Account(username.trim().to_lowercase())
+    }
+
+    fn scope(&self) -> &'static str {
+        match self {
+            Self::Account(_) => "account",
+            Self::Ip(_) => "ip",
+        }
+    }
+
+    fn subject(&self) -> &str {
+        match self {
+            Self::Account(subject) | Self::Ip(subject) => subject,
+        }
+    }
+}
+
+// 统计窗口内的连续失败记录
+#[derive(Debug, Clone)]
+pub struct FailureRecord {
+    pub failures: u32,
+    pub first_failed_at: DateTime<Utc>,
+    pub last_failed_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+// 失败记录的存储接口
+#[async_trait]
+pub trait LockoutStore: Send + Sync {
+    async fn get(&self, key: &LockoutKey) -> AppResult<Option<FailureRecord>>;
+    async fn put(&self, key: &LockoutKey, record: &FailureRecord) -> AppResult<()>;
+    async fn clear(&self, key: &LockoutKey) -> AppResult<()>;
+}
+
+// 根据配置创建失败记录的存储，内存存储在进程内共享
+pub fn lockout_store(config: &LoginConfig, db: Arc<Pool<MySql>>) -> Arc<dyn LockoutStore> {
+    static MEMORY: OnceLock<Arc<MemoryLockoutStore>> = OnceLock::new();
+
+    match config.store {
+        LockoutStoreKind::Memory => MEMORY.get_or_init(Default::default).clone(),
+        LockoutStoreKind::Mysql => Arc::new(MySqlLockoutStore { db }),
+    }
+}
+
+// 保存在进程内存中，重启后清空
+#[derive(Default)]
+pub struct MemoryLockoutStore {
+    records: Mutex<HashMap<LockoutKey, FailureRecord>>,
+}
+
+#[async_trait]
+impl LockoutStore for MemoryLockoutStore {
+    async fn get(&self, key: &LockoutKey) -> AppResult<Option<FailureRecord>> {
+        Ok(self.records.lock().unwrap().get(key).cloned())
+    }
+
+    async fn put(&self, key: &LockoutKey, record: &FailureRecord) -> AppResult<()> {
+        self.records
+            .lock()
+            .unwrap()
+            .insert(key.clone(), record.clone());
+        Ok(())
+    }
+
+    async fn clear(&self, key: &LockoutKey) -> AppResult<()> {
+        self.records.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+// 保存在login_failures表中，多个实例共享
+pub struct MySqlLockoutStore {
+    db: Arc<Pool<MySql>>,
+}
+
+#[async_trait]
+impl LockoutStore for MySqlLockoutStore {
+    async fn get(&self, key: &LockoutKey) -> AppResult<Option<FailureRecord>> {
+        let record = sqlx::query!(
+            r#"
+            SELECT failure_count,
+                   first_failed_at as "first_failed_at: chrono::DateTime<chrono::Utc>",
+                   last_failed_at as "last_failed_at: chrono::DateTime<chrono::Utc>",
+                   locked_until as "locked_until: chrono::DateTime<chrono::Utc>"
+            FROM login_failures
+            WHERE scope = ? AND subject = ?
+            "#,
+            key.scope(),
+            key.subject()
+        )
+        .fetch_optional(&*self.db)
+        .await?
+        .map(|row| FailureRecord {
+            failures: row.failure_count.max(0) as u32,
+            first_failed_at: row.first_failed_at,
+            last_failed_at: row.last_failed_at,
+            locked_until: row.locked_until,
+        });
+
+        Ok(record)
+    }
+
+    async fn put(&self, key: &LockoutKey, record: &FailureRecord) -> AppResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO login_failures
+                (scope, subject, failure_count, first_failed_at, last_failed_at, locked_until)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                failure_count = VALUES(failure_count),
+                first_failed_at = VALUES(first_failed_at),
+                last_failed_at = VALUES(last_failed_at),
+                locked_until = VALUES(locked_until)
+            "#,
+            key.scope(),
+            key.subject(),
+            record.failures,
+            record.first_failed_at,
+            record.last_failed_at,
+            record.locked_until
+        )
+        .execute(&*self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn clear(&self, key: &LockoutKey) -> AppResult<()> {
+        sqlx::query!(
+            "DELETE FROM login_failures WHERE scope = ? AND subject = ?",
+            key.scope(),
+            key.subject()
+        )
+        .execute(&*self.db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+// 登录被拒绝的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginBlock {
+    Locked { retry_after: u64 },    // 失败次数达到上限，暂时锁定
+    Throttled { retry_after: u64 }, // 距上次失败的时间太短
+}
+
+impl LoginBlock {
+    pub fn retry_after(&self) -> u64 {
+        match self {
+            Self::Locked { retry_after } | Self::Throttled { retry_after } => *retry_after,
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Locked { .. } => "locked",
+            Self::Throttled { .. } => "throttled",
+        }
+    }
+}
+
+// 登录限制策略：每次失败后需要等待的时间成倍增加，达到上限后锁定一段时间
+pub struct LoginGuard {
+    store: Arc<dyn LockoutStore>,
+    config: LoginConfig,
+}
+
+impl LoginGuard {
+    pub fn new(store: Arc<dyn LockoutStore>, config: LoginConfig) -> Self {
+        Self { store, config }
+    }
+
+    // 检查是否允许本次登录尝试，账号和IP任一受限即拒绝
+    pub async fn check(
+        &self,
+        username: &str,
+        ip_address: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<LoginBlock>> {
+        let mut block: Option<LoginBlock> = None;
+
+        for key in Self::keys(username, ip_address) {
+            let Some(record) = self.store.get(&key).await? else {
+                continue;
+            };
+
+            let current = if let Some(locked_until) = record.locked_until.filter(|t| *t > now) {
+                Some(LoginBlock::Locked {
+                    retry_after: seconds_until(now, locked_until),
+                })
+            } else if self.in_window(&record, now) {
+                let allowed_at = record.last_failed_at + self.delay(record.failures);
+                (allowed_at > now).then(|| LoginBlock::Throttled {
+                    retry_after: seconds_until(now, allowed_at),
+                })
+            } else {
+                None
+            };
+
+            // 同时受限时返回需要等待更久的一个
+            if let Some(current) = current {
+                if block.is_none_or(|block| current.retry_after() > block.retry_after()) {
+                    block = Some(current);
+                }
+            }
+        }
+
+        Ok(block)
+    }
+
+    // 记录一次失败，达到上限时锁定
+    pub async fn record_failure(
+        &self,
+        username: &str,
+        ip_address: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> AppResult<()> {
+        for key in Self::keys(username, ip_address) {
+            let limit = match key {
+                LockoutKey::Account(_) => self.config.max_failures,
+                LockoutKey::Ip(_) => self.config.ip_max_failures,
+            };
+
+            // 窗口已过或锁定已到期时重新计数
+            let mut record = self
+                .store
+                .get(&key)
+                .await?
+                .filter(|record| {
+                    self.in_window(record, now) && record.locked_until.is_none_or(|t| t > now)
+                })
+                .unwrap_or(FailureRecord {
+                    failures: 0,
+                    first_failed_at: now,
+                    last_failed_at: now,
+                    locked_until: None,
+                });
+
+            record.failures += 1;
+            record.last_failed_at = now;
+            if record.failures >= limit.max(1) {
+                record.locked_until =
+                    Some(now + Duration::seconds(self.config.lockout_duration as i64));
+            }
+
+            self.store.put(&key, &record).await?;
+        }
+
+        Ok(())
+    }
+
+    // 登录成功或重置密码后清除账号的失败记录；IP的记录保留，避免攻击者用自己的账号重置计数
+    pub async fn clear_account(&self, username: &str) -> AppResult<()> {
+        self.store.clear(&LockoutKey::account(username)).await
+    }
+
+    fn keys(username: &str, ip_address: Option<&str>) -> Vec<LockoutKey> {
+        let mut keys = vec![LockoutKey::account(username)];
+        if let Some(ip_address) = ip_address {
+            keys.push(LockoutKey::Ip(ip_address.to_string()));
+        }
+        keys
+    }
+
+    fn in_window(&self, record: &FailureRecord, now: DateTime<Utc>) -> bool {
+        record.first_failed_at + Duration::seconds(self.config.failure_window as i64) > now
+    }
+
+    // 第n次失败后需要等待2^(n-1)秒，不超过max_delay
+    fn delay(&self, failures: u32) -> Duration {
+        let seconds = 1u64
+            .checked_shl(failures.saturating_sub(1))
+            .unwrap_or(u64::MAX)
+            .min(self.config.max_delay);
+        Duration::seconds(seconds as i64)
+    }
+}
+
+fn seconds_until(now: DateTime<Utc>, until: DateTime<Utc>) -> u64 {
+    // 向上取整，避免客户端按Retry-After重试时仍被拒绝
+    ((until - now).num_milliseconds().max(0) as u64).div_ceil(1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn config() -> LoginConfig {
+        LoginConfig {
+            store: LockoutStoreKind::Memory,
+            max_failures: 3,
+            ip_max_failures: 5,
+            failure_window: 600,
+            lockout_duration: 900,
+            max_delay: 30,
+            challenge_expiration: 300,
+            totp_issuer: "test".to_string(),
+        }
+    }
+
+    fn guard() -> (LoginGuard, Arc<MemoryLockoutStore>) {
+        let store = Arc::new(MemoryLockoutStore::default());
+        (LoginGuard::new(store.clone(), config()), store)
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(seconds)
+    }
+
+    #[tokio::test]
+    async fn throttles_then_locks_at_threshold() {
+        let (guard, _) = guard();
+
+        guard.record_failure("alice", None, at(0)).await.unwrap();
+        guard.record_failure("alice", None, at(10)).await.unwrap();
+        // 第2次失败后需要等待2秒
+        assert_eq!(
+            guard.check("alice", None, at(11)).await.unwrap(),
+            Some(LoginBlock::Throttled { retry_after: 1 })
+        );
+        assert_eq!(guard.check("alice", None, at(12)).await.unwrap(), None);
+
+        guard.record_failure("alice", None, at(20)).await.unwrap();
+        assert_eq!(
+            guard.check("alice", None, at(21)).await.unwrap(),
+            Some(LoginBlock::Locked { retry_after: 899 })
+        );
+        // 账号名不区分大小写
+        assert!(guard.check(" Alice ", None, at(21)).await.unwrap().is_some());
+        // 锁定到期后允许再次尝试
+        assert_eq!(guard.check("alice", None, at(920)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn ip_is_locked_across_usernames() {
+        let (guard, _) = guard();
+
+        for (index, username) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            let now = at(index as i64 * 60);
+            guard.record_failure(username, Some("10.0.0.1"), now).await.unwrap();
+        }
+
+        assert!(matches!(
+            guard.check("f", Some("10.0.0.1"), at(300)).await.unwrap(),
+            Some(LoginBlock::Locked { .. })
+        ));
+        assert_eq!(guard.check("f", Some("10.0.0.2"), at(300)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn failures_reset_after_window() {
+        let (guard, store) = guard();
+
+        guard.record_failure("alice", None, at(0)).await.unwrap();
+        guard.record_failure("alice", None, at(60)).await.unwrap();
+        // 窗口外的失败记录不再限制登录，再次失败时重新计数
+        assert_eq!(guard.check("alice", None, at(600)).await.unwrap(), None);
+        guard.record_failure("alice", None, at(600)).await.unwrap();
+        guard.record_failure("alice", None, at(660)).await.unwrap();
+
+        let record = store.get(&LockoutKey::account("alice")).await.unwrap().unwrap();
+        assert_eq!(record.failures, 2);
+        assert_eq!(record.first_failed_at, at(600));
+        assert_eq!(record.locked_until, None);
+    }
+
+    #[tokio::test]
+    async fn clear_account_keeps_ip_record() {
+        let (guard, store) = guard();
+
+        for index in 0..3 {
+            guard
+                .record_failure("Alice", Some("10.0.0.1"), at(index * 60))
+                .await
+                .unwrap();
+        }
+        assert!(guard.check("alice", None, at(200)).await.unwrap().is_some());
+
+        guard.clear_account("ALICE").await.unwrap();
+
+        assert_eq!(guard.check("alice", None, at(200)).await.unwrap(), None);
+        let ip = store.get(&LockoutKey::Ip("10.0.0.1".to_string())).await.unwrap().unwrap();
+        assert_eq!(ip.failures, 3);
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::config::{MailBackend, MailConfig, SmtpConfig, SmtpTls};
+use crate::models::error::{AppError, AppResult};
+
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// 邮件模板，主题和正文中的{name}会被替换为对应的变量，未提供的变量原样保留
+pub struct MailTemplate {
+    pub subject: &'static str,
+    pub body: &'static str,
+}
+
+impl MailTemplate {
+    pub fn render(&self, to: String, vars: &[(&str, &str)]) -> MailMessage {
+        MailMessage {
+            to,
+            subject: render(self.subject, vars),
+            body: render(self.body, vars),
+        }
+    }
+}
+
+// 只扫描一遍模板，变量的值中即使包含{name}也不会被再次替换
+fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            vars.iter()
+                .find(|(name, _)| *name == &after[..end])
+                .map(|(_, value)| (end, *value))
+        });
+
+        match value {
+            Some((end, value)) => {
+                output.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// 邮件发送接口，接入第三方邮件服务时实现该trait即可
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send(&self, message: MailMessage) -> AppResult<()>;
+}
+
+// 根据配置创建邮件发送器
+pub fn mail_sender(config: &MailConfig) -> Arc<dyn MailSender> {
+    match config.backend {
+        MailBackend::Smtp => Arc::new(SmtpMailSender {
+            from: config.from.clone(),
+            smtp: config.smtp.clone().expect("MAIL_BACKEND为smtp时必须配置SMTP服务器"),
+        }),
+        MailBackend::Log => Arc::new(LogMailSender {
+            from: config.from.clone(),
+        }),
+        MailBackend::File => Arc::new(FileMailSender {
+            from: config.from.clone(),
+            dir: PathBuf::from(&config.dir),
+        }),
+    }
+}
+
+// 通过SMTP服务器发送
+pub struct SmtpMailSender {
+    from: String,
+    smtp: SmtpConfig,
+}
+
+impl SmtpMailSender {
+    fn transport(&self) -> AppResult<AsyncSmtpTransport<Tokio1Executor>> {
+        let builder = match self.smtp.tls {
+            SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.smtp.host),
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.smtp.host),
+            SmtpTls::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &self.smtp.host,
+            )),
+        }
+        .map_err(|e| AppError::InternalServerError(format!("连接邮件服务器失败: {}", e)))?
+        .port(self.smtp.port);
+
+        let builder = match (&self.smtp.username, &self.smtp.password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            _ => builder,
+        };
+
+        Ok(builder.build())
+    }
+}
+
+#[async_trait]
+impl MailSender for SmtpMailSender {
+    async fn send(&self, message: MailMessage) -> AppResult<()> {
+        let from: Mailbox = self
+            .from
+            .parse()
+            .map_err(|e| AppError::InternalServerError(format!("发件人地址不正确: {}", e)))?;
+        let to: Mailbox = message
+            .to
+            .parse()
+            .map_err(|e| AppError::InternalServerError(format!("收件人地址不正确: {}", e)))?;
+
+        let email = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(message.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body)
+            .map_err(|e| AppError::InternalServerError(format!("生成邮件失败: {}", e)))?;
+
+        self.transport()?
+            .send(email)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("发送邮件失败: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+// 只把邮件内容写入日志
+pub struct LogMailSender {
+    from: String,
+}
+
+#[async_trait]
+impl MailSender for LogMailSender {
+    async fn send(&self, message: MailMessage) -> AppResult<()> {
+        info!(
+            "Mail from {} to {}: {}\n{}",
+            self.from, message.to, message.subject, message.body
+        );
+        Ok(())
+    }
+}
+
+// 每封邮件保存为目录下的一个.eml文件
+pub struct FileMailSender {
+    from: String,
+    dir: PathBuf,
+}
+
+#[async_trait]
+impl MailSender for FileMailSender {
+    async fn send(&self, message: MailMessage) -> AppResult<()> {
+        let now = Utc::now();
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            self.from,
+            message.to,
+            message.subject,
+            now.to_rfc2822(),
+            message.body
+        );
+        let path = self
+            .dir
+            .join(format!("{}-{}.eml", now.format("%Y%m%d%H%M%S"), Uuid::new_v4()));
+
+        let save = async {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            tokio::fs::write(&path, content).await
+        };
+        save.await
+            .map_err(|e| AppError::InternalServerError(format!("保存邮件失败: {}", e)))?;
+
+        info!("Mail to {} saved to {}", message.to, path.display());
+        Ok(())
+    }
+}
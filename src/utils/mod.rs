@@ -1,2 +1,11 @@
 pub mod auth;
-pub mod response; 
\ No newline at end of file
+pub mod response; pub mod client;
+pub mod zip;
+pub mod export;
+pub mod definition;
+pub mod text;
+pub mod mail;
+pub mod lockout;
+pub mod totp;
+pub mod oidc;
+pub mod webhook;
\ No newline at end of file
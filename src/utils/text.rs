@@ -0,0 +1,124 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use jieba_rs::Jieba;
+
+// 加载词典较慢，全局共用一个分词器
+static JIEBA: LazyLock<Jieba> = LazyLock::new(Jieba::new);
+
+// 常见的虚词、代词和英文停用词，不计入词频
+const STOP_WORDS: &[&str] = &[
+    "的", "了", "是", "在", "和", "与", "及", "或", "也", "就", "都", "而", "着", "把", "被",
+    "对", "从", "到", "为", "让", "给", "很", "还", "又", "再", "吧", "吗", "呢", "啊", "呀",
+    "哦", "嗯", "我", "你", "他", "她", "它", "我们", "你们", "他们", "这", "那", "这个", "那个",
+    "这些", "那些", "有", "没有", "一个", "一些", "什么", "自己", "但", "但是", "因为", "所以",
+    "如果", "就是", "可以", "还是", "比较", "非常", "觉得", "a", "an", "the", "and", "or",
+    "of", "to", "in", "on", "for", "with", "is", "are", "was", "be", "it", "this", "that",
+    "i", "we", "you", "they", "my", "our",
+];
+
+// 词语或短语的出现次数和包含它的文本数
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TermCount {
+    pub count: i32,
+    pub text_count: i32,
+}
+
+#[derive(Debug, Default)]
+pub struct TermCounter {
+    min_length: usize,
+    words: HashMap<String, TermCount>,
+    phrases: HashMap<String, TermCount>,
+}
+
+impl TermCounter {
+    pub fn new(min_length: usize) -> Self {
+        Self {
+            min_length,
+            ..Default::default()
+        }
+    }
+
+    // 统计一段文本中的词语和相邻词语组成的短语
+    pub fn add(&mut self, text: &str) {
+        let tokens = tokenize(text);
+        let mut seen_words = HashSet::new();
+        let mut seen_phrases = HashSet::new();
+
+        for token in tokens.iter().flatten() {
+            if token.chars().count() >= self.min_length {
+                record(&mut self.words, &mut seen_words, token.clone());
+            }
+        }
+
+        for pair in tokens.windows(2) {
+            if let [Some(first), Some(second)] = pair {
+                record(&mut self.phrases, &mut seen_phrases, join_phrase(first, second));
+            }
+        }
+    }
+
+    // 按出现次数从多到少排列的前top个词语和短语
+    pub fn top(self, top: usize) -> (Vec<(String, TermCount)>, Vec<(String, TermCount)>) {
+        (ranked(self.words, top), ranked(self.phrases, top))
+    }
+}
+
+// 将文本切分为词语，英文统一为小写；标点和停用词处为None，短语不会跨越这些位置
+pub fn tokenize(text: &str) -> Vec<Option<String>> {
+    JIEBA
+        .cut(text, true)
+        .into_iter()
+        .filter(|token| !token.trim().is_empty())
+        .map(|token| {
+            let token = token.trim().to_lowercase();
+            if token.chars().any(char::is_alphanumeric) && !STOP_WORDS.contains(&token.as_str()) {
+                Some(token)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn record(terms: &mut HashMap<String, TermCount>, seen: &mut HashSet<String>, term: String) {
+    let entry = terms.entry(term.clone()).or_default();
+    entry.count += 1;
+    if seen.insert(term) {
+        entry.text_count += 1;
+    }
+}
+
+// 中文词语直接相连，英文单词之间保留空格
+fn join_phrase(first: &str, second: &str) -> String {
+    let ascii_boundary = first.chars().last().is_some_and(|c| c.is_ascii_alphanumeric())
+        && second.chars().next().is_some_and(|c| c.is_ascii_alphanumeric());
+    if ascii_boundary {
+        format!("{} {}", first, second)
+    } else {
+        format!("{}{}", first, second)
+    }
+}
+
+fn ranked(terms: HashMap<String, TermCount>, top: usize) -> Vec<(String, TermCount)> {
+    let mut terms: Vec<_> = terms.into_iter().collect();
+    terms.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0)));
+    terms.truncate(top);
+    terms
+}
+
+// 生成MySQL布尔模式的全文搜索条件，每个搜索词都必须出现
+pub fn boolean_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| term.replace(['"', '\\'], ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("+\"{}\"", term))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
@@ -0,0 +1,300 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use url::Url;
+
+// 响应内容只读取并保留开头部分写入投递记录
+const MAX_RESPONSE_BODY: usize = 1000;
+const MAX_RESPONSE_BYTES: usize = MAX_RESPONSE_BODY * 4;
+const MAX_ERROR_LENGTH: usize = 500;
+
+// 一次投递的结果
+pub struct DeliveryOutcome {
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub response_body: Option<String>,
+    pub duration_ms: i32,
+}
+
+impl DeliveryOutcome {
+    // 接收方返回2xx时视为投递成功
+    pub fn is_success(&self) -> bool {
+        self.status_code.is_some_and(|code| (200..300).contains(&code))
+    }
+}
+
+// 请求签名：对"{时间戳}.{请求体}"计算HMAC-SHA256，接收方用相同的密钥验证
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC可以使用任意长度的密钥");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// 投递目标：订阅地址和检查过的IP，请求时固定连接该IP，防止DNS重绑定绕过检查
+pub struct Target {
+    pub url: Url,
+    pub addr: SocketAddr,
+    pub public: bool,
+}
+
+// 订阅地址只允许http和https；解析域名后拒绝回环、链路本地、内网等地址，
+// 防止借助Webhook访问服务所在网络中的其他服务，allow_private只用于内网部署和本地调试
+pub async fn resolve_target(url: &str, allow_private: bool) -> Result<Target, String> {
+    const INVALID: &str = "地址必须是有效的http或https地址";
+
+    let url = Url::parse(url.trim()).map_err(|_| INVALID.to_string())?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(INVALID.to_string());
+    }
+    let host = url
+        .host_str()
+        .ok_or(INVALID)?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = url.port_or_known_default().ok_or(INVALID)?;
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|_| format!("无法解析地址中的域名{}", host))?
+        .collect();
+    let addr = *addrs
+        .first()
+        .ok_or_else(|| format!("无法解析地址中的域名{}", host))?;
+
+    // 域名的任一地址不是公网地址都拒绝，避免每次解析得到不同的结果
+    let public = addrs.iter().all(|addr| is_public_ip(addr.ip()));
+    if !public && !allow_private {
+        return Err("地址不能指向内网、回环或链路本地地址".to_string());
+    }
+
+    Ok(Target { url, addr, public })
+}
+
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => {
+            // IPv4映射地址和NAT64地址按其中的IPv4地址判断
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ipv4(v4);
+            }
+            let segments = ip.segments();
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let octets = ip.octets();
+                return is_public_ipv4(Ipv4Addr::new(
+                    octets[12], octets[13], octets[14], octets[15],
+                ));
+            }
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00 // 唯一本地地址fc00::/7
+                || (segments[0] & 0xffc0) == 0xfe80 // 链路本地地址fe80::/10
+                || (segments[0] & 0xffc0) == 0xfec0) // 站点本地地址fec0::/10
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0 // 0.0.0.0/8
+        || (a == 100 && (b & 0xc0) == 64) // 运营商级NAT 100.64.0.0/10
+        || (a == 198 && (b & 0xfe) == 18) // 基准测试 198.18.0.0/15
+        || a >= 240) // 保留地址
+}
+
+pub struct WebhookClient {
+    timeout: u64,
+    allow_private: bool,
+}
+
+impl WebhookClient {
+    pub fn new(timeout: u64, allow_private: bool) -> Self {
+        Self {
+            timeout,
+            allow_private,
+        }
+    }
+
+    // 发送一次推送，带上事件名称、推送ID、时间戳和签名请求头
+    pub async fn deliver(
+        &self,
+        url: &str,
+        secret: &str,
+        event: &str,
+        delivery_uuid: &str,
+        payload: &str,
+    ) -> DeliveryOutcome {
+        let timestamp = chrono::Utc::now().timestamp();
+        let started = Instant::now();
+
+        // 每次投递前重新检查地址，域名解析结果可能已经改变
+        let target = match resolve_target(url, self.allow_private).await {
+            Ok(target) => target,
+            Err(error) => {
+                return DeliveryOutcome {
+                    status_code: None,
+                    error: truncate(&error, MAX_ERROR_LENGTH),
+                    response_body: None,
+                    duration_ms: elapsed_ms(started),
+                }
+            }
+        };
+
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.timeout))
+            // 不跟随重定向，避免签名后的内容被转发到其他地址
+            .redirect(reqwest::redirect::Policy::none());
+        if let Some(domain) = target.url.domain() {
+            builder = builder.resolve(domain, target.addr);
+        }
+        let http = match builder.build() {
+            Ok(http) => http,
+            Err(e) => {
+                return DeliveryOutcome {
+                    status_code: None,
+                    error: truncate(&format!("创建HTTP客户端失败: {}", e), MAX_ERROR_LENGTH),
+                    response_body: None,
+                    duration_ms: elapsed_ms(started),
+                }
+            }
+        };
+
+        let result = http
+            .post(target.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::USER_AGENT, "questionnaire-webhook/1.0")
+            .header("X-Webhook-Event", event)
+            .header("X-Webhook-Delivery", delivery_uuid)
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .header("X-Webhook-Signature", sign(secret, timestamp, payload))
+            .body(payload.to_string())
+            .send()
+            .await;
+
+        let (status_code, response_body, error) = match result {
+            Ok(response) => {
+                let status = response.status();
+                let error = (!status.is_success()).then(|| format!("接收方返回{}", status));
+                // 只记录公网接收方的响应内容，且最多读取MAX_RESPONSE_BYTES字节
+                let body = if target.public {
+                    read_prefix(response).await
+                } else {
+                    String::new()
+                };
+                (
+                    Some(status.as_u16()),
+                    truncate(&body, MAX_RESPONSE_BODY),
+                    error,
+                )
+            }
+            Err(e) => (None, None, Some(format!("请求失败: {}", e))),
+        };
+
+        DeliveryOutcome {
+            status_code,
+            error: error.and_then(|error| truncate(&error, MAX_ERROR_LENGTH)),
+            response_body,
+            duration_ms: elapsed_ms(started),
+        }
+    }
+}
+
+// 读取响应内容的开头部分，超过上限后不再继续读取
+async fn read_prefix(mut response: reqwest::Response) -> String {
+    let mut body = Vec::new();
+    while body.len() < MAX_RESPONSE_BYTES {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            _ => break,
+        }
+    }
+    body.truncate(MAX_RESPONSE_BYTES);
+    String::from_utf8_lossy(&body).into_owned()
+}
+
+fn elapsed_ms(started: Instant) -> i32 {
+    started.elapsed().as_millis().min(i32::MAX as u128) as i32
+}
+
+fn truncate(value: &str, max_chars: usize) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.chars().take(max_chars).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_non_public_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(
+                !is_public_ip(ip.parse().unwrap()),
+                "{} 不应视为公网地址",
+                ip
+            );
+        }
+        for ip in [
+            "93.184.216.34",
+            "8.8.8.8",
+            "2606:4700:4700::1111",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} 应视为公网地址", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_target_checks_resolved_address() {
+        let error = resolve_target("http://localhost:8080/hook", false)
+            .await
+            .err()
+            .unwrap();
+        assert!(error.contains("内网"));
+        assert!(resolve_target("http://[::1]/hook", false).await.is_err());
+        assert!(resolve_target("ftp://93.184.216.34/hook", false)
+            .await
+            .is_err());
+
+        let target = resolve_target("http://127.0.0.1:8080/hook", true)
+            .await
+            .unwrap();
+        assert_eq!(target.addr, "127.0.0.1:8080".parse().unwrap());
+        assert!(!target.public);
+
+        let target = resolve_target("https://93.184.216.34/hook", false)
+            .await
+            .unwrap();
+        assert_eq!(target.addr, "93.184.216.34:443".parse().unwrap());
+        assert!(target.public);
+    }
+}
@@ -0,0 +1,376 @@
+// 流式ZIP写入器：条目不压缩（stored），大小和CRC写在数据描述符中，
+// 因此可以边生成边输出，不需要预先知道条目内容的长度。
+// 写本地文件头时还不知道条目是否会超过4GiB，因此每个条目都带ZIP64扩展字段，
+// 数据描述符统一使用64位的大小，流式读取时据此判断数据描述符的长度；
+// 偏移量或条目数超出32位字段的范围时在中央目录中写入ZIP64记录
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+
+// 第3位：大小和CRC在数据描述符中；第11位：文件名为UTF-8
+const FLAGS: u16 = 0x0808;
+const ZIP64_VERSION: u16 = 45;
+// 1980-01-01 00:00:00（DOS时间格式）
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = 0x21;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// 32位字段取该值时表示实际的值在ZIP64记录中
+const ZIP64_MARKER_U32: u64 = 0xffff_ffff;
+const ZIP64_MARKER_U16: u64 = 0xffff;
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u64,
+    offset: u64,
+}
+
+pub struct ZipWriter {
+    entries: Vec<Entry>,
+    offset: u64,
+    crc: u32, // 当前条目的CRC（未取反）
+    size: u64,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            offset: 0,
+            crc: 0xffff_ffff,
+            size: 0,
+        }
+    }
+
+    // 开始一个新条目，返回本地文件头
+    pub fn start_entry(&mut self, name: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(50 + name.len());
+        put_u32(&mut out, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut out, ZIP64_VERSION);
+        put_u16(&mut out, FLAGS);
+        put_u16(&mut out, 0); // stored
+        put_u16(&mut out, DOS_TIME);
+        put_u16(&mut out, DOS_DATE);
+        put_u32(&mut out, 0); // CRC，写在数据描述符中
+        put_u32(&mut out, ZIP64_MARKER_U32 as u32); // 大小在ZIP64扩展字段和数据描述符中
+        put_u32(&mut out, ZIP64_MARKER_U32 as u32);
+        put_u16(&mut out, name.len() as u16);
+        put_u16(&mut out, 20); // 扩展字段长度
+        out.extend_from_slice(name.as_bytes());
+        put_u16(&mut out, ZIP64_EXTRA_FIELD_ID);
+        put_u16(&mut out, 16);
+        put_u64(&mut out, 0);
+        put_u64(&mut out, 0);
+
+        self.entries.push(Entry {
+            name: name.to_string(),
+            crc: 0,
+            size: 0,
+            offset: self.offset,
+        });
+        self.offset += out.len() as u64;
+        self.crc = 0xffff_ffff;
+        self.size = 0;
+
+        out
+    }
+
+    // 写入当前条目的内容，返回需要输出的字节
+    pub fn write<'a>(&mut self, data: &'a [u8]) -> &'a [u8] {
+        for byte in data {
+            self.crc = CRC_TABLE[((self.crc ^ *byte as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+        self.size += data.len() as u64;
+        self.offset += data.len() as u64;
+        data
+    }
+
+    // 结束当前条目，返回数据描述符
+    pub fn finish_entry(&mut self) -> Vec<u8> {
+        let crc = !self.crc;
+        let entry = self.entries.last_mut().expect("没有正在写入的条目");
+        entry.crc = crc;
+        entry.size = self.size;
+
+        // 本地文件头带有ZIP64扩展字段，数据描述符中的大小总是64位
+        let mut out = Vec::with_capacity(24);
+        put_u32(&mut out, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut out, crc);
+        put_u64(&mut out, self.size);
+        put_u64(&mut out, self.size);
+        self.offset += out.len() as u64;
+
+        out
+    }
+
+    // 写入单个完整的条目
+    pub fn entry(&mut self, name: &str, data: &[u8]) -> Vec<u8> {
+        let mut out = self.start_entry(name);
+        out.extend_from_slice(self.write(data));
+        out.extend(self.finish_entry());
+        out
+    }
+
+    // 结束整个文件，返回中央目录
+    pub fn finish(self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        for entry in &self.entries {
+            // 超出范围的大小和偏移量按顺序写入ZIP64扩展字段，原字段写0xffffffff
+            let mut extra = Vec::new();
+            if entry.size >= ZIP64_MARKER_U32 {
+                put_u64(&mut extra, entry.size);
+                put_u64(&mut extra, entry.size);
+            }
+            if entry.offset >= ZIP64_MARKER_U32 {
+                put_u64(&mut extra, entry.offset);
+            }
+            let zip64 = !extra.is_empty();
+
+            put_u32(&mut out, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut out, ZIP64_VERSION);
+            put_u16(&mut out, ZIP64_VERSION);
+            put_u16(&mut out, FLAGS);
+            put_u16(&mut out, 0);
+            put_u16(&mut out, DOS_TIME);
+            put_u16(&mut out, DOS_DATE);
+            put_u32(&mut out, entry.crc);
+            put_u32(&mut out, entry.size.min(ZIP64_MARKER_U32) as u32);
+            put_u32(&mut out, entry.size.min(ZIP64_MARKER_U32) as u32);
+            put_u16(&mut out, entry.name.len() as u16);
+            put_u16(&mut out, if zip64 { extra.len() as u16 + 4 } else { 0 }); // 扩展字段长度
+            put_u16(&mut out, 0); // 注释长度
+            put_u16(&mut out, 0); // 磁盘号
+            put_u16(&mut out, 0); // 内部属性
+            put_u32(&mut out, 0); // 外部属性
+            put_u32(&mut out, entry.offset.min(ZIP64_MARKER_U32) as u32);
+            out.extend_from_slice(entry.name.as_bytes());
+            if zip64 {
+                put_u16(&mut out, ZIP64_EXTRA_FIELD_ID);
+                put_u16(&mut out, extra.len() as u16);
+                out.extend_from_slice(&extra);
+            }
+        }
+
+        let count = self.entries.len() as u64;
+        let directory_size = out.len() as u64;
+        let directory_offset = self.offset;
+
+        if count >= ZIP64_MARKER_U16
+            || directory_size >= ZIP64_MARKER_U32
+            || directory_offset >= ZIP64_MARKER_U32
+        {
+            let record_offset = directory_offset + directory_size;
+            put_u32(&mut out, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+            put_u64(&mut out, 44); // 记录中此字段之后的长度
+            put_u16(&mut out, ZIP64_VERSION);
+            put_u16(&mut out, ZIP64_VERSION);
+            put_u32(&mut out, 0);
+            put_u32(&mut out, 0);
+            put_u64(&mut out, count);
+            put_u64(&mut out, count);
+            put_u64(&mut out, directory_size);
+            put_u64(&mut out, directory_offset);
+
+            put_u32(&mut out, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE);
+            put_u32(&mut out, 0);
+            put_u64(&mut out, record_offset);
+            put_u32(&mut out, 1); // 磁盘总数
+        }
+
+        put_u32(&mut out, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut out, 0);
+        put_u16(&mut out, 0);
+        put_u16(&mut out, count.min(ZIP64_MARKER_U16) as u16);
+        put_u16(&mut out, count.min(ZIP64_MARKER_U16) as u16);
+        put_u32(&mut out, directory_size.min(ZIP64_MARKER_U32) as u32);
+        put_u32(&mut out, directory_offset.min(ZIP64_MARKER_U32) as u32);
+        put_u16(&mut out, 0);
+
+        out
+    }
+}
+
+impl Default for ZipWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use super::*;
+
+    fn read_all(archive: Vec<u8>) -> Vec<(String, Vec<u8>)> {
+        let mut archive = ::zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+        (0..archive.len())
+            .map(|index| {
+                let mut file = archive.by_index(index).unwrap();
+                let mut data = Vec::new();
+                file.read_to_end(&mut data).unwrap();
+                (file.name().to_string(), data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_reference_reader() {
+        let mut zip = ZipWriter::new();
+        let mut out = Vec::new();
+        out.extend(zip.entry("a.txt", b"hello"));
+        out.extend(zip.entry("目录/空文件", b""));
+        out.extend(zip.start_entry("stream.csv"));
+        for line in ["a,b\r\n", "1,2\r\n", "3,4\r\n"] {
+            out.extend_from_slice(zip.write(line.as_bytes()));
+        }
+        out.extend(zip.finish_entry());
+        out.extend(zip.finish());
+
+        // 读取时会校验每个条目的CRC
+        assert_eq!(
+            read_all(out),
+            vec![
+                ("a.txt".to_string(), b"hello".to_vec()),
+                ("目录/空文件".to_string(), Vec::new()),
+                ("stream.csv".to_string(), b"a,b\r\n1,2\r\n3,4\r\n".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn crc_matches_reference_value() {
+        let mut zip = ZipWriter::new();
+        zip.start_entry("check");
+        zip.write(b"123456789");
+        let descriptor = zip.finish_entry();
+
+        assert_eq!(&descriptor[4..8], &0xcbf4_3926u32.to_le_bytes());
+    }
+
+    #[test]
+    fn local_header_always_declares_zip64() {
+        let mut zip = ZipWriter::new();
+        let header = zip.start_entry("a");
+        let descriptor = zip.finish_entry();
+
+        assert_eq!(&header[4..6], &ZIP64_VERSION.to_le_bytes());
+        assert_eq!(&header[18..26], &[0xff; 8]);
+        assert_eq!(&header[28..30], &20u16.to_le_bytes());
+        assert_eq!(&header[31..33], &ZIP64_EXTRA_FIELD_ID.to_le_bytes());
+        assert_eq!(&header[35..], &[0; 16]);
+        assert_eq!(descriptor.len(), 24);
+    }
+
+    #[test]
+    fn writes_zip64_records_for_many_entries() {
+        let mut zip = ZipWriter::new();
+        let mut out = Vec::new();
+        for index in 0..70_000 {
+            out.extend(zip.entry(&index.to_string(), b"x"));
+        }
+        out.extend(zip.finish());
+
+        let entries = read_all(out);
+        assert_eq!(entries.len(), 70_000);
+        assert_eq!(entries[69_999], ("69999".to_string(), b"x".to_vec()));
+    }
+
+    // 未写出的字节按0读取，用来表示跳过的大段内容
+    struct SparseReader {
+        parts: Vec<(u64, Vec<u8>)>,
+        len: u64,
+        position: u64,
+    }
+
+    impl Read for SparseReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let count = buf.len().min((self.len - self.position) as usize);
+            let buf = &mut buf[..count];
+            buf.fill(0);
+            for (start, data) in &self.parts {
+                let end = start + data.len() as u64;
+                let from = self.position.max(*start);
+                let to = (self.position + count as u64).min(end);
+                if from < to {
+                    buf[(from - self.position) as usize..(to - self.position) as usize]
+                        .copy_from_slice(&data[(from - start) as usize..(to - start) as usize]);
+                }
+            }
+            self.position += count as u64;
+            Ok(count)
+        }
+    }
+
+    impl std::io::Seek for SparseReader {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.position = match pos {
+                std::io::SeekFrom::Start(offset) => offset,
+                std::io::SeekFrom::End(offset) => (self.len as i64 + offset) as u64,
+                std::io::SeekFrom::Current(offset) => (self.position as i64 + offset) as u64,
+            };
+            Ok(self.position)
+        }
+    }
+
+    #[test]
+    fn writes_zip64_sizes_and_offsets_past_4gib() {
+        let mut zip = ZipWriter::new();
+        let mut parts = vec![(0, zip.start_entry("large"))];
+
+        // 不实际生成5GiB的内容，只推进大小和偏移量
+        let skipped: u64 = 5 << 30;
+        zip.size += skipped;
+        zip.offset += skipped;
+        parts.push((zip.offset, zip.finish_entry()));
+        parts.push((zip.offset, zip.entry("after", b"after")));
+        let directory_offset = zip.offset;
+        let directory = zip.finish();
+        let len = directory_offset + directory.len() as u64;
+        parts.push((directory_offset, directory));
+
+        let reader = SparseReader { parts, len, position: 0 };
+        let mut archive = ::zip::ZipArchive::new(reader).unwrap();
+        assert_eq!(archive.by_name("large").unwrap().size(), skipped);
+
+        let mut data = Vec::new();
+        archive.by_name("after").unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"after");
+    }
+}